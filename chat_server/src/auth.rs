@@ -0,0 +1,76 @@
+use crate::error::ChatError;
+use argon2::Argon2;
+use argon2::password_hash::rand_core::OsRng;
+use argon2::password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString};
+use subtle::ConstantTimeEq;
+
+pub const MIN_PASSWORD_LEN: usize = 6;
+
+/// Hashes `password` with a random salt, returning the PHC string
+/// (algorithm, parameters, salt and hash) that gets stored.
+///
+/// Argon2 is deliberately slow, so this runs on the blocking pool.
+pub async fn hash_password(password: &str) -> Result<String, ChatError> {
+    let password = password.to_string();
+
+    tokio::task::spawn_blocking(move || {
+        let salt = SaltString::generate(&mut OsRng);
+
+        Argon2::default()
+            .hash_password(password.as_bytes(), &salt)
+            .map(|hash| hash.to_string())
+            .map_err(|e| ChatError::PasswordHash(e.to_string()))
+    })
+    .await?
+}
+
+pub async fn verify_password(password: &str, stored_hash: &str) -> Result<bool, ChatError> {
+    let password = password.to_string();
+    let stored_hash = stored_hash.to_string();
+
+    tokio::task::spawn_blocking(move || {
+        let hash =
+            PasswordHash::new(&stored_hash).map_err(|e| ChatError::PasswordHash(e.to_string()))?;
+
+        Ok(Argon2::default()
+            .verify_password(password.as_bytes(), &hash)
+            .is_ok())
+    })
+    .await?
+}
+
+/// Whether `given` is the `expected` secret. Takes as long whichever byte
+/// differs, so timing the answers doesn't reveal the secret byte by byte.
+pub fn secrets_match(given: &str, expected: &str) -> bool {
+    given.as_bytes().ct_eq(expected.as_bytes()).into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_hash_and_verify() {
+        let hash = hash_password("hunter22").await.unwrap();
+
+        assert!(hash.starts_with("$argon2"));
+        assert!(verify_password("hunter22", &hash).await.unwrap());
+        assert!(!verify_password("hunter23", &hash).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_hashes_are_salted() {
+        let first = hash_password("hunter22").await.unwrap();
+        let second = hash_password("hunter22").await.unwrap();
+
+        assert_ne!(first, second);
+    }
+
+    #[test]
+    fn test_secrets_match() {
+        assert!(secrets_match("s3cret", "s3cret"));
+        assert!(!secrets_match("s3cres", "s3cret"));
+        assert!(!secrets_match("s3cre", "s3cret"));
+        assert!(!secrets_match("", "s3cret"));
+    }
+}
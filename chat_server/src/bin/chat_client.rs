@@ -0,0 +1,187 @@
+use chat_server::client::{self, Screen};
+use chat_server::error::ChatError;
+use clap::Parser;
+use crossterm::event::{Event, EventStream, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use crossterm::style::Print;
+use crossterm::terminal::{self, ClearType};
+use crossterm::{cursor, execute, queue};
+use futures_util::StreamExt;
+use std::io::{self, Stdout, Write};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+
+/// Lines moved by PageUp / PageDown.
+const SCROLL_STEP: usize = 10;
+
+/// Terminal client for the chat server: server output scrolls above, your
+/// input stays on the bottom line.
+#[derive(Debug, Parser)]
+#[command(name = "chat_client", about = "Terminal client for chat_server")]
+struct Args {
+    /// Server address
+    #[arg(long, default_value = "127.0.0.1")]
+    host: String,
+
+    /// Server port (the raw TCP listener)
+    #[arg(long, default_value_t = 8080)]
+    port: u16,
+}
+
+/// Puts the terminal back to normal when dropped, even after a panic or
+/// an error.
+struct RawTerminal;
+
+impl RawTerminal {
+    fn enter(stdout: &mut Stdout) -> io::Result<Self> {
+        terminal::enable_raw_mode()?;
+        execute!(stdout, terminal::EnterAlternateScreen)?;
+
+        Ok(RawTerminal)
+    }
+}
+
+impl Drop for RawTerminal {
+    fn drop(&mut self) {
+        let _ = execute!(io::stdout(), terminal::LeaveAlternateScreen);
+        let _ = terminal::disable_raw_mode();
+    }
+}
+
+#[tokio::main]
+async fn main() -> Result<(), ChatError> {
+    let args = Args::parse();
+    let stream = TcpStream::connect((args.host.as_str(), args.port)).await?;
+    let (mut reader, mut writer) = stream.into_split();
+
+    let mut stdout = io::stdout();
+    let raw = RawTerminal::enter(&mut stdout)?;
+    let mut events = EventStream::new();
+    let mut screen = Screen::new();
+    let mut buf = vec![0; 4096];
+
+    screen.push_line(format!(
+        "Connected to {}:{} (PageUp/PageDown to scroll, Esc to quit)",
+        args.host, args.port
+    ));
+    draw(&mut stdout, &screen)?;
+
+    let reason = loop {
+        tokio::select! {
+            result = reader.read(&mut buf) => {
+                match result? {
+                    0 => break "Connection closed by the server",
+                    n => screen.push_output(&buf[..n]),
+                }
+            }
+
+            event = events.next() => {
+                match event {
+                    Some(Ok(Event::Key(key))) if key.kind != KeyEventKind::Release => {
+                        match handle_key(&mut screen, key) {
+                            Action::Send(line) => {
+                                writer.write_all(format!("{}\n", line).as_bytes()).await?;
+                            }
+                            Action::Quit => break "Bye!",
+                            Action::None => {}
+                        }
+                    }
+                    Some(Ok(_)) => {}
+                    Some(Err(e)) => return Err(e.into()),
+                    None => break "Terminal closed",
+                }
+            }
+        }
+
+        draw(&mut stdout, &screen)?;
+    };
+
+    drop(raw);
+    // whatever the server said last, e.g. why we were disconnected
+    for line in screen.visible_rows(usize::MAX, 3) {
+        println!("{}", line);
+    }
+    println!("{}", reason);
+
+    Ok(())
+}
+
+enum Action {
+    Send(String),
+    Quit,
+    None,
+}
+
+fn handle_key(screen: &mut Screen, key: KeyEvent) -> Action {
+    let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+
+    match key.code {
+        KeyCode::Esc => Action::Quit,
+        KeyCode::Char('c') | KeyCode::Char('d') if ctrl => Action::Quit,
+        KeyCode::Char('u') if ctrl => {
+            screen.clear_input();
+            Action::None
+        }
+        KeyCode::Char(c) => {
+            screen.type_char(c);
+            Action::None
+        }
+        KeyCode::Backspace => {
+            screen.backspace();
+            Action::None
+        }
+        KeyCode::Enter => {
+            // prompt answers (username, password) aren't chat
+            let prompted = screen.is_prompting();
+            let line = screen.submit();
+            if !prompted {
+                let time = chrono::Local::now().format("%H:%M:%S").to_string();
+                if let Some(echo) = client::local_echo(&line, &time) {
+                    screen.push_line(echo);
+                }
+            }
+
+            Action::Send(line)
+        }
+        KeyCode::PageUp => {
+            screen.scroll_up(SCROLL_STEP);
+            Action::None
+        }
+        KeyCode::PageDown => {
+            screen.scroll_down(SCROLL_STEP);
+            Action::None
+        }
+        _ => Action::None,
+    }
+}
+
+/// Redraws everything: the scrollback on top, a separator, then the prompt
+/// and input on the last row.
+fn draw(stdout: &mut Stdout, screen: &Screen) -> io::Result<()> {
+    let (width, height) = terminal::size()?;
+    let (width, height) = (width as usize, height as usize);
+    let scrollback = height.saturating_sub(2);
+
+    queue!(stdout, terminal::Clear(ClearType::All))?;
+    for (row, line) in screen.visible_rows(width, scrollback).iter().enumerate() {
+        queue!(stdout, cursor::MoveTo(0, row as u16), Print(line))?;
+    }
+    queue!(
+        stdout,
+        cursor::MoveTo(0, scrollback as u16),
+        Print("─".repeat(width))
+    )?;
+
+    // only the end of a long input fits on the line
+    let input_line = format!("{}{}", screen.prompt(), screen.shown_input());
+    let chars: Vec<char> = input_line.chars().collect();
+    let visible: String = chars[chars.len().saturating_sub(width.saturating_sub(1))..]
+        .iter()
+        .collect();
+    queue!(
+        stdout,
+        cursor::MoveTo(0, height.saturating_sub(1) as u16),
+        Print(&visible)
+    )?;
+
+    stdout.flush()
+}
@@ -0,0 +1,277 @@
+use crate::error::ChatError;
+use crate::protocol::{ServerMessage, format_time};
+use crate::registry::{Registry, Routed};
+use chrono_tz::Tz;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::fs::{File, OpenOptions};
+use std::io::{LineWriter, Write};
+use std::path::Path;
+use tokio::sync::mpsc;
+use tracing::error;
+
+/// Where the `logger` bot writes the transcript.
+pub const TRANSCRIPT_PATH: &str = "transcript.log";
+/// Bots that can be turned on with `--bot` or `bots = [...]`.
+pub const BOT_NAMES: [&str; 2] = ["dice", "logger"];
+/// Most dice a single `!roll` throws.
+const MAX_DICE: u32 = 100;
+/// Most sides a die can have.
+const MAX_SIDES: u32 = 1000;
+
+/// What a bot does about a message. It happens in the message's room.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Reply {
+    /// A chat message from the bot.
+    Say(String),
+    /// A `/me` style action from the bot.
+    Act(String),
+    /// A notice, like the server's own announcements.
+    Notice(String),
+}
+
+/// A server-side bot.
+///
+/// Bots see every message routed to a room (chat, actions and notices),
+/// including other bots' but not their own, and answer with `Reply`s. They
+/// all run on one task next to the registry, so `on_message` should be
+/// quick.
+pub trait Bot: Send {
+    /// Shown as the sender of what the bot says. Clients can't take it.
+    fn name(&self) -> &str;
+
+    fn on_message(&mut self, room: &str, message: &ServerMessage) -> Vec<Reply>;
+}
+
+/// Creates a built-in bot from its name in the config.
+pub fn load(name: &str) -> Result<Box<dyn Bot>, ChatError> {
+    match name {
+        "dice" => Ok(Box::new(DiceBot::new())),
+        "logger" => Ok(Box::new(LoggerBot::open(TRANSCRIPT_PATH)?)),
+        _ => Err(ChatError::Config(format!(
+            "unknown bot '{}', available: {}",
+            name,
+            BOT_NAMES.join(", ")
+        ))),
+    }
+}
+
+/// Feeds the messages of `registry.watch()` to the bots and routes their
+/// replies. Returns when the registry stops.
+pub async fn run(
+    mut bots: Vec<Box<dyn Bot>>,
+    mut messages: mpsc::Receiver<Routed>,
+    registry: Registry,
+) -> Result<(), ChatError> {
+    while let Some(Routed { room, message }) = messages.recv().await {
+        for bot in &mut bots {
+            // a bot answering itself could go on forever
+            if message.sender() == Some(bot.name()) {
+                continue;
+            }
+
+            for reply in bot.on_message(&room, &message) {
+                let message = match reply {
+                    Reply::Say(body) => ServerMessage::chat(bot.name(), &room, &body),
+                    Reply::Act(body) => ServerMessage::action(bot.name(), &room, &body),
+                    Reply::Notice(body) => ServerMessage::notice(body),
+                };
+                registry.route(&room, message).await?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Rolls dice for `!roll`, `!roll 20` or `!roll 2d6`.
+pub struct DiceBot {
+    rng: StdRng,
+}
+
+impl DiceBot {
+    pub fn new() -> Self {
+        DiceBot {
+            rng: StdRng::from_os_rng(),
+        }
+    }
+
+    /// A bot with predictable rolls, for tests.
+    pub fn with_seed(seed: u64) -> Self {
+        DiceBot {
+            rng: StdRng::seed_from_u64(seed),
+        }
+    }
+}
+
+impl Default for DiceBot {
+    fn default() -> Self {
+        DiceBot::new()
+    }
+}
+
+impl Bot for DiceBot {
+    fn name(&self) -> &str {
+        "dice"
+    }
+
+    fn on_message(&mut self, _room: &str, message: &ServerMessage) -> Vec<Reply> {
+        let ServerMessage::Chat { from, body, .. } = message else {
+            return Vec::new();
+        };
+        let mut words = body.split_whitespace();
+        if words.next() != Some("!roll") {
+            return Vec::new();
+        }
+
+        let dice = match (words.next(), words.next()) {
+            (None, _) => Some((1, 6)),
+            (Some(spec), None) => parse_dice(spec),
+            _ => None,
+        };
+        let Some((count, sides)) = dice else {
+            return vec![Reply::Say(format!(
+                "Usage: !roll [NdM], e.g. !roll 2d6 (up to {} dice of 2 to {} sides)",
+                MAX_DICE, MAX_SIDES
+            ))];
+        };
+
+        let rolls: Vec<u32> = (0..count)
+            .map(|_| self.rng.random_range(1..=sides))
+            .collect();
+        let total: u32 = rolls.iter().sum();
+        let text = if count == 1 {
+            format!("{} rolled a d{}: {}", from, sides, total)
+        } else {
+            let rolls: Vec<String> = rolls.iter().map(|r| r.to_string()).collect();
+            format!(
+                "{} rolled {}d{}: {} = {}",
+                from,
+                count,
+                sides,
+                rolls.join(" + "),
+                total
+            )
+        };
+
+        vec![Reply::Say(text)]
+    }
+}
+
+/// `2d6` -> `(2, 6)`, `20` and `d20` -> `(1, 20)`.
+fn parse_dice(spec: &str) -> Option<(u32, u32)> {
+    let (count, sides) = match spec.to_lowercase().split_once('d') {
+        Some(("", sides)) => (1, sides.parse().ok()?),
+        Some((count, sides)) => (count.parse().ok()?, sides.parse().ok()?),
+        None => (1, spec.parse().ok()?),
+    };
+
+    ((1..=MAX_DICE).contains(&count) && (2..=MAX_SIDES).contains(&sides)).then_some((count, sides))
+}
+
+/// Appends everything said in every room to a transcript file, one line
+/// per message with the date in UTC. Never says anything itself.
+pub struct LoggerBot {
+    file: LineWriter<File>,
+}
+
+impl LoggerBot {
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, ChatError> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+
+        Ok(LoggerBot {
+            file: LineWriter::new(file),
+        })
+    }
+}
+
+impl Bot for LoggerBot {
+    fn name(&self) -> &str {
+        "logger"
+    }
+
+    fn on_message(&mut self, room: &str, message: &ServerMessage) -> Vec<Reply> {
+        if let Some(line) = transcript_line(room, message)
+            && let Err(e) = writeln!(self.file, "{}", line)
+        {
+            error!("Error writing the transcript: {}", e);
+        }
+
+        Vec::new()
+    }
+}
+
+fn transcript_line(room: &str, message: &ServerMessage) -> Option<String> {
+    let text = match message {
+        ServerMessage::Chat { from, body, .. } => format!("{}: {}", from, body),
+        ServerMessage::Action { from, body, .. } => format!("* {} {}", from, body),
+        ServerMessage::Notice { body, .. } => format!("*** {} ***", body),
+        _ => return None,
+    };
+    let time = format_time(message.ts(), Tz::UTC, "%Y-%m-%d %H:%M:%S");
+
+    Some(format!("[{}] #{} {}", time, room, text))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn roll(bot: &mut DiceBot, body: &str) -> Vec<Reply> {
+        bot.on_message("lobby", &ServerMessage::chat("alice", "lobby", body))
+    }
+
+    #[test]
+    fn test_parse_dice() {
+        assert_eq!(parse_dice("2d6"), Some((2, 6)));
+        assert_eq!(parse_dice("D20"), Some((1, 20)));
+        assert_eq!(parse_dice("12"), Some((1, 12)));
+        assert_eq!(parse_dice("0d6"), None);
+        assert_eq!(parse_dice("1d1"), None);
+        assert_eq!(parse_dice("101d6"), None);
+        assert_eq!(parse_dice("2d"), None);
+        assert_eq!(parse_dice("lots"), None);
+    }
+
+    #[test]
+    fn test_dice_bot() {
+        let mut bot = DiceBot::with_seed(7);
+        assert!(roll(&mut bot, "hello").is_empty());
+        assert!(roll(&mut bot, "!rolling").is_empty());
+
+        let replies = roll(&mut bot, "!roll 3d6");
+        let [Reply::Say(text)] = replies.as_slice() else {
+            panic!("expected one message, got {:?}", replies);
+        };
+        let (rolls, total) = text
+            .strip_prefix("alice rolled 3d6: ")
+            .and_then(|rest| rest.split_once(" = "))
+            .unwrap();
+        let rolls: Vec<u32> = rolls.split(" + ").map(|r| r.parse().unwrap()).collect();
+        assert_eq!(rolls.len(), 3);
+        assert!(rolls.iter().all(|r| (1..=6).contains(r)));
+        assert_eq!(rolls.iter().sum::<u32>(), total.parse::<u32>().unwrap());
+
+        assert!(matches!(
+            roll(&mut bot, "!roll").as_slice(),
+            [Reply::Say(text)] if text.starts_with("alice rolled a d6: ")
+        ));
+        assert!(matches!(
+            roll(&mut bot, "!roll 2d6 now").as_slice(),
+            [Reply::Say(text)] if text.starts_with("Usage: ")
+        ));
+    }
+
+    #[test]
+    fn test_transcript_line() {
+        let mut message = ServerMessage::action("bob", "rust", "waves");
+        if let ServerMessage::Action { ts, .. } = &mut message {
+            *ts = 0;
+        }
+        assert_eq!(
+            transcript_line("rust", &message).unwrap(),
+            "[1970-01-01 00:00:00] #rust * bob waves"
+        );
+        assert!(transcript_line("rust", &ServerMessage::info("only for you")).is_none());
+    }
+}
@@ -0,0 +1,105 @@
+use chrono_tz::Tz;
+use clap::Parser;
+use std::net::IpAddr;
+use std::path::PathBuf;
+
+/// Every setting is optional here: flags that are given override the
+/// config file, which overrides the defaults (see `Config`).
+#[derive(Debug, Parser)]
+#[command(name = "chat_server", about = "A concurrent TCP chat server")]
+pub struct Cli {
+    /// TOML config file
+    #[arg(long, short)]
+    pub config: Option<PathBuf>,
+
+    /// Address to listen on [default: 127.0.0.1]
+    #[arg(long)]
+    pub bind: Option<IpAddr>,
+
+    /// Port of the raw TCP listener [default: 8080]
+    #[arg(long)]
+    pub port: Option<u16>,
+
+    /// Port of the WebSocket listener [default: 8081]
+    #[arg(long)]
+    pub ws_port: Option<u16>,
+
+    /// Serve Prometheus metrics over HTTP on this port, at `/metrics`
+    /// [default: off]
+    #[arg(long)]
+    pub metrics_port: Option<u16>,
+
+    /// Maximum number of simultaneous connections [default: 100]
+    #[arg(long, value_parser = clap::value_parser!(u64).range(1..))]
+    pub max_clients: Option<u64>,
+
+    /// Most messages `/history` can show [default: 100]
+    #[arg(long, value_parser = clap::value_parser!(u64).range(1..))]
+    pub history_size: Option<u64>,
+
+    /// Recent messages of a room replayed to whoever joins it, 0 to
+    /// disable [default: 20]
+    #[arg(long)]
+    pub replay_size: Option<u64>,
+
+    /// Messages queued per client before a slow one starts missing some
+    /// [default: 100]
+    #[arg(long, value_parser = clap::value_parser!(u64).range(1..))]
+    pub channel_capacity: Option<u64>,
+
+    /// Longest line a client may send in bytes, longer ones are dropped
+    /// with an error [default: 1024]
+    #[arg(long, value_parser = clap::value_parser!(u64).range(1..))]
+    pub max_message_len: Option<u64>,
+
+    /// Messages a client may send per second before being throttled
+    /// [default: 5]
+    #[arg(long, value_parser = clap::value_parser!(u32).range(1..))]
+    pub max_messages_per_sec: Option<u32>,
+
+    /// Seconds without any input before a client is disconnected, 0 to
+    /// never disconnect idle clients [default: 600]
+    #[arg(long)]
+    pub idle_timeout: Option<u64>,
+
+    /// Seconds between pings to WebSocket and JSON clients, which are
+    /// dropped after 3 unanswered ones, 0 to disable [default: 30]
+    #[arg(long)]
+    pub heartbeat_interval: Option<u64>,
+
+    /// Seconds connected clients get to leave after Ctrl-C / SIGTERM
+    /// [default: 10]
+    #[arg(long)]
+    pub shutdown_grace: Option<u64>,
+
+    /// Timezone clients see timestamps in until they pick their own with
+    /// `/settimezone`, e.g. Europe/Berlin [default: UTC]
+    #[arg(long)]
+    pub timezone: Option<Tz>,
+
+    /// Registered username allowed to use the moderation commands
+    /// (can be repeated, replaces the operators of the config file)
+    #[arg(long = "operator", value_name = "USERNAME")]
+    pub operators: Vec<String>,
+
+    /// Built-in bot to run: dice (answers `!roll 2d6`) or logger (writes
+    /// transcript.log). Can be repeated, replaces the bots of the config file
+    #[arg(long = "bot", value_name = "NAME")]
+    pub bots: Vec<String>,
+
+    /// Also write logs to this file, one JSON object per line
+    #[arg(long)]
+    pub log_file: Option<PathBuf>,
+
+    /// Encrypt client connections with TLS (requires --cert and --key)
+    #[arg(long, requires_all = ["cert", "key"])]
+    pub tls: bool,
+
+    /// PEM file with the server certificate chain
+    #[arg(long)]
+    pub cert: Option<PathBuf>,
+
+    /// PEM file with the server private key
+    #[arg(long)]
+    pub key: Option<PathBuf>,
+}
@@ -0,0 +1,270 @@
+use std::collections::VecDeque;
+
+/// Lines kept in the scrollback, older ones are dropped.
+pub const MAX_SCROLLBACK: usize = 1000;
+
+/// What the terminal client shows, kept apart from the terminal itself so
+/// it can be tested.
+///
+/// Server output goes into the scrollback one line at a time. Output that
+/// isn't ended by a newline yet (the login prompts) is shown in front of the
+/// input line instead.
+#[derive(Debug, Default)]
+pub struct Screen {
+    lines: VecDeque<String>,
+    /// Unfinished last line of output, e.g. `Enter your Username: `.
+    prompt: String,
+    /// Bytes of a UTF-8 character split across two reads.
+    pending: Vec<u8>,
+    input: String,
+    /// How many lines the view is scrolled up from the bottom.
+    scroll: usize,
+}
+
+impl Screen {
+    pub fn new() -> Self {
+        Screen::default()
+    }
+
+    /// Adds bytes read from the server.
+    pub fn push_output(&mut self, bytes: &[u8]) {
+        self.pending.extend_from_slice(bytes);
+
+        // keep a character cut in half for the next read
+        let valid = match std::str::from_utf8(&self.pending) {
+            Ok(text) => text.len(),
+            Err(e) if e.error_len().is_none() => e.valid_up_to(),
+            Err(_) => self.pending.len(),
+        };
+        let rest = self.pending.split_off(valid);
+        let text = String::from_utf8_lossy(&self.pending).into_owned();
+        self.pending = rest;
+
+        for c in text.chars() {
+            match c {
+                '\n' => {
+                    let line = std::mem::take(&mut self.prompt);
+                    self.push_line(line);
+                }
+                '\r' => {}
+                c => self.prompt.push(c),
+            }
+        }
+    }
+
+    /// Adds a line of our own, like a status message.
+    pub fn push_line(&mut self, line: String) {
+        self.lines.push_back(line);
+        if self.lines.len() > MAX_SCROLLBACK {
+            self.lines.pop_front();
+        }
+        // a scrolled up view stays on the same lines
+        if self.scroll > 0 {
+            self.scroll = (self.scroll + 1).min(self.lines.len());
+        }
+    }
+
+    pub fn prompt(&self) -> &str {
+        &self.prompt
+    }
+
+    /// Whether the server is waiting for an answer to a prompt, like the
+    /// username, rather than for chat.
+    pub fn is_prompting(&self) -> bool {
+        !self.prompt.is_empty()
+    }
+
+    /// Whether the server is asking for a password, so the input is hidden.
+    pub fn is_secret(&self) -> bool {
+        self.prompt.contains("Password")
+    }
+
+    /// The input line as it should be shown.
+    pub fn shown_input(&self) -> String {
+        if self.is_secret() {
+            "*".repeat(self.input.chars().count())
+        } else {
+            self.input.clone()
+        }
+    }
+
+    pub fn type_char(&mut self, c: char) {
+        self.input.push(c);
+    }
+
+    pub fn backspace(&mut self) {
+        self.input.pop();
+    }
+
+    pub fn clear_input(&mut self) {
+        self.input.clear();
+    }
+
+    /// Takes the typed line to send it. An answered prompt moves to the
+    /// scrollback together with the answer, so the login reads like it does
+    /// in a plain terminal.
+    pub fn submit(&mut self) -> String {
+        if !self.prompt.is_empty() {
+            let line = format!("{}{}", self.prompt, self.shown_input());
+            self.prompt.clear();
+            self.push_line(line);
+        }
+        self.scroll = 0;
+
+        std::mem::take(&mut self.input)
+    }
+
+    pub fn scroll_up(&mut self, lines: usize) {
+        self.scroll = (self.scroll + lines).min(self.lines.len().saturating_sub(1));
+    }
+
+    pub fn scroll_down(&mut self, lines: usize) {
+        self.scroll = self.scroll.saturating_sub(lines);
+    }
+
+    /// The rows to draw in a scrollback area of `width` x `height`, with
+    /// long lines wrapped.
+    pub fn visible_rows(&self, width: usize, height: usize) -> Vec<String> {
+        let width = width.max(1);
+        let end = self.lines.len() - self.scroll.min(self.lines.len());
+
+        let mut rows = Vec::new();
+        for line in self.lines.range(..end).rev() {
+            let mut wrapped = wrap(line, width);
+            while let Some(row) = wrapped.pop() {
+                rows.push(row);
+                if rows.len() == height {
+                    rows.reverse();
+                    return rows;
+                }
+            }
+        }
+        rows.reverse();
+
+        rows
+    }
+}
+
+/// How a line we sent shows in our own scrollback, since the server doesn't
+/// send chat back to its author. `None` for commands with no echo.
+pub fn local_echo(line: &str, time: &str) -> Option<String> {
+    let line = line.trim();
+    if line.is_empty() {
+        return None;
+    }
+
+    if let Some(action) = line.strip_prefix("/me ") {
+        return Some(format!("[{}] * you {}", time, action.trim()));
+    }
+    if let Some(rest) = line.strip_prefix("/msg ")
+        && let Some((to, body)) = rest.trim().split_once(char::is_whitespace)
+    {
+        return Some(format!("[{}] [you -> {}] {}", time, to, body.trim()));
+    }
+    if line.starts_with('/') {
+        return None;
+    }
+
+    Some(format!("[{}] you: {}", time, line))
+}
+
+fn wrap(line: &str, width: usize) -> Vec<String> {
+    let chars: Vec<char> = line.chars().collect();
+    if chars.is_empty() {
+        return vec![String::new()];
+    }
+
+    chars
+        .chunks(width)
+        .map(|row| row.iter().collect())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_prompt_and_lines() {
+        let mut screen = Screen::new();
+        screen.push_output(b"Enter your Username: ");
+        assert_eq!(screen.prompt(), "Enter your Username: ");
+        assert!(screen.visible_rows(80, 10).is_empty());
+
+        screen.type_char('b');
+        screen.type_char('o');
+        screen.type_char('b');
+        assert_eq!(screen.submit(), "bob");
+        assert_eq!(screen.prompt(), "");
+
+        screen.push_output(b"*** bob has joined #lobby ***\nalice: hi\n");
+        assert_eq!(
+            screen.visible_rows(80, 2),
+            vec!["*** bob has joined #lobby ***", "alice: hi"]
+        );
+        assert_eq!(screen.visible_rows(80, 10)[0], "Enter your Username: bob");
+    }
+
+    #[test]
+    fn test_password_is_hidden() {
+        let mut screen = Screen::new();
+        screen.push_output(b"This username is registered. Password (empty to pick another name): ");
+        assert!(screen.is_secret());
+
+        for c in "secret".chars() {
+            screen.type_char(c);
+        }
+        assert_eq!(screen.shown_input(), "******");
+        assert_eq!(screen.submit(), "secret");
+
+        let rows = screen.visible_rows(200, 1);
+        assert!(rows[0].ends_with("******"));
+        assert!(!screen.is_secret());
+    }
+
+    #[test]
+    fn test_utf8_split_across_reads() {
+        let mut screen = Screen::new();
+        let text = "bob: héllo\n".as_bytes();
+        screen.push_output(&text[..7]);
+        screen.push_output(&text[7..]);
+
+        assert_eq!(screen.visible_rows(80, 1), vec!["bob: héllo"]);
+    }
+
+    #[test]
+    fn test_local_echo() {
+        assert_eq!(
+            local_echo("hi all", "10:00:00").unwrap(),
+            "[10:00:00] you: hi all"
+        );
+        assert_eq!(
+            local_echo("/me waves", "10:00:00").unwrap(),
+            "[10:00:00] * you waves"
+        );
+        assert_eq!(
+            local_echo("/msg bob see you", "10:00:00").unwrap(),
+            "[10:00:00] [you -> bob] see you"
+        );
+        assert_eq!(local_echo("/users", "10:00:00"), None);
+        assert_eq!(local_echo("/msg bob", "10:00:00"), None);
+        assert_eq!(local_echo("  ", "10:00:00"), None);
+    }
+
+    #[test]
+    fn test_wrap_and_scroll() {
+        let mut screen = Screen::new();
+        screen.push_output(b"abcdef\nline 2\nline 3\n");
+        assert_eq!(screen.visible_rows(4, 3), vec![" 2", "line", " 3"]);
+
+        screen.scroll_up(2);
+        assert_eq!(screen.visible_rows(4, 2), vec!["abcd", "ef"]);
+
+        // new output doesn't move a scrolled up view
+        screen.push_output(b"line 4\n");
+        assert_eq!(screen.visible_rows(4, 2), vec!["abcd", "ef"]);
+
+        screen.scroll_down(10);
+        assert_eq!(screen.visible_rows(80, 1), vec!["line 4"]);
+    }
+}
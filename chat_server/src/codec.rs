@@ -0,0 +1,198 @@
+use std::fmt;
+use std::io;
+use tokio_util::bytes::{Buf, BytesMut};
+use tokio_util::codec::Decoder;
+
+/// Why a line from a client was dropped. Displays as the error sent back to
+/// the client.
+#[derive(Debug, Clone, PartialEq)]
+pub enum LineError {
+    /// Over the limit in bytes, newline excluded.
+    TooLong {
+        max: usize,
+    },
+    InvalidUtf8,
+}
+
+impl fmt::Display for LineError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LineError::TooLong { max } => write!(f, "Message too long (max {} bytes)", max),
+            LineError::InvalidUtf8 => write!(f, "Message dropped, it isn't valid UTF-8"),
+        }
+    }
+}
+
+/// Splits client input into lines, like `LinesCodec`, but a bad line
+/// doesn't end the stream: it comes out as a `LineError` and decoding goes
+/// on with the next one.
+///
+/// Good lines have their control characters removed (tabs become spaces),
+/// so nobody can send terminal escapes or bells to the rest of the room.
+#[derive(Debug)]
+pub struct LineCodec {
+    max_len: usize,
+    /// Where to resume looking for a newline in the buffer.
+    next_index: usize,
+    /// Skipping the rest of a line already reported as too long.
+    discarding: bool,
+}
+
+impl LineCodec {
+    pub fn new(max_len: usize) -> Self {
+        LineCodec {
+            max_len,
+            next_index: 0,
+            discarding: false,
+        }
+    }
+
+    fn check(&self, line: &[u8]) -> Result<String, LineError> {
+        let line = line.strip_suffix(b"\r").unwrap_or(line);
+        if line.len() > self.max_len {
+            return Err(LineError::TooLong { max: self.max_len });
+        }
+
+        match std::str::from_utf8(line) {
+            Ok(line) => Ok(strip_control(line)),
+            Err(_) => Err(LineError::InvalidUtf8),
+        }
+    }
+}
+
+impl Decoder for LineCodec {
+    type Item = Result<String, LineError>;
+    type Error = io::Error;
+
+    fn decode(&mut self, buf: &mut BytesMut) -> Result<Option<Self::Item>, io::Error> {
+        loop {
+            let newline = buf[self.next_index..]
+                .iter()
+                .position(|b| *b == b'\n')
+                .map(|i| self.next_index + i);
+
+            match (newline, self.discarding) {
+                (Some(end), true) => {
+                    buf.advance(end + 1);
+                    self.next_index = 0;
+                    self.discarding = false;
+                }
+                (None, true) => {
+                    buf.clear();
+                    self.next_index = 0;
+                    return Ok(None);
+                }
+                (Some(end), false) => {
+                    let line = buf.split_to(end + 1);
+                    self.next_index = 0;
+                    return Ok(Some(self.check(&line[..end])));
+                }
+                // no need to wait for the end of the line to know it's
+                // too long, and no point in buffering it
+                (None, false) if buf.len() > self.max_len + 1 => {
+                    buf.clear();
+                    self.next_index = 0;
+                    self.discarding = true;
+                    return Ok(Some(Err(LineError::TooLong { max: self.max_len })));
+                }
+                (None, false) => {
+                    self.next_index = buf.len();
+                    return Ok(None);
+                }
+            }
+        }
+    }
+
+    fn decode_eof(&mut self, buf: &mut BytesMut) -> Result<Option<Self::Item>, io::Error> {
+        // a last line without a newline still counts
+        match self.decode(buf)? {
+            Some(line) => Ok(Some(line)),
+            None if buf.is_empty() || self.discarding => Ok(None),
+            None => {
+                let line = buf.split_to(buf.len());
+                self.next_index = 0;
+                Ok(Some(self.check(&line)))
+            }
+        }
+    }
+}
+
+/// Drops control characters, tabs become spaces.
+pub fn strip_control(text: &str) -> String {
+    text.chars()
+        .filter_map(|c| match c {
+            '\t' => Some(' '),
+            c if c.is_control() => None,
+            c => Some(c),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn decode_all(codec: &mut LineCodec, input: &[u8]) -> Vec<Result<String, LineError>> {
+        let mut buf = BytesMut::from(input);
+        let mut lines = Vec::new();
+        while let Some(line) = codec.decode(&mut buf).unwrap() {
+            lines.push(line);
+        }
+
+        lines
+    }
+
+    #[test]
+    fn test_lines() {
+        let mut codec = LineCodec::new(32);
+        assert_eq!(
+            decode_all(&mut codec, b"hi\r\nsecond\tline\x07\x1b[31m\n"),
+            vec![Ok("hi".to_string()), Ok("second line[31m".to_string())]
+        );
+
+        // the rest of the line arrives later
+        let mut codec = LineCodec::new(32);
+        let mut buf = BytesMut::from(&b"half"[..]);
+        assert_eq!(codec.decode(&mut buf).unwrap(), None);
+        buf.extend_from_slice(b" done\n");
+        assert_eq!(
+            codec.decode(&mut buf).unwrap(),
+            Some(Ok("half done".to_string()))
+        );
+
+        let mut buf = BytesMut::from(&b"no newline"[..]);
+        assert_eq!(
+            codec.decode_eof(&mut buf).unwrap(),
+            Some(Ok("no newline".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_bad_lines_are_skipped() {
+        let mut codec = LineCodec::new(8);
+        assert_eq!(
+            decode_all(&mut codec, b"12345678\n123456789\nbad \xff\xfe\nok\n"),
+            vec![
+                Ok("12345678".to_string()),
+                Err(LineError::TooLong { max: 8 }),
+                Err(LineError::InvalidUtf8),
+                Ok("ok".to_string()),
+            ]
+        );
+
+        // a long line is reported before its end arrives, and what comes
+        // after it is dropped too
+        let mut buf = BytesMut::from(&b"0123456789"[..]);
+        assert_eq!(
+            codec.decode(&mut buf).unwrap(),
+            Some(Err(LineError::TooLong { max: 8 }))
+        );
+        buf.extend_from_slice(b"more of it");
+        assert_eq!(codec.decode(&mut buf).unwrap(), None);
+        buf.extend_from_slice(b" end\nnext\n");
+        assert_eq!(
+            codec.decode(&mut buf).unwrap(),
+            Some(Ok("next".to_string()))
+        );
+    }
+}
@@ -0,0 +1,388 @@
+use crate::protocol::Format;
+use crate::room::is_valid_room_name;
+use chrono_tz::Tz;
+use std::fmt;
+use std::str::FromStr;
+
+/// Everything a logged in client can type that starts with `/`.
+///
+/// Adding a command takes a variant, an entry in `COMMANDS` (for `/help`
+/// and the usage errors), a line in `from_str` and its handling in the
+/// server's `dispatch`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Command {
+    Users,
+    Rooms,
+    Join(String),
+    Leave,
+    /// `/me waves` shows as `* alice waves`.
+    Me(String),
+    /// A private message, only `to` sees it.
+    Msg {
+        to: String,
+        body: String,
+    },
+    Whois(String),
+    /// The reason, empty if none was given.
+    Away(String),
+    Back,
+    /// How many messages, `None` for the default.
+    History(Option<usize>),
+    Format(Format),
+    /// `None` asks for the current timezone.
+    SetTimezone(Option<Tz>),
+    Register(String),
+    Login {
+        username: String,
+        password: String,
+    },
+    Help,
+    /// Answer to a heartbeat `ping`.
+    Pong,
+    Quit,
+    Kick(String),
+    Ban(String),
+    Unban(String),
+    Mute {
+        username: String,
+        minutes: u64,
+    },
+    Stats,
+}
+
+/// What `/help` says about a command.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CommandInfo {
+    pub name: &'static str,
+    pub usage: &'static str,
+    pub help: &'static str,
+    pub operator_only: bool,
+}
+
+const fn info(name: &'static str, usage: &'static str, help: &'static str) -> CommandInfo {
+    CommandInfo {
+        name,
+        usage,
+        help,
+        operator_only: false,
+    }
+}
+
+const fn operator(name: &'static str, usage: &'static str, help: &'static str) -> CommandInfo {
+    CommandInfo {
+        name,
+        usage,
+        help,
+        operator_only: true,
+    }
+}
+
+/// Every command, in the order `/help` lists them.
+pub const COMMANDS: &[CommandInfo] = &[
+    info("/users", "/users", "List the users in your room"),
+    info("/rooms", "/rooms", "List all rooms with their member count"),
+    info(
+        "/join",
+        "/join <room>",
+        "Move to another room, created on first join",
+    ),
+    info("/leave", "/leave", "Go back to the lobby"),
+    info("/me", "/me <action>", "Tell the room what you are doing"),
+    info(
+        "/msg",
+        "/msg <user> <message>",
+        "Send a message only that user sees",
+    ),
+    info(
+        "/whois",
+        "/whois <user>",
+        "Show who a user is and how long they've been here",
+    ),
+    info(
+        "/away",
+        "/away [reason]",
+        "Tell your room you're away from the keyboard",
+    ),
+    info("/back", "/back", "Tell your room you're back"),
+    info(
+        "/history",
+        "/history [n]",
+        "Show the last n messages of your room",
+    ),
+    info(
+        "/format",
+        "/format <text|json>",
+        "Switch how the server writes to you",
+    ),
+    info(
+        "/settimezone",
+        "/settimezone [zone]",
+        "Show timestamps in another timezone, e.g. Europe/Paris",
+    ),
+    info(
+        "/register",
+        "/register <password>",
+        "Reserve your username with a password",
+    ),
+    info(
+        "/login",
+        "/login <username> <password>",
+        "Switch to a registered username",
+    ),
+    info("/help", "/help", "Show this list"),
+    info("/pong", "/pong", "Answer a heartbeat ping from the server"),
+    info("/quit", "/quit", "Leave the chat"),
+    operator("/kick", "/kick <user>", "Disconnect a user"),
+    operator(
+        "/ban",
+        "/ban <user|ip>",
+        "Ban a username or IP address and disconnect it",
+    ),
+    operator("/unban", "/unban <user|ip>", "Lift a ban"),
+    operator(
+        "/mute",
+        "/mute <user> <minutes>",
+        "Stop a user from talking for a while",
+    ),
+    operator("/stats", "/stats", "Show server statistics"),
+];
+
+/// Why a line starting with `/` isn't a valid command. Displays as the
+/// error shown to the client.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ParseError {
+    Unknown(String),
+    /// A known command with the wrong arguments, carries its usage.
+    Usage(&'static str),
+    /// Arguments of the right shape with a bad value.
+    Invalid(String),
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseError::Unknown(name) => write!(f, "Unknown command: {} (see /help)", name),
+            ParseError::Usage(usage) => write!(f, "Usage: {}", usage),
+            ParseError::Invalid(reason) => write!(f, "{}", reason),
+        }
+    }
+}
+
+impl Command {
+    /// Whether only operators may run the command.
+    pub fn is_operator_only(&self) -> bool {
+        matches!(
+            self,
+            Command::Kick(_)
+                | Command::Ban(_)
+                | Command::Unban(_)
+                | Command::Mute { .. }
+                | Command::Stats
+        )
+    }
+}
+
+impl FromStr for Command {
+    type Err = ParseError;
+
+    fn from_str(line: &str) -> Result<Self, Self::Err> {
+        let line = line.trim();
+        let (name, rest) = match line.split_once(char::is_whitespace) {
+            Some((name, rest)) => (name, rest.trim()),
+            None => (line, ""),
+        };
+        let args: Vec<&str> = rest.split_whitespace().collect();
+
+        let Some(info) = COMMANDS.iter().find(|c| c.name == name) else {
+            return Err(ParseError::Unknown(name.to_string()));
+        };
+        let usage = ParseError::Usage(info.usage);
+
+        let command = match (name, args.as_slice()) {
+            ("/users", []) => Command::Users,
+            ("/rooms", []) => Command::Rooms,
+            ("/join", [room]) => {
+                if !is_valid_room_name(room) {
+                    return Err(ParseError::Invalid(
+                        "Room names use letters, digits, '-' and '_' (up to 32)".to_string(),
+                    ));
+                }
+                Command::Join(room.to_string())
+            }
+            ("/leave", []) => Command::Leave,
+            ("/me", [_, ..]) => Command::Me(rest.to_string()),
+            ("/msg", [to, _, ..]) => Command::Msg {
+                to: to.to_string(),
+                body: rest[to.len()..].trim().to_string(),
+            },
+            ("/whois", [username]) => Command::Whois(username.to_string()),
+            ("/away", _) => Command::Away(rest.to_string()),
+            ("/back", []) => Command::Back,
+            ("/history", []) => Command::History(None),
+            ("/history", [n]) => match n.parse::<usize>() {
+                Ok(n) if n > 0 => Command::History(Some(n)),
+                _ => return Err(usage),
+            },
+            ("/format", [format]) => Command::Format(format.parse().map_err(|()| usage)?),
+            ("/settimezone", []) => Command::SetTimezone(None),
+            ("/settimezone", [zone]) => match zone.parse::<Tz>() {
+                Ok(timezone) => Command::SetTimezone(Some(timezone)),
+                Err(_) => {
+                    return Err(ParseError::Invalid(format!(
+                        "Unknown timezone '{}', use a name like Europe/London or UTC",
+                        zone
+                    )));
+                }
+            },
+            // the password rules are checked when registering
+            ("/register", _) => Command::Register(rest.to_string()),
+            ("/login", [username, password]) => Command::Login {
+                username: username.to_string(),
+                password: password.to_string(),
+            },
+            ("/help", []) => Command::Help,
+            ("/pong", []) => Command::Pong,
+            ("/quit", []) => Command::Quit,
+            ("/kick", [username]) => Command::Kick(username.to_string()),
+            ("/ban", [target]) => Command::Ban(target.to_string()),
+            ("/unban", [target]) => Command::Unban(target.to_string()),
+            ("/mute", [username, minutes]) => match minutes.parse::<u64>() {
+                Ok(minutes) if minutes > 0 => Command::Mute {
+                    username: username.to_string(),
+                    minutes,
+                },
+                _ => return Err(usage),
+            },
+            ("/stats", []) => Command::Stats,
+            _ => return Err(usage),
+        };
+
+        Ok(command)
+    }
+}
+
+/// The `/help` text, operator commands are only listed for operators.
+pub fn help(is_operator: bool) -> String {
+    let mut text = String::from("Commands:");
+    for command in COMMANDS.iter().filter(|c| is_operator || !c.operator_only) {
+        text.push_str(&format!("\n  {:<30} {}", command.usage, command.help));
+    }
+
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_commands() {
+        assert_eq!("/users".parse(), Ok(Command::Users));
+        assert_eq!(
+            "/me  waves at everyone ".parse(),
+            Ok(Command::Me("waves at everyone".to_string()))
+        );
+        assert_eq!(
+            "/msg bob  see you  at 5".parse(),
+            Ok(Command::Msg {
+                to: "bob".to_string(),
+                body: "see you  at 5".to_string()
+            })
+        );
+        assert_eq!("/away".parse(), Ok(Command::Away(String::new())));
+        assert_eq!(
+            "/away  back in 5 ".parse(),
+            Ok(Command::Away("back in 5".to_string()))
+        );
+        assert_eq!("/history 5".parse(), Ok(Command::History(Some(5))));
+        assert_eq!("/format JSON".parse(), Ok(Command::Format(Format::Json)));
+        assert_eq!(
+            "/mute bob 10".parse(),
+            Ok(Command::Mute {
+                username: "bob".to_string(),
+                minutes: 10
+            })
+        );
+        assert_eq!(
+            "/settimezone Europe/Paris".parse(),
+            Ok(Command::SetTimezone(Some(Tz::Europe__Paris)))
+        );
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert_eq!(
+            "/dance".parse::<Command>(),
+            Err(ParseError::Unknown("/dance".to_string()))
+        );
+        assert_eq!(
+            "/join".parse::<Command>(),
+            Err(ParseError::Usage("/join <room>"))
+        );
+        assert_eq!(
+            "/history -1".parse::<Command>().unwrap_err().to_string(),
+            "Usage: /history [n]"
+        );
+        assert!(matches!(
+            "/join two words".parse::<Command>(),
+            Err(ParseError::Usage(_))
+        ));
+        assert!(matches!(
+            "/join no!".parse::<Command>(),
+            Err(ParseError::Invalid(_))
+        ));
+        assert!(matches!(
+            "/me".parse::<Command>(),
+            Err(ParseError::Usage(_))
+        ));
+    }
+
+    #[test]
+    fn test_every_command_is_parsed() {
+        // a command missing from `from_str` would always answer with its usage
+        let examples = [
+            "/users",
+            "/rooms",
+            "/join rust",
+            "/leave",
+            "/me waves",
+            "/msg bob hi",
+            "/whois bob",
+            "/away lunch",
+            "/back",
+            "/history",
+            "/format text",
+            "/settimezone",
+            "/register hunter22",
+            "/login bob hunter22",
+            "/help",
+            "/pong",
+            "/quit",
+            "/kick bob",
+            "/ban bob",
+            "/unban bob",
+            "/mute bob 1",
+            "/stats",
+        ];
+        assert_eq!(examples.len(), COMMANDS.len());
+
+        for (example, info) in examples.iter().zip(COMMANDS) {
+            assert!(example.starts_with(info.name));
+            let command: Command = example.parse().unwrap();
+            assert_eq!(
+                command.is_operator_only(),
+                info.operator_only,
+                "{}",
+                example
+            );
+        }
+    }
+
+    #[test]
+    fn test_help() {
+        let help_text = help(false);
+        assert!(help_text.contains("/me <action>"));
+        assert!(!help_text.contains("/kick"));
+        assert!(help(true).contains("/kick <user>"));
+    }
+}
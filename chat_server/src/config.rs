@@ -0,0 +1,250 @@
+use crate::cli::Cli;
+use crate::error::ChatError;
+use chrono_tz::Tz;
+use serde::Deserialize;
+use std::net::{IpAddr, Ipv4Addr};
+use std::path::{Path, PathBuf};
+
+/// Server settings, built from the defaults, then the TOML config file (if
+/// any), then the command line flags.
+///
+/// ```toml
+/// bind = "0.0.0.0"
+/// port = 9000
+/// metrics_port = 9090
+/// max_clients = 50
+/// timezone = "Europe/Berlin"
+/// operators = ["alice"]
+/// operator_key = "change-me"
+/// bots = ["dice"]
+/// log_file = "chat.log"
+///
+/// [tls]
+/// cert = "cert.pem"
+/// key = "key.pem"
+/// ```
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub bind: IpAddr,
+    pub port: u16,
+    pub ws_port: u16,
+    /// Port of the HTTP metrics endpoint, `None` to not serve metrics.
+    pub metrics_port: Option<u16>,
+    pub max_clients: usize,
+    pub history_size: usize,
+    /// Messages replayed on join, 0 disables the replay.
+    pub replay_size: usize,
+    pub channel_capacity: usize,
+    /// Longest line a client may send, in bytes.
+    pub max_message_len: usize,
+    pub max_messages_per_sec: u32,
+    /// Seconds, 0 disables the idle timeout.
+    pub idle_timeout: u64,
+    /// Seconds between pings, 0 disables the heartbeat.
+    pub heartbeat_interval: u64,
+    /// Seconds.
+    pub shutdown_grace: u64,
+    /// Default timezone of the timestamps in text output.
+    pub timezone: Tz,
+    pub operators: Vec<String>,
+    /// Needed to `/register` one of the operator names, which can't be
+    /// claimed without it. Only read from the file, so it stays out of `ps`.
+    pub operator_key: Option<String>,
+    /// Built-in bots started with the server, see `bot::load`.
+    pub bots: Vec<String>,
+    /// JSON log file, on top of the logs on stderr.
+    pub log_file: Option<PathBuf>,
+    pub tls: Option<TlsConfig>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TlsConfig {
+    pub cert: PathBuf,
+    pub key: PathBuf,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            bind: IpAddr::V4(Ipv4Addr::LOCALHOST),
+            port: 8080,
+            ws_port: 8081,
+            metrics_port: None,
+            max_clients: 100,
+            history_size: 100,
+            replay_size: 20,
+            channel_capacity: 100,
+            max_message_len: 1024,
+            max_messages_per_sec: 5,
+            idle_timeout: 600,
+            heartbeat_interval: 30,
+            shutdown_grace: 10,
+            timezone: Tz::UTC,
+            operators: Vec::new(),
+            operator_key: None,
+            bots: Vec::new(),
+            log_file: None,
+            tls: None,
+        }
+    }
+}
+
+impl Config {
+    pub fn load(cli: &Cli) -> Result<Self, ChatError> {
+        let mut config = match &cli.config {
+            Some(path) => Config::from_file(path)?,
+            None => Config::default(),
+        };
+        config.apply_cli(cli);
+        config.validate()?;
+
+        Ok(config)
+    }
+
+    pub fn from_file(path: &Path) -> Result<Self, ChatError> {
+        let text = std::fs::read_to_string(path)
+            .map_err(|e| ChatError::Config(format!("can't read {}: {}", path.display(), e)))?;
+
+        Config::from_toml(&text)
+            .map_err(|e| ChatError::Config(format!("{}: {}", path.display(), e)))
+    }
+
+    pub fn from_toml(text: &str) -> Result<Self, toml::de::Error> {
+        toml::from_str(text)
+    }
+
+    fn apply_cli(&mut self, cli: &Cli) {
+        if let Some(bind) = cli.bind {
+            self.bind = bind;
+        }
+        if let Some(port) = cli.port {
+            self.port = port;
+        }
+        if let Some(ws_port) = cli.ws_port {
+            self.ws_port = ws_port;
+        }
+        if let Some(metrics_port) = cli.metrics_port {
+            self.metrics_port = Some(metrics_port);
+        }
+        if let Some(max_clients) = cli.max_clients {
+            self.max_clients = max_clients as usize;
+        }
+        if let Some(history_size) = cli.history_size {
+            self.history_size = history_size as usize;
+        }
+        if let Some(replay_size) = cli.replay_size {
+            self.replay_size = replay_size as usize;
+        }
+        if let Some(channel_capacity) = cli.channel_capacity {
+            self.channel_capacity = channel_capacity as usize;
+        }
+        if let Some(max_message_len) = cli.max_message_len {
+            self.max_message_len = max_message_len as usize;
+        }
+        if let Some(max_messages_per_sec) = cli.max_messages_per_sec {
+            self.max_messages_per_sec = max_messages_per_sec;
+        }
+        if let Some(idle_timeout) = cli.idle_timeout {
+            self.idle_timeout = idle_timeout;
+        }
+        if let Some(heartbeat_interval) = cli.heartbeat_interval {
+            self.heartbeat_interval = heartbeat_interval;
+        }
+        if let Some(shutdown_grace) = cli.shutdown_grace {
+            self.shutdown_grace = shutdown_grace;
+        }
+        if let Some(timezone) = cli.timezone {
+            self.timezone = timezone;
+        }
+        if !cli.operators.is_empty() {
+            self.operators = cli.operators.clone();
+        }
+        if !cli.bots.is_empty() {
+            self.bots = cli.bots.clone();
+        }
+        if let Some(log_file) = &cli.log_file {
+            self.log_file = Some(log_file.clone());
+        }
+        if let (true, Some(cert), Some(key)) = (cli.tls, &cli.cert, &cli.key) {
+            self.tls = Some(TlsConfig {
+                cert: cert.clone(),
+                key: key.clone(),
+            });
+        }
+    }
+
+    // clap already checks the flags, this catches bad values in the file
+    fn validate(&self) -> Result<(), ChatError> {
+        let positive = [
+            ("max_clients", self.max_clients),
+            ("history_size", self.history_size),
+            ("channel_capacity", self.channel_capacity),
+            ("max_message_len", self.max_message_len),
+            ("max_messages_per_sec", self.max_messages_per_sec as usize),
+        ];
+
+        match positive.iter().find(|(_, value)| *value == 0) {
+            Some((name, _)) => Err(ChatError::Config(format!("{} must be at least 1", name))),
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Parser;
+
+    #[test]
+    fn test_from_toml() {
+        let config = Config::from_toml(
+            r#"
+            bind = "0.0.0.0"
+            port = 9000
+            metrics_port = 9090
+            timezone = "America/New_York"
+            operators = ["alice"]
+            operator_key = "s3cret"
+
+            [tls]
+            cert = "cert.pem"
+            key = "key.pem"
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(config.bind, IpAddr::V4(Ipv4Addr::UNSPECIFIED));
+        assert_eq!(config.port, 9000);
+        assert_eq!(config.metrics_port, Some(9090));
+        assert_eq!(config.operators, vec!["alice"]);
+        assert_eq!(config.operator_key.as_deref(), Some("s3cret"));
+        assert_eq!(config.timezone, Tz::America__New_York);
+        assert_eq!(config.tls.unwrap().key, PathBuf::from("key.pem"));
+        // everything else keeps its default
+        assert_eq!(config.ws_port, 8081);
+        assert_eq!(config.max_clients, 100);
+
+        assert!(Config::from_toml("prot = 9000").is_err());
+    }
+
+    #[test]
+    fn test_cli_overrides_file() {
+        let mut config = Config::from_toml("port = 9000\nmax_clients = 10").unwrap();
+        let cli = Cli::parse_from(["chat_server", "--port", "7000", "--operator", "bob"]);
+        config.apply_cli(&cli);
+
+        assert_eq!(config.port, 7000);
+        assert_eq!(config.max_clients, 10);
+        assert_eq!(config.operators, vec!["bob"]);
+    }
+
+    #[test]
+    fn test_validate() {
+        assert!(Config::default().validate().is_ok());
+
+        let config = Config::from_toml("channel_capacity = 0").unwrap();
+        assert!(config.validate().is_err());
+    }
+}
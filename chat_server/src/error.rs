@@ -6,10 +6,28 @@ pub enum ChatError {
     Io(#[from] std::io::Error),
     #[error("Tokio Join error: {0}")]
     JoinError(#[from] tokio::task::JoinError),
-    #[error("Send error: {0}")]
-    SendError(#[from] tokio::sync::broadcast::error::SendError<String>),
+    #[error("Registry task stopped")]
+    RegistryClosed,
+    #[error("Database error: {0}")]
+    Database(#[from] rusqlite::Error),
+    #[error("Password hashing error: {0}")]
+    PasswordHash(String),
+    #[error("Config error: {0}")]
+    Config(String),
+    #[error("TLS error: {0}")]
+    Tls(String),
+    #[error("WebSocket error: {0}")]
+    WebSocket(Box<tokio_tungstenite::tungstenite::Error>),
     // #[error("UTF-8 error: {0}")]
     // Utf8(#[from] std::string::FromUtf8Error),
     #[error("Unknown error")]
     Unknown,
 }
+
+// boxed because tungstenite's error would make every `Result` in the crate
+// several times bigger
+impl From<tokio_tungstenite::tungstenite::Error> for ChatError {
+    fn from(e: tokio_tungstenite::tungstenite::Error) -> Self {
+        ChatError::WebSocket(Box::new(e))
+    }
+}
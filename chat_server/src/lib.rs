@@ -1 +1,18 @@
+pub mod auth;
+pub mod bot;
+pub mod cli;
+pub mod client;
+pub mod codec;
+pub mod command;
+pub mod config;
 pub mod error;
+pub mod logging;
+pub mod metrics;
+pub mod protocol;
+pub mod rate_limit;
+pub mod registry;
+pub mod room;
+pub mod stats;
+pub mod storage;
+pub mod tls;
+pub mod ws;
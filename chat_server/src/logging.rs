@@ -0,0 +1,49 @@
+use crate::error::ChatError;
+use std::fs::OpenOptions;
+use std::path::Path;
+use tracing_appender::non_blocking::WorkerGuard;
+use tracing_subscriber::EnvFilter;
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+
+/// Level used when `RUST_LOG` isn't set.
+const DEFAULT_FILTER: &str = "info";
+
+/// Sets up logging to stderr, filtered by `RUST_LOG` (e.g.
+/// `RUST_LOG=chat_server=debug`), and when `log_file` is given also
+/// appends every event as one JSON object per line to that file.
+///
+/// The returned guard flushes the file when dropped, so keep it alive until
+/// the server stops.
+pub fn init(log_file: Option<&Path>) -> Result<Option<WorkerGuard>, ChatError> {
+    let filter =
+        EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new(DEFAULT_FILTER));
+
+    let (file_layer, guard) = match log_file {
+        Some(path) => {
+            let file = OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(path)
+                .map_err(|e| ChatError::Config(format!("can't open {}: {}", path.display(), e)))?;
+            let (writer, guard) = tracing_appender::non_blocking(file);
+            let layer = tracing_subscriber::fmt::layer()
+                .json()
+                .with_current_span(true)
+                .with_span_list(false)
+                .with_writer(writer);
+
+            (Some(layer), Some(guard))
+        }
+        None => (None, None),
+    };
+
+    tracing_subscriber::registry()
+        .with(filter)
+        .with(tracing_subscriber::fmt::layer().with_writer(std::io::stderr))
+        .with(file_layer)
+        .try_init()
+        .map_err(|e| ChatError::Config(format!("can't set up logging: {}", e)))?;
+
+    Ok(guard)
+}
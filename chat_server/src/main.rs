@@ -1,148 +1,1269 @@
+use chat_server::auth::{self, MIN_PASSWORD_LEN};
+use chat_server::cli::Cli;
+use chat_server::codec::LineCodec;
+use chat_server::command::{self, Command};
+use chat_server::config::Config;
 use chat_server::error::ChatError;
-use std::collections::HashMap;
-use std::net::SocketAddr;
+use chat_server::metrics::{self, Metered};
+use chat_server::protocol::{
+    Format, HistoryEntry, MAX_MISSED_PONGS, Output, RoomSummary, ServerMessage, Status,
+};
+use chat_server::rate_limit::{RateLimiter, Verdict};
+use chat_server::registry::{Joined, Registry, Target};
+use chat_server::room::{ChatState, DEFAULT_ROOM, same_name};
+use chat_server::stats::{Stats, format_duration};
+use chat_server::storage::{Storage, StoredMessage};
+use chat_server::{bot, logging, tls, ws};
+use chrono_tz::Tz;
+use clap::Parser;
+use futures_util::StreamExt;
+use std::net::{IpAddr, SocketAddr};
 use std::result::Result;
-use std::sync::{Arc, Mutex};
-use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncRead, AsyncWrite, AsyncWriteExt};
 use tokio::net::{TcpListener, TcpStream};
-use tokio::sync::broadcast::{self, Receiver, Sender};
+#[cfg(unix)]
+use tokio::signal::unix::{SignalKind, signal};
+use tokio::time;
+use tokio_rustls::TlsAcceptor;
+use tokio_util::codec::FramedRead;
+use tokio_util::sync::CancellationToken;
+use tokio_util::task::TaskTracker;
+use tracing::{Instrument, debug, error, info, info_span, warn};
+
+const DB_PATH: &str = "chat.db";
+const DEFAULT_HISTORY: usize = 20;
+const MAX_LOGIN_ATTEMPTS: usize = 3;
+/// How long clients still get once the grace period has run out.
+const FORCE_CLOSE_TIMEOUT: Duration = Duration::from_secs(5);
+/// Time a client turned away for a full server gets to read why.
+const REJECT_TIMEOUT: Duration = Duration::from_secs(5);
+/// How long before the idle timeout clients are warned.
+const IDLE_WARNING: Duration = Duration::from_secs(60);
 
 #[tokio::main]
 async fn main() -> Result<(), ChatError> {
-    let users = Arc::new(Mutex::new(HashMap::<SocketAddr, String>::new()));
-    let listener = TcpListener::bind("127.0.0.1:8080").await?;
-    println!("✈️ server listening on 127.0.0.1:8080");
-
-    let (tx, _rx) = broadcast::channel::<String>(100);
+    let config = Config::load(&Cli::parse())?;
+    // flushes the log file when main returns
+    let _log_guard = logging::init(config.log_file.as_deref())?;
 
-    loop {
-        let (stream, address) = listener.accept().await?;
-        println!("New connection from: {}", address);
+    let acceptor = match &config.tls {
+        Some(tls) => Some(tls::load_acceptor(&tls.cert, &tls.key)?),
+        None => None,
+    };
 
-        let tx = tx.clone();
-        let rx = tx.subscribe();
-        let users = users.clone();
+    let mut state = ChatState::new(config.channel_capacity);
+    state.set_operators(&config.operators);
+    state.set_replay_size(config.replay_size);
+    let bots = config
+        .bots
+        .iter()
+        .map(|name| bot::load(name))
+        .collect::<Result<Vec<_>, _>>()?;
+    for bot in &bots {
+        state.reserve_name(bot.name());
+    }
 
+    let stats = Stats::new(config.max_clients);
+    let registry = Registry::spawn(state, stats.clone());
+    if !bots.is_empty() {
+        info!("🤖 bots: {}", config.bots.join(", "));
+        // watching before any client connects, so the bots miss nothing
+        let messages = registry.watch().await?;
+        let registry = registry.clone();
         tokio::spawn(async move {
-            if let Err(e) = handle_client(stream, address, tx, rx, users).await {
-                eprintln!("Error handling client {}: {}", address, e);
+            if let Err(e) = bot::run(bots, messages, registry).await {
+                error!("Bots stopped: {}", e);
             }
         });
     }
-}
+    let storage = Storage::open(DB_PATH)?;
+    let tls_note = if acceptor.is_some() { " (TLS)" } else { "" };
 
-async fn handle_client(
-    mut stream: TcpStream,
-    address: SocketAddr,
-    tx: Sender<String>,
-    mut rx: Receiver<String>,
-    users: Arc<Mutex<HashMap<SocketAddr, String>>>,
-) -> Result<(), ChatError> {
-    let mut buf = vec![0; 1024];
-    let mut username = String::new();
+    let address = SocketAddr::new(config.bind, config.port);
+    let listener = TcpListener::bind(address).await?;
+    info!("✈️ server listening on {}{}", address, tls_note);
 
-    while username.trim().is_empty() {
-        stream.write_all(b"Enter your Username: ").await?;
+    let ws_address = SocketAddr::new(config.bind, config.ws_port);
+    let ws_listener = TcpListener::bind(ws_address).await?;
+    info!("🌐 websocket listening on {}{}", ws_address, tls_note);
 
-        match stream.read(&mut buf).await {
-            Ok(0) => {
-                println!("Connection closed by client: {}", address);
-                return Ok(());
-            }
-            Ok(n) => {
-                username = String::from_utf8_lossy(&buf[..n]).trim().to_string();
+    let metrics_listener = match config.metrics_port {
+        Some(port) => {
+            let metrics_address = SocketAddr::new(config.bind, port);
+            let listener = TcpListener::bind(metrics_address).await?;
+            info!("📈 metrics at http://{}/metrics", metrics_address);
+            Some(listener)
+        }
+        None => None,
+    };
+
+    let ctx = ServerContext {
+        registry: registry.clone(),
+        storage: storage.clone(),
+        config: ClientConfig {
+            max_message_len: config.max_message_len,
+            max_messages_per_sec: config.max_messages_per_sec as usize,
+            idle_timeout: (config.idle_timeout > 0)
+                .then(|| Duration::from_secs(config.idle_timeout)),
+            heartbeat: (config.heartbeat_interval > 0)
+                .then(|| Duration::from_secs(config.heartbeat_interval)),
+            history_size: config.history_size,
+            timezone: config.timezone,
+        },
+        operator_key: config.operator_key.as_deref().map(Arc::from),
+        stats,
+        disconnect: CancellationToken::new(),
+    };
+    let stop_accepting = CancellationToken::new();
+    let clients = TaskTracker::new();
+
+    // both transports feed the same rooms, users and history
+    let accepting = async {
+        tokio::try_join!(
+            accept_loop(
+                listener,
+                Transport::Tcp,
+                acceptor.clone(),
+                ctx.clone(),
+                &clients,
+                &stop_accepting
+            ),
+            accept_loop(
+                ws_listener,
+                Transport::WebSocket,
+                acceptor,
+                ctx.clone(),
+                &clients,
+                &stop_accepting
+            ),
+            async {
+                match metrics_listener {
+                    Some(listener) => {
+                        metrics::serve(listener, ctx.stats.clone(), &stop_accepting).await
+                    }
+                    None => Ok(()),
+                }
+            },
+        )
+    };
+
+    tokio::select! {
+        result = accepting => {
+            result?;
+        }
+        _ = shutdown_signal() => {}
+    }
+
+    // stop taking new clients, then give the connected ones some time to
+    // say goodbye before closing their connections ourselves
+    stop_accepting.cancel();
+    clients.close();
+
+    let grace = Duration::from_secs(config.shutdown_grace);
+    info!(
+        "Shutting down, {} client(s) connected, waiting up to {}s",
+        clients.len(),
+        grace.as_secs()
+    );
+    registry
+        .route_all(ServerMessage::notice(format!(
+            "Server is shutting down in {} seconds",
+            grace.as_secs()
+        )))
+        .await?;
+
+    if tokio::time::timeout(grace, clients.wait()).await.is_err() {
+        warn!("Grace period over, disconnecting remaining clients");
+        ctx.disconnect.cancel();
+
+        if tokio::time::timeout(FORCE_CLOSE_TIMEOUT, clients.wait())
+            .await
+            .is_err()
+        {
+            error!("{} client task(s) did not stop in time", clients.len());
+        }
+    }
+
+    // every client task has finished its writes by now
+    drop(ctx);
+    storage.close()?;
+    info!("Server stopped");
+
+    Ok(())
+}
+
+/// Resolves on Ctrl-C, or SIGTERM on unix.
+async fn shutdown_signal() {
+    let ctrl_c = async {
+        if let Err(e) = tokio::signal::ctrl_c().await {
+            error!("Error listening for Ctrl-C: {}", e);
+            std::future::pending::<()>().await;
+        }
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        match signal(SignalKind::terminate()) {
+            Ok(mut sigterm) => {
+                sigterm.recv().await;
             }
             Err(e) => {
-                eprintln!("Error reading username from {}: {}", address, e);
-                return Err(ChatError::Io(e));
+                error!("Error listening for SIGTERM: {}", e);
+                std::future::pending::<()>().await;
             }
+        }
+    };
+
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => {},
+        _ = terminate => {},
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Transport {
+    Tcp,
+    WebSocket,
+}
+
+/// Per-connection settings taken from the command line.
+#[derive(Debug, Clone, Copy)]
+struct ClientConfig {
+    /// Longest accepted line in bytes.
+    max_message_len: usize,
+    max_messages_per_sec: usize,
+    /// `None` keeps idle clients forever.
+    idle_timeout: Option<Duration>,
+    /// How often JSON clients are pinged, `None` to never ping them.
+    heartbeat: Option<Duration>,
+    /// Most messages `/history` shows.
+    history_size: usize,
+    /// Until the client picks its own with `/settimezone`.
+    timezone: Tz,
+}
+
+/// Everything a client task needs besides its socket.
+#[derive(Clone)]
+struct ServerContext {
+    registry: Registry,
+    storage: Storage,
+    config: ClientConfig,
+    /// Needed to register an operator name, see `register`.
+    operator_key: Option<Arc<str>>,
+    /// Also holds the connection limit.
+    stats: Arc<Stats>,
+    /// Cancelled when the shutdown grace period is over.
+    disconnect: CancellationToken,
+}
+
+async fn accept_loop(
+    listener: TcpListener,
+    transport: Transport,
+    acceptor: Option<TlsAcceptor>,
+    ctx: ServerContext,
+    clients: &TaskTracker,
+    stop: &CancellationToken,
+) -> Result<(), ChatError> {
+    loop {
+        let (stream, address) = tokio::select! {
+            result = listener.accept() => result?,
+            _ = stop.cancelled() => return Ok(()),
+        };
+        // everything logged for this connection carries its address, and
+        // its username once logged in
+        let span = info_span!("client", %address, ?transport, user = tracing::field::Empty);
+        info!(parent: &span, "New connection");
+
+        let acceptor = acceptor.clone();
+
+        let Some(slot) = ctx.stats.try_connect() else {
+            warn!(parent: &span, "Server full, rejecting connection");
+            let max_clients = ctx.stats.max_clients();
+            clients.spawn(reject_full(stream, transport, acceptor, max_clients).instrument(span));
+            continue;
         };
+
+        let ctx = ctx.clone();
+        let stream = Metered::new(stream, ctx.stats.clone());
+
+        clients.spawn(
+            async move {
+                // frees the connection slot when the client is gone
+                let _slot = slot;
+
+                match ctx.storage.is_banned(&address.ip().to_string()).await {
+                    Ok(true) => {
+                        info!("Rejected banned address");
+                        return;
+                    }
+                    Ok(false) => {}
+                    Err(e) => error!("Error checking bans: {}", e),
+                }
+
+                // the handshake runs in the client task so a slow client can't
+                // hold up the accept loop
+                let result = match acceptor {
+                    Some(acceptor) => match acceptor.accept(stream).await {
+                        Ok(stream) => serve(stream, transport, address, ctx).await,
+                        Err(e) => Err(ChatError::Io(e)),
+                    },
+                    None => serve(stream, transport, address, ctx).await,
+                };
+
+                if let Err(e) = result {
+                    error!("Error handling client: {}", e);
+                }
+            }
+            .instrument(span),
+        );
     }
+}
 
-    {
-        let mut users_list = users.lock().unwrap();
-        users_list.insert(address, username.clone());
+/// Tells a client the server is full and hangs up. The TLS and WebSocket
+/// handshakes still happen so the client can actually read the message.
+async fn reject_full(
+    stream: TcpStream,
+    transport: Transport,
+    acceptor: Option<TlsAcceptor>,
+    max_clients: usize,
+) {
+    let text = format!(
+        "Server is full ({} clients), try again later\n",
+        max_clients
+    );
+
+    let result = time::timeout(REJECT_TIMEOUT, async {
+        match acceptor {
+            Some(acceptor) => {
+                let stream = acceptor.accept(stream).await?;
+                send_and_close(stream, transport, &text).await
+            }
+            None => send_and_close(stream, transport, &text).await,
+        }
+    })
+    .await;
+
+    match result {
+        Ok(Ok(())) => {}
+        Ok(Err(e)) => debug!("Error rejecting client: {}", e),
+        Err(_) => debug!("Rejected client did not finish the handshake in time"),
+    }
+}
+
+async fn send_and_close<S>(mut stream: S, transport: Transport, text: &str) -> Result<(), ChatError>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    match transport {
+        Transport::Tcp => {
+            stream.write_all(text.as_bytes()).await?;
+            stream.shutdown().await?;
+            Ok(())
+        }
+        Transport::WebSocket => ws::reject(stream, text.trim_end()).await,
     }
+}
+
+async fn serve<S>(
+    stream: S,
+    transport: Transport,
+    address: SocketAddr,
+    mut ctx: ServerContext,
+) -> Result<(), ChatError>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    match transport {
+        Transport::Tcp => handle_client(stream, address, ctx).await,
+        Transport::WebSocket => {
+            // the client task talks to one end of an in-memory pipe and the
+            // bridge relays the other end to the websocket
+            let ws_stream = tokio_tungstenite::accept_async(stream).await?;
+            let (ws_end, client_end) = tokio::io::duplex(ws::PIPE_SIZE);
+            // websockets have ping frames, which browsers answer on their own
+            let heartbeat = ctx.config.heartbeat.take();
 
-    let join_msg = format!("*** {} has joined the chat ***\n", username);
-    tx.send(join_msg)?;
+            let (bridged, handled) = tokio::join!(
+                ws::bridge(ws_stream, ws_end, heartbeat),
+                handle_client(client_end, address, ctx)
+            );
+
+            handled.and(bridged)
+        }
+    }
+}
+
+async fn handle_client<S>(
+    stream: S,
+    address: SocketAddr,
+    ctx: ServerContext,
+) -> Result<(), ChatError>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    let ServerContext {
+        registry,
+        storage,
+        config,
+        disconnect,
+        ..
+    } = &ctx;
 
-    println!("User '{}' connected!", username);
+    // one message per line, however the bytes were split into packets
+    let (reader, mut writer) = tokio::io::split(stream);
+    let mut lines = FramedRead::new(reader, LineCodec::new(config.max_message_len));
+
+    let login = tokio::select! {
+        result = login(&mut lines, &mut writer, storage, registry, address) => result?,
+        _ = idle_sleep(config.idle_timeout) => {
+            let _ = writer.write_all(b"\nDisconnected for inactivity\n").await;
+            None
+        },
+        _ = disconnect.cancelled() => None,
+    };
+    let Some((username, joined)) = login else {
+        info!("Connection closed during login");
+        return Ok(());
+    };
+
+    let mut session = Session {
+        address,
+        username,
+        room: DEFAULT_ROOM.to_string(),
+        output: Output::new(Format::default(), config.timezone),
+    };
+    let result = chat(&mut lines, &mut writer, &mut session, joined, &ctx).await;
+
+    // however the session ended, even on a write to a socket that is gone,
+    // the user leaves, or their name would stay taken. The registry tells
+    // the room, and only stops once every client is gone
+    registry.leave(address).await?;
+
+    result
+}
+
+/// The logged in part of a connection, until the client quits, goes away
+/// or is disconnected.
+async fn chat<R, W>(
+    lines: &mut FramedRead<R, LineCodec>,
+    writer: &mut W,
+    session: &mut Session,
+    joined: Joined,
+    ctx: &ServerContext,
+) -> Result<(), ChatError>
+where
+    R: AsyncRead + Unpin,
+    W: AsyncWrite + Unpin,
+{
+    let ServerContext {
+        registry,
+        config,
+        disconnect,
+        ..
+    } = ctx;
+    let Joined {
+        mut rx,
+        kick,
+        recent,
+    } = joined;
+    let mut limiter = RateLimiter::new(config.max_messages_per_sec);
+
+    // the first wake-up is the warning, the second one disconnects
+    let mut last_activity = time::Instant::now();
+    let mut idle_warned = false;
+    // pings sent since the client last sent anything
+    let mut missed_pongs = 0;
+    let mut next_ping = time::Instant::now() + config.heartbeat.unwrap_or_default();
+
+    // what was said before they came in
+    if let Some(catch_up) = replay(&session.room, recent) {
+        send(writer, session.output, &catch_up).await?;
+    }
+    let join_msg =
+        ServerMessage::notice(format!("{} has joined #{}", session.username, session.room));
+    registry.route(&session.room, join_msg).await?;
+
+    tracing::Span::current().record("user", session.username.as_str());
+    info!("User '{}' connected!", session.username);
 
     loop {
         tokio::select! {
-            result = stream.read(&mut buf) => {
+            result = lines.next() => {
+                // any line shows the connection is alive, but a pong alone
+                // doesn't mean the user is there
+                missed_pongs = 0;
+                if !matches!(&result, Some(Ok(Ok(line))) if line.trim() == "/pong") {
+                    last_activity = time::Instant::now();
+                    idle_warned = false;
+                }
+
                 match result {
-                    Ok(0) => {
-                        println!("Connection closed by client: {}", username);
+                    None => {
+                        info!("Connection closed by client");
                         break;
                     },
-                    Ok(n) => {
-                        let message = String::from_utf8_lossy(&buf[..n]).trim().to_string();
-                        if message.starts_with("/") {
-                            match message.as_str() {
-                                "/users" => {
-                                    let response = {
-                                        let users_list = users.lock().unwrap();
-                                        let usernames: Vec<&str> = users_list.values().map(|x| x.as_str()).collect();
-                                        format!("Connected users: {}\n", usernames.join(", "))
-                                    };
-                                    stream.write_all(response.as_bytes()).await?;
-                                },
-                                "/quit" => {
-                                    stream.write_all(b"Goodbye!\n").await?;
-                                    break;
-                                },
-                                _ => {
-                                    let response = format!("Unknown command: {}\n", message);
-                                    stream.write_all(response.as_bytes()).await?;
-                                }
+                    // too long or not UTF-8, only the sender hears about it
+                    Some(Ok(Err(e))) => {
+                        send(writer, session.output, &ServerMessage::error(e.to_string())).await?;
+                    },
+                    // blank, or nothing left once the control characters are gone
+                    Some(Ok(Ok(line))) if line.trim().is_empty() => {},
+                    Some(Ok(Ok(line))) => {
+                        match limiter.check(Instant::now()) {
+                            Verdict::Allow => {},
+                            Verdict::Warn => {
+                                let reply = ServerMessage::error(format!("Slow down! More than {} messages per second will get you muted", config.max_messages_per_sec));
+                                send(writer, session.output, &reply).await?;
+                                continue;
+                            },
+                            Verdict::Muted(remaining) => {
+                                let reply = ServerMessage::error(format!("You are sending too fast, muted for {}s", remaining.as_secs().max(1)));
+                                send(writer, session.output, &reply).await?;
+                                continue;
+                            },
+                            Verdict::Disconnect => {
+                                warn!("Disconnecting for flooding");
+                                send(writer, session.output, &ServerMessage::error("Disconnected for flooding")).await?;
+                                break;
+                            },
+                        }
+
+                        let message = line.trim();
+                        let outcome = if message.starts_with('/') {
+                            match message.parse::<Command>() {
+                                Ok(command) => dispatch(command, session, ctx).await?,
+                                Err(e) => Outcome::Reply(Some(ServerMessage::error(e.to_string()))),
                             }
                         } else {
-                            println!("Received message: {}", message);
+                            let chat = ServerMessage::chat(&session.username, &session.room, message);
+                            Outcome::Reply(say(session, ctx, chat).await?)
+                        };
 
-                            let formatted_message = format!("{}: {}\n", username, message);
-                            if let Err(_) = tx.send(formatted_message) {
+                        match outcome {
+                            Outcome::Reply(Some(reply)) => send(writer, session.output, &reply).await?,
+                            Outcome::Reply(None) => {},
+                            Outcome::Switched(recent) => {
+                                if let Some(catch_up) = replay(&session.room, recent) {
+                                    send(writer, session.output, &catch_up).await?;
+                                }
+                            },
+                            Outcome::Quit => {
+                                send(writer, session.output, &ServerMessage::info("Goodbye!")).await?;
                                 break;
-                            }
+                            },
                         }
                     }
-                    Err(e) => {
-                        eprintln!("Error reading from {}: {}", username, e);
+                    Some(Err(e)) => {
+                        error!("Error reading from client: {}", e);
                         break;
                     }
                 }
             }
 
+            _ = time::sleep_until(idle_wake_up(last_activity, config.idle_timeout, idle_warned)), if config.idle_timeout.is_some() => {
+                if idle_warned {
+                    info!("Disconnecting idle user");
+                    let _ = send(writer, session.output, &ServerMessage::error("Disconnected for inactivity")).await;
+                    break;
+                }
+
+                idle_warned = true;
+                let warning = ServerMessage::error(format!("You have been idle for a while, you will be disconnected in {} seconds", idle_warning_lead(config.idle_timeout.unwrap_or_default()).as_secs()));
+                send(writer, session.output, &warning).await?;
+            }
+
+            _ = time::sleep_until(next_ping), if config.heartbeat.is_some() && session.output.format == Format::Json => {
+                if missed_pongs >= MAX_MISSED_PONGS {
+                    info!("No pong for {} pings, dropping the connection", missed_pongs);
+                    break;
+                }
+
+                missed_pongs += 1;
+                next_ping = time::Instant::now() + config.heartbeat.unwrap_or_default();
+                send(writer, session.output, &ServerMessage::ping()).await?;
+            }
+
+            _ = disconnect.cancelled() => {
+                let _ = send(writer, session.output, &ServerMessage::info("Server is shutting down, goodbye!")).await;
+                break;
+            }
+
+            _ = kick.notified() => {
+                info!("Removed by an operator");
+                let reply = ServerMessage::error("You have been disconnected by an operator");
+                let _ = send(writer, session.output, &reply).await;
+                break;
+            }
+
             result = rx.recv() => {
                 match result {
-                    Ok(msg) => {
-                        if let Err(e) = stream.write_all(msg.as_bytes()).await {
-                            eprintln!("Error writing message to {}: {}", username, e);
+                    Some(msg) => {
+                        if let Err(e) = send(writer, session.output, &msg).await {
+                            error!("Error writing message: {}", e);
                             break;
                         }
                     },
-                    Err(broadcast::error::RecvError::Closed) => {
-                        println!("Broadcast channel closed");
-                        break;
-                    },
-                    Err(broadcast::error::RecvError::Lagged(_)) => {
-                        println!("Client {} lagged behind", username);
-                    }
+                    // the registry let go of the client, it was kicked
+                    None => break,
+                }
+            }
+
+        }
+    }
+
+    Ok(())
+}
+
+/// Who a logged in client is and how they want to be written to.
+struct Session {
+    address: SocketAddr,
+    username: String,
+    room: String,
+    output: Output,
+}
+
+/// What running a command means for the connection.
+enum Outcome {
+    /// Something to tell the client, if anything.
+    Reply(Option<ServerMessage>),
+    /// The client moved to another room, with its last messages.
+    Switched(Vec<HistoryEntry>),
+    Quit,
+}
+
+async fn dispatch(
+    command: Command,
+    session: &mut Session,
+    ctx: &ServerContext,
+) -> Result<Outcome, ChatError> {
+    let ServerContext {
+        registry,
+        storage,
+        config,
+        stats,
+        ..
+    } = ctx;
+
+    if command.is_operator_only() && !is_operator(registry, storage, &session.username).await {
+        return Ok(Outcome::Reply(Some(ServerMessage::error(
+            "Only operators can use this command",
+        ))));
+    }
+
+    let reply = match command {
+        Command::Users => {
+            let (users, away) = registry.users_in(&session.room).await?;
+            ServerMessage::users(&session.room, users, away)
+        }
+        Command::Rooms => {
+            let rooms = registry
+                .rooms()
+                .await?
+                .into_iter()
+                .map(|(name, users)| RoomSummary { name, users })
+                .collect();
+            ServerMessage::rooms(rooms)
+        }
+        Command::Join(target) => {
+            if target == session.room {
+                ServerMessage::error(format!("You are already in #{}", session.room))
+            } else {
+                let recent = switch_room(registry, session, &target).await?;
+                return Ok(Outcome::Switched(recent));
+            }
+        }
+        Command::Leave => {
+            if session.room == DEFAULT_ROOM {
+                ServerMessage::error("You are already in the lobby")
+            } else {
+                let recent = switch_room(registry, session, DEFAULT_ROOM).await?;
+                return Ok(Outcome::Switched(recent));
+            }
+        }
+        Command::Me(action) => {
+            let action = ServerMessage::action(&session.username, &session.room, &action);
+            return Ok(Outcome::Reply(say(session, ctx, action).await?));
+        }
+        Command::Msg { to, body } => {
+            if same_name(&to, &session.username) {
+                ServerMessage::error("You can't send a private message to yourself")
+            } else {
+                let private = ServerMessage::private(&session.username, &to, &body);
+                return Ok(Outcome::Reply(say(session, ctx, private).await?));
+            }
+        }
+        Command::Whois(name) => whois(ctx, &session.username, &name).await?,
+        // the room hears about it, the user included
+        Command::Away(reason) => {
+            // a reason would let muted users talk to the room
+            if !reason.is_empty()
+                && let Some(refused) = check_mute(registry, &session.username).await?
+            {
+                return Ok(Outcome::Reply(Some(refused)));
+            }
+            registry
+                .set_away(session.address, Some(reason.clone()))
+                .await?;
+            let presence = ServerMessage::presence(&session.username, Status::Away, &reason);
+            registry.route(&session.room, presence).await?;
+            return Ok(Outcome::Reply(None));
+        }
+        Command::Back => {
+            if registry.set_away(session.address, None).await?.is_none() {
+                ServerMessage::error("You aren't away")
+            } else {
+                let presence = ServerMessage::presence(&session.username, Status::Online, "");
+                registry.route(&session.room, presence).await?;
+                return Ok(Outcome::Reply(None));
+            }
+        }
+        Command::History(limit) => {
+            let limit = limit.unwrap_or(DEFAULT_HISTORY).min(config.history_size);
+            history_response(storage, &session.room, limit).await
+        }
+        Command::Format(format) => {
+            session.output.format = format;
+            ServerMessage::info(format!("Output format set to {}", format))
+        }
+        Command::SetTimezone(None) => ServerMessage::info(format!(
+            "Your timezone is {} (usage: /settimezone <zone>)",
+            session.output.timezone
+        )),
+        Command::SetTimezone(Some(timezone)) => {
+            session.output.timezone = timezone;
+            ServerMessage::info(format!("Timezone set to {}", timezone))
+        }
+        Command::Register(args) => {
+            let operator_name = registry.is_operator(&session.username).await?;
+            let key = ctx.operator_key.as_deref();
+            register(storage, &session.username, &args, operator_name, key).await
+        }
+        Command::Login { username, password } => {
+            if storage.is_banned(&username).await? {
+                ServerMessage::error("That username is banned")
+            } else if !check_password(storage, &username, &password).await? {
+                ServerMessage::error("Invalid username or password")
+            } else if !registry.rename(session.address, &username).await? {
+                ServerMessage::error(format!("Username '{}' is already in use", username))
+            } else {
+                let rename_msg = ServerMessage::notice(format!(
+                    "{} is now known as {}",
+                    session.username, username
+                ));
+                info!("Logged in as '{}'", username);
+                session.username = username;
+                tracing::Span::current().record("user", session.username.as_str());
+                registry.route(&session.room, rename_msg).await?;
+                return Ok(Outcome::Reply(None));
+            }
+        }
+        Command::Help => {
+            let operator = is_operator(registry, storage, &session.username).await;
+            ServerMessage::info(command::help(operator))
+        }
+        // the read loop already took it as a sign of life
+        Command::Pong => return Ok(Outcome::Reply(None)),
+        Command::Quit => return Ok(Outcome::Quit),
+        Command::Stats => ServerMessage::info(stats.snapshot().summary()),
+        Command::Kick(_) | Command::Ban(_) | Command::Unban(_) | Command::Mute { .. } => {
+            match moderate(registry, storage, &session.username, &command).await {
+                Ok(reply) => reply,
+                Err(e) => {
+                    error!("Error running {:?}: {}", command, e);
+                    ServerMessage::error("Could not run the command")
+                }
+            }
+        }
+    };
+
+    Ok(Outcome::Reply(Some(reply)))
+}
+
+/// Sends a chat message or `/me` action to the rest of the client's room,
+/// or a private message to its recipient. Plain chat is also saved for
+/// `/history`. Returns the error for a muted client or a missing recipient.
+async fn say(
+    session: &Session,
+    ctx: &ServerContext,
+    message: ServerMessage,
+) -> Result<Option<ServerMessage>, ChatError> {
+    let ServerContext {
+        registry, storage, ..
+    } = ctx;
+
+    if let Some(refused) = check_mute(registry, &session.username).await? {
+        return Ok(Some(refused));
+    }
+    ctx.stats.record_message();
+
+    if let ServerMessage::Chat { body, ts, .. } = &message {
+        debug!("Received message: {}", body);
+
+        let stored = StoredMessage {
+            sender: session.username.clone(),
+            room: session.room.clone(),
+            timestamp: *ts,
+            body: body.clone(),
+        };
+        if let Err(e) = storage.save_message(stored).await {
+            error!("Error saving message: {}", e);
+        }
+    }
+
+    // the sender's own client shows what it said, nothing comes back
+    if let ServerMessage::Private { to, .. } = &message {
+        let to = to.clone();
+        if !registry.send_to(&to, message).await? {
+            return Ok(Some(ServerMessage::error(format!(
+                "No user named '{}'",
+                to
+            ))));
+        }
+    } else {
+        registry
+            .route_from(session.address, &session.room, message)
+            .await?;
+    }
+
+    Ok(None)
+}
+
+/// The error for a muted user, `None` if they may talk.
+async fn check_mute(
+    registry: &Registry,
+    username: &str,
+) -> Result<Option<ServerMessage>, ChatError> {
+    let remaining = registry.mute_remaining(username).await?;
+
+    Ok(remaining.map(|remaining| {
+        ServerMessage::error(format!(
+            "You are muted for {} more minute(s)",
+            remaining.as_secs().div_ceil(60)
+        ))
+    }))
+}
+
+/// The `/whois` answer. Operators also see where the user connects from.
+async fn whois(
+    ctx: &ServerContext,
+    requester: &str,
+    name: &str,
+) -> Result<ServerMessage, ChatError> {
+    let ServerContext {
+        registry, storage, ..
+    } = ctx;
+
+    let Some((address, user)) = registry.whois(name).await? else {
+        return Ok(ServerMessage::error(format!("No user named '{}'", name)));
+    };
+
+    let mut text = format!(
+        "{} is in #{}, connected for {}",
+        user.name,
+        user.room,
+        format_duration(user.connected_at.elapsed())
+    );
+    match user.away.as_deref() {
+        Some("") => text.push_str(", away"),
+        Some(reason) => text.push_str(&format!(", away ({})", reason)),
+        None => {}
+    }
+    if storage.password_hash(&user.name).await?.is_some() {
+        text.push_str(", registered");
+    } else {
+        text.push_str(", not registered");
+    }
+    if is_operator(registry, storage, &user.name).await {
+        text.push_str(", operator");
+    }
+    if is_operator(registry, storage, requester).await {
+        text.push_str(&format!(", from {}", address.ip()));
+    }
+
+    Ok(ServerMessage::info(text))
+}
+
+/// When an idle client should next be woken up: a little before the
+/// timeout for the warning, then at the timeout itself.
+fn idle_wake_up(
+    last_activity: time::Instant,
+    timeout: Option<Duration>,
+    warned: bool,
+) -> time::Instant {
+    let Some(timeout) = timeout else {
+        return far_future();
+    };
+    let deadline = last_activity + timeout;
+
+    if warned {
+        deadline
+    } else {
+        deadline - idle_warning_lead(timeout)
+    }
+}
+
+/// `IDLE_WARNING`, or half the timeout for very short timeouts.
+fn idle_warning_lead(timeout: Duration) -> Duration {
+    IDLE_WARNING.min(timeout / 2)
+}
+
+fn far_future() -> time::Instant {
+    // roughly 30 years, what tokio itself uses for "never"
+    time::Instant::now() + Duration::from_secs(86400 * 365 * 30)
+}
+
+/// Sleeps for the idle timeout, or forever if there is none.
+async fn idle_sleep(timeout: Option<Duration>) {
+    match timeout {
+        Some(timeout) => time::sleep(timeout).await,
+        None => std::future::pending().await,
+    }
+}
+
+async fn send<W>(writer: &mut W, output: Output, message: &ServerMessage) -> std::io::Result<()>
+where
+    W: AsyncWrite + Unpin,
+{
+    writer.write_all(output.render(message).as_bytes()).await
+}
+
+/// Asks for a username until a usable one is given. Registered usernames
+/// also need their password. On success the user has joined the default
+/// room; `None` means the client went away (or ran out of password
+/// attempts, or is banned).
+async fn login<R, W>(
+    lines: &mut FramedRead<R, LineCodec>,
+    writer: &mut W,
+    storage: &Storage,
+    registry: &Registry,
+    address: SocketAddr,
+) -> Result<Option<(String, Joined)>, ChatError>
+where
+    R: AsyncRead + Unpin,
+    W: AsyncWrite + Unpin,
+{
+    'username: loop {
+        let Some(username) = prompt(lines, writer, "Enter your Username: ").await? else {
+            return Ok(None);
+        };
+
+        if username.is_empty() {
+            continue;
+        }
+
+        if storage.is_banned(&username).await? {
+            writer
+                .write_all(b"You are banned from this server\n")
+                .await?;
+            info!("Rejected banned user: {}", username);
+            return Ok(None);
+        }
+
+        // checked early so nobody types a password for a name in use, the
+        // final check happens atomically when joining
+        if registry.is_name_taken(address, &username).await? {
+            let text = format!("Username '{}' is already taken\n", username);
+            writer.write_all(text.as_bytes()).await?;
+            continue;
+        }
 
+        if let Some(hash) = storage.password_hash(&username).await? {
+            let mut attempts = 0;
+            loop {
+                let text = "This username is registered. Password (empty to pick another name): ";
+                let Some(password) = prompt(lines, writer, text).await? else {
+                    return Ok(None);
+                };
+
+                if password.is_empty() {
+                    continue 'username;
+                }
+
+                if auth::verify_password(&password, &hash).await? {
+                    break;
+                }
+
+                attempts += 1;
+                if attempts == MAX_LOGIN_ATTEMPTS {
+                    writer.write_all(b"Too many failed attempts\n").await?;
+                    return Ok(None);
                 }
+
+                writer.write_all(b"Wrong password\n").await?;
+            }
+        }
+
+        match registry.join(address, &username, DEFAULT_ROOM).await? {
+            Some(joined) => return Ok(Some((username, joined))),
+            None => {
+                let text = format!("Username '{}' is already taken\n", username);
+                writer.write_all(text.as_bytes()).await?;
             }
+        }
+    }
+}
+
+async fn prompt<R, W>(
+    lines: &mut FramedRead<R, LineCodec>,
+    writer: &mut W,
+    text: &str,
+) -> Result<Option<String>, ChatError>
+where
+    R: AsyncRead + Unpin,
+    W: AsyncWrite + Unpin,
+{
+    loop {
+        writer.write_all(text.as_bytes()).await?;
+
+        match lines.next().await.transpose()? {
+            Some(Ok(line)) => return Ok(Some(line.trim().to_string())),
+            Some(Err(e)) => writer.write_all(format!("{}\n", e).as_bytes()).await?,
+            None => return Ok(None),
+        }
+    }
+}
+
+/// Operator names also need the `operator_key` of the config, otherwise
+/// anyone could register one before its operator does and get the commands.
+async fn register(
+    storage: &Storage,
+    username: &str,
+    args: &str,
+    operator_name: bool,
+    operator_key: Option<&str>,
+) -> ServerMessage {
+    if operator_name && operator_key.is_none() {
+        return ServerMessage::error(format!("Username '{}' is reserved", username));
+    }
+
+    let args: Vec<&str> = args.split_whitespace().collect();
+    let (password, key) = match (args.as_slice(), operator_name) {
+        ([password], false) => (*password, None),
+        ([password, key], true) => (*password, Some(*key)),
+        ([_], true) => {
+            return ServerMessage::error(format!(
+                "'{}' is an operator name: /register <password> <operator key>",
+                username
+            ));
+        }
+        _ => ("", None),
+    };
+    if password.len() < MIN_PASSWORD_LEN {
+        return ServerMessage::error(format!(
+            "Usage: /register <password> (at least {} characters, no spaces)",
+            MIN_PASSWORD_LEN
+        ));
+    }
+    let key_matches = key
+        .zip(operator_key)
+        .is_some_and(|(key, expected)| auth::secrets_match(key, expected));
+    if operator_name && !key_matches {
+        warn!("Wrong operator key registering {}", username);
+        return ServerMessage::error("Wrong operator key");
+    }
+
+    let created = match auth::hash_password(password).await {
+        Ok(hash) => storage.create_account(username, &hash).await,
+        Err(e) => Err(e),
+    };
+
+    match created {
+        Ok(true) => {
+            ServerMessage::info(format!("Username '{}' is now registered to you", username))
+        }
+        Ok(false) => ServerMessage::error(format!("Username '{}' is already registered", username)),
+        Err(e) => {
+            error!("Error registering {}: {}", username, e);
+            ServerMessage::error("Could not register the username")
+        }
+    }
+}
+
+async fn check_password(
+    storage: &Storage,
+    username: &str,
+    password: &str,
+) -> Result<bool, ChatError> {
+    match storage.password_hash(username).await? {
+        Some(hash) => auth::verify_password(password, &hash).await,
+        None => Ok(false),
+    }
+}
 
+/// Operators have to be registered, otherwise anyone could claim the name.
+async fn is_operator(registry: &Registry, storage: &Storage, username: &str) -> bool {
+    if !registry.is_operator(username).await.unwrap_or(false) {
+        return false;
+    }
+
+    match storage.password_hash(username).await {
+        Ok(hash) => hash.is_some(),
+        Err(e) => {
+            error!("Error checking operator {}: {}", username, e);
+            false
         }
     }
+}
 
+/// Runs an operator command and returns the reply for the operator.
+async fn moderate(
+    registry: &Registry,
+    storage: &Storage,
+    operator: &str,
+    command: &Command,
+) -> Result<ServerMessage, ChatError> {
+    if let Command::Kick(target)
+    | Command::Ban(target)
+    | Command::Unban(target)
+    | Command::Mute {
+        username: target, ..
+    } = command
+        && same_name(target, operator)
     {
-        let mut users_list = users.lock().unwrap();
-        users_list.remove(&address);
+        return Ok(ServerMessage::error("You can't use that on yourself"));
     }
 
-    let leave_msg = format!("*** {} has left the chat ***\n", username);
-    let _ = tx.send(leave_msg);
+    let response = match command {
+        Command::Kick(target) => {
+            let target_name = Target::Name(target.to_string());
+            match registry.kick(target_name, operator, "kicked").await? {
+                0 => ServerMessage::error(format!("No user named '{}'", target)),
+                _ => ServerMessage::info(format!("Kicked {}", target)),
+            }
+        }
+        Command::Ban(target) => {
+            if !storage.add_ban(target, operator).await? {
+                return Ok(ServerMessage::error(format!(
+                    "{} is already banned",
+                    target
+                )));
+            }
 
-    Ok(())
+            let targets = match target.parse::<IpAddr>() {
+                Ok(ip) => Target::Ip(ip),
+                Err(_) => Target::Name(target.to_string()),
+            };
+            let removed = registry.kick(targets, operator, "banned").await?;
+            ServerMessage::info(format!(
+                "Banned {} ({} connection(s) closed)",
+                target, removed
+            ))
+        }
+        Command::Unban(target) => {
+            if storage.remove_ban(target).await? {
+                ServerMessage::info(format!("Unbanned {}", target))
+            } else {
+                ServerMessage::error(format!("{} is not banned", target))
+            }
+        }
+        Command::Mute {
+            username: target,
+            minutes,
+        } => {
+            let mute_msg = ServerMessage::notice(format!(
+                "{} was muted for {} minute(s) by {}",
+                target, minutes, operator
+            ));
+            registry
+                .mute(target, Duration::from_secs(minutes * 60), mute_msg)
+                .await?;
+
+            ServerMessage::info(format!("Muted {} for {} minute(s)", target, minutes))
+        }
+        _ => unreachable!("not a moderation command: {:?}", command),
+    };
+
+    Ok(response)
+}
+
+async fn history_response(storage: &Storage, room: &str, limit: usize) -> ServerMessage {
+    match storage.recent_messages(room, limit).await {
+        Ok(messages) => {
+            let entries = messages
+                .into_iter()
+                .map(|m| HistoryEntry {
+                    from: m.sender,
+                    body: m.body,
+                    ts: m.timestamp,
+                })
+                .collect();
+
+            ServerMessage::history(room, entries)
+        }
+        Err(e) => {
+            error!("Error loading history for #{}: {}", room, e);
+            ServerMessage::error("Could not load the message history")
+        }
+    }
+}
+
+async fn switch_room(
+    registry: &Registry,
+    session: &mut Session,
+    target: &str,
+) -> Result<Vec<HistoryEntry>, ChatError> {
+    let leave_msg =
+        ServerMessage::notice(format!("{} has left #{}", session.username, session.room));
+    registry.route(&session.room, leave_msg).await?;
+
+    let recent = registry.switch_room(session.address, target).await?;
+    session.room = target.to_string();
+
+    let join_msg =
+        ServerMessage::notice(format!("{} has joined #{}", session.username, session.room));
+    registry.route(&session.room, join_msg).await?;
+
+    Ok(recent)
+}
+
+/// The catch-up sent right after joining `room`, `None` when there is
+/// nothing to catch up on.
+fn replay(room: &str, recent: Vec<HistoryEntry>) -> Option<ServerMessage> {
+    (!recent.is_empty()).then(|| ServerMessage::history(room, recent))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_register_operator_name() {
+        let storage = Storage::open_in_memory().unwrap();
+        let register = |name, args, operator_name, key| {
+            let storage = storage.clone();
+            async move { register(&storage, name, args, operator_name, key).await }
+        };
+        let is_error = |reply: ServerMessage| matches!(reply, ServerMessage::Error { .. });
+
+        // without a key nobody can claim an operator name
+        assert!(is_error(register("alice", "hunter22", true, None).await));
+        assert!(is_error(
+            register("alice", "hunter22 key", true, None).await
+        ));
+        // with one, it has to be given
+        assert!(is_error(
+            register("alice", "hunter22", true, Some("key")).await
+        ));
+        assert!(is_error(
+            register("alice", "hunter22 nope", true, Some("key")).await
+        ));
+        assert!(storage.password_hash("alice").await.unwrap().is_none());
+        assert!(!is_error(
+            register("alice", "hunter22 key", true, Some("key")).await
+        ));
+        assert!(storage.password_hash("alice").await.unwrap().is_some());
+
+        // everyone else doesn't need it
+        assert!(!is_error(
+            register("bob", "hunter22", false, Some("key")).await
+        ));
+        assert!(is_error(
+            register("carol", "hunter22 key", false, Some("key")).await
+        ));
+    }
 }
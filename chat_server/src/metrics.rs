@@ -0,0 +1,246 @@
+use crate::error::ChatError;
+use crate::stats::{Stats, StatsSnapshot};
+use std::fmt::Write as _;
+use std::io;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, ReadBuf};
+use tokio::net::{TcpListener, TcpStream};
+use tokio_util::sync::CancellationToken;
+use tracing::{debug, error};
+
+/// Largest request head the metrics endpoint reads.
+const MAX_REQUEST_LEN: usize = 8192;
+/// Time a scraper gets to send its request.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// The counters in the Prometheus text format, served at `/metrics`.
+pub fn render(snapshot: &StatsSnapshot) -> String {
+    let metrics: [(&str, &str, &str, f64); 12] = [
+        (
+            "chat_connected_clients",
+            "gauge",
+            "Clients currently connected",
+            snapshot.connected as f64,
+        ),
+        (
+            "chat_max_clients",
+            "gauge",
+            "Connection limit",
+            snapshot.max_clients as f64,
+        ),
+        (
+            "chat_connections_accepted_total",
+            "counter",
+            "Connections let in since start",
+            snapshot.accepted as f64,
+        ),
+        (
+            "chat_connections_rejected_total",
+            "counter",
+            "Connections turned away because the server was full",
+            snapshot.rejected as f64,
+        ),
+        (
+            "chat_messages_total",
+            "counter",
+            "Chat messages and actions sent by clients",
+            snapshot.messages as f64,
+        ),
+        (
+            "chat_messages_per_second",
+            "gauge",
+            "Messages per second over the last minute",
+            snapshot.messages_per_sec,
+        ),
+        (
+            "chat_deliveries_total",
+            "counter",
+            "Messages handed to room members, one per receiver",
+            snapshot.deliveries as f64,
+        ),
+        (
+            "chat_lag_events_total",
+            "counter",
+            "Times a client's queue filled up and it started missing messages",
+            snapshot.lag_events as f64,
+        ),
+        (
+            "chat_lagged_messages_total",
+            "counter",
+            "Messages dropped for clients with a full queue",
+            snapshot.lagged_messages as f64,
+        ),
+        (
+            "chat_bytes_received_total",
+            "counter",
+            "Bytes read from client sockets",
+            snapshot.bytes_in as f64,
+        ),
+        (
+            "chat_bytes_sent_total",
+            "counter",
+            "Bytes written to client sockets",
+            snapshot.bytes_out as f64,
+        ),
+        (
+            "chat_uptime_seconds",
+            "gauge",
+            "Seconds since the server started",
+            snapshot.uptime.as_secs() as f64,
+        ),
+    ];
+
+    let mut text = String::new();
+    for (name, kind, help, value) in metrics {
+        let _ = writeln!(text, "# HELP {} {}", name, help);
+        let _ = writeln!(text, "# TYPE {} {}", name, kind);
+        let _ = writeln!(text, "{} {}", name, value);
+    }
+
+    text
+}
+
+/// Answers `GET /metrics` on `listener` until `stop` is cancelled. Every
+/// request gets its own connection, which is closed after the response.
+pub async fn serve(
+    listener: TcpListener,
+    stats: Arc<Stats>,
+    stop: &CancellationToken,
+) -> Result<(), ChatError> {
+    loop {
+        let (stream, address) = tokio::select! {
+            result = listener.accept() => result?,
+            _ = stop.cancelled() => return Ok(()),
+        };
+
+        let stats = stats.clone();
+        tokio::spawn(async move {
+            match tokio::time::timeout(REQUEST_TIMEOUT, respond(stream, &stats)).await {
+                Ok(Ok(())) => {}
+                Ok(Err(e)) => error!("Error serving metrics to {}: {}", address, e),
+                Err(_) => debug!("Metrics request from {} timed out", address),
+            }
+        });
+    }
+}
+
+async fn respond(mut stream: TcpStream, stats: &Stats) -> io::Result<()> {
+    let mut request = Vec::new();
+    let mut buf = [0; 1024];
+    while !request.windows(4).any(|w| w == b"\r\n\r\n") {
+        let n = stream.read(&mut buf).await?;
+        if n == 0 || request.len() + n > MAX_REQUEST_LEN {
+            return Ok(());
+        }
+        request.extend_from_slice(&buf[..n]);
+    }
+
+    let request_line = String::from_utf8_lossy(&request);
+    let mut parts = request_line.split_whitespace();
+    let response = match (parts.next(), parts.next()) {
+        (Some("GET"), Some("/metrics")) => {
+            let body = render(&stats.snapshot());
+            format!(
+                "HTTP/1.1 200 OK\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                body.len(),
+                body
+            )
+        }
+        _ => "HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n".to_string(),
+    };
+
+    stream.write_all(response.as_bytes()).await?;
+    stream.shutdown().await
+}
+
+/// A client socket that adds everything read and written to the byte
+/// counters of `Stats`.
+pub struct Metered<S> {
+    inner: S,
+    stats: Arc<Stats>,
+}
+
+impl<S> Metered<S> {
+    pub fn new(inner: S, stats: Arc<Stats>) -> Self {
+        Metered { inner, stats }
+    }
+}
+
+impl<S: AsyncRead + Unpin> AsyncRead for Metered<S> {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let before = buf.filled().len();
+        let poll = Pin::new(&mut self.inner).poll_read(cx, buf);
+        if let Poll::Ready(Ok(())) = poll {
+            self.stats.record_bytes_in(buf.filled().len() - before);
+        }
+
+        poll
+    }
+}
+
+impl<S: AsyncWrite + Unpin> AsyncWrite for Metered<S> {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let poll = Pin::new(&mut self.inner).poll_write(cx, buf);
+        if let Poll::Ready(Ok(n)) = poll {
+            self.stats.record_bytes_out(n);
+        }
+
+        poll
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.inner).poll_flush(cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.inner).poll_shutdown(cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render() {
+        let stats = Stats::new(10);
+        let _client = stats.try_connect().unwrap();
+        stats.record_message();
+        stats.record_lag(1, 3);
+
+        let text = render(&stats.snapshot());
+        assert!(text.contains("# TYPE chat_connected_clients gauge\nchat_connected_clients 1\n"));
+        assert!(text.contains("\nchat_messages_total 1\n"));
+        assert!(text.contains("\nchat_lagged_messages_total 3\n"));
+        // every metric has its HELP and TYPE lines
+        let samples = text.lines().filter(|l| !l.starts_with('#')).count();
+        assert_eq!(text.lines().count(), samples * 3);
+    }
+
+    #[tokio::test]
+    async fn test_metered_counts_bytes() {
+        let stats = Stats::new(10);
+        let (client, server) = tokio::io::duplex(64);
+        let mut server = Metered::new(server, stats.clone());
+        let mut client = client;
+
+        client.write_all(b"hello\n").await.unwrap();
+        let mut buf = [0; 6];
+        server.read_exact(&mut buf).await.unwrap();
+        server.write_all(b"hi\n").await.unwrap();
+
+        let snapshot = stats.snapshot();
+        assert_eq!((snapshot.bytes_in, snapshot.bytes_out), (6, 3));
+    }
+}
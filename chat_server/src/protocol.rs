@@ -0,0 +1,531 @@
+use chrono_tz::Tz;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::str::FromStr;
+
+/// Bumped whenever a message type changes in a way old clients can't read.
+pub const PROTOCOL_VERSION: u32 = 1;
+/// Pings a client may leave unanswered before it is dropped.
+pub const MAX_MISSED_PONGS: u32 = 3;
+
+/// Everything the server sends to a client once it is logged in.
+///
+/// Timestamps are Unix seconds. In JSON mode every message is one line:
+/// `{"v":1,"type":"chat","from":"alice","room":"lobby","body":"hi","ts":1700000000}`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ServerMessage {
+    /// A message someone sent to a room.
+    Chat {
+        from: String,
+        room: String,
+        body: String,
+        ts: i64,
+    },
+    /// `/me` in a room: `* alice waves`.
+    Action {
+        from: String,
+        room: String,
+        body: String,
+        ts: i64,
+    },
+    /// `/msg`, only seen by `to`.
+    Private {
+        from: String,
+        to: String,
+        body: String,
+        ts: i64,
+    },
+    /// Something that happened in a room (joins, leaves, kicks...).
+    Notice {
+        body: String,
+        ts: i64,
+    },
+    /// Reply to one of the client's own commands.
+    Info {
+        body: String,
+        ts: i64,
+    },
+    Error {
+        body: String,
+        ts: i64,
+    },
+    Users {
+        room: String,
+        users: Vec<String>,
+        /// Those of `users` who are away, with their reason (maybe empty).
+        #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+        away: BTreeMap<String, String>,
+        ts: i64,
+    },
+    Rooms {
+        rooms: Vec<RoomSummary>,
+        ts: i64,
+    },
+    History {
+        room: String,
+        messages: Vec<HistoryEntry>,
+        ts: i64,
+    },
+    /// Someone in the room went away or came back.
+    Presence {
+        user: String,
+        status: Status,
+        /// Why they are away, empty when they didn't say or are back.
+        #[serde(default, skip_serializing_if = "String::is_empty")]
+        reason: String,
+        ts: i64,
+    },
+    /// Heartbeat, the client answers with a `/pong` line.
+    Ping {
+        ts: i64,
+    },
+}
+
+/// Whether a user is at their keyboard, set with `/away` and `/back`.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Status {
+    Online,
+    Away,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RoomSummary {
+    pub name: String,
+    pub users: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HistoryEntry {
+    pub from: String,
+    pub body: String,
+    pub ts: i64,
+}
+
+/// A message with the protocol version, as written on the wire.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Envelope {
+    pub v: u32,
+    #[serde(flatten)]
+    pub message: ServerMessage,
+}
+
+fn now() -> i64 {
+    chrono::Utc::now().timestamp()
+}
+
+impl ServerMessage {
+    pub fn chat(from: &str, room: &str, body: &str) -> Self {
+        ServerMessage::Chat {
+            from: from.to_string(),
+            room: room.to_string(),
+            body: body.to_string(),
+            ts: now(),
+        }
+    }
+
+    pub fn action(from: &str, room: &str, body: &str) -> Self {
+        ServerMessage::Action {
+            from: from.to_string(),
+            room: room.to_string(),
+            body: body.to_string(),
+            ts: now(),
+        }
+    }
+
+    pub fn private(from: &str, to: &str, body: &str) -> Self {
+        ServerMessage::Private {
+            from: from.to_string(),
+            to: to.to_string(),
+            body: body.to_string(),
+            ts: now(),
+        }
+    }
+
+    pub fn notice(body: impl Into<String>) -> Self {
+        ServerMessage::Notice {
+            body: body.into(),
+            ts: now(),
+        }
+    }
+
+    pub fn info(body: impl Into<String>) -> Self {
+        ServerMessage::Info {
+            body: body.into(),
+            ts: now(),
+        }
+    }
+
+    pub fn error(body: impl Into<String>) -> Self {
+        ServerMessage::Error {
+            body: body.into(),
+            ts: now(),
+        }
+    }
+
+    pub fn users(room: &str, users: Vec<String>, away: BTreeMap<String, String>) -> Self {
+        ServerMessage::Users {
+            room: room.to_string(),
+            users,
+            away,
+            ts: now(),
+        }
+    }
+
+    pub fn presence(user: &str, status: Status, reason: &str) -> Self {
+        ServerMessage::Presence {
+            user: user.to_string(),
+            status,
+            reason: reason.to_string(),
+            ts: now(),
+        }
+    }
+
+    pub fn rooms(rooms: Vec<RoomSummary>) -> Self {
+        ServerMessage::Rooms { rooms, ts: now() }
+    }
+
+    pub fn history(room: &str, messages: Vec<HistoryEntry>) -> Self {
+        ServerMessage::History {
+            room: room.to_string(),
+            messages,
+            ts: now(),
+        }
+    }
+
+    pub fn ping() -> Self {
+        ServerMessage::Ping { ts: now() }
+    }
+
+    /// Who wrote a chat message, action or private message.
+    pub fn sender(&self) -> Option<&str> {
+        match self {
+            ServerMessage::Chat { from, .. }
+            | ServerMessage::Action { from, .. }
+            | ServerMessage::Private { from, .. } => Some(from),
+            _ => None,
+        }
+    }
+
+    pub fn ts(&self) -> i64 {
+        match self {
+            ServerMessage::Chat { ts, .. }
+            | ServerMessage::Action { ts, .. }
+            | ServerMessage::Private { ts, .. }
+            | ServerMessage::Notice { ts, .. }
+            | ServerMessage::Info { ts, .. }
+            | ServerMessage::Error { ts, .. }
+            | ServerMessage::Users { ts, .. }
+            | ServerMessage::Rooms { ts, .. }
+            | ServerMessage::History { ts, .. }
+            | ServerMessage::Presence { ts, .. }
+            | ServerMessage::Ping { ts } => *ts,
+        }
+    }
+}
+
+/// How a client wants to receive messages, picked with `/format`.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Format {
+    /// Human readable lines, for `nc` and `telnet`.
+    #[default]
+    Text,
+    /// One JSON object per line.
+    Json,
+}
+
+impl FromStr for Format {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "text" => Ok(Format::Text),
+            "json" => Ok(Format::Json),
+            _ => Err(()),
+        }
+    }
+}
+
+impl fmt::Display for Format {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Format::Text => write!(f, "text"),
+            Format::Json => write!(f, "json"),
+        }
+    }
+}
+
+/// How one client wants to see messages, picked with `/format` and
+/// `/settimezone`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Output {
+    pub format: Format,
+    /// Zone the timestamps of text output are shown in. JSON always carries
+    /// Unix timestamps.
+    pub timezone: Tz,
+}
+
+impl Default for Output {
+    fn default() -> Self {
+        Output::new(Format::default(), Tz::UTC)
+    }
+}
+
+impl Output {
+    pub fn new(format: Format, timezone: Tz) -> Self {
+        Output { format, timezone }
+    }
+
+    /// The message as a newline terminated string.
+    pub fn render(&self, message: &ServerMessage) -> String {
+        match self.format {
+            Format::Text => render_text(message, self.timezone),
+            Format::Json => {
+                let envelope = Envelope {
+                    v: PROTOCOL_VERSION,
+                    message: message.clone(),
+                };
+                // only strings and numbers inside, serializing can't fail
+                let mut json = serde_json::to_string(&envelope).unwrap();
+                json.push('\n');
+
+                json
+            }
+        }
+    }
+}
+
+fn render_text(message: &ServerMessage, timezone: Tz) -> String {
+    match message {
+        ServerMessage::Chat { from, body, ts, .. } => {
+            format!(
+                "[{}] {}: {}\n",
+                format_time(*ts, timezone, "%H:%M:%S"),
+                from,
+                body
+            )
+        }
+        ServerMessage::Action { from, body, ts, .. } => {
+            format!(
+                "[{}] * {} {}\n",
+                format_time(*ts, timezone, "%H:%M:%S"),
+                from,
+                body
+            )
+        }
+        ServerMessage::Private { from, to, body, ts } => {
+            format!(
+                "[{}] [{} -> {}] {}\n",
+                format_time(*ts, timezone, "%H:%M:%S"),
+                from,
+                to,
+                body
+            )
+        }
+        ServerMessage::Notice { body, ts } => {
+            format!(
+                "[{}] *** {} ***\n",
+                format_time(*ts, timezone, "%H:%M:%S"),
+                body
+            )
+        }
+        ServerMessage::Info { body, .. } | ServerMessage::Error { body, .. } => {
+            format!("{}\n", body)
+        }
+        ServerMessage::Users {
+            room, users, away, ..
+        } => {
+            let users: Vec<String> = users
+                .iter()
+                .map(|user| match away.get(user).map(String::as_str) {
+                    Some("") => format!("{} (away)", user),
+                    Some(reason) => format!("{} (away: {})", user, reason),
+                    None => user.clone(),
+                })
+                .collect();
+            format!("Users in #{}: {}\n", room, users.join(", "))
+        }
+        ServerMessage::Rooms { rooms, .. } => {
+            let rooms: Vec<String> = rooms
+                .iter()
+                .map(|r| format!("#{} ({})", r.name, r.users))
+                .collect();
+            format!("Rooms: {}\n", rooms.join(", "))
+        }
+        ServerMessage::History { room, messages, .. } => {
+            let mut text = format!("--- last {} message(s) in #{} ---\n", messages.len(), room);
+            for m in messages {
+                let time = format_time(m.ts, timezone, "%Y-%m-%d %H:%M:%S");
+                text.push_str(&format!("[{}] {}: {}\n", time, m.from, m.body));
+            }
+            text.push_str("--- end of history ---\n");
+
+            text
+        }
+        ServerMessage::Presence {
+            user,
+            status,
+            reason,
+            ts,
+        } => {
+            let text = match (status, reason.as_str()) {
+                (Status::Online, _) => format!("{} is back", user),
+                (Status::Away, "") => format!("{} is away", user),
+                (Status::Away, reason) => format!("{} is away: {}", user, reason),
+            };
+            format!(
+                "[{}] *** {} ***\n",
+                format_time(*ts, timezone, "%H:%M:%S"),
+                text
+            )
+        }
+        // only JSON clients are pinged
+        ServerMessage::Ping { .. } => "Ping, answer with /pong\n".to_string(),
+    }
+}
+
+/// `ts` in `timezone`, with a `chrono` format `pattern`.
+pub fn format_time(ts: i64, timezone: Tz, pattern: &str) -> String {
+    chrono::DateTime::from_timestamp(ts, 0)
+        .map(|t| t.with_timezone(&timezone).format(pattern).to_string())
+        .unwrap_or_else(|| "??:??:??".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const JSON: Output = Output {
+        format: Format::Json,
+        timezone: Tz::UTC,
+    };
+
+    #[test]
+    fn test_json_wire_format() {
+        let message = ServerMessage::Chat {
+            from: "alice".to_string(),
+            room: "lobby".to_string(),
+            body: "hi".to_string(),
+            ts: 1_700_000_000,
+        };
+
+        assert_eq!(
+            JSON.render(&message),
+            "{\"v\":1,\"type\":\"chat\",\"from\":\"alice\",\"room\":\"lobby\",\"body\":\"hi\",\"ts\":1700000000}\n"
+        );
+
+        let line = JSON.render(&ServerMessage::users(
+            "rust",
+            vec!["bob".to_string()],
+            BTreeMap::new(),
+        ));
+        let envelope: Envelope = serde_json::from_str(&line).unwrap();
+        assert_eq!(envelope.v, PROTOCOL_VERSION);
+        assert!(matches!(envelope.message, ServerMessage::Users { room, .. } if room == "rust"));
+
+        assert_eq!(
+            JSON.render(&ServerMessage::Ping { ts: 1_700_000_000 }),
+            "{\"v\":1,\"type\":\"ping\",\"ts\":1700000000}\n"
+        );
+    }
+
+    #[test]
+    fn test_text_rendering() {
+        let text = Output::default();
+
+        assert_eq!(
+            text.render(&ServerMessage::Notice {
+                body: "bob has joined #lobby".to_string(),
+                ts: 3_723,
+            }),
+            "[01:02:03] *** bob has joined #lobby ***\n"
+        );
+        assert_eq!(
+            text.render(&ServerMessage::rooms(vec![RoomSummary {
+                name: "lobby".to_string(),
+                users: 2
+            }])),
+            "Rooms: #lobby (2)\n"
+        );
+
+        let users = ServerMessage::users(
+            "lobby",
+            vec!["alice".to_string(), "bob".to_string(), "carol".to_string()],
+            BTreeMap::from([
+                ("alice".to_string(), "lunch".to_string()),
+                ("carol".to_string(), String::new()),
+            ]),
+        );
+        assert_eq!(
+            text.render(&users),
+            "Users in #lobby: alice (away: lunch), bob, carol (away)\n"
+        );
+        let back = ServerMessage::Presence {
+            user: "alice".to_string(),
+            status: Status::Online,
+            reason: String::new(),
+            ts: 0,
+        };
+        assert_eq!(text.render(&back), "[00:00:00] *** alice is back ***\n");
+        assert_eq!(
+            JSON.render(&back),
+            "{\"v\":1,\"type\":\"presence\",\"user\":\"alice\",\"status\":\"online\",\"ts\":0}\n"
+        );
+
+        let history = ServerMessage::history(
+            "lobby",
+            vec![HistoryEntry {
+                from: "alice".to_string(),
+                body: "hi".to_string(),
+                ts: 0,
+            }],
+        );
+        assert_eq!(
+            text.render(&history),
+            "--- last 1 message(s) in #lobby ---\n[1970-01-01 00:00:00] alice: hi\n--- end of history ---\n"
+        );
+    }
+
+    #[test]
+    fn test_timezone() {
+        let message = ServerMessage::Chat {
+            from: "alice".to_string(),
+            room: "lobby".to_string(),
+            body: "hi".to_string(),
+            ts: 1_700_000_000,
+        };
+
+        let utc = Output::default();
+        let action = ServerMessage::Action {
+            from: "alice".to_string(),
+            room: "lobby".to_string(),
+            body: "waves".to_string(),
+            ts: 1_700_000_000,
+        };
+        assert_eq!(utc.render(&action), "[22:13:20] * alice waves\n");
+        let private = ServerMessage::Private {
+            from: "alice".to_string(),
+            to: "bob".to_string(),
+            body: "psst".to_string(),
+            ts: 1_700_000_000,
+        };
+        assert_eq!(utc.render(&private), "[22:13:20] [alice -> bob] psst\n");
+
+        let tokyo = Output::new(Format::Text, "Asia/Tokyo".parse().unwrap());
+        assert_eq!(utc.render(&message), "[22:13:20] alice: hi\n");
+        assert_eq!(tokyo.render(&message), "[07:13:20] alice: hi\n");
+
+        // JSON clients get the same Unix timestamp wherever they are
+        let json = Output::new(Format::Json, tokyo.timezone);
+        assert_eq!(json.render(&message), JSON.render(&message));
+    }
+
+    #[test]
+    fn test_parse_format() {
+        assert_eq!("JSON".parse(), Ok(Format::Json));
+        assert_eq!("text".parse(), Ok(Format::Text));
+        assert_eq!("xml".parse::<Format>(), Err(()));
+    }
+}
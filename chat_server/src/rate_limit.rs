@@ -0,0 +1,150 @@
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+const WINDOW: Duration = Duration::from_secs(1);
+/// How long a flooding client stays muted after its second strike.
+pub const FLOOD_MUTE: Duration = Duration::from_secs(30);
+/// Strikes are forgotten after this long without flooding.
+const STRIKE_RESET: Duration = Duration::from_secs(60);
+
+#[derive(Debug, PartialEq)]
+pub enum Verdict {
+    Allow,
+    /// First strike, the message is dropped.
+    Warn,
+    /// Second strike (or still muted), the message is dropped.
+    Muted(Duration),
+    /// Third strike.
+    Disconnect,
+}
+
+/// Sliding-window limit on the messages of one connection.
+///
+/// Going over the limit escalates: a warning, then a temporary mute, then
+/// the connection is dropped.
+pub struct RateLimiter {
+    max_per_second: usize,
+    sent: VecDeque<Instant>,
+    strikes: u32,
+    last_strike: Option<Instant>,
+    muted_until: Option<Instant>,
+}
+
+impl RateLimiter {
+    pub fn new(max_per_second: usize) -> Self {
+        RateLimiter {
+            max_per_second,
+            sent: VecDeque::with_capacity(max_per_second + 1),
+            strikes: 0,
+            last_strike: None,
+            muted_until: None,
+        }
+    }
+
+    pub fn check(&mut self, now: Instant) -> Verdict {
+        if let Some(until) = self.muted_until {
+            if now < until {
+                return Verdict::Muted(until - now);
+            }
+            self.muted_until = None;
+        }
+
+        if self
+            .last_strike
+            .is_some_and(|last| now.duration_since(last) >= STRIKE_RESET)
+        {
+            self.strikes = 0;
+            self.last_strike = None;
+        }
+
+        while self
+            .sent
+            .front()
+            .is_some_and(|t| now.duration_since(*t) >= WINDOW)
+        {
+            self.sent.pop_front();
+        }
+
+        if self.sent.len() < self.max_per_second {
+            self.sent.push_back(now);
+            return Verdict::Allow;
+        }
+
+        self.strikes += 1;
+        self.last_strike = Some(now);
+        // start the next window fresh so a warned client isn't struck
+        // again for the messages already counted
+        self.sent.clear();
+
+        match self.strikes {
+            1 => Verdict::Warn,
+            2 => {
+                self.muted_until = Some(now + FLOOD_MUTE);
+                Verdict::Muted(FLOOD_MUTE)
+            }
+            _ => Verdict::Disconnect,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn flood(limiter: &mut RateLimiter, now: Instant, count: usize) -> Verdict {
+        let mut verdict = Verdict::Allow;
+        for _ in 0..count {
+            verdict = limiter.check(now);
+        }
+
+        verdict
+    }
+
+    #[test]
+    fn test_allows_messages_under_the_limit() {
+        let mut limiter = RateLimiter::new(3);
+        let start = Instant::now();
+
+        for i in 0..10 {
+            let now = start + Duration::from_millis(400 * i);
+            assert_eq!(limiter.check(now), Verdict::Allow);
+        }
+    }
+
+    #[test]
+    fn test_escalation() {
+        let mut limiter = RateLimiter::new(3);
+        let start = Instant::now();
+
+        assert_eq!(flood(&mut limiter, start, 3), Verdict::Allow);
+        assert_eq!(limiter.check(start), Verdict::Warn);
+
+        let now = start + Duration::from_secs(2);
+        assert_eq!(flood(&mut limiter, now, 3), Verdict::Allow);
+        assert_eq!(limiter.check(now), Verdict::Muted(FLOOD_MUTE));
+
+        // still muted a bit later
+        let now = now + Duration::from_secs(10);
+        assert_eq!(
+            limiter.check(now),
+            Verdict::Muted(FLOOD_MUTE - Duration::from_secs(10))
+        );
+
+        let now = now + FLOOD_MUTE;
+        assert_eq!(flood(&mut limiter, now, 3), Verdict::Allow);
+        assert_eq!(limiter.check(now), Verdict::Disconnect);
+    }
+
+    #[test]
+    fn test_strikes_reset_after_quiet_period() {
+        let mut limiter = RateLimiter::new(1);
+        let start = Instant::now();
+
+        limiter.check(start);
+        assert_eq!(limiter.check(start), Verdict::Warn);
+
+        let now = start + STRIKE_RESET;
+        limiter.check(now);
+        assert_eq!(limiter.check(now), Verdict::Warn);
+    }
+}
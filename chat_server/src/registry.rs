@@ -0,0 +1,816 @@
+use crate::error::ChatError;
+use crate::protocol::{HistoryEntry, ServerMessage};
+use crate::room::{ChatState, Delivery, User, same_name};
+use crate::stats::Stats;
+use std::collections::BTreeMap;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::mpsc::Receiver;
+use tokio::sync::mpsc::error::TrySendError;
+use tokio::sync::{Notify, mpsc, oneshot};
+use tracing::warn;
+
+/// Commands waiting for the registry before senders have to wait.
+const QUEUE_SIZE: usize = 1024;
+/// Messages waiting for a watcher before newer ones are dropped.
+const WATCH_QUEUE_SIZE: usize = 256;
+
+/// What a client gets back when it connects.
+#[derive(Debug)]
+pub struct Joined {
+    /// Everything sent to the client, for as long as it is connected.
+    pub rx: Receiver<ServerMessage>,
+    /// Notified when an operator kicks or bans the client.
+    pub kick: Arc<Notify>,
+    /// The room's last messages, taken when joining so none are missed or
+    /// received twice.
+    pub recent: Vec<HistoryEntry>,
+}
+
+/// A message the registry routed to a room, as seen by watchers.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Routed {
+    pub room: String,
+    pub message: ServerMessage,
+}
+
+/// Who a moderation command applies to.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Target {
+    Name(String),
+    Ip(IpAddr),
+}
+
+/// Everything client tasks can ask of the registry. Commands that need an
+/// answer carry a oneshot `reply` sender.
+#[derive(Debug)]
+pub enum Command {
+    /// Joins the default room with a new connection, unless the name is in
+    /// use. Replies `None` when it is.
+    Join {
+        address: SocketAddr,
+        username: String,
+        room: String,
+        reply: oneshot::Sender<Option<Joined>>,
+    },
+    /// Moves a connected user to another room. Replies with the room's
+    /// last messages.
+    SwitchRoom {
+        address: SocketAddr,
+        room: String,
+        reply: oneshot::Sender<Vec<HistoryEntry>>,
+    },
+    /// Removes a user and tells their room they left.
+    Leave { address: SocketAddr },
+    Rename {
+        address: SocketAddr,
+        username: String,
+        reply: oneshot::Sender<bool>,
+    },
+    NameTaken {
+        address: SocketAddr,
+        username: String,
+        reply: oneshot::Sender<bool>,
+    },
+    /// Replies with the members of `room` and the reasons of those who
+    /// are away.
+    ListUsers {
+        room: String,
+        reply: oneshot::Sender<(Vec<String>, BTreeMap<String, String>)>,
+    },
+    ListRooms {
+        reply: oneshot::Sender<Vec<(String, usize)>>,
+    },
+    /// Sends a message to everyone in `room` but its `sender`. Chat
+    /// messages are also kept for replaying to newcomers.
+    Route {
+        room: String,
+        message: ServerMessage,
+        sender: Option<SocketAddr>,
+    },
+    /// Sends a message to every connected user.
+    RouteAll { message: ServerMessage },
+    /// Sends a message to the users called `username`, replies whether
+    /// there was one.
+    SendTo {
+        username: String,
+        message: ServerMessage,
+        reply: oneshot::Sender<bool>,
+    },
+    /// Marks a user away with a reason, or back with `None`. Replies with
+    /// their previous status.
+    SetAway {
+        address: SocketAddr,
+        away: Option<String>,
+        reply: oneshot::Sender<Option<String>>,
+    },
+    /// Starts copying every routed message to the replied receiver, for as
+    /// long as it is kept.
+    Watch {
+        reply: oneshot::Sender<mpsc::Receiver<Routed>>,
+    },
+    /// The connected user called `username`, with their address.
+    Whois {
+        username: String,
+        reply: oneshot::Sender<Option<(SocketAddr, User)>>,
+    },
+    IsOperator {
+        username: String,
+        reply: oneshot::Sender<bool>,
+    },
+    /// Mutes a user and tells the rooms they are in with `notice`.
+    Mute {
+        username: String,
+        duration: Duration,
+        notice: ServerMessage,
+    },
+    MuteRemaining {
+        username: String,
+        reply: oneshot::Sender<Option<Duration>>,
+    },
+    /// Disconnects the users matching `target`, except `operator` themself,
+    /// and tells their rooms they were `action` ("kicked", "banned"...).
+    /// Replies how many were disconnected.
+    Kick {
+        target: Target,
+        operator: String,
+        action: &'static str,
+        reply: oneshot::Sender<usize>,
+    },
+}
+
+/// Handle to the registry task, which owns the `ChatState`.
+///
+/// Client tasks never lock anything: they send a `Command` and, when they
+/// need an answer, wait for the reply. The task stops once every handle has
+/// been dropped.
+#[derive(Debug, Clone)]
+pub struct Registry {
+    tx: mpsc::Sender<Command>,
+}
+
+impl Registry {
+    /// Starts the registry task on the current runtime. Deliveries are
+    /// counted in `stats`.
+    pub fn spawn(state: ChatState, stats: Arc<Stats>) -> Self {
+        let (tx, rx) = mpsc::channel(QUEUE_SIZE);
+        tokio::spawn(run(state, stats, rx));
+
+        Registry { tx }
+    }
+
+    pub async fn join(
+        &self,
+        address: SocketAddr,
+        username: &str,
+        room: &str,
+    ) -> Result<Option<Joined>, ChatError> {
+        self.request(|reply| Command::Join {
+            address,
+            username: username.to_string(),
+            room: room.to_string(),
+            reply,
+        })
+        .await
+    }
+
+    /// Moves the user to `room` and returns its last messages.
+    pub async fn switch_room(
+        &self,
+        address: SocketAddr,
+        room: &str,
+    ) -> Result<Vec<HistoryEntry>, ChatError> {
+        self.request(|reply| Command::SwitchRoom {
+            address,
+            room: room.to_string(),
+            reply,
+        })
+        .await
+    }
+
+    pub async fn leave(&self, address: SocketAddr) -> Result<(), ChatError> {
+        self.send(Command::Leave { address }).await
+    }
+
+    /// Returns whether the user was renamed, it fails when the name is
+    /// taken.
+    pub async fn rename(&self, address: SocketAddr, username: &str) -> Result<bool, ChatError> {
+        self.request(|reply| Command::Rename {
+            address,
+            username: username.to_string(),
+            reply,
+        })
+        .await
+    }
+
+    pub async fn is_name_taken(
+        &self,
+        address: SocketAddr,
+        username: &str,
+    ) -> Result<bool, ChatError> {
+        self.request(|reply| Command::NameTaken {
+            address,
+            username: username.to_string(),
+            reply,
+        })
+        .await
+    }
+
+    /// The users in `room`, and the reasons of those who are away.
+    pub async fn users_in(
+        &self,
+        room: &str,
+    ) -> Result<(Vec<String>, BTreeMap<String, String>), ChatError> {
+        self.request(|reply| Command::ListUsers {
+            room: room.to_string(),
+            reply,
+        })
+        .await
+    }
+
+    pub async fn rooms(&self) -> Result<Vec<(String, usize)>, ChatError> {
+        self.request(|reply| Command::ListRooms { reply }).await
+    }
+
+    pub async fn route(&self, room: &str, message: ServerMessage) -> Result<(), ChatError> {
+        self.send(Command::Route {
+            room: room.to_string(),
+            message,
+            sender: None,
+        })
+        .await
+    }
+
+    /// Like `route`, but the client at `sender` doesn't get its own
+    /// message back.
+    pub async fn route_from(
+        &self,
+        sender: SocketAddr,
+        room: &str,
+        message: ServerMessage,
+    ) -> Result<(), ChatError> {
+        self.send(Command::Route {
+            room: room.to_string(),
+            message,
+            sender: Some(sender),
+        })
+        .await
+    }
+
+    pub async fn route_all(&self, message: ServerMessage) -> Result<(), ChatError> {
+        self.send(Command::RouteAll { message }).await
+    }
+
+    /// Sends a private message, returns whether `username` is connected.
+    pub async fn send_to(&self, username: &str, message: ServerMessage) -> Result<bool, ChatError> {
+        self.request(|reply| Command::SendTo {
+            username: username.to_string(),
+            message,
+            reply,
+        })
+        .await
+    }
+
+    /// Sets the user's away status (`None` when back), returns the previous
+    /// one.
+    pub async fn set_away(
+        &self,
+        address: SocketAddr,
+        away: Option<String>,
+    ) -> Result<Option<String>, ChatError> {
+        self.request(|reply| Command::SetAway {
+            address,
+            away,
+            reply,
+        })
+        .await
+    }
+
+    /// Every message routed to a room from now on. A watcher that falls
+    /// more than `WATCH_QUEUE_SIZE` messages behind misses some.
+    pub async fn watch(&self) -> Result<mpsc::Receiver<Routed>, ChatError> {
+        self.request(|reply| Command::Watch { reply }).await
+    }
+
+    pub async fn whois(&self, username: &str) -> Result<Option<(SocketAddr, User)>, ChatError> {
+        self.request(|reply| Command::Whois {
+            username: username.to_string(),
+            reply,
+        })
+        .await
+    }
+
+    pub async fn is_operator(&self, username: &str) -> Result<bool, ChatError> {
+        self.request(|reply| Command::IsOperator {
+            username: username.to_string(),
+            reply,
+        })
+        .await
+    }
+
+    pub async fn mute(
+        &self,
+        username: &str,
+        duration: Duration,
+        notice: ServerMessage,
+    ) -> Result<(), ChatError> {
+        self.send(Command::Mute {
+            username: username.to_string(),
+            duration,
+            notice,
+        })
+        .await
+    }
+
+    pub async fn mute_remaining(&self, username: &str) -> Result<Option<Duration>, ChatError> {
+        self.request(|reply| Command::MuteRemaining {
+            username: username.to_string(),
+            reply,
+        })
+        .await
+    }
+
+    pub async fn kick(
+        &self,
+        target: Target,
+        operator: &str,
+        action: &'static str,
+    ) -> Result<usize, ChatError> {
+        self.request(|reply| Command::Kick {
+            target,
+            operator: operator.to_string(),
+            action,
+            reply,
+        })
+        .await
+    }
+
+    async fn send(&self, command: Command) -> Result<(), ChatError> {
+        self.tx
+            .send(command)
+            .await
+            .map_err(|_| ChatError::RegistryClosed)
+    }
+
+    async fn request<T>(
+        &self,
+        command: impl FnOnce(oneshot::Sender<T>) -> Command,
+    ) -> Result<T, ChatError> {
+        let (reply, response) = oneshot::channel();
+        self.send(command(reply)).await?;
+
+        response.await.map_err(|_| ChatError::RegistryClosed)
+    }
+}
+
+async fn run(mut state: ChatState, stats: Arc<Stats>, mut commands: mpsc::Receiver<Command>) {
+    let mut watchers = Vec::new();
+    while let Some(command) = commands.recv().await {
+        handle(&mut state, &stats, &mut watchers, command);
+        remove_closed(&mut state, &stats, &mut watchers);
+    }
+}
+
+// a client task that ended without leaving would keep its name taken and
+// still be listed in its room, so it leaves as soon as a delivery finds its
+// queue closed
+fn remove_closed(state: &mut ChatState, stats: &Stats, watchers: &mut Vec<mpsc::Sender<Routed>>) {
+    loop {
+        let gone = state.remove_closed();
+        if gone.is_empty() {
+            break;
+        }
+
+        for user in gone {
+            warn!("'{}' went away without leaving, removed", user.name);
+            left(state, stats, watchers, user);
+        }
+    }
+}
+
+fn left(
+    state: &mut ChatState,
+    stats: &Stats,
+    watchers: &mut Vec<mpsc::Sender<Routed>>,
+    user: User,
+) {
+    let notice = ServerMessage::notice(format!("{} has left the chat", user.name));
+    route(state, stats, watchers, &user.room, notice, None);
+}
+
+// replies are ignored when the asking client has gone away in the meantime
+fn handle(
+    state: &mut ChatState,
+    stats: &Stats,
+    watchers: &mut Vec<mpsc::Sender<Routed>>,
+    command: Command,
+) {
+    match command {
+        Command::Join {
+            address,
+            username,
+            room,
+            reply,
+        } => {
+            let joined = state.try_join(address, &username, &room).map(|rx| Joined {
+                rx,
+                kick: state.user(&address).unwrap().kick.clone(),
+                recent: state.recent(&room),
+            });
+            let _ = reply.send(joined);
+        }
+        Command::SwitchRoom {
+            address,
+            room,
+            reply,
+        } => {
+            // a user kicked in the meantime has nothing to catch up on
+            let recent = if state.move_to(&address, &room) {
+                state.recent(&room)
+            } else {
+                Vec::new()
+            };
+            let _ = reply.send(recent);
+        }
+        Command::Leave { address } => {
+            // already gone when the registry found its queue closed
+            if let Some(user) = state.leave(&address) {
+                left(state, stats, watchers, user);
+            }
+        }
+        Command::Rename {
+            address,
+            username,
+            reply,
+        } => {
+            let _ = reply.send(state.try_rename(&address, &username));
+        }
+        Command::NameTaken {
+            address,
+            username,
+            reply,
+        } => {
+            let _ = reply.send(state.is_name_taken(&username, &address));
+        }
+        Command::ListUsers { room, reply } => {
+            let _ = reply.send((state.users_in(&room), state.away_in(&room)));
+        }
+        Command::ListRooms { reply } => {
+            let _ = reply.send(state.rooms());
+        }
+        Command::Route {
+            room,
+            message,
+            sender,
+        } => {
+            route(state, stats, watchers, &room, message, sender);
+        }
+        Command::RouteAll { message } => {
+            let delivery = state.send_to_all(&message);
+            record(stats, delivery);
+        }
+        Command::SendTo {
+            username,
+            message,
+            reply,
+        } => {
+            let addresses = state.find_by_name(&username);
+            for address in &addresses {
+                let delivery = state.send_to(address, &message);
+                record(stats, delivery);
+            }
+            let _ = reply.send(!addresses.is_empty());
+        }
+        Command::SetAway {
+            address,
+            away,
+            reply,
+        } => {
+            let _ = reply.send(state.set_away(&address, away));
+        }
+        Command::Watch { reply } => {
+            let (tx, rx) = mpsc::channel(WATCH_QUEUE_SIZE);
+            watchers.push(tx);
+            let _ = reply.send(rx);
+        }
+        Command::Whois { username, reply } => {
+            let user = state.find_by_name(&username).first().and_then(|address| {
+                let user = state.user(address)?.clone();
+                Some((*address, user))
+            });
+            let _ = reply.send(user);
+        }
+        Command::IsOperator { username, reply } => {
+            let _ = reply.send(state.is_operator(&username));
+        }
+        Command::Mute {
+            username,
+            duration,
+            notice,
+        } => {
+            state.mute(&username, duration);
+
+            for address in state.find_by_name(&username) {
+                if let Some(room) = state.user(&address).map(|user| user.room.clone()) {
+                    let delivery = state.send_to_room(&room, &notice, None);
+                    record(stats, delivery);
+                }
+            }
+        }
+        Command::MuteRemaining { username, reply } => {
+            let _ = reply.send(state.mute_remaining(&username));
+        }
+        Command::Kick {
+            target,
+            operator,
+            action,
+            reply,
+        } => {
+            let targets = match &target {
+                Target::Name(name) => state.find_by_name(name),
+                Target::Ip(ip) => state.find_by_ip(*ip),
+            };
+            let _ = reply.send(kick(state, stats, &targets, &operator, action));
+        }
+    }
+}
+
+fn route(
+    state: &mut ChatState,
+    stats: &Stats,
+    watchers: &mut Vec<mpsc::Sender<Routed>>,
+    room: &str,
+    message: ServerMessage,
+    sender: Option<SocketAddr>,
+) {
+    state.record(room, &message);
+
+    // the registry never waits on a watcher, a slow one loses
+    // messages and a dropped one is forgotten
+    watchers.retain(|watcher| {
+        let routed = Routed {
+            room: room.to_string(),
+            message: message.clone(),
+        };
+        match watcher.try_send(routed) {
+            Ok(()) => true,
+            Err(TrySendError::Full(_)) => {
+                warn!("Watcher lagged behind, dropped a message");
+                true
+            }
+            Err(TrySendError::Closed(_)) => false,
+        }
+    });
+
+    let delivery = state.send_to_room(room, &message, sender);
+    record(stats, delivery);
+}
+
+fn record(stats: &Stats, delivery: Delivery) {
+    stats.record_deliveries(delivery.delivered);
+    if delivery.missed > 0 {
+        if delivery.started_lagging > 0 {
+            warn!(
+                "{} client(s) can't keep up and are missing messages",
+                delivery.started_lagging
+            );
+        }
+        stats.record_lag(delivery.started_lagging, delivery.missed);
+    }
+}
+
+fn kick(
+    state: &mut ChatState,
+    stats: &Stats,
+    targets: &[SocketAddr],
+    operator: &str,
+    action: &str,
+) -> usize {
+    let mut removed = 0;
+
+    for address in targets {
+        // an IP ban can match the operator's own connection
+        if state
+            .user(address)
+            .is_none_or(|user| same_name(&user.name, operator))
+        {
+            continue;
+        }
+
+        let Some(user) = state.kick(address) else {
+            continue;
+        };
+
+        let room = user.room.clone();
+        let msg = ServerMessage::notice(format!("{} was {} by {}", user.name, action, operator));
+        let delivery = state.send_to_room(&room, &msg, None);
+        record(stats, delivery);
+        removed += 1;
+    }
+
+    removed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::room::DEFAULT_ROOM;
+    use futures_util::FutureExt;
+
+    fn addr(port: u16) -> SocketAddr {
+        SocketAddr::from(([127, 0, 0, 1], port))
+    }
+
+    #[tokio::test]
+    async fn test_join_route_and_leave() {
+        let registry = Registry::spawn(ChatState::new(16), Stats::new(16));
+
+        let mut alice = registry
+            .join(addr(1), "alice", DEFAULT_ROOM)
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(
+            registry
+                .join(addr(2), "Alice", DEFAULT_ROOM)
+                .await
+                .unwrap()
+                .is_none()
+        );
+        let mut bob = registry
+            .join(addr(2), "bob", DEFAULT_ROOM)
+            .await
+            .unwrap()
+            .unwrap();
+
+        // bob doesn't get his own message back
+        let message = ServerMessage::chat("bob", DEFAULT_ROOM, "hi");
+        registry
+            .route_from(addr(2), DEFAULT_ROOM, message.clone())
+            .await
+            .unwrap();
+        assert_eq!(alice.rx.recv().await.unwrap(), message);
+        assert!(bob.rx.try_recv().is_err());
+
+        // only bob gets a private message
+        let private = ServerMessage::private("alice", "bob", "psst");
+        assert!(registry.send_to("BOB", private.clone()).await.unwrap());
+        assert!(!registry.send_to("carol", private.clone()).await.unwrap());
+        assert_eq!(bob.rx.recv().await.unwrap(), private);
+
+        // bob moves away and no longer gets the lobby's messages
+        registry.switch_room(addr(2), "rust").await.unwrap();
+        registry
+            .route(DEFAULT_ROOM, ServerMessage::notice("lobby only"))
+            .await
+            .unwrap();
+        assert_eq!(
+            registry.rooms().await.unwrap(),
+            vec![("lobby".to_string(), 1), ("rust".to_string(), 1)]
+        );
+        assert!(bob.rx.try_recv().is_err());
+
+        registry.leave(addr(1)).await.unwrap();
+        assert!(registry.users_in(DEFAULT_ROOM).await.unwrap().0.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_leave_tells_the_room() {
+        let registry = Registry::spawn(ChatState::new(16), Stats::new(16));
+        let mut alice = registry
+            .join(addr(1), "alice", DEFAULT_ROOM)
+            .await
+            .unwrap()
+            .unwrap();
+        registry.join(addr(2), "bob", DEFAULT_ROOM).await.unwrap();
+
+        registry.leave(addr(2)).await.unwrap();
+        // leaving twice only tells the room once
+        registry.leave(addr(2)).await.unwrap();
+        registry
+            .route(DEFAULT_ROOM, ServerMessage::notice("end"))
+            .await
+            .unwrap();
+
+        assert!(matches!(
+            alice.rx.recv().await.unwrap(),
+            ServerMessage::Notice { body, .. } if body == "bob has left the chat"
+        ));
+        assert!(matches!(
+            alice.rx.recv().await.unwrap(),
+            ServerMessage::Notice { body, .. } if body == "end"
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_gone_clients_are_removed() {
+        let registry = Registry::spawn(ChatState::new(16), Stats::new(16));
+        let mut alice = registry
+            .join(addr(1), "alice", DEFAULT_ROOM)
+            .await
+            .unwrap()
+            .unwrap();
+        let bob = registry
+            .join(addr(2), "bob", DEFAULT_ROOM)
+            .await
+            .unwrap()
+            .unwrap();
+
+        // bob's task ended without leaving, the next delivery finds out
+        drop(bob);
+        registry
+            .route(DEFAULT_ROOM, ServerMessage::notice("anyone?"))
+            .await
+            .unwrap();
+
+        assert_eq!(
+            registry.users_in(DEFAULT_ROOM).await.unwrap().0,
+            vec!["alice"]
+        );
+        assert!(!registry.is_name_taken(addr(3), "bob").await.unwrap());
+        alice.rx.recv().await.unwrap();
+        assert!(matches!(
+            alice.rx.recv().await.unwrap(),
+            ServerMessage::Notice { body, .. } if body == "bob has left the chat"
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_newcomers_get_recent_messages() {
+        let mut state = ChatState::new(16);
+        state.set_replay_size(10);
+        let registry = Registry::spawn(state, Stats::new(16));
+
+        let alice = registry
+            .join(addr(1), "alice", DEFAULT_ROOM)
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(alice.recent.is_empty());
+
+        let message = ServerMessage::chat("alice", DEFAULT_ROOM, "anyone here?");
+        registry.route(DEFAULT_ROOM, message).await.unwrap();
+
+        let mut bob = registry
+            .join(addr(2), "bob", DEFAULT_ROOM)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(bob.recent.len(), 1);
+        assert_eq!(bob.recent[0].body, "anyone here?");
+        // and it isn't delivered a second time
+        assert!(bob.rx.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_kick_spares_the_operator() {
+        let registry = Registry::spawn(ChatState::new(16), Stats::new(16));
+        let admin = registry
+            .join(addr(1), "admin", DEFAULT_ROOM)
+            .await
+            .unwrap()
+            .unwrap();
+        let mut bob = registry
+            .join(addr(2), "bob", DEFAULT_ROOM)
+            .await
+            .unwrap()
+            .unwrap();
+
+        // both connect from 127.0.0.1
+        let ip = Target::Ip([127, 0, 0, 1].into());
+        assert_eq!(registry.kick(ip, "admin", "banned").await.unwrap(), 1);
+
+        assert!(bob.kick.notified().now_or_never().is_some());
+        assert!(admin.kick.notified().now_or_never().is_none());
+        assert!(matches!(
+            bob.rx.recv().await.unwrap(),
+            ServerMessage::Notice { body, .. } if body == "bob was banned by admin"
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_watch() {
+        let registry = Registry::spawn(ChatState::new(16), Stats::new(16));
+        let mut watcher = registry.watch().await.unwrap();
+
+        // rooms nobody is in still reach the watchers
+        let message = ServerMessage::chat("bob", "rust", "hi");
+        registry.route("rust", message.clone()).await.unwrap();
+        assert_eq!(
+            watcher.recv().await.unwrap(),
+            Routed {
+                room: "rust".to_string(),
+                message
+            }
+        );
+
+        // a dropped watcher doesn't hold the registry up
+        drop(watcher);
+        registry
+            .route(DEFAULT_ROOM, ServerMessage::notice("still fine"))
+            .await
+            .unwrap();
+        assert!(registry.rooms().await.is_ok());
+    }
+}
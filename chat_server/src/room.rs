@@ -0,0 +1,646 @@
+use crate::protocol::{HistoryEntry, ServerMessage};
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::net::{IpAddr, SocketAddr};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::Notify;
+use tokio::sync::mpsc::error::TrySendError;
+use tokio::sync::mpsc::{self, Receiver, Sender};
+
+pub const DEFAULT_ROOM: &str = "lobby";
+const MAX_ROOM_NAME_LEN: usize = 32;
+
+#[derive(Debug, Clone)]
+pub struct User {
+    pub name: String,
+    pub room: String,
+    /// Notified when an operator kicks or bans the user.
+    pub kick: Arc<Notify>,
+    pub connected_at: Instant,
+    /// Set by `/away`, with the reason (empty if none was given).
+    pub away: Option<String>,
+    /// The client's outbound queue.
+    tx: Sender<ServerMessage>,
+    /// Messages dropped since the queue was last found full.
+    missed: u64,
+    /// Set when the queue was found closed: the client task is gone
+    /// without having left.
+    closed: bool,
+}
+
+/// What happened to a message handed to one or more clients.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct Delivery {
+    /// Clients whose queue took the message.
+    pub delivered: usize,
+    /// Clients whose queue was full, so they missed it.
+    pub missed: usize,
+    /// Of those, the ones that were keeping up until now.
+    pub started_lagging: usize,
+}
+
+/// Connected users and the rooms they are in, owned by the registry task
+/// (see `Registry`).
+///
+/// Every client has its own bounded queue, so a message can go to a room,
+/// to one user, or to a room minus its sender. A client that reads too
+/// slowly only fills up its own queue: it misses messages and is told so
+/// once it catches up, everyone else carries on. Rooms are created on first
+/// join and dropped once the last member leaves (except the default room).
+///
+/// The last few chat messages of every room are kept in memory so newcomers
+/// can catch up; they go away with the room.
+///
+/// Moderation state that doesn't need to survive a restart (operators and
+/// mutes) lives here as well; bans are persisted in `Storage`.
+pub struct ChatState {
+    users: HashMap<SocketAddr, User>,
+    rooms: HashSet<String>,
+    /// Size of each client's queue.
+    capacity: usize,
+    /// room -> its most recent chat messages, oldest first
+    recent: HashMap<String, VecDeque<HistoryEntry>>,
+    /// Messages kept per room, 0 keeps none.
+    replay_size: usize,
+    /// lowercased usernames
+    operators: HashSet<String>,
+    /// lowercased username -> end of the mute
+    mutes: HashMap<String, Instant>,
+    /// lowercased names nobody can take, like the bots'
+    reserved: HashSet<String>,
+}
+
+impl ChatState {
+    pub fn new(capacity: usize) -> Self {
+        let rooms = HashSet::from([DEFAULT_ROOM.to_string()]);
+
+        ChatState {
+            users: HashMap::new(),
+            rooms,
+            capacity,
+            recent: HashMap::new(),
+            replay_size: 0,
+            operators: HashSet::new(),
+            mutes: HashMap::new(),
+            reserved: HashSet::new(),
+        }
+    }
+
+    /// Adds a new connection to `room`, unless someone else is using the
+    /// username (case-insensitively). Returns the receiving end of the
+    /// client's queue. The registry handles one command at a time, so two
+    /// clients can't grab the same name.
+    pub fn try_join(
+        &mut self,
+        address: SocketAddr,
+        username: &str,
+        room: &str,
+    ) -> Option<Receiver<ServerMessage>> {
+        if self.is_name_taken(username, &address) {
+            return None;
+        }
+
+        let (tx, rx) = mpsc::channel(self.capacity);
+        self.users.insert(
+            address,
+            User {
+                name: username.to_string(),
+                room: room.to_string(),
+                kick: Arc::new(Notify::new()),
+                connected_at: Instant::now(),
+                away: None,
+                tx,
+                missed: 0,
+                closed: false,
+            },
+        );
+        self.rooms.insert(room.to_string());
+
+        Some(rx)
+    }
+
+    /// Moves a connected user to `room`, returns whether they are still
+    /// connected.
+    pub fn move_to(&mut self, address: &SocketAddr, room: &str) -> bool {
+        let Some(user) = self.users.get_mut(address) else {
+            return false;
+        };
+        let previous_room = std::mem::replace(&mut user.room, room.to_string());
+
+        self.rooms.insert(room.to_string());
+        if previous_room != room {
+            self.remove_if_empty(&previous_room);
+        }
+
+        true
+    }
+
+    /// Whether a user other than `address` is called `username`, or the
+    /// name is reserved.
+    pub fn is_name_taken(&self, username: &str, address: &SocketAddr) -> bool {
+        self.reserved.contains(&name_key(username))
+            || self
+                .users
+                .iter()
+                .any(|(addr, u)| addr != address && same_name(&u.name, username))
+    }
+
+    /// Keeps clients from using `name`, so nobody can pose as a bot.
+    pub fn reserve_name(&mut self, name: &str) {
+        self.reserved.insert(name_key(name));
+    }
+
+    pub fn leave(&mut self, address: &SocketAddr) -> Option<User> {
+        let user = self.users.remove(address)?;
+        self.remove_if_empty(&user.room);
+
+        Some(user)
+    }
+
+    /// Removes the users whose client task went away without leaving, as
+    /// found when delivering to them, and returns them.
+    pub fn remove_closed(&mut self) -> Vec<User> {
+        let closed: Vec<SocketAddr> = self
+            .users
+            .iter()
+            .filter(|(_, user)| user.closed)
+            .map(|(address, _)| *address)
+            .collect();
+
+        closed
+            .iter()
+            .filter_map(|address| self.leave(address))
+            .collect()
+    }
+
+    /// Renames the user unless someone else has the name, returns whether
+    /// it was renamed.
+    pub fn try_rename(&mut self, address: &SocketAddr, username: &str) -> bool {
+        if self.is_name_taken(username, address) {
+            return false;
+        }
+
+        match self.users.get_mut(address) {
+            Some(user) => {
+                user.name = username.to_string();
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn user(&self, address: &SocketAddr) -> Option<&User> {
+        self.users.get(address)
+    }
+
+    /// Queues `message` for everyone in `room` except `sender`.
+    pub fn send_to_room(
+        &mut self,
+        room: &str,
+        message: &ServerMessage,
+        sender: Option<SocketAddr>,
+    ) -> Delivery {
+        let mut delivery = Delivery::default();
+        for (address, user) in &mut self.users {
+            if user.room == room && Some(*address) != sender {
+                deliver(user, message, &mut delivery);
+            }
+        }
+
+        delivery
+    }
+
+    /// Queues `message` for the user at `address`.
+    pub fn send_to(&mut self, address: &SocketAddr, message: &ServerMessage) -> Delivery {
+        let mut delivery = Delivery::default();
+        if let Some(user) = self.users.get_mut(address) {
+            deliver(user, message, &mut delivery);
+        }
+
+        delivery
+    }
+
+    /// Queues `message` for every connected user.
+    pub fn send_to_all(&mut self, message: &ServerMessage) -> Delivery {
+        let mut delivery = Delivery::default();
+        for user in self.users.values_mut() {
+            deliver(user, message, &mut delivery);
+        }
+
+        delivery
+    }
+
+    pub fn users_in(&self, room: &str) -> Vec<String> {
+        let mut names: Vec<String> = self
+            .users
+            .values()
+            .filter(|u| u.room == room)
+            .map(|u| u.name.clone())
+            .collect();
+        names.sort();
+
+        names
+    }
+
+    /// Members of `room` who are away, with their reason.
+    pub fn away_in(&self, room: &str) -> BTreeMap<String, String> {
+        self.users
+            .values()
+            .filter(|u| u.room == room)
+            .filter_map(|u| Some((u.name.clone(), u.away.clone()?)))
+            .collect()
+    }
+
+    /// Marks the user away (with a reason) or back (`None`), returns what
+    /// their status was.
+    pub fn set_away(&mut self, address: &SocketAddr, away: Option<String>) -> Option<String> {
+        let user = self.users.get_mut(address)?;
+
+        std::mem::replace(&mut user.away, away)
+    }
+
+    /// Room names with their member count, sorted by name.
+    pub fn rooms(&self) -> Vec<(String, usize)> {
+        let mut rooms: Vec<(String, usize)> = self
+            .rooms
+            .iter()
+            .map(|room| (room.clone(), self.users_in(room).len()))
+            .collect();
+        rooms.sort();
+
+        rooms
+    }
+
+    /// Addresses of the users called `username` (case-insensitive).
+    pub fn find_by_name(&self, username: &str) -> Vec<SocketAddr> {
+        self.users
+            .iter()
+            .filter(|(_, u)| same_name(&u.name, username))
+            .map(|(addr, _)| *addr)
+            .collect()
+    }
+
+    /// Addresses of the users connected from `ip`.
+    pub fn find_by_ip(&self, ip: IpAddr) -> Vec<SocketAddr> {
+        self.users
+            .keys()
+            .filter(|addr| addr.ip() == ip)
+            .copied()
+            .collect()
+    }
+
+    /// Wakes up the client task of `address` so it disconnects, returns the
+    /// kicked user.
+    pub fn kick(&self, address: &SocketAddr) -> Option<&User> {
+        let user = self.users.get(address)?;
+        user.kick.notify_one();
+
+        Some(user)
+    }
+
+    pub fn set_replay_size(&mut self, replay_size: usize) {
+        self.replay_size = replay_size;
+    }
+
+    /// Remembers a chat message sent to `room` for `recent`, anything else
+    /// is ignored.
+    pub fn record(&mut self, room: &str, message: &ServerMessage) {
+        let ServerMessage::Chat { from, body, ts, .. } = message else {
+            return;
+        };
+        if self.replay_size == 0 || !self.rooms.contains(room) {
+            return;
+        }
+
+        let recent = self.recent.entry(room.to_string()).or_default();
+        recent.push_back(HistoryEntry {
+            from: from.clone(),
+            body: body.clone(),
+            ts: *ts,
+        });
+        if recent.len() > self.replay_size {
+            recent.pop_front();
+        }
+    }
+
+    /// The last chat messages of `room`, oldest first.
+    pub fn recent(&self, room: &str) -> Vec<HistoryEntry> {
+        self.recent
+            .get(room)
+            .map(|recent| recent.iter().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    pub fn set_operators(&mut self, operators: &[String]) {
+        self.operators = operators.iter().map(|o| name_key(o)).collect();
+    }
+
+    pub fn is_operator(&self, username: &str) -> bool {
+        self.operators.contains(&name_key(username))
+    }
+
+    pub fn mute(&mut self, username: &str, duration: Duration) {
+        self.mutes
+            .insert(name_key(username), Instant::now() + duration);
+    }
+
+    /// Time left on the user's mute, expired mutes are dropped.
+    pub fn mute_remaining(&mut self, username: &str) -> Option<Duration> {
+        let key = name_key(username);
+        let remaining = self
+            .mutes
+            .get(&key)?
+            .checked_duration_since(Instant::now())
+            .filter(|d| !d.is_zero());
+
+        if remaining.is_none() {
+            self.mutes.remove(&key);
+        }
+
+        remaining
+    }
+
+    fn remove_if_empty(&mut self, room: &str) {
+        if room != DEFAULT_ROOM && !self.users.values().any(|u| u.room == room) {
+            self.rooms.remove(room);
+            self.recent.remove(room);
+        }
+    }
+}
+
+// a client that was missing messages first hears how many, once its queue
+// has room again
+fn deliver(user: &mut User, message: &ServerMessage, delivery: &mut Delivery) {
+    if user.missed > 0 {
+        let notice = ServerMessage::error(format!(
+            "You missed {} message(s), your connection is too slow",
+            user.missed
+        ));
+        if user.tx.try_send(notice).is_ok() {
+            user.missed = 0;
+        }
+    }
+
+    match user.tx.try_send(message.clone()) {
+        Ok(()) => delivery.delivered += 1,
+        Err(TrySendError::Full(_)) => {
+            if user.missed == 0 {
+                delivery.started_lagging += 1;
+            }
+            user.missed += 1;
+            delivery.missed += 1;
+        }
+        // the client task is gone, the registry removes the user
+        Err(TrySendError::Closed(_)) => user.closed = true,
+    }
+}
+
+/// The form usernames are compared and stored in: the same person whatever
+/// the case, for accented letters too.
+pub fn name_key(username: &str) -> String {
+    username.to_lowercase()
+}
+
+pub fn same_name(a: &str, b: &str) -> bool {
+    name_key(a) == name_key(b)
+}
+
+pub fn is_valid_room_name(name: &str) -> bool {
+    !name.is_empty()
+        && name.len() <= MAX_ROOM_NAME_LEN
+        && name
+            .chars()
+            .all(|c| c.is_alphanumeric() || c == '-' || c == '_')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures_util::FutureExt;
+
+    fn addr(port: u16) -> SocketAddr {
+        SocketAddr::from(([127, 0, 0, 1], port))
+    }
+
+    fn join(state: &mut ChatState, port: u16, name: &str, room: &str) -> Receiver<ServerMessage> {
+        state.try_join(addr(port), name, room).unwrap()
+    }
+
+    #[test]
+    fn test_join_and_leave_rooms() {
+        let mut state = ChatState::new(16);
+
+        let _alice = join(&mut state, 1, "alice", DEFAULT_ROOM);
+        let _bob = join(&mut state, 2, "bob", "rust");
+
+        assert_eq!(state.users_in(DEFAULT_ROOM), vec!["alice"]);
+        assert_eq!(state.users_in("rust"), vec!["bob"]);
+        assert_eq!(
+            state.rooms(),
+            vec![("lobby".to_string(), 1), ("rust".to_string(), 1)]
+        );
+
+        // moving the last member out drops the room
+        assert!(state.move_to(&addr(2), DEFAULT_ROOM));
+        assert_eq!(state.rooms(), vec![("lobby".to_string(), 2)]);
+
+        let user = state.leave(&addr(1)).unwrap();
+        assert_eq!(user.name, "alice");
+        assert_eq!(state.users_in(DEFAULT_ROOM), vec!["bob"]);
+
+        // the default room is kept even when empty
+        state.leave(&addr(2));
+        assert_eq!(state.rooms(), vec![("lobby".to_string(), 0)]);
+        assert!(!state.move_to(&addr(2), "rust"));
+    }
+
+    #[test]
+    fn test_away() {
+        let mut state = ChatState::new(16);
+        let _alice = join(&mut state, 1, "alice", DEFAULT_ROOM);
+        let _bob = join(&mut state, 2, "bob", DEFAULT_ROOM);
+        let _carol = join(&mut state, 3, "carol", "rust");
+
+        assert_eq!(state.set_away(&addr(1), Some("lunch".to_string())), None);
+        assert_eq!(state.set_away(&addr(3), Some(String::new())), None);
+        assert_eq!(
+            state.away_in(DEFAULT_ROOM),
+            BTreeMap::from([("alice".to_string(), "lunch".to_string())])
+        );
+        assert_eq!(state.away_in("rust").len(), 1);
+
+        // coming back returns the old status, the second time there is none
+        assert_eq!(state.set_away(&addr(1), None), Some("lunch".to_string()));
+        assert_eq!(state.set_away(&addr(1), None), None);
+        assert!(state.away_in(DEFAULT_ROOM).is_empty());
+    }
+
+    #[test]
+    fn test_targeted_delivery() {
+        let mut state = ChatState::new(16);
+
+        let mut alice = join(&mut state, 1, "alice", DEFAULT_ROOM);
+        let mut bob = join(&mut state, 2, "bob", "rust");
+        let mut carol = join(&mut state, 3, "carol", "rust");
+
+        // rooms are scoped and the sender doesn't get its own message back
+        let message = ServerMessage::chat("bob", "rust", "hi");
+        let delivery = state.send_to_room("rust", &message, Some(addr(2)));
+        assert_eq!(delivery.delivered, 1);
+        assert_eq!(carol.try_recv().unwrap(), message);
+        assert!(bob.try_recv().is_err());
+        assert!(alice.try_recv().is_err());
+
+        let message = ServerMessage::chat("carol", "rust", "psst");
+        assert_eq!(state.send_to(&addr(1), &message).delivered, 1);
+        assert_eq!(alice.try_recv().unwrap(), message);
+        assert!(bob.try_recv().is_err());
+
+        assert_eq!(
+            state.send_to_all(&ServerMessage::notice("hey")).delivered,
+            3
+        );
+    }
+
+    #[test]
+    fn test_slow_clients_only_lag_themselves() {
+        let mut state = ChatState::new(2);
+        let mut slow = join(&mut state, 1, "slow", DEFAULT_ROOM);
+        let mut fast = join(&mut state, 2, "fast", DEFAULT_ROOM);
+
+        let mut lagging = 0;
+        for n in 0..4 {
+            let message = ServerMessage::chat("bob", DEFAULT_ROOM, &n.to_string());
+            let delivery = state.send_to_room(DEFAULT_ROOM, &message, None);
+            lagging += delivery.started_lagging;
+            // fast keeps up
+            assert_eq!(fast.try_recv().unwrap(), message);
+        }
+        assert_eq!(lagging, 1);
+
+        // slow got the first two, then hears what it missed
+        assert!(slow.try_recv().is_ok());
+        assert!(slow.try_recv().is_ok());
+        let delivery = state.send_to_room(DEFAULT_ROOM, &ServerMessage::notice("later"), None);
+        assert_eq!(delivery.delivered, 2);
+        assert!(matches!(
+            slow.try_recv().unwrap(),
+            ServerMessage::Error { body, .. } if body.contains("missed 2 message(s)")
+        ));
+        assert!(matches!(
+            slow.try_recv().unwrap(),
+            ServerMessage::Notice { .. }
+        ));
+    }
+
+    #[test]
+    fn test_closed_queues_are_removed() {
+        let mut state = ChatState::new(16);
+        let alice = join(&mut state, 1, "alice", DEFAULT_ROOM);
+        let _bob = join(&mut state, 2, "bob", "rust");
+
+        // alice's task went away without leaving
+        drop(alice);
+        assert!(state.remove_closed().is_empty());
+        state.send_to_all(&ServerMessage::notice("hey"));
+
+        let removed = state.remove_closed();
+        assert_eq!(removed.len(), 1);
+        assert_eq!(removed[0].name, "alice");
+        assert!(state.users_in(DEFAULT_ROOM).is_empty());
+        assert!(!state.is_name_taken("alice", &addr(3)));
+        assert!(state.remove_closed().is_empty());
+    }
+
+    #[test]
+    fn test_unique_names() {
+        let mut state = ChatState::new(16);
+
+        assert!(state.try_join(addr(1), "alice", DEFAULT_ROOM).is_some());
+        assert!(state.try_join(addr(2), "ALICE", DEFAULT_ROOM).is_none());
+        assert!(state.user(&addr(2)).is_none());
+        assert!(state.try_join(addr(2), "bob", DEFAULT_ROOM).is_some());
+
+        assert!(!state.try_rename(&addr(2), "Alice"));
+        // changing the case of your own name is fine
+        assert!(state.try_rename(&addr(1), "Alice"));
+        assert_eq!(state.user(&addr(1)).unwrap().name, "Alice");
+
+        // the name is free again once its owner leaves
+        state.leave(&addr(1));
+        assert!(state.try_rename(&addr(2), "alice"));
+        state.reserve_name("Dice");
+        assert!(state.try_join(addr(3), "dice", DEFAULT_ROOM).is_none());
+        assert!(!state.try_rename(&addr(2), "DICE"));
+
+        // accented names fold the same way as mutes and operators
+        assert!(state.try_join(addr(4), "Élan", DEFAULT_ROOM).is_some());
+        assert!(state.try_join(addr(5), "élan", DEFAULT_ROOM).is_none());
+        assert_eq!(state.find_by_name("ÉLAN"), vec![addr(4)]);
+        state.mute("élan", Duration::from_secs(60));
+        assert!(state.mute_remaining("Élan").is_some());
+    }
+
+    #[test]
+    fn test_kick_and_find() {
+        let mut state = ChatState::new(16);
+        let _alice = join(&mut state, 1, "Alice", DEFAULT_ROOM);
+        let _bob = join(&mut state, 2, "bob", "rust");
+
+        assert_eq!(state.find_by_name("alice"), vec![addr(1)]);
+        assert!(state.find_by_name("carol").is_empty());
+        assert_eq!(state.find_by_ip([127, 0, 0, 1].into()).len(), 2);
+
+        // switching rooms keeps the same kick handle
+        let kick = state.user(&addr(2)).unwrap().kick.clone();
+        state.move_to(&addr(2), DEFAULT_ROOM);
+        assert!(Arc::ptr_eq(&kick, &state.user(&addr(2)).unwrap().kick));
+
+        assert_eq!(state.kick(&addr(2)).unwrap().name, "bob");
+        assert!(kick.notified().now_or_never().is_some());
+    }
+
+    #[test]
+    fn test_operators_and_mutes() {
+        let mut state = ChatState::new(16);
+        state.set_operators(&["Admin".to_string()]);
+
+        assert!(state.is_operator("admin"));
+        assert!(!state.is_operator("bob"));
+
+        state.mute("Bob", Duration::from_secs(60));
+        assert!(state.mute_remaining("bob").unwrap() > Duration::from_secs(59));
+
+        state.mute("carol", Duration::ZERO);
+        assert_eq!(state.mute_remaining("carol"), None);
+    }
+
+    #[test]
+    fn test_recent_messages() {
+        let mut state = ChatState::new(16);
+        state.set_replay_size(2);
+        let _alice = join(&mut state, 1, "alice", "rust");
+
+        for body in ["one", "two", "three"] {
+            state.record("rust", &ServerMessage::chat("alice", "rust", body));
+        }
+        state.record("rust", &ServerMessage::notice("not a chat message"));
+        state.record("nowhere", &ServerMessage::chat("alice", "nowhere", "hi"));
+
+        let bodies: Vec<String> = state.recent("rust").into_iter().map(|m| m.body).collect();
+        assert_eq!(bodies, vec!["two", "three"]);
+        assert!(state.recent("nowhere").is_empty());
+
+        // forgotten along with the room
+        state.leave(&addr(1));
+        assert!(state.recent("rust").is_empty());
+    }
+
+    #[test]
+    fn test_room_names() {
+        assert!(is_valid_room_name("rust-lang_2024"));
+        assert!(!is_valid_room_name(""));
+        assert!(!is_valid_room_name("two words"));
+        assert!(!is_valid_room_name(&"a".repeat(33)));
+    }
+}
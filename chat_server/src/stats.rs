@@ -0,0 +1,310 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+/// Seconds of traffic the messages per second are averaged over.
+pub const RATE_WINDOW: usize = 60;
+
+/// Server wide counters, shared by every connection and the registry, shown
+/// to operators by `/stats` and exported by the metrics endpoint.
+///
+/// Also enforces the connection limit: every client holds one of
+/// `max_clients` slots for as long as it is connected.
+#[derive(Debug)]
+pub struct Stats {
+    started: Instant,
+    max_clients: usize,
+    slots: Arc<Semaphore>,
+    accepted: AtomicU64,
+    rejected: AtomicU64,
+    messages: AtomicU64,
+    deliveries: AtomicU64,
+    lag_events: AtomicU64,
+    lagged_messages: AtomicU64,
+    bytes_in: AtomicU64,
+    bytes_out: AtomicU64,
+    recent_messages: Mutex<RateWindow>,
+}
+
+/// The counters at one point in time.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct StatsSnapshot {
+    pub connected: usize,
+    pub max_clients: usize,
+    /// Connections let in since the server started.
+    pub accepted: u64,
+    /// Connections turned away because the server was full.
+    pub rejected: u64,
+    /// Chat messages and `/me` actions sent by clients.
+    pub messages: u64,
+    /// Averaged over the last `RATE_WINDOW` seconds.
+    pub messages_per_sec: f64,
+    /// Messages the registry handed to room members, one per receiver.
+    pub deliveries: u64,
+    /// Times a client's queue filled up and it started missing messages.
+    pub lag_events: u64,
+    /// Messages dropped for clients with a full queue.
+    pub lagged_messages: u64,
+    /// Raw bytes on the client sockets, TLS and WebSocket framing included.
+    pub bytes_in: u64,
+    pub bytes_out: u64,
+    pub uptime: Duration,
+}
+
+/// Message counts of the last `RATE_WINDOW` seconds, one bucket per second
+/// since the server started.
+#[derive(Debug)]
+struct RateWindow {
+    /// `(second, count)`, bucket `second % RATE_WINDOW`.
+    buckets: [(u64, u64); RATE_WINDOW],
+}
+
+impl Default for RateWindow {
+    fn default() -> Self {
+        RateWindow {
+            buckets: [(0, 0); RATE_WINDOW],
+        }
+    }
+}
+
+impl RateWindow {
+    fn record(&mut self, second: u64) {
+        let bucket = &mut self.buckets[second as usize % RATE_WINDOW];
+        if bucket.0 != second {
+            *bucket = (second, 0);
+        }
+        bucket.1 += 1;
+    }
+
+    /// Messages per second over the window ending at `second`, or over the
+    /// uptime when the server is younger than the window.
+    fn rate(&self, second: u64) -> f64 {
+        let window = (second + 1).min(RATE_WINDOW as u64);
+        let count: u64 = self
+            .buckets
+            .iter()
+            .filter(|(s, _)| *s <= second && second - s < window)
+            .map(|(_, count)| count)
+            .sum();
+
+        count as f64 / window as f64
+    }
+}
+
+impl Stats {
+    pub fn new(max_clients: usize) -> Arc<Self> {
+        Arc::new(Stats {
+            started: Instant::now(),
+            max_clients,
+            slots: Arc::new(Semaphore::new(max_clients)),
+            accepted: AtomicU64::new(0),
+            rejected: AtomicU64::new(0),
+            messages: AtomicU64::new(0),
+            deliveries: AtomicU64::new(0),
+            lag_events: AtomicU64::new(0),
+            lagged_messages: AtomicU64::new(0),
+            bytes_in: AtomicU64::new(0),
+            bytes_out: AtomicU64::new(0),
+            recent_messages: Mutex::new(RateWindow::default()),
+        })
+    }
+
+    /// Takes a connection slot, `None` when the server is full. The slot
+    /// is freed when the returned permit is dropped.
+    pub fn try_connect(&self) -> Option<OwnedSemaphorePermit> {
+        match self.slots.clone().try_acquire_owned() {
+            Ok(permit) => {
+                self.accepted.fetch_add(1, Ordering::Relaxed);
+                Some(permit)
+            }
+            Err(_) => {
+                self.rejected.fetch_add(1, Ordering::Relaxed);
+                None
+            }
+        }
+    }
+
+    pub fn max_clients(&self) -> usize {
+        self.max_clients
+    }
+
+    pub fn connected(&self) -> usize {
+        self.max_clients - self.slots.available_permits()
+    }
+
+    /// A chat message or action came in from a client.
+    pub fn record_message(&self) {
+        self.messages.fetch_add(1, Ordering::Relaxed);
+        let second = self.started.elapsed().as_secs();
+        self.recent_messages
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .record(second);
+    }
+
+    /// A message reached `receivers` members of a room.
+    pub fn record_deliveries(&self, receivers: usize) {
+        self.deliveries
+            .fetch_add(receivers as u64, Ordering::Relaxed);
+    }
+
+    /// `started` clients fell behind, and `missed` messages were dropped
+    /// for clients with a full queue.
+    pub fn record_lag(&self, started: usize, missed: usize) {
+        self.lag_events.fetch_add(started as u64, Ordering::Relaxed);
+        self.lagged_messages
+            .fetch_add(missed as u64, Ordering::Relaxed);
+    }
+
+    pub fn record_bytes_in(&self, bytes: usize) {
+        self.bytes_in.fetch_add(bytes as u64, Ordering::Relaxed);
+    }
+
+    pub fn record_bytes_out(&self, bytes: usize) {
+        self.bytes_out.fetch_add(bytes as u64, Ordering::Relaxed);
+    }
+
+    pub fn snapshot(&self) -> StatsSnapshot {
+        let uptime = self.started.elapsed();
+        let messages_per_sec = self
+            .recent_messages
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .rate(uptime.as_secs());
+
+        StatsSnapshot {
+            connected: self.connected(),
+            max_clients: self.max_clients,
+            accepted: self.accepted.load(Ordering::Relaxed),
+            rejected: self.rejected.load(Ordering::Relaxed),
+            messages: self.messages.load(Ordering::Relaxed),
+            messages_per_sec,
+            deliveries: self.deliveries.load(Ordering::Relaxed),
+            lag_events: self.lag_events.load(Ordering::Relaxed),
+            lagged_messages: self.lagged_messages.load(Ordering::Relaxed),
+            bytes_in: self.bytes_in.load(Ordering::Relaxed),
+            bytes_out: self.bytes_out.load(Ordering::Relaxed),
+            uptime,
+        }
+    }
+}
+
+impl StatsSnapshot {
+    /// The `/stats` reply.
+    pub fn summary(&self) -> String {
+        format!(
+            "Clients: {}/{} connected, {} accepted and {} rejected since start, up {}\n\
+             Messages: {} ({:.1}/s over the last minute), {} deliveries, {} lag event(s) missing {} message(s)\n\
+             Traffic: {} in, {} out",
+            self.connected,
+            self.max_clients,
+            self.accepted,
+            self.rejected,
+            format_duration(self.uptime),
+            self.messages,
+            self.messages_per_sec,
+            self.deliveries,
+            self.lag_events,
+            self.lagged_messages,
+            format_bytes(self.bytes_in),
+            format_bytes(self.bytes_out)
+        )
+    }
+}
+
+/// `512 B`, `1.5 KiB`, `3.2 MiB`...
+pub fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KiB", "MiB", "GiB", "TiB"];
+    if bytes < 1024 {
+        return format!("{} B", bytes);
+    }
+
+    let mut value = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+
+    format!("{:.1} {}", value, UNITS[unit])
+}
+
+/// `1h 02m 05s`
+pub fn format_duration(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    format!(
+        "{}h {:02}m {:02}s",
+        secs / 3600,
+        secs % 3600 / 60,
+        secs % 60
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_connection_limit() {
+        let stats = Stats::new(2);
+        let first = stats.try_connect().unwrap();
+        let _second = stats.try_connect().unwrap();
+        assert!(stats.try_connect().is_none());
+        assert_eq!(stats.connected(), 2);
+
+        // a client leaving frees its slot
+        drop(first);
+        assert_eq!(stats.connected(), 1);
+        assert!(stats.try_connect().is_some());
+
+        let snapshot = stats.snapshot();
+        assert_eq!((snapshot.accepted, snapshot.rejected), (3, 1));
+    }
+
+    #[test]
+    fn test_summary() {
+        let snapshot = StatsSnapshot {
+            connected: 3,
+            max_clients: 100,
+            accepted: 12,
+            rejected: 2,
+            messages: 40,
+            messages_per_sec: 0.5,
+            deliveries: 95,
+            lag_events: 1,
+            lagged_messages: 7,
+            bytes_in: 900,
+            bytes_out: 1536,
+            uptime: Duration::from_secs(3725),
+        };
+
+        let summary = snapshot.summary();
+        let lines: Vec<&str> = summary.lines().collect();
+        assert_eq!(
+            lines,
+            vec![
+                "Clients: 3/100 connected, 12 accepted and 2 rejected since start, up 1h 02m 05s",
+                "Messages: 40 (0.5/s over the last minute), 95 deliveries, 1 lag event(s) missing 7 message(s)",
+                "Traffic: 900 B in, 1.5 KiB out",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_message_rate() {
+        let mut window = RateWindow::default();
+        // young server: averaged over its uptime
+        window.record(0);
+        window.record(1);
+        window.record(1);
+        assert_eq!(window.rate(1), 1.5);
+
+        // only the last minute counts once the buckets wrap around
+        for _ in 0..30 {
+            window.record(100);
+        }
+        assert_eq!(window.rate(100), 0.5);
+        assert_eq!(window.rate(200), 0.0);
+    }
+}
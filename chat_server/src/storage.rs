@@ -0,0 +1,296 @@
+use crate::error::ChatError;
+use rusqlite::{Connection, OptionalExtension, params};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct StoredMessage {
+    pub sender: String,
+    pub room: String,
+    /// Unix timestamp in seconds
+    pub timestamp: i64,
+    pub body: String,
+}
+
+/// Chat history persisted in an embedded SQLite database.
+///
+/// `rusqlite` is blocking, so every query runs on tokio's blocking pool
+/// instead of stalling the client tasks.
+#[derive(Clone)]
+pub struct Storage {
+    conn: Arc<Mutex<Connection>>,
+}
+
+impl Storage {
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, ChatError> {
+        Self::init(Connection::open(path)?)
+    }
+
+    pub fn open_in_memory() -> Result<Self, ChatError> {
+        Self::init(Connection::open_in_memory()?)
+    }
+
+    fn init(conn: Connection) -> Result<Self, ChatError> {
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS messages (
+                id        INTEGER PRIMARY KEY AUTOINCREMENT,
+                sender    TEXT NOT NULL,
+                room      TEXT NOT NULL,
+                timestamp INTEGER NOT NULL,
+                body      TEXT NOT NULL
+            );
+            CREATE INDEX IF NOT EXISTS idx_messages_room ON messages (room, id);
+            CREATE TABLE IF NOT EXISTS accounts (
+                username      TEXT PRIMARY KEY COLLATE NOCASE,
+                password_hash TEXT NOT NULL
+            );
+            CREATE TABLE IF NOT EXISTS bans (
+                target    TEXT PRIMARY KEY COLLATE NOCASE,
+                banned_by TEXT NOT NULL,
+                timestamp INTEGER NOT NULL
+            );",
+        )?;
+
+        Ok(Storage {
+            conn: Arc::new(Mutex::new(conn)),
+        })
+    }
+
+    /// Closes the database, reporting anything SQLite failed to write.
+    /// If other clones are still alive the connection stays open until the
+    /// last one is dropped.
+    pub fn close(self) -> Result<(), ChatError> {
+        match Arc::try_unwrap(self.conn) {
+            Ok(conn) => conn
+                .into_inner()
+                .unwrap()
+                .close()
+                .map_err(|(_, e)| ChatError::Database(e)),
+            Err(_) => Ok(()),
+        }
+    }
+
+    pub async fn save_message(&self, message: StoredMessage) -> Result<(), ChatError> {
+        let conn = self.conn.clone();
+
+        tokio::task::spawn_blocking(move || {
+            conn.lock().unwrap().execute(
+                "INSERT INTO messages (sender, room, timestamp, body) VALUES (?1, ?2, ?3, ?4)",
+                params![
+                    message.sender,
+                    message.room,
+                    message.timestamp,
+                    message.body
+                ],
+            )?;
+
+            Ok(())
+        })
+        .await?
+    }
+
+    /// The last `limit` messages of `room`, oldest first.
+    pub async fn recent_messages(
+        &self,
+        room: &str,
+        limit: usize,
+    ) -> Result<Vec<StoredMessage>, ChatError> {
+        let conn = self.conn.clone();
+        let room = room.to_string();
+
+        tokio::task::spawn_blocking(move || {
+            let conn = conn.lock().unwrap();
+            let mut stmt = conn.prepare(
+                "SELECT sender, room, timestamp, body FROM messages
+                 WHERE room = ?1 ORDER BY id DESC LIMIT ?2",
+            )?;
+
+            let mut messages = stmt
+                .query_map(params![room, limit as i64], |row| {
+                    Ok(StoredMessage {
+                        sender: row.get(0)?,
+                        room: row.get(1)?,
+                        timestamp: row.get(2)?,
+                        body: row.get(3)?,
+                    })
+                })?
+                .collect::<Result<Vec<_>, _>>()?;
+            messages.reverse();
+
+            Ok(messages)
+        })
+        .await?
+    }
+
+    /// Stores a new account, returns `false` if the username is taken
+    /// (usernames are compared case-insensitively).
+    pub async fn create_account(
+        &self,
+        username: &str,
+        password_hash: &str,
+    ) -> Result<bool, ChatError> {
+        let conn = self.conn.clone();
+        let username = username.to_string();
+        let password_hash = password_hash.to_string();
+
+        tokio::task::spawn_blocking(move || {
+            let inserted = conn.lock().unwrap().execute(
+                "INSERT OR IGNORE INTO accounts (username, password_hash) VALUES (?1, ?2)",
+                params![username, password_hash],
+            )?;
+
+            Ok(inserted == 1)
+        })
+        .await?
+    }
+
+    pub async fn password_hash(&self, username: &str) -> Result<Option<String>, ChatError> {
+        let conn = self.conn.clone();
+        let username = username.to_string();
+
+        tokio::task::spawn_blocking(move || {
+            let hash = conn
+                .lock()
+                .unwrap()
+                .query_row(
+                    "SELECT password_hash FROM accounts WHERE username = ?1",
+                    params![username],
+                    |row| row.get(0),
+                )
+                .optional()?;
+
+            Ok(hash)
+        })
+        .await?
+    }
+
+    /// Bans a username or an IP address, returns `false` if it was
+    /// already banned.
+    pub async fn add_ban(&self, target: &str, banned_by: &str) -> Result<bool, ChatError> {
+        let conn = self.conn.clone();
+        let target = target.to_string();
+        let banned_by = banned_by.to_string();
+        let timestamp = chrono::Utc::now().timestamp();
+
+        tokio::task::spawn_blocking(move || {
+            let inserted = conn.lock().unwrap().execute(
+                "INSERT OR IGNORE INTO bans (target, banned_by, timestamp) VALUES (?1, ?2, ?3)",
+                params![target, banned_by, timestamp],
+            )?;
+
+            Ok(inserted == 1)
+        })
+        .await?
+    }
+
+    /// Lifts a ban, returns `false` if `target` wasn't banned.
+    pub async fn remove_ban(&self, target: &str) -> Result<bool, ChatError> {
+        let conn = self.conn.clone();
+        let target = target.to_string();
+
+        tokio::task::spawn_blocking(move || {
+            let removed = conn
+                .lock()
+                .unwrap()
+                .execute("DELETE FROM bans WHERE target = ?1", params![target])?;
+
+            Ok(removed == 1)
+        })
+        .await?
+    }
+
+    pub async fn is_banned(&self, target: &str) -> Result<bool, ChatError> {
+        let conn = self.conn.clone();
+        let target = target.to_string();
+
+        tokio::task::spawn_blocking(move || {
+            let banned = conn
+                .lock()
+                .unwrap()
+                .query_row(
+                    "SELECT 1 FROM bans WHERE target = ?1",
+                    params![target],
+                    |_| Ok(()),
+                )
+                .optional()?
+                .is_some();
+
+            Ok(banned)
+        })
+        .await?
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message(sender: &str, room: &str, body: &str) -> StoredMessage {
+        StoredMessage {
+            sender: sender.to_string(),
+            room: room.to_string(),
+            timestamp: 1_700_000_000,
+            body: body.to_string(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_recent_messages() {
+        let storage = Storage::open_in_memory().unwrap();
+
+        for body in ["one", "two", "three"] {
+            storage
+                .save_message(message("alice", "lobby", body))
+                .await
+                .unwrap();
+        }
+        storage
+            .save_message(message("bob", "rust", "elsewhere"))
+            .await
+            .unwrap();
+
+        let recent = storage.recent_messages("lobby", 2).await.unwrap();
+        assert_eq!(
+            recent,
+            vec![
+                message("alice", "lobby", "two"),
+                message("alice", "lobby", "three")
+            ]
+        );
+
+        let recent = storage.recent_messages("rust", 10).await.unwrap();
+        assert_eq!(recent, vec![message("bob", "rust", "elsewhere")]);
+    }
+
+    #[tokio::test]
+    async fn test_accounts() {
+        let storage = Storage::open_in_memory().unwrap();
+
+        assert_eq!(storage.password_hash("alice").await.unwrap(), None);
+        assert!(storage.create_account("alice", "hash-1").await.unwrap());
+
+        // usernames are reserved case-insensitively
+        assert!(!storage.create_account("ALICE", "hash-2").await.unwrap());
+        assert_eq!(
+            storage.password_hash("Alice").await.unwrap(),
+            Some("hash-1".to_string())
+        );
+    }
+
+    #[tokio::test]
+    async fn test_bans() {
+        let storage = Storage::open_in_memory().unwrap();
+
+        assert!(storage.add_ban("mallory", "admin").await.unwrap());
+        assert!(storage.add_ban("10.0.0.7", "admin").await.unwrap());
+        assert!(!storage.add_ban("Mallory", "admin").await.unwrap());
+
+        assert!(storage.is_banned("MALLORY").await.unwrap());
+        assert!(storage.is_banned("10.0.0.7").await.unwrap());
+        assert!(!storage.is_banned("alice").await.unwrap());
+
+        assert!(storage.remove_ban("mallory").await.unwrap());
+        assert!(!storage.remove_ban("mallory").await.unwrap());
+        assert!(!storage.is_banned("mallory").await.unwrap());
+    }
+}
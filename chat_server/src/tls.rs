@@ -0,0 +1,42 @@
+use crate::error::ChatError;
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+use std::sync::Arc;
+use tokio_rustls::TlsAcceptor;
+use tokio_rustls::rustls::ServerConfig;
+use tokio_rustls::rustls::pki_types::{CertificateDer, PrivateKeyDer};
+
+/// Builds a TLS acceptor from a PEM certificate chain and private key.
+pub fn load_acceptor(cert_path: &Path, key_path: &Path) -> Result<TlsAcceptor, ChatError> {
+    let certs = load_certs(cert_path)?;
+    let key = load_key(key_path)?;
+
+    let config = ServerConfig::builder()
+        .with_no_client_auth()
+        .with_single_cert(certs, key)
+        .map_err(|e| ChatError::Tls(e.to_string()))?;
+
+    Ok(TlsAcceptor::from(Arc::new(config)))
+}
+
+fn load_certs(path: &Path) -> Result<Vec<CertificateDer<'static>>, ChatError> {
+    let mut reader = BufReader::new(File::open(path)?);
+    let certs = rustls_pemfile::certs(&mut reader).collect::<Result<Vec<_>, _>>()?;
+
+    if certs.is_empty() {
+        return Err(ChatError::Tls(format!(
+            "no certificates found in {}",
+            path.display()
+        )));
+    }
+
+    Ok(certs)
+}
+
+fn load_key(path: &Path) -> Result<PrivateKeyDer<'static>, ChatError> {
+    let mut reader = BufReader::new(File::open(path)?);
+
+    rustls_pemfile::private_key(&mut reader)?
+        .ok_or_else(|| ChatError::Tls(format!("no private key found in {}", path.display())))
+}
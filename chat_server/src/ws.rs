@@ -0,0 +1,230 @@
+use crate::error::ChatError;
+use crate::protocol::MAX_MISSED_PONGS;
+use futures_util::{SinkExt, StreamExt};
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, DuplexStream};
+use tokio::time::{self, Instant};
+use tokio_tungstenite::WebSocketStream;
+use tokio_tungstenite::tungstenite::Message;
+use tracing::info;
+
+/// Size of the in-memory pipe between a WebSocket and its client task.
+pub const PIPE_SIZE: usize = 4096;
+
+/// How long output without a newline waits for the rest of its line before
+/// going out as a prompt.
+const PROMPT_WAIT: Duration = Duration::from_millis(50);
+
+/// Copies chat traffic between a WebSocket and one end of a duplex pipe.
+///
+/// The client task only sees the other end of the pipe, so it can treat a
+/// browser exactly like a TCP client: every text frame becomes one input
+/// line and every line the server writes goes out as its own text frame,
+/// however the pipe splits it. Output that stays without a newline, like a
+/// prompt, goes out as a frame of its own after `PROMPT_WAIT`.
+///
+/// With a `heartbeat` a ping frame goes out every interval, and the
+/// connection is closed after `MAX_MISSED_PONGS` pings without any frame
+/// coming back. Returns when either side closes.
+pub async fn bridge<S>(
+    mut ws: WebSocketStream<S>,
+    mut pipe: DuplexStream,
+    heartbeat: Option<Duration>,
+) -> Result<(), ChatError>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    let mut buf = vec![0; PIPE_SIZE];
+    let mut frames = Frames::default();
+    let mut prompt_at = None;
+    let mut missed_pongs = 0;
+    let mut next_ping = Instant::now() + heartbeat.unwrap_or_default();
+
+    loop {
+        tokio::select! {
+            frame = ws.next() => {
+                missed_pongs = 0;
+                match frame {
+                    Some(Ok(Message::Text(text))) => {
+                        let line = format!("{}\n", text.trim_end());
+                        pipe.write_all(line.as_bytes()).await?;
+                    }
+                    Some(Ok(Message::Close(_))) | None => break,
+                    // pings are answered by tungstenite itself, pongs were
+                    // counted above and binary frames have no meaning in
+                    // the chat
+                    Some(Ok(_)) => {}
+                    Some(Err(e)) => return Err(e.into()),
+                }
+            }
+
+            result = pipe.read(&mut buf) => {
+                let n = result?;
+                if n == 0 {
+                    if let Some(rest) = frames.flush() {
+                        let _ = ws.send(Message::text(rest)).await;
+                    }
+                    let _ = ws.close(None).await;
+                    break;
+                }
+
+                for line in frames.push(&buf[..n]) {
+                    ws.send(Message::text(line)).await?;
+                }
+                prompt_at = frames.has_partial().then(|| Instant::now() + PROMPT_WAIT);
+            }
+
+            _ = time::sleep_until(prompt_at.unwrap_or_else(Instant::now)), if prompt_at.is_some() => {
+                prompt_at = None;
+                if let Some(prompt) = frames.flush() {
+                    ws.send(Message::text(prompt)).await?;
+                }
+            }
+
+            _ = time::sleep_until(next_ping), if heartbeat.is_some() => {
+                if missed_pongs >= MAX_MISSED_PONGS {
+                    info!("No pong for {} pings, dropping the connection", missed_pongs);
+                    break;
+                }
+
+                missed_pongs += 1;
+                next_ping = Instant::now() + heartbeat.unwrap_or_default();
+                ws.send(Message::Ping(Default::default())).await?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Completes the WebSocket handshake only to send `text` and close, for
+/// clients that can't be let in.
+pub async fn reject<S>(stream: S, text: &str) -> Result<(), ChatError>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    let mut ws = tokio_tungstenite::accept_async(stream).await?;
+    ws.send(Message::text(text)).await?;
+    ws.close(None).await?;
+
+    Ok(())
+}
+
+/// Server output cut into one frame per line. Bytes are kept until their
+/// line is complete, so neither a line nor a character split between two
+/// reads of the pipe is cut in half.
+#[derive(Debug, Default)]
+struct Frames {
+    partial: Vec<u8>,
+}
+
+impl Frames {
+    /// The lines completed by `bytes`, blank ones left out
+    fn push(&mut self, bytes: &[u8]) -> Vec<String> {
+        self.partial.extend_from_slice(bytes);
+        let Some(end) = self.partial.iter().rposition(|&b| b == b'\n') else {
+            return Vec::new();
+        };
+
+        let rest = self.partial.split_off(end + 1);
+        let complete = std::mem::replace(&mut self.partial, rest);
+        // a newline is never part of a multi-byte character, so every
+        // line is whole
+        complete
+            .split(|&b| b == b'\n')
+            .filter(|line| !line.is_empty())
+            .map(|line| String::from_utf8_lossy(line).into_owned())
+            .collect()
+    }
+
+    fn has_partial(&self) -> bool {
+        !self.partial.is_empty()
+    }
+
+    /// What is left of an unfinished line, like a prompt
+    fn flush(&mut self) -> Option<String> {
+        let partial = std::mem::take(&mut self.partial);
+        (!partial.is_empty()).then(|| String::from_utf8_lossy(&partial).into_owned())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_frames() {
+        let mut frames = Frames::default();
+        assert_eq!(
+            frames.push(b"*** bob has joined #lobby ***\nalice: hi\n"),
+            vec!["*** bob has joined #lobby ***", "alice: hi"]
+        );
+        assert!(frames.push(b"\n").is_empty());
+
+        assert!(frames.push(b"Enter your Username: ").is_empty());
+        assert_eq!(frames.flush().as_deref(), Some("Enter your Username: "));
+        assert_eq!(frames.flush(), None);
+    }
+
+    #[test]
+    fn test_frames_split_across_reads() {
+        let mut frames = Frames::default();
+        let line = "alice: caf\u{e9} \u{1f980}\n".as_bytes();
+        // in the middle of the crab's four bytes
+        let (first, second) = line.split_at(line.len() - 3);
+
+        assert!(frames.push(&line[..4]).is_empty());
+        assert!(frames.push(&first[4..]).is_empty());
+        assert!(frames.has_partial());
+        assert_eq!(frames.push(second), vec!["alice: caf\u{e9} \u{1f980}"]);
+        assert!(!frames.has_partial());
+    }
+
+    #[tokio::test]
+    async fn test_bridge_sends_whole_lines() {
+        use tokio_tungstenite::tungstenite::protocol::Role;
+
+        let (server, client) = tokio::io::duplex(PIPE_SIZE);
+        let ws = WebSocketStream::from_raw_socket(server, Role::Server, None).await;
+        let mut client = WebSocketStream::from_raw_socket(client, Role::Client, None).await;
+        let (pipe, mut client_end) = tokio::io::duplex(PIPE_SIZE);
+        let bridged = tokio::spawn(bridge(ws, pipe, None));
+
+        let line = "alice: \u{1f980} ".repeat(1000) + "\n";
+        let (first, second) = line.as_bytes().split_at(line.len() / 2 + 1);
+        client_end.write_all(first).await.unwrap();
+        client_end.flush().await.unwrap();
+        time::sleep(PROMPT_WAIT / 5).await;
+        client_end.write_all(second).await.unwrap();
+        client_end.write_all(b"Password: ").await.unwrap();
+
+        let frame = client.next().await.unwrap().unwrap();
+        assert_eq!(
+            frame.into_text().unwrap().as_str(),
+            line.trim_end_matches('\n')
+        );
+        let prompt = client.next().await.unwrap().unwrap();
+        assert_eq!(prompt.into_text().unwrap().as_str(), "Password: ");
+
+        drop(client_end);
+        bridged.await.unwrap().unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_heartbeat_drops_silent_peer() {
+        use tokio_tungstenite::tungstenite::protocol::Role;
+
+        let (server, client) = tokio::io::duplex(PIPE_SIZE);
+        let ws = WebSocketStream::from_raw_socket(server, Role::Server, None).await;
+        // never polled, so it never answers the pings
+        let _client = WebSocketStream::from_raw_socket(client, Role::Client, None).await;
+        let (pipe, _client_end) = tokio::io::duplex(PIPE_SIZE);
+
+        let bridged = time::timeout(
+            Duration::from_secs(1),
+            bridge(ws, pipe, Some(Duration::from_millis(10))),
+        )
+        .await;
+        assert!(matches!(bridged, Ok(Ok(()))));
+    }
+}
@@ -0,0 +1,112 @@
+use crate::contact::Contact;
+use chrono::{Datelike, NaiveDate};
+
+/// How many days ahead `birthdays` looks unless told otherwise.
+pub const DEFAULT_DAYS: u32 = 30;
+
+/// A birthday coming up.
+#[derive(Debug, PartialEq)]
+pub struct Upcoming {
+    /// Index of the contact in the list.
+    pub index: usize,
+    pub date: NaiveDate,
+    /// How old the contact turns that day.
+    pub age: i32,
+}
+
+/// The contacts with a birthday in the next `days` days, today included,
+/// soonest first.
+pub fn upcoming(contacts: &[Contact], today: NaiveDate, days: u32) -> Vec<Upcoming> {
+    let mut upcoming: Vec<Upcoming> = contacts
+        .iter()
+        .enumerate()
+        .filter_map(|(index, contact)| {
+            let birthday = contact.birthday?;
+            let date = next_birthday(birthday, today);
+            ((date - today).num_days() <= i64::from(days)).then(|| Upcoming {
+                index,
+                date,
+                age: date.year() - birthday.year(),
+            })
+        })
+        .collect();
+    // stable, so the same day keeps the list order
+    upcoming.sort_by_key(|u| u.date);
+
+    upcoming
+}
+
+/// The first birthday on or after `today`. Someone born on 29 February
+/// gets the 28th in the other years.
+pub fn next_birthday(birthday: NaiveDate, today: NaiveDate) -> NaiveDate {
+    let in_year = |year| {
+        birthday
+            .with_year(year)
+            .or_else(|| NaiveDate::from_ymd_opt(year, 2, 28))
+            .expect("every year has a 28 February")
+    };
+
+    let date = in_year(today.year());
+    if date >= today {
+        date
+    } else {
+        in_year(today.year() + 1)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn date(y: i32, m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, d).unwrap()
+    }
+
+    fn born(name: &str, birthday: NaiveDate) -> Contact {
+        Contact {
+            name: name.to_string(),
+            birthday: Some(birthday),
+            ..Contact::default()
+        }
+    }
+
+    #[test]
+    fn test_next_birthday() {
+        let today = date(2025, 12, 20);
+        assert_eq!(next_birthday(date(1990, 12, 20), today), date(2025, 12, 20));
+        assert_eq!(next_birthday(date(1990, 12, 31), today), date(2025, 12, 31));
+        assert_eq!(next_birthday(date(1990, 1, 2), today), date(2026, 1, 2));
+        assert_eq!(next_birthday(date(2000, 2, 29), today), date(2026, 2, 28));
+        assert_eq!(
+            next_birthday(date(2000, 2, 29), date(2028, 1, 1)),
+            date(2028, 2, 29)
+        );
+    }
+
+    #[test]
+    fn test_upcoming() {
+        let contacts = vec![
+            born("ann", date(1990, 1, 5)),
+            Contact::new("bob", "090", "bob@best.com"),
+            born("cid", date(1985, 12, 24)),
+            born("dee", date(2001, 3, 1)),
+        ];
+
+        let upcoming = upcoming(&contacts, date(2025, 12, 20), 30);
+        assert_eq!(
+            upcoming,
+            vec![
+                Upcoming {
+                    index: 2,
+                    date: date(2025, 12, 24),
+                    age: 40
+                },
+                Upcoming {
+                    index: 0,
+                    date: date(2026, 1, 5),
+                    age: 36
+                },
+            ]
+        );
+    }
+}
@@ -0,0 +1,385 @@
+use crate::birthday;
+use crate::contact::{Contact, check_birthday};
+use chrono::NaiveDate;
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use std::path::PathBuf;
+
+/// Without a command the contact book asks what to do, one action at a
+/// time. With one it runs it, saves and exits, so it can be scripted.
+#[derive(Debug, Parser)]
+#[command(
+    name = "contact_book",
+    about = "A contact book, interactive unless a command is given"
+)]
+pub struct Cli {
+    /// Where contacts are kept: `contact.json` or the SQLite database
+    /// `contacts.db`
+    #[arg(long, value_enum, default_value_t = Backend::Json, global = true)]
+    pub store: Backend,
+
+    /// The file to keep the contacts in, by default in the platform data
+    /// directory (`~/.local/share/contact_book` on Linux)
+    #[arg(long, env = "CONTACT_BOOK_FILE", global = true)]
+    pub file: Option<PathBuf>,
+
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Add a contact
+    Add(AddArgs),
+    /// List contacts, all of them or the ones with a tag
+    List {
+        /// Only the contacts with this tag
+        tag: Option<String>,
+        #[arg(long, value_enum)]
+        sort: Option<SortField>,
+        /// Smallest first, the default except for `--sort recent`
+        #[arg(long, conflicts_with = "desc")]
+        asc: bool,
+        #[arg(long)]
+        desc: bool,
+        /// Show only this page of 20 contacts
+        #[arg(long, value_parser = clap::value_parser!(u64).range(1..))]
+        page: Option<u64>,
+    },
+    /// Find contacts by name (typos allowed), phone or email
+    Search {
+        /// What to look for, `name:`, `phone:` or `email:` first to look in
+        /// one field only, e.g. `email:@gmail.com`
+        #[arg(required = true)]
+        query: Vec<String>,
+    },
+    /// Change fields of a contact, e.g. `phone+=0908 "address=1 Main St"`
+    Edit {
+        /// The number `list` shows
+        number: usize,
+        /// field=value, field+=value or field-=value
+        #[arg(required = true)]
+        changes: Vec<String>,
+    },
+    /// Delete a contact
+    Delete {
+        number: usize,
+        /// Don't ask for confirmation
+        #[arg(long, short)]
+        yes: bool,
+    },
+    /// Add tags to a contact
+    Tag {
+        number: usize,
+        #[arg(required = true)]
+        tags: Vec<String>,
+    },
+    /// Remove tags from a contact
+    Untag {
+        number: usize,
+        #[arg(required = true)]
+        tags: Vec<String>,
+    },
+    /// Show every tag and how many contacts have it
+    Tags,
+    /// List the birthdays coming up, soonest first
+    Birthdays {
+        /// How many days ahead to look
+        #[arg(default_value_t = birthday::DEFAULT_DAYS)]
+        days: u32,
+    },
+    /// Find duplicates and ask before merging each pair
+    Dedupe,
+    /// Write all contacts to a file
+    Export { format: Format, path: PathBuf },
+    /// Add the contacts of a file
+    Import { format: Format, path: PathBuf },
+    /// Encrypt the JSON file with a passphrase, or change the passphrase
+    Encrypt,
+    /// Save the JSON file as plain JSON again
+    Decrypt,
+    /// Browse, search and change contacts in a full screen interface
+    Tui,
+    /// List the backups of the JSON file, or put one back
+    Restore {
+        /// The backup to put back, 1 is the newest
+        number: Option<usize>,
+    },
+}
+
+#[derive(Debug, Args)]
+pub struct AddArgs {
+    name: String,
+    /// Can be given several times
+    #[arg(long = "phone", short)]
+    phones: Vec<String>,
+    /// Can be given several times
+    #[arg(long = "email", short)]
+    emails: Vec<String>,
+    #[arg(long)]
+    address: Option<String>,
+    #[arg(long)]
+    company: Option<String>,
+    #[arg(long)]
+    notes: Option<String>,
+    /// YYYY-MM-DD
+    #[arg(long, value_parser = check_birthday)]
+    birthday: Option<NaiveDate>,
+    /// Can be given several times
+    #[arg(long = "tag", short)]
+    tags: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum Backend {
+    Json,
+    Sqlite,
+}
+
+impl Backend {
+    /// The name of the file in the data directory.
+    pub fn file_name(self) -> &'static str {
+        match self {
+            Backend::Json => "contact.json",
+            Backend::Sqlite => "contacts.db",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum SortField {
+    Name,
+    Recent,
+    Email,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum Format {
+    Csv,
+    Vcf,
+}
+
+impl AddArgs {
+    /// The contact to add, not checked yet.
+    pub fn contact(self) -> Contact {
+        let mut contact = Contact {
+            name: self.name,
+            phones: self.phones,
+            emails: self.emails,
+            address: self.address,
+            company: self.company,
+            notes: self.notes,
+            birthday: self.birthday,
+            tags: Vec::new(),
+        };
+        for tag in self.tags {
+            contact.tag(&tag);
+        }
+
+        contact
+    }
+}
+
+impl Command {
+    /// The command as it would be typed at the prompt, for the commands
+    /// that work the same way there. `None` for `add`, whose options
+    /// don't fit the prompt's `add <name> <phone> <email>`, and for
+    /// `encrypt`, `decrypt`, `tui` and `restore`, which only exist here.
+    pub fn to_args(&self) -> Option<Vec<String>> {
+        let with_number = |command: &str, number: &usize, rest: &[String]| {
+            [command.to_string(), number.to_string()]
+                .into_iter()
+                .chain(rest.iter().cloned())
+                .collect()
+        };
+        let file = |command: &str, format: &Format, path: &PathBuf| {
+            let format = format.to_possible_value().expect("no skipped formats");
+            vec![
+                command.to_string(),
+                format.get_name().to_string(),
+                path.display().to_string(),
+            ]
+        };
+
+        let args = match self {
+            Command::Add(_)
+            | Command::Encrypt
+            | Command::Decrypt
+            | Command::Tui
+            | Command::Restore { .. } => return None,
+            Command::List {
+                tag,
+                sort,
+                asc,
+                desc,
+                page,
+            } => {
+                let mut args = vec!["list".to_string()];
+                args.extend(tag.clone());
+                if let Some(sort) = sort {
+                    let sort = sort.to_possible_value().expect("no skipped fields");
+                    args.extend(["--sort".to_string(), sort.get_name().to_string()]);
+                }
+                if *asc {
+                    args.push("--asc".to_string());
+                }
+                if *desc {
+                    args.push("--desc".to_string());
+                }
+                if let Some(page) = page {
+                    args.extend(["--page".to_string(), page.to_string()]);
+                }
+                args
+            }
+            Command::Search { query } => ["search".to_string()]
+                .into_iter()
+                .chain(query.iter().cloned())
+                .collect(),
+            Command::Edit { number, changes } => with_number("edit", number, changes),
+            Command::Delete { number, yes } => {
+                let flags = if *yes {
+                    vec!["--yes".to_string()]
+                } else {
+                    vec![]
+                };
+                with_number("delete", number, &flags)
+            }
+            Command::Tag { number, tags } => with_number("tag", number, tags),
+            Command::Untag { number, tags } => with_number("untag", number, tags),
+            Command::Tags => vec!["tags".to_string()],
+            Command::Birthdays { days } => vec!["birthdays".to_string(), days.to_string()],
+            Command::Dedupe => vec!["dedupe".to_string()],
+            Command::Export { format, path } => file("export", format, path),
+            Command::Import { format, path } => file("import", format, path),
+        };
+
+        Some(args)
+    }
+}
+
+/// Splits a line typed at the prompt into words the way a shell would:
+/// spaces separate them, except inside `"..."` or `'...'`, and `\` keeps
+/// the next character as it is (outside single quotes). So
+/// `add "John Doe" 0901 j@x.com` has the name `John Doe`.
+pub fn split_line(line: &str) -> Result<Vec<String>, String> {
+    let mut words = Vec::new();
+    // `None` between words, so `""` still makes an (empty) word
+    let mut word: Option<String> = None;
+    let mut quote = None;
+    let mut chars = line.chars();
+
+    while let Some(c) = chars.next() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (None | Some('"'), '\\') => {
+                let escaped = chars.next().ok_or("Nothing after the last '\\'")?;
+                word.get_or_insert_default().push(escaped);
+            }
+            (Some(_), c) => word.get_or_insert_default().push(c),
+            (None, '"' | '\'') => {
+                quote = Some(c);
+                word.get_or_insert_default();
+            }
+            (None, c) if c.is_whitespace() => words.extend(word.take()),
+            (None, c) => word.get_or_insert_default().push(c),
+        }
+    }
+
+    if let Some(q) = quote {
+        return Err(format!("Missing the closing {}", q));
+    }
+    words.extend(word);
+
+    Ok(words)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn args(line: &[&str]) -> Option<Vec<String>> {
+        let cli = Cli::try_parse_from(["contact_book"].iter().chain(line)).unwrap();
+        cli.command.unwrap().to_args()
+    }
+
+    #[test]
+    fn test_commands_as_typed_at_the_prompt() {
+        assert_eq!(
+            args(&["list", "family", "--sort", "name", "--desc"]).unwrap(),
+            vec!["list", "family", "--sort", "name", "--desc"]
+        );
+        assert_eq!(args(&["search", "mike"]).unwrap(), vec!["search", "mike"]);
+        // one argument with a space stays one argument
+        assert_eq!(
+            args(&["edit", "2", "address=1 Main St", "phone+=0908"]).unwrap(),
+            vec!["edit", "2", "address=1 Main St", "phone+=0908"]
+        );
+        assert_eq!(
+            args(&["export", "vcf", "out.vcf"]).unwrap(),
+            vec!["export", "vcf", "out.vcf"]
+        );
+        assert_eq!(args(&["add", "John"]), None);
+
+        assert!(Cli::try_parse_from(["contact_book", "delete", "two"]).is_err());
+        assert!(Cli::try_parse_from(["contact_book", "export", "pdf", "x"]).is_err());
+        assert!(Cli::try_parse_from(["contact_book", "list", "--asc", "--desc"]).is_err());
+        assert!(
+            Cli::try_parse_from(["contact_book"])
+                .unwrap()
+                .command
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn test_split_line() {
+        let words = |line| split_line(line).unwrap();
+        assert_eq!(words("  list  family "), vec!["list", "family"]);
+        assert_eq!(
+            words(r#"add "John Doe" 0901 'j@x.com'"#),
+            vec!["add", "John Doe", "0901", "j@x.com"]
+        );
+        // quotes can start in the middle of a word, like in a shell
+        assert_eq!(
+            words(r#"edit 2 address="1 Main St" notes=''"#),
+            vec!["edit", "2", "address=1 Main St", "notes="]
+        );
+        assert_eq!(
+            words(r#"add "Dwayne \"The Rock\" Johnson" O\'Brien"#),
+            vec!["add", "Dwayne \"The Rock\" Johnson", "O'Brien"]
+        );
+        assert_eq!(words(r#"a "" b"#), vec!["a", "", "b"]);
+
+        assert!(split_line(r#"add "John Doe 0901"#).is_err());
+        assert!(split_line(r"add john\").is_err());
+    }
+
+    #[test]
+    fn test_add_args() {
+        let cli = Cli::try_parse_from([
+            "contact_book",
+            "add",
+            "John Doe",
+            "--phone",
+            "090123",
+            "-p",
+            "555",
+            "--email",
+            "j@x.com",
+            "--company",
+            "Acme Inc",
+            "--tag",
+            "Work",
+        ])
+        .unwrap();
+        let Some(Command::Add(add)) = cli.command else {
+            panic!("not an add");
+        };
+
+        let contact = add.contact();
+        assert_eq!(contact.name, "John Doe");
+        assert_eq!(contact.phones, vec!["090123", "555"]);
+        assert_eq!(contact.emails, vec!["j@x.com"]);
+        assert_eq!(contact.company.as_deref(), Some("Acme Inc"));
+        assert_eq!(contact.tags, vec!["work"]);
+    }
+}
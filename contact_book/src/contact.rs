@@ -0,0 +1,456 @@
+use chrono::{Local, NaiveDate};
+use serde::{Deserialize, Deserializer, Serialize};
+use std::fmt;
+
+/// Fields `edit` knows, in the order they are asked for.
+pub const FIELDS: [&str; 7] = [
+    "name", "phone", "email", "address", "company", "notes", "birthday",
+];
+
+/// One entry of the contact book.
+///
+/// Files from before contacts had several phones and emails store a single
+/// `phone` and `email` string. They still load, and are saved in the new
+/// shape.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Contact {
+    pub name: String,
+    #[serde(default, alias = "phone", deserialize_with = "one_or_many")]
+    pub phones: Vec<String>,
+    #[serde(default, alias = "email", deserialize_with = "one_or_many")]
+    pub emails: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub address: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub company: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub notes: Option<String>,
+    /// Saved as `YYYY-MM-DD`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub birthday: Option<NaiveDate>,
+    /// Groups the contact is in, lowercase and sorted.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+}
+
+/// How a `field=value` change applies to a list field.
+enum Change {
+    /// `phone=...` replaces the list (comma separated, empty clears it).
+    Set,
+    /// `phone+=...` adds to it.
+    Add,
+    /// `phone-=...` removes one entry.
+    Remove,
+}
+
+impl Contact {
+    pub fn new(name: &str, phone: &str, email: &str) -> Self {
+        Contact {
+            name: name.to_string(),
+            phones: vec![phone.to_string()],
+            emails: vec![email.to_string()],
+            ..Contact::default()
+        }
+    }
+
+    /// Checks what `add` and `edit` check, for contacts that come from
+    /// another file.
+    pub fn validate(&self) -> Result<(), String> {
+        if self.name.trim().is_empty() {
+            return Err("The name can't be empty".to_string());
+        }
+        self.phones
+            .iter()
+            .try_for_each(|phone| check_phone(phone))?;
+        self.emails
+            .iter()
+            .try_for_each(|email| check_email(email))?;
+        self.tags
+            .iter()
+            .try_for_each(|tag| check_tag(tag).map(drop))
+    }
+
+    /// The optional fields that are set and the tags, as `field: value`
+    /// lines.
+    pub fn details(&self) -> Vec<String> {
+        let birthday = self.birthday.map(|date| date.to_string());
+        let tags = (!self.tags.is_empty()).then(|| self.tags.join(", "));
+        [
+            ("address", &self.address),
+            ("company", &self.company),
+            ("notes", &self.notes),
+            ("birthday", &birthday),
+            ("tags", &tags),
+        ]
+        .into_iter()
+        .filter_map(|(field, value)| Some(format!("{}: {}", field, value.as_ref()?)))
+        .collect()
+    }
+
+    pub fn has_tag(&self, tag: &str) -> bool {
+        self.tags.iter().any(|t| t.eq_ignore_ascii_case(tag))
+    }
+
+    /// Adds a tag checked by `check_tag`, false if the contact already had
+    /// it.
+    pub fn tag(&mut self, tag: &str) -> bool {
+        if self.has_tag(tag) {
+            return false;
+        }
+        self.tags.push(tag.to_lowercase());
+        self.tags.sort();
+
+        true
+    }
+
+    /// Removes a tag, false if the contact didn't have it.
+    pub fn untag(&mut self, tag: &str) -> bool {
+        let before = self.tags.len();
+        self.tags.retain(|t| !t.eq_ignore_ascii_case(tag));
+
+        self.tags.len() != before
+    }
+
+    /// The current value of a field as `edit` shows it.
+    pub fn field(&self, field: &str) -> String {
+        match field {
+            "name" => self.name.clone(),
+            "phone" => self.phones.join(","),
+            "email" => self.emails.join(","),
+            "address" => self.address.clone().unwrap_or_default(),
+            "company" => self.company.clone().unwrap_or_default(),
+            "notes" => self.notes.clone().unwrap_or_default(),
+            "birthday" => self
+                .birthday
+                .map(|date| date.to_string())
+                .unwrap_or_default(),
+            _ => String::new(),
+        }
+    }
+
+    /// Applies `field=value` changes to a copy of the contact, so nothing
+    /// is changed unless they are all valid. Phones and emails also take
+    /// `+=` and `-=`, an empty value clears the optional fields.
+    pub fn edited(&self, changes: &[String]) -> Result<Contact, String> {
+        let mut edited = self.clone();
+
+        for change in changes {
+            let Some((field, value)) = change.split_once('=') else {
+                return Err(format!("Expected field=value, got '{}'", change));
+            };
+            let (field, how) = if let Some(field) = field.strip_suffix('+') {
+                (field, Change::Add)
+            } else if let Some(field) = field.strip_suffix('-') {
+                (field, Change::Remove)
+            } else {
+                (field, Change::Set)
+            };
+            let field = field.to_lowercase();
+            let value = value.trim();
+
+            match (field.as_str(), how) {
+                ("name", Change::Set) => {
+                    if value.is_empty() {
+                        return Err("The name can't be empty".to_string());
+                    }
+                    edited.name = value.to_string();
+                }
+                ("phone" | "phones", how) => {
+                    edit_list(&mut edited.phones, how, value, "phone", check_phone)?
+                }
+                ("email" | "emails", how) => {
+                    edit_list(&mut edited.emails, how, value, "email", check_email)?
+                }
+                ("address", Change::Set) => edited.address = optional(value),
+                ("company", Change::Set) => edited.company = optional(value),
+                ("notes", Change::Set) => edited.notes = optional(value),
+                ("birthday", Change::Set) => {
+                    edited.birthday = optional(value).map(|v| check_birthday(&v)).transpose()?
+                }
+                ("name" | "address" | "company" | "notes" | "birthday", _) => {
+                    return Err(format!(
+                        "Only phone and email take += and -=, not {}",
+                        field
+                    ));
+                }
+                _ => {
+                    return Err(format!(
+                        "Unknown field '{}', use {}",
+                        field,
+                        FIELDS.join(", ")
+                    ));
+                }
+            }
+        }
+
+        Ok(edited)
+    }
+}
+
+impl fmt::Display for Contact {
+    /// `john 0908213, 0908999 john@best.com`
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name)?;
+        for list in [&self.phones, &self.emails] {
+            if !list.is_empty() {
+                write!(f, " {}", list.join(", "))?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Checks a phone number: digits, with `-` between them and maybe a
+/// leading `+`, like `+44 20-7946-0958` without the spaces.
+pub fn check_phone(phone: &str) -> Result<(), String> {
+    let digits = phone.strip_prefix('+').unwrap_or(phone);
+    if !digits.chars().all(|c| c.is_ascii_digit() || c == '-') {
+        return Err(format!(
+            "Invalid phone '{}': use only digits, '-' and a leading '+'",
+            phone
+        ));
+    }
+    if !digits.chars().any(|c| c.is_ascii_digit()) {
+        return Err(format!("Invalid phone '{}': it has no digits", phone));
+    }
+
+    Ok(())
+}
+
+/// Checks that an email looks like `name@example.com`. Only the shape is
+/// checked, not whether the address exists.
+pub fn check_email(email: &str) -> Result<(), String> {
+    let valid = match email.split_once('@') {
+        Some((name, domain)) => {
+            !name.is_empty()
+                && !domain.contains('@')
+                && domain.contains('.')
+                && !domain.starts_with('.')
+                && !domain.ends_with('.')
+                && !email.chars().any(|c| c.is_whitespace() || c == ',')
+        }
+        None => false,
+    };
+
+    if valid {
+        Ok(())
+    } else {
+        Err(format!(
+            "Invalid email '{}': expected something like name@example.com",
+            email
+        ))
+    }
+}
+
+/// Reads a birthday written as `YYYY-MM-DD`, which can't be in the future.
+pub fn check_birthday(birthday: &str) -> Result<NaiveDate, String> {
+    let date = NaiveDate::parse_from_str(birthday.trim(), "%Y-%m-%d").map_err(|_| {
+        format!(
+            "Invalid birthday '{}': use YYYY-MM-DD, like 1990-05-17",
+            birthday
+        )
+    })?;
+    if date > Local::now().date_naive() {
+        return Err(format!(
+            "Invalid birthday '{}': it's in the future",
+            birthday
+        ));
+    }
+
+    Ok(date)
+}
+
+/// Checks a tag and returns it the way it's stored: one word of letters,
+/// digits, `-` and `_`, lowercase.
+pub fn check_tag(tag: &str) -> Result<String, String> {
+    let tag = tag.trim();
+    if tag.is_empty()
+        || !tag
+            .chars()
+            .all(|c| c.is_alphanumeric() || c == '-' || c == '_')
+    {
+        return Err(format!(
+            "Invalid tag '{}': use letters, digits, '-' and '_'",
+            tag
+        ));
+    }
+
+    Ok(tag.to_lowercase())
+}
+
+fn edit_list(
+    list: &mut Vec<String>,
+    how: Change,
+    value: &str,
+    what: &str,
+    check: fn(&str) -> Result<(), String>,
+) -> Result<(), String> {
+    let values: Vec<String> = value
+        .split(',')
+        .map(|v| v.trim().to_string())
+        .filter(|v| !v.is_empty())
+        .collect();
+    if !matches!(how, Change::Remove) {
+        values.iter().try_for_each(|v| check(v))?;
+    }
+
+    match how {
+        Change::Set => *list = values,
+        Change::Add => {
+            let before = list.len();
+            list.extend(values);
+            if list.len() == before {
+                return Err(format!("No {} to add", what));
+            }
+        }
+        Change::Remove => {
+            let before = list.len();
+            list.retain(|v| v != value);
+            if list.len() == before {
+                return Err(format!("No {} '{}' to remove", what, value));
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn optional(value: &str) -> Option<String> {
+    (!value.is_empty()).then(|| value.to_string())
+}
+
+/// Reads a list field that older files stored as a single string.
+fn one_or_many<'de, D>(deserializer: D) -> Result<Vec<String>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum OneOrMany {
+        One(String),
+        Many(Vec<String>),
+    }
+
+    Ok(match OneOrMany::deserialize(deserializer)? {
+        OneOrMany::One(value) if value.is_empty() => Vec::new(),
+        OneOrMany::One(value) => vec![value],
+        OneOrMany::Many(values) => values,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn changes(changes: &[&str]) -> Vec<String> {
+        changes.iter().map(|c| c.to_string()).collect()
+    }
+
+    #[test]
+    fn test_load_old_format() {
+        let json = r#"[{"name": "john", "phone": "0908213", "email": "john@best.com"}]"#;
+        let contacts: Vec<Contact> = serde_json::from_str(json).unwrap();
+        assert_eq!(
+            contacts,
+            vec![Contact::new("john", "0908213", "john@best.com")]
+        );
+
+        // saved in the new shape, without the optional fields nobody set
+        let json = serde_json::to_string(&contacts[0]).unwrap();
+        assert_eq!(
+            json,
+            r#"{"name":"john","phones":["0908213"],"emails":["john@best.com"]}"#
+        );
+        assert_eq!(serde_json::from_str::<Contact>(&json).unwrap(), contacts[0]);
+    }
+
+    #[test]
+    fn test_edited() {
+        let john = Contact::new("john", "090", "john@best.com");
+
+        let edited = john
+            .edited(&changes(&[
+                "phone+=091, 092",
+                "phone-=090",
+                "email=a@b.com,c@d.com",
+                "company=Acme",
+            ]))
+            .unwrap();
+        assert_eq!(edited.phones, vec!["091", "092"]);
+        assert_eq!(edited.emails, vec!["a@b.com", "c@d.com"]);
+        assert_eq!(edited.company.as_deref(), Some("Acme"));
+        assert_eq!(edited.to_string(), "john 091, 092 a@b.com, c@d.com");
+        assert_eq!(edited.details(), vec!["company: Acme"]);
+
+        let cleared = edited.edited(&changes(&["company=", "phone="])).unwrap();
+        assert_eq!(cleared.company, None);
+        assert_eq!(cleared.to_string(), "john a@b.com, c@d.com");
+
+        assert!(john.edited(&changes(&["phone-=123"])).is_err());
+        assert!(john.edited(&changes(&["notes+=more"])).is_err());
+        assert!(john.edited(&changes(&["age=30"])).is_err());
+
+        let born = john.edited(&changes(&["birthday=1990-05-17"])).unwrap();
+        assert_eq!(born.birthday, NaiveDate::from_ymd_opt(1990, 5, 17));
+        assert_eq!(born.details(), vec!["birthday: 1990-05-17"]);
+        assert_eq!(born.edited(&changes(&["birthday="])).unwrap(), john);
+        assert!(john.edited(&changes(&["birthday=17/05/1990"])).is_err());
+        assert!(john.edited(&changes(&["birthday=1990-02-30"])).is_err());
+        assert!(john.edited(&changes(&["birthday=2999-01-01"])).is_err());
+    }
+
+    #[test]
+    fn test_check_phone_and_email() {
+        for phone in ["0908213", "+44-20-7946-0958", "555-1234"] {
+            assert_eq!(check_phone(phone), Ok(()), "{}", phone);
+        }
+        for phone in ["09o8213", "12+34", "+", "--", "call me"] {
+            assert!(check_phone(phone).is_err(), "{}", phone);
+        }
+
+        for email in ["john@best.com", "j.doe+tag@mail.co.uk"] {
+            assert_eq!(check_email(email), Ok(()), "{}", email);
+        }
+        for email in [
+            "john",
+            "@best.com",
+            "john@best",
+            "john@.com",
+            "a@b@c.com",
+            "john@best.",
+        ] {
+            assert!(check_email(email).is_err(), "{}", email);
+        }
+
+        // nothing is changed by an edit with a bad value
+        let john = Contact::new("john", "090", "john@best.com");
+        assert_eq!(
+            john.edited(&changes(&["phone+=091", "email=john-at-best.com"])),
+            Err(
+                "Invalid email 'john-at-best.com': expected something like name@example.com"
+                    .to_string()
+            )
+        );
+        assert!(john.edited(&changes(&["phone=090,abc"])).is_err());
+    }
+
+    #[test]
+    fn test_tags() {
+        let mut john = Contact::new("john", "090", "john@best.com");
+        assert!(john.tag("work"));
+        assert!(john.tag("family"));
+        assert!(!john.tag("Family"));
+        assert_eq!(john.tags, vec!["family", "work"]);
+        assert!(john.has_tag("WORK"));
+        assert_eq!(john.details(), vec!["tags: family, work"]);
+
+        assert!(john.untag("Work"));
+        assert!(!john.untag("work"));
+        assert_eq!(john.tags, vec!["family"]);
+
+        assert_eq!(check_tag(" Book-Club "), Ok("book-club".to_string()));
+        assert!(check_tag("two words").is_err());
+        assert!(check_tag("a,b").is_err());
+    }
+}
@@ -0,0 +1,78 @@
+use argon2::Argon2;
+use chacha20poly1305::aead::rand_core::RngCore;
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+
+/// Start of an encrypted contact file, so it's recognised when loading.
+const MAGIC: &[u8] = b"contact_book encrypted v1\n";
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 12;
+
+pub fn is_encrypted(data: &[u8]) -> bool {
+    data.starts_with(MAGIC)
+}
+
+/// Encrypts with ChaCha20-Poly1305, under a key derived from the passphrase
+/// with Argon2. The file is the header, the salt, the nonce and the
+/// ciphertext; both the salt and the nonce are new every time.
+pub fn encrypt(plaintext: &[u8], passphrase: &str) -> Result<Vec<u8>, String> {
+    let mut salt = [0; SALT_LEN];
+    OsRng.fill_bytes(&mut salt);
+    let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
+
+    let cipher = ChaCha20Poly1305::new(&key(passphrase, &salt)?);
+    let ciphertext = cipher
+        .encrypt(&nonce, plaintext)
+        .map_err(|_| "Encryption failed".to_string())?;
+
+    Ok([MAGIC, &salt, &nonce, &ciphertext].concat())
+}
+
+pub fn decrypt(data: &[u8], passphrase: &str) -> Result<Vec<u8>, String> {
+    let data = data
+        .strip_prefix(MAGIC)
+        .filter(|data| data.len() >= SALT_LEN + NONCE_LEN)
+        .ok_or_else(|| "Not an encrypted contact file".to_string())?;
+    let (salt, rest) = data.split_at(SALT_LEN);
+    let (nonce, ciphertext) = rest.split_at(NONCE_LEN);
+
+    let cipher = ChaCha20Poly1305::new(&key(passphrase, salt)?);
+    // the tag can't tell a wrong passphrase from a damaged file
+    cipher
+        .decrypt(Nonce::from_slice(nonce), ciphertext)
+        .map_err(|_| "Wrong passphrase, or the file is damaged".to_string())
+}
+
+fn key(passphrase: &str, salt: &[u8]) -> Result<Key, String> {
+    let mut key = Key::default();
+    Argon2::default()
+        .hash_password_into(passphrase.as_bytes(), salt, &mut key)
+        .map_err(|e| format!("Can't derive a key from the passphrase: {}", e))?;
+
+    Ok(key)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_encrypt_decrypt() {
+        let json = br#"[{"name":"john"}]"#;
+        let data = encrypt(json, "correct horse").unwrap();
+
+        assert!(is_encrypted(&data));
+        assert!(!is_encrypted(json));
+        assert!(!data.windows(4).any(|w| w == b"john"));
+        assert_eq!(decrypt(&data, "correct horse").unwrap(), json);
+
+        assert!(decrypt(&data, "wrong horse").is_err());
+        let mut damaged = data.clone();
+        *damaged.last_mut().unwrap() ^= 1;
+        assert!(decrypt(&damaged, "correct horse").is_err());
+        assert!(decrypt(json, "correct horse").is_err());
+
+        // a new salt and nonce every time
+        assert_ne!(encrypt(json, "correct horse").unwrap(), data);
+    }
+}
@@ -0,0 +1,217 @@
+use crate::contact::{Contact, FIELDS};
+use crate::search::levenshtein;
+
+/// Two contacts that look like the same person, by their index in the
+/// list, and why.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Duplicate {
+    pub first: usize,
+    pub second: usize,
+    pub reason: String,
+}
+
+/// Every pair of contacts sharing a phone number (compared by digits) or an
+/// email (ignoring case), or with names one typo apart.
+pub fn find_duplicates(contacts: &[Contact]) -> Vec<Duplicate> {
+    let mut duplicates = Vec::new();
+    for (first, a) in contacts.iter().enumerate() {
+        for (second, b) in contacts.iter().enumerate().skip(first + 1) {
+            if let Some(reason) = why_duplicate(a, b) {
+                duplicates.push(Duplicate {
+                    first,
+                    second,
+                    reason,
+                });
+            }
+        }
+    }
+
+    duplicates
+}
+
+fn why_duplicate(a: &Contact, b: &Contact) -> Option<String> {
+    if let Some(phone) = a
+        .phones
+        .iter()
+        .find(|p| b.phones.iter().any(|q| same_phone(p, q)))
+    {
+        return Some(format!("same phone {}", phone));
+    }
+    if let Some(email) = a
+        .emails
+        .iter()
+        .find(|e| b.emails.iter().any(|f| e.eq_ignore_ascii_case(f)))
+    {
+        return Some(format!("same email {}", email));
+    }
+
+    let (name_a, name_b) = (simple_name(&a.name), simple_name(&b.name));
+    if name_a == name_b {
+        return Some("same name".to_string());
+    }
+    // short names one letter apart are often different people: Ann and Dan
+    if name_a.chars().count() >= 4 && levenshtein(&name_a, &name_b) <= 1 {
+        return Some("similar names".to_string());
+    }
+
+    None
+}
+
+/// `0908-213` and `0908213` are the same number.
+fn same_phone(a: &str, b: &str) -> bool {
+    let digits = |phone: &str| {
+        phone
+            .chars()
+            .filter(char::is_ascii_digit)
+            .collect::<String>()
+    };
+    digits(a) == digits(b)
+}
+
+/// Lowercase with single spaces.
+fn simple_name(name: &str) -> String {
+    name.split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase()
+}
+
+/// One contact with everything from both: the name and optional fields of
+/// `first` unless it doesn't have them, all the phones, emails and tags,
+/// and both notes.
+pub fn merge(first: &Contact, second: &Contact) -> Contact {
+    let mut merged = first.clone();
+
+    for phone in &second.phones {
+        if !merged.phones.iter().any(|p| same_phone(p, phone)) {
+            merged.phones.push(phone.clone());
+        }
+    }
+    for email in &second.emails {
+        if !merged.emails.iter().any(|e| e.eq_ignore_ascii_case(email)) {
+            merged.emails.push(email.clone());
+        }
+    }
+    for tag in &second.tags {
+        merged.tag(tag);
+    }
+
+    merged.address = merged.address.or(second.address.clone());
+    merged.company = merged.company.or(second.company.clone());
+    merged.birthday = merged.birthday.or(second.birthday);
+    merged.notes = match (merged.notes, &second.notes) {
+        (Some(a), Some(b)) if a != *b => Some(format!("{}; {}", a, b)),
+        (notes, other) => notes.or(other.clone()),
+    };
+
+    merged
+}
+
+/// The two contacts in columns, one line per field, with a `*` on the
+/// fields where they differ.
+pub fn side_by_side(first: (usize, &Contact), second: (usize, &Contact)) -> String {
+    let tags = |contact: &Contact| contact.tags.join(",");
+    let mut rows = vec![(
+        String::new(),
+        format!("#{}", first.0 + 1),
+        format!("#{}", second.0 + 1),
+    )];
+    for field in FIELDS {
+        rows.push((
+            field.to_string(),
+            first.1.field(field),
+            second.1.field(field),
+        ));
+    }
+    rows.push(("tags".to_string(), tags(first.1), tags(second.1)));
+
+    let width = rows
+        .iter()
+        .map(|(_, a, _)| a.chars().count())
+        .max()
+        .unwrap_or(0);
+    rows.iter()
+        .map(|(field, a, b)| {
+            let marker = if !field.is_empty() && a != b {
+                '*'
+            } else {
+                ' '
+            };
+            format!(
+                "{} {:<8} {:<width$}  {}",
+                marker,
+                field,
+                a,
+                b,
+                width = width
+            )
+            .trim_end()
+            .to_string()
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_find_duplicates() {
+        let contacts = vec![
+            Contact::new("John Smith", "0908-213", "john@best.com"),
+            Contact::new("mike", "098732", "mike@best.com"),
+            Contact::new("Johnny", "0908213", "jsmith@home.com"),
+            Contact::new("sarah", "345432", "MIKE@best.com"),
+            Contact::new("john  smith", "111", "js@x.com"),
+            Contact::new("Jon Smith", "222", "jon@x.com"),
+            Contact::new("Ann", "333", "ann@x.com"),
+            Contact::new("Dan", "444", "dan@x.com"),
+        ];
+
+        let duplicates = find_duplicates(&contacts);
+        let pairs: Vec<(usize, usize)> = duplicates.iter().map(|d| (d.first, d.second)).collect();
+        // Ann and Dan are too short to count as similar
+        assert_eq!(pairs, vec![(0, 2), (0, 4), (0, 5), (1, 3), (4, 5)]);
+        assert_eq!(duplicates[0].reason, "same phone 0908-213");
+        assert_eq!(duplicates[1].reason, "same name");
+        assert_eq!(duplicates[2].reason, "similar names");
+        assert_eq!(duplicates[3].reason, "same email mike@best.com");
+    }
+
+    #[test]
+    fn test_merge() {
+        let mut john = Contact::new("john", "0908-213", "john@best.com");
+        john.notes = Some("met at work".to_string());
+        john.tag("work");
+        let mut johnny = Contact::new("johnny", "0908213", "JOHN@best.com");
+        johnny.phones.push("555".to_string());
+        johnny.emails.push("john@home.com".to_string());
+        johnny.company = Some("Acme".to_string());
+        johnny.notes = Some("likes tea".to_string());
+        johnny.tag("family");
+
+        let merged = merge(&john, &johnny);
+        assert_eq!(merged.name, "john");
+        assert_eq!(merged.phones, vec!["0908-213", "555"]);
+        assert_eq!(merged.emails, vec!["john@best.com", "john@home.com"]);
+        assert_eq!(merged.company.as_deref(), Some("Acme"));
+        assert_eq!(merged.notes.as_deref(), Some("met at work; likes tea"));
+        assert_eq!(merged.tags, vec!["family", "work"]);
+
+        assert_eq!(merge(&john, &john), john);
+    }
+
+    #[test]
+    fn test_side_by_side() {
+        let john = Contact::new("john", "090", "john@best.com");
+        let johnny = Contact::new("johnny", "090", "john@best.com");
+
+        let text = side_by_side((0, &john), (3, &johnny));
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines[0], "           #1             #4");
+        assert_eq!(lines[1], "* name     john           johnny");
+        assert_eq!(lines[2], "  phone    090            090");
+        assert_eq!(lines.len(), 9);
+    }
+}
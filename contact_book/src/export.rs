@@ -0,0 +1,537 @@
+use crate::contact::{Contact, check_birthday, check_tag};
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+
+/// Longest vCard line in bytes, longer ones are folded.
+const VCARD_LINE_LEN: usize = 75;
+
+/// A contact as one CSV row. Phones, emails and tags share a cell,
+/// separated by commas, and missing optional fields are empty cells.
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+struct Row {
+    name: String,
+    #[serde(alias = "phone")]
+    phones: String,
+    #[serde(alias = "email")]
+    emails: String,
+    address: String,
+    company: String,
+    notes: String,
+    birthday: String,
+    tags: String,
+}
+
+impl From<&Contact> for Row {
+    fn from(contact: &Contact) -> Self {
+        Row {
+            name: contact.name.clone(),
+            phones: contact.phones.join(", "),
+            emails: contact.emails.join(", "),
+            address: contact.address.clone().unwrap_or_default(),
+            company: contact.company.clone().unwrap_or_default(),
+            notes: contact.notes.clone().unwrap_or_default(),
+            birthday: contact
+                .birthday
+                .map(|date| date.to_string())
+                .unwrap_or_default(),
+            tags: contact.tags.join(", "),
+        }
+    }
+}
+
+impl TryFrom<Row> for Contact {
+    type Error = String;
+
+    fn try_from(row: Row) -> Result<Self, Self::Error> {
+        let list = |cell: &str| -> Vec<String> {
+            cell.split([',', ';'])
+                .map(|v| v.trim().to_string())
+                .filter(|v| !v.is_empty())
+                .collect()
+        };
+        let optional = |cell: String| {
+            let cell = cell.trim();
+            (!cell.is_empty()).then(|| cell.to_string())
+        };
+
+        let mut contact = Contact {
+            name: row.name.trim().to_string(),
+            phones: list(&row.phones),
+            emails: list(&row.emails),
+            address: optional(row.address),
+            company: optional(row.company),
+            notes: optional(row.notes),
+            birthday: optional(row.birthday)
+                .map(|date| check_birthday(&date))
+                .transpose()?,
+            tags: Vec::new(),
+        };
+        for tag in list(&row.tags).iter().filter_map(|tag| import_tag(tag)) {
+            contact.tag(&tag);
+        }
+        contact.validate()?;
+
+        Ok(contact)
+    }
+}
+
+/// Writes the contacts to `path` as CSV, with a header row.
+pub fn export_csv(contacts: &[Contact], path: &Path) -> Result<(), String> {
+    let write = || -> Result<(), csv::Error> {
+        let mut writer = csv::Writer::from_path(path)?;
+        for contact in contacts {
+            writer.serialize(Row::from(contact))?;
+        }
+        writer.flush()?;
+
+        Ok(())
+    };
+
+    write().map_err(|e| format!("Can't write {}: {}", path.display(), e))
+}
+
+/// Reads contacts from a CSV file with a header row. Columns are matched by
+/// header, so their order doesn't matter and only `name` is required.
+///
+/// A bad row doesn't stop the import: the good contacts come back with one
+/// message per skipped row.
+pub fn import_csv(path: &Path) -> Result<(Vec<Contact>, Vec<String>), String> {
+    let mut reader = csv::ReaderBuilder::new()
+        .trim(csv::Trim::Headers)
+        .flexible(true)
+        .from_path(path)
+        .map_err(|e| format!("Can't read {}: {}", path.display(), e))?;
+
+    let headers = reader
+        .headers()
+        .map_err(|e| format!("Can't read {}: {}", path.display(), e))?
+        .clone();
+    if !headers.iter().any(|h| h == "name") {
+        return Err(format!("{} has no 'name' column", path.display()));
+    }
+
+    let mut contacts = Vec::new();
+    let mut errors = Vec::new();
+    for record in reader.records() {
+        let mut record = match record {
+            Ok(record) => record,
+            Err(e) => {
+                errors.push(e.to_string());
+                continue;
+            }
+        };
+        let line = record.position().map_or(0, |p| p.line());
+        // a row that stops early leaves the remaining fields empty
+        while record.len() < headers.len() {
+            record.push_field("");
+        }
+
+        let contact = record
+            .deserialize::<Row>(Some(&headers))
+            .map_err(|e| e.to_string())
+            .and_then(Contact::try_from);
+        match contact {
+            Ok(contact) => contacts.push(contact),
+            Err(e) => errors.push(format!("line {}: {}", line, e)),
+        }
+    }
+
+    Ok((contacts, errors))
+}
+
+/// Writes the contacts to `path` as vCard 3.0, one card per contact.
+pub fn export_vcf(contacts: &[Contact], path: &Path) -> Result<(), String> {
+    fs::write(path, to_vcard(contacts))
+        .map_err(|e| format!("Can't write {}: {}", path.display(), e))
+}
+
+/// Reads the cards of a `.vcf` file, as phones and Google Contacts export
+/// them (vCard 2.1, 3.0 or 4.0). Like `import_csv`, a card that fails the
+/// checks is skipped with a message and the others are still imported.
+pub fn import_vcf(path: &Path) -> Result<(Vec<Contact>, Vec<String>), String> {
+    let text =
+        fs::read_to_string(path).map_err(|e| format!("Can't read {}: {}", path.display(), e))?;
+
+    Ok(from_vcard(&text))
+}
+
+fn to_vcard(contacts: &[Contact]) -> String {
+    let mut text = String::new();
+    for contact in contacts {
+        let mut lines = vec![
+            "BEGIN:VCARD".to_string(),
+            "VERSION:3.0".to_string(),
+            format!("FN:{}", escape(&contact.name)),
+            // the name isn't split into given and family names
+            format!("N:;{};;;", escape(&contact.name)),
+        ];
+        for phone in &contact.phones {
+            lines.push(format!("TEL;TYPE=VOICE:{}", escape(phone)));
+        }
+        for email in &contact.emails {
+            lines.push(format!("EMAIL;TYPE=INTERNET:{}", escape(email)));
+        }
+        if let Some(address) = &contact.address {
+            // the whole address goes in the street part
+            lines.push(format!("ADR:;;{};;;;", escape(address)));
+        }
+        if let Some(company) = &contact.company {
+            lines.push(format!("ORG:{}", escape(company)));
+        }
+        if let Some(notes) = &contact.notes {
+            lines.push(format!("NOTE:{}", escape(notes)));
+        }
+        if let Some(birthday) = contact.birthday {
+            lines.push(format!("BDAY:{}", birthday));
+        }
+        if !contact.tags.is_empty() {
+            let tags: Vec<String> = contact.tags.iter().map(|tag| escape(tag)).collect();
+            lines.push(format!("CATEGORIES:{}", tags.join(",")));
+        }
+        lines.push("END:VCARD".to_string());
+
+        for line in lines {
+            text.push_str(&fold(&line));
+            text.push_str("\r\n");
+        }
+    }
+
+    text
+}
+
+fn from_vcard(text: &str) -> (Vec<Contact>, Vec<String>) {
+    let mut contacts = Vec::new();
+    let mut errors = Vec::new();
+    let mut card: Option<Contact> = None;
+    let mut count = 0;
+
+    for line in unfold(text) {
+        let Some((name, value)) = line.split_once(':') else {
+            continue;
+        };
+        // `item1.EMAIL;TYPE=INTERNET` -> `EMAIL`
+        let property = name.split(';').next().unwrap_or_default();
+        let property = property.rsplit('.').next().unwrap_or_default();
+
+        match (property.to_uppercase().as_str(), card.as_mut()) {
+            ("BEGIN", None) if value.eq_ignore_ascii_case("VCARD") => {
+                count += 1;
+                card = Some(Contact::default());
+            }
+            ("END", Some(_)) if value.eq_ignore_ascii_case("VCARD") => {
+                let contact = card.take().unwrap_or_default();
+                match contact.validate() {
+                    Ok(()) => contacts.push(contact),
+                    Err(e) => errors.push(format!("card {}: {}", count, e)),
+                }
+            }
+            ("FN", Some(contact)) => contact.name = unescape(value),
+            // only used when there's no FN, which vCard 2.1 allows
+            ("N", Some(contact)) if contact.name.is_empty() => {
+                let parts = split_unescaped(value);
+                // family;given;additional;prefix;suffix
+                let order = [3, 1, 2, 0, 4];
+                contact.name = order
+                    .iter()
+                    .filter_map(|&i| parts.get(i).filter(|p| !p.is_empty()))
+                    .cloned()
+                    .collect::<Vec<_>>()
+                    .join(" ");
+            }
+            ("TEL", Some(contact)) => {
+                let phone = unescape(value);
+                let phone = phone.strip_prefix("tel:").unwrap_or(&phone);
+                // `+1 (555) 123.4567` -> `+15551234567`
+                let phone: String = phone
+                    .chars()
+                    .filter(|c| !matches!(c, ' ' | '(' | ')' | '.'))
+                    .collect();
+                if !phone.is_empty() {
+                    contact.phones.push(phone);
+                }
+            }
+            ("EMAIL", Some(contact)) => {
+                let email = unescape(value);
+                if !email.is_empty() {
+                    contact.emails.push(email);
+                }
+            }
+            ("ADR", Some(contact)) => {
+                // pobox;extended;street;city;region;code;country
+                let address = split_unescaped(value)
+                    .into_iter()
+                    .filter(|p| !p.is_empty())
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                if !address.is_empty() {
+                    contact.address = Some(address);
+                }
+            }
+            ("ORG", Some(contact)) => {
+                let company = split_unescaped(value).join(", ");
+                contact.company = Some(company).filter(|c| !c.is_empty());
+            }
+            // Google Contacts puts its groups here
+            ("CATEGORIES", Some(contact)) => {
+                for tag in value
+                    .split(',')
+                    .filter_map(|tag| import_tag(&unescape(tag)))
+                {
+                    contact.tag(&tag);
+                }
+            }
+            ("NOTE", Some(contact)) => {
+                let notes = unescape(value);
+                contact.notes = Some(notes).filter(|n| !n.is_empty());
+            }
+            // `1990-05-17` or `19900517`, maybe with a time after it.
+            // Birthdays without a year (`--0517`) can't be kept
+            ("BDAY", Some(contact)) => {
+                let date = value.split('T').next().unwrap_or_default();
+                contact.birthday = NaiveDate::parse_from_str(date, "%Y-%m-%d")
+                    .or_else(|_| NaiveDate::parse_from_str(date, "%Y%m%d"))
+                    .ok();
+            }
+            _ => {}
+        }
+    }
+
+    if card.is_some() {
+        errors.push(format!("card {}: no END:VCARD", count));
+    }
+
+    (contacts, errors)
+}
+
+/// Turns a group from another app into a tag: anything but letters, digits,
+/// `-` and `_` becomes one `-`, so `Friends & Family` is `friends-family`.
+/// `None` when nothing is left, a group never fails the whole contact.
+fn import_tag(group: &str) -> Option<String> {
+    let mut tag = String::new();
+    for c in group.chars() {
+        if c.is_alphanumeric() || c == '_' {
+            tag.push(c);
+        } else if !tag.is_empty() && !tag.ends_with('-') {
+            tag.push('-');
+        }
+    }
+
+    check_tag(tag.trim_end_matches('-')).ok()
+}
+
+/// Escapes a text value: `\`, `,`, `;` and newlines.
+fn escape(value: &str) -> String {
+    let mut escaped = String::new();
+    for c in value.chars() {
+        match c {
+            '\\' | ',' | ';' => {
+                escaped.push('\\');
+                escaped.push(c);
+            }
+            '\n' => escaped.push_str("\\n"),
+            '\r' => {}
+            c => escaped.push(c),
+        }
+    }
+
+    escaped
+}
+
+fn unescape(value: &str) -> String {
+    let mut unescaped = String::new();
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            unescaped.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n' | 'N') => unescaped.push('\n'),
+            Some(c) => unescaped.push(c),
+            None => {}
+        }
+    }
+
+    unescaped
+}
+
+/// Splits a structured value like `N` or `ADR` on the `;` that aren't
+/// escaped, and unescapes the parts.
+fn split_unescaped(value: &str) -> Vec<String> {
+    let mut parts = Vec::new();
+    let mut part = String::new();
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => {
+                part.push(c);
+                part.extend(chars.next());
+            }
+            ';' => parts.push(unescape(&std::mem::take(&mut part))),
+            c => part.push(c),
+        }
+    }
+    parts.push(unescape(&part));
+
+    parts
+}
+
+/// Breaks a line longer than `VCARD_LINE_LEN` bytes into lines starting with
+/// a space, without cutting a character in two.
+fn fold(line: &str) -> String {
+    let mut folded = String::new();
+    let mut len = 0;
+    for c in line.chars() {
+        if len + c.len_utf8() > VCARD_LINE_LEN {
+            folded.push_str("\r\n ");
+            len = 1;
+        }
+        folded.push(c);
+        len += c.len_utf8();
+    }
+
+    folded
+}
+
+/// Joins folded lines back together.
+fn unfold(text: &str) -> Vec<String> {
+    let mut lines: Vec<String> = Vec::new();
+    for line in text.lines() {
+        match (line.strip_prefix([' ', '\t']), lines.last_mut()) {
+            (Some(rest), Some(last)) => last.push_str(rest),
+            _ => lines.push(line.to_string()),
+        }
+    }
+
+    lines
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::fs;
+
+    fn temp_path(name: &str) -> std::path::PathBuf {
+        std::env::temp_dir().join(format!("contact_book_{}_{}", std::process::id(), name))
+    }
+
+    #[test]
+    fn test_csv_round_trip() {
+        let mut john = Contact::new("john", "0908213", "john@best.com");
+        john.phones.push("+44-555".to_string());
+        john.address = Some("1 Main St, Springfield".to_string());
+        john.tag("family");
+        john.tag("work");
+        john.notes = Some("says \"hi\"".to_string());
+        john.birthday = NaiveDate::from_ymd_opt(1990, 5, 17);
+        let contacts = vec![john, Contact::new("sarah", "345432", "sarah@it.com")];
+
+        let path = temp_path("round_trip.csv");
+        export_csv(&contacts, &path).unwrap();
+        let text = fs::read_to_string(&path).unwrap();
+        assert!(text.starts_with("name,phones,emails,address,company,notes,birthday,tags\n"));
+        assert!(text.contains(
+            r#"john,"0908213, +44-555",john@best.com,"1 Main St, Springfield",,"says ""hi""",1990-05-17,"family, work""#
+        ));
+
+        let (imported, errors) = import_csv(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(imported, contacts);
+        assert!(errors.is_empty());
+    }
+
+    #[test]
+    fn test_import_reports_bad_rows() {
+        let path = temp_path("bad_rows.csv");
+        // columns in another order, a missing one and singular headers
+        fs::write(
+            &path,
+            "email,name,phone,tags\n\
+             bob@x.com,bob,0908,Book Club; work/clients; &\n\
+             nope,amy,0908\n\
+             ,,0908\n\
+             tom@x.com;t@y.com,tom\n",
+        )
+        .unwrap();
+        let (imported, errors) = import_csv(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        let names: Vec<&str> = imported.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(names, vec!["bob", "tom"]);
+        assert_eq!(imported[0].tags, vec!["book-club", "work-clients"]);
+        assert_eq!(imported[1].emails, vec!["tom@x.com", "t@y.com"]);
+        assert_eq!(
+            errors,
+            vec![
+                "line 3: Invalid email 'nope': expected something like name@example.com",
+                "line 4: The name can't be empty",
+            ]
+        );
+
+        assert!(import_csv(&temp_path("missing.csv")).is_err());
+    }
+
+    #[test]
+    fn test_vcard_round_trip() {
+        let mut john = Contact::new("john", "0908213", "john@best.com");
+        john.emails.push("john@home.com".to_string());
+        john.tag("family");
+        john.address = Some("1 Main St; Flat 2, Springfield".to_string());
+        john.notes = Some(format!("line one\n{}", "long ".repeat(20)));
+        john.birthday = NaiveDate::from_ymd_opt(2000, 2, 29);
+        let contacts = vec![john, Contact::new("sarah", "345432", "sarah@it.com")];
+
+        let text = to_vcard(&contacts);
+        assert!(text.starts_with("BEGIN:VCARD\r\nVERSION:3.0\r\nFN:john\r\n"));
+        assert!(text.contains("\r\nADR:;;1 Main St\\; Flat 2\\, Springfield;;;;\r\n"));
+        assert!(text.contains("\r\nCATEGORIES:family\r\n"));
+        assert!(text.contains("\r\nBDAY:2000-02-29\r\n"));
+        assert!(text.lines().all(|line| line.len() <= VCARD_LINE_LEN));
+
+        let (imported, errors) = from_vcard(&text);
+        assert_eq!(imported, contacts);
+        assert!(errors.is_empty());
+    }
+
+    #[test]
+    fn test_import_phone_export() {
+        // what Google Contacts and phones write
+        let text = "BEGIN:VCARD\n\
+                    VERSION:2.1\n\
+                    N:Doe;Jane;;Dr.;\n\
+                    TEL;CELL;PREF:+1 (555) 123.4567\n\
+                    item1.EMAIL;TYPE=INTERNET:jane@example.com\n\
+                    ORG:Acme;R&D\n\
+                    CATEGORIES:Friends,myContacts,My Contacts,Friends & Family,--\n\
+                    END:VCARD\n\
+                    BEGIN:VCARD\n\
+                    VERSION:4.0\n\
+                    FN:Bad Email\n\
+                    EMAIL:not-an-email\n\
+                    END:VCARD\n\
+                    BEGIN:VCARD\n\
+                    VERSION:4.0\n\
+                    FN:Tel Uri\n\
+                    TEL;VALUE=uri;TYPE=home:tel:+44-20-7946\n\
+                    END:VCARD\n";
+
+        let (imported, errors) = from_vcard(text);
+        assert_eq!(imported.len(), 2);
+        assert_eq!(imported[0].name, "Dr. Jane Doe");
+        assert_eq!(imported[0].phones, vec!["+15551234567"]);
+        assert_eq!(imported[0].emails, vec!["jane@example.com"]);
+        assert_eq!(imported[0].company.as_deref(), Some("Acme, R&D"));
+        assert_eq!(
+            imported[0].tags,
+            vec!["friends", "friends-family", "my-contacts", "mycontacts"]
+        );
+        assert_eq!(imported[1].phones, vec!["+44-20-7946"]);
+        assert_eq!(
+            errors,
+            vec!["card 2: Invalid email 'not-an-email': expected something like name@example.com"]
+        );
+    }
+}
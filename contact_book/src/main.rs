@@ -1,40 +1,208 @@
 // use io::Write;
-use serde::{Deserialize, Serialize};
+mod birthday;
+mod cli;
+mod contact;
+mod crypto;
+mod dedupe;
+mod export;
+mod search;
+mod sort;
+mod store;
+mod tui;
+mod undo;
+
+use chrono::{DateTime, Local};
+use clap::Parser;
+use cli::{Backend, Cli, Command, split_line};
+use contact::{Contact, FIELDS, check_tag};
+use search::Field;
 use std::{
-    fs::File,
-    io::{self, Read, Write},
+    collections::BTreeMap,
+    fs,
+    io::{self, IsTerminal, Write},
+    path::{Path, PathBuf},
+    process,
 };
+use store::{ContactStore, JsonStore, SqliteStore};
+use undo::History;
+
+/// Read instead of asking for the passphrase of an encrypted contact file,
+/// for scripts.
+const PASSPHRASE_VAR: &str = "CONTACT_BOOK_PASSPHRASE";
+const ADD_USAGE: &str =
+    "Usage: add <name> <phone> <email>, e.g. add \"John Doe\" 0234xxxx john@best.com
+Or just add, to be asked for each field";
+const SEARCH_USAGE: &str = "Usage: search <text>
+Or search name:<text>, phone:<text> or email:<text> to look in one field";
+const EDIT_USAGE: &str = "Usage: edit <number> [field=value ...]
+Fields: name, phone, email, address, company, notes, birthday (phone+=x / email+=x adds one, phone-=x / email-=x removes one)";
 
-#[derive(Debug, PartialEq, Serialize, Deserialize)]
-struct Contact {
-    name: String,
-    phone: String,
-    email: String,
+fn main() {
+    if let Err(e) = start(Cli::parse()) {
+        eprintln!("{}", e);
+        process::exit(1);
+    }
 }
 
-const FILE_PATH: &str = "contact.json";
+fn start(cli: Cli) -> Result<(), String> {
+    let path = data_file(cli.file, cli.store)?;
+
+    // works on the files, without reading or decrypting them
+    if let Some(Command::Restore { number }) = cli.command {
+        if !matches!(cli.store, Backend::Json) {
+            return Err("Only the JSON store keeps backups".to_string());
+        }
+        return restore(&JsonStore::new(&path), number);
+    }
+
+    let mut store = open_store(cli.store, &path)?;
+
+    // a search doesn't need every contact in memory
+    if let Some(Command::Search { query }) = &cli.command {
+        let query = query.join(" ");
+        let found = store.search(&query)?;
+        print_found(
+            &query,
+            &found
+                .iter()
+                .map(|(i, c, field)| (*i, c, *field))
+                .collect::<Vec<_>>(),
+        );
+        return Ok(());
+    }
+
+    let mut contact_list = store.load()?;
+
+    let before = contact_list.clone();
+    let Some(command) = cli.command else {
+        let mut history = History::default();
+        while execute(&mut contact_list, &mut history) {}
+        // an unchanged list would only push out a backup
+        if contact_list != before {
+            store.save(&contact_list)?;
+        }
+        return Ok(());
+    };
+
+    match command {
+        Command::Add(add) => add_contact(&mut contact_list, add.contact())?,
+        Command::Tui => tui::run(&mut contact_list)?,
+        Command::Restore { .. } => unreachable!("handled before loading"),
+        Command::Encrypt | Command::Decrypt if !matches!(cli.store, Backend::Json) => {
+            return Err("Only the JSON store can be encrypted".to_string());
+        }
+        Command::Encrypt => {
+            let passphrase = new_passphrase()?;
+            let mut encrypted = JsonStore::encrypted(&path, passphrase);
+            encrypted.save(&contact_list)?;
+            // they are in plain JSON, or use the old passphrase
+            encrypted.remove_backups()?;
+            println!("{} is encrypted, its backups are removed", path.display());
+            return Ok(());
+        }
+        Command::Decrypt => {
+            JsonStore::new(&path).save(&contact_list)?;
+            println!("{} is plain JSON again", path.display());
+            return Ok(());
+        }
+        command => {
+            let args = command.to_args().expect("the others have a prompt form");
+            let args: Vec<&str> = args.iter().map(String::as_str).collect();
+            run(&mut contact_list, &args)?;
+        }
+    }
+
+    if contact_list != before {
+        store.save(&contact_list)?;
+    }
 
-fn save_contact(contact_list: &Vec<Contact>) {
-    let json = serde_json::to_string_pretty(contact_list).unwrap();
-    let mut file = File::create(FILE_PATH).unwrap();
-    file.write_all(json.as_bytes()).unwrap();
+    Ok(())
 }
 
-fn load_contact() -> Vec<Contact> {
-    if let Ok(mut file) = File::open(FILE_PATH) {
-        let mut content = String::new();
-        file.read_to_string(&mut content).unwrap();
-        serde_json::from_str(&content).unwrap_or_else(|_| Vec::new())
-    } else {
-        Vec::new()
+/// `--file` or `CONTACT_BOOK_FILE` if given, otherwise the backend's file
+/// in the platform data directory. The directory is created if it's
+/// missing, the file itself on the first save.
+fn data_file(file: Option<PathBuf>, backend: Backend) -> Result<PathBuf, String> {
+    let path = match file {
+        Some(file) => file,
+        None => dirs::data_dir()
+            .ok_or("Can't find a data directory, use --file or set CONTACT_BOOK_FILE")?
+            .join("contact_book")
+            .join(backend.file_name()),
+    };
+
+    if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+        fs::create_dir_all(dir).map_err(|e| format!("Can't create {}: {}", dir.display(), e))?;
     }
+
+    Ok(path)
 }
 
-fn main() {
-    let mut contact_list = load_contact();
+/// Lists the backups, newest first, or puts backup `number` back.
+fn restore(store: &JsonStore, number: Option<usize>) -> Result<(), String> {
+    let Some(number) = number else {
+        let backups = store.backups();
+        if backups.is_empty() {
+            println!("No backups yet, one is made every time the contacts are saved");
+        }
+        for number in backups {
+            let path = store.backup_path(number);
+            let saved = fs::metadata(&path)
+                .and_then(|metadata| metadata.modified())
+                .map(|time| {
+                    DateTime::<Local>::from(time)
+                        .format("%Y-%m-%d %H:%M")
+                        .to_string()
+                })
+                .unwrap_or_default();
+            println!("{}. {} {}", number, path.display(), saved);
+        }
+        return Ok(());
+    };
+
+    store.restore(number)?;
+    println!(
+        "Backup {} restored, the file it replaced is now backup 1",
+        number
+    );
+
+    Ok(())
+}
+
+fn open_store(backend: Backend, path: &Path) -> Result<Box<dyn ContactStore>, String> {
+    Ok(match backend {
+        Backend::Json if JsonStore::new(path).is_encrypted() => {
+            let passphrase = passphrase(&format!("Passphrase for {}: ", path.display()))?;
+            Box::new(JsonStore::encrypted(path, passphrase))
+        }
+        Backend::Json => Box::new(JsonStore::new(path)),
+        Backend::Sqlite => Box::new(SqliteStore::open(path)?),
+    })
+}
+
+/// From `CONTACT_BOOK_PASSPHRASE` if it's set, otherwise asked for without
+/// showing what is typed.
+fn passphrase(prompt: &str) -> Result<String, String> {
+    if let Ok(passphrase) = std::env::var(PASSPHRASE_VAR) {
+        return Ok(passphrase);
+    }
+
+    rpassword::prompt_password(prompt)
+        .map_err(|e| format!("Can't read the passphrase: {}, set {}", e, PASSPHRASE_VAR))
+}
+
+/// A passphrase to encrypt with, typed twice unless it comes from
+/// `CONTACT_BOOK_PASSPHRASE`.
+fn new_passphrase() -> Result<String, String> {
+    let passphrase = passphrase("New passphrase: ")?;
+    if passphrase.is_empty() {
+        return Err("The passphrase can't be empty".to_string());
+    }
+    if std::env::var(PASSPHRASE_VAR).is_err() && self::passphrase("Again: ")? != passphrase {
+        return Err("The passphrases don't match".to_string());
+    }
 
-    while execute(&mut contact_list) {}
-    save_contact(&contact_list);
+    Ok(passphrase)
 }
 
 fn get_input() -> String {
@@ -43,103 +211,427 @@ fn get_input() -> String {
     buf.trim().to_string()
 }
 
-fn execute(contact_list: &mut Vec<Contact>) -> bool {
-    println!("Choose an action: add/list/delete/search/exit");
+fn execute(contact_list: &mut Vec<Contact>, history: &mut History) -> bool {
+    println!(
+        "Choose an action: add/list/edit/delete/undo/search/tag/untag/tags/birthdays/dedupe/import/export/exit"
+    );
 
     let input = get_input();
 
-    manage_contact(contact_list, input)
+    manage_contact(contact_list, history, input)
 }
 
-fn manage_contact(contact_list: &mut Vec<Contact>, input: String) -> bool {
-    let res: Vec<&str> = input.split_whitespace().collect();
+/// Runs a command typed in the interactive mode, where every change is
+/// recorded so `undo` can take it back.
+fn manage_contact(contact_list: &mut Vec<Contact>, history: &mut History, input: String) -> bool {
+    let words = match split_line(&input) {
+        Ok(words) => words,
+        Err(e) => {
+            println!("{}", e);
+            return true;
+        }
+    };
+    let res: Vec<&str> = words.iter().map(String::as_str).collect();
+
+    if res
+        .first()
+        .is_some_and(|command| command.eq_ignore_ascii_case("undo"))
+    {
+        match history.undo() {
+            Some((command, before)) => {
+                *contact_list = before;
+                println!("Undone: {}", command);
+            }
+            None => println!("Nothing to undo"),
+        }
+        return true;
+    }
+
+    let before = contact_list.clone();
+    let result = run(contact_list, &res);
+    if *contact_list != before {
+        history.record(input.trim(), before);
+    }
 
-    let binding = res[0].to_lowercase();
+    match result {
+        Ok(keep_going) => keep_going,
+        Err(e) => {
+            println!("{}", e);
+            true
+        }
+    }
+}
+
+/// Runs one command, `args[0]` being its name. `Ok(false)` means exit, an
+/// error is the message for whoever typed the command.
+fn run(contact_list: &mut Vec<Contact>, res: &[&str]) -> Result<bool, String> {
+    let Some(binding) = res.first().map(|command| command.to_lowercase()) else {
+        return Ok(true);
+    };
     let command = binding.as_str();
 
     match command {
         "add" => {
-            if res.len() < 4 {
-                println!("Please enter a valid contact, e.g. `add john 0234xxxx john@best.com");
-                return true;
-            }
-
-            let new_contact = Contact {
-                name: res[1].to_string(),
-                phone: res[2].to_string(),
-                email: res[3].to_string(),
+            let contact = match &res[1..] {
+                // with nothing after `add`, ask for each field
+                [] => Contact::default().edited(&prompt_changes(&Contact::default()))?,
+                // the phone and email are the last two words, so the name
+                // can have spaces even without quotes
+                [name @ .., phone, email] if !name.is_empty() => {
+                    Contact::new(&name.join(" "), phone, email)
+                }
+                _ => return Err(ADD_USAGE.to_string()),
             };
 
-            contact_list.push(new_contact);
-
-            return true;
+            add_contact(contact_list, contact)?;
         }
         "list" => {
+            let options = sort::parse_list_options(&res[1..])?;
+
             if contact_list.is_empty() {
                 println!("Contact is empty!");
             }
 
-            for i in 0..contact_list.len() {
-                let contact = contact_list.get(i).unwrap();
-                println!(
-                    "{}. {} {} {}",
-                    i + 1,
-                    contact.name,
-                    contact.phone,
-                    contact.email
-                );
+            let shown: Vec<(usize, &Contact)> =
+                sort::sorted(contact_list, options.sort_by, options.descending)
+                    .into_iter()
+                    .filter(|(_, contact)| {
+                        options.tag.as_ref().is_none_or(|tag| contact.has_tag(tag))
+                    })
+                    .collect();
+
+            if let (Some(tag), true) = (&options.tag, shown.is_empty()) {
+                println!("No contact tagged '{}'", tag);
             }
 
-            return true;
+            let pages = sort::page_count(shown.len());
+            match options.page {
+                Some(page) if page > pages => {
+                    return Err(format!("No page {}, the last one is {}", page, pages));
+                }
+                Some(page) => print_page(&shown, page),
+                // paging only makes sense for someone reading, a script
+                // gets everything
+                None if pages > 1 && io::stdin().is_terminal() && io::stdout().is_terminal() => {
+                    page_through(&shown)
+                }
+                None => shown
+                    .iter()
+                    .for_each(|(i, contact)| print_contact(i + 1, contact)),
+            }
         }
         "delete" => {
+            let yes = match res.get(2).copied() {
+                None => false,
+                Some("--yes" | "-y") if res.len() == 3 => true,
+                Some(_) => return Err("Usage: delete <number> [--yes]".to_string()),
+            };
             if res.len() < 2 {
-                println!("Usage: delete <number>");
-                return true;
+                return Err("Usage: delete <number> [--yes]".to_string());
             }
 
-            match res[1].parse::<usize>() {
-                Ok(i) if i > 0 && i < contact_list.len() => {
-                    contact_list.remove(i - 1);
-                    println!("Contact Deleted!")
-                }
-                _ => println!("Invalid contact number"),
+            let index = contact_index(res[1], contact_list.len())?;
+            let question = format!("Delete {}. {}? (y/n): ", index + 1, contact_list[index]);
+            if !yes && !confirm(&question) {
+                println!("Nothing deleted");
+                return Ok(true);
+            }
+            contact_list.remove(index);
+            println!("Contact Deleted!")
+        }
+        "edit" => {
+            let Some(number) = res.get(1) else {
+                return Err(EDIT_USAGE.to_string());
             };
+            let index = contact_index(number, contact_list.len())?;
 
-            return true;
+            // without changes on the line, ask for each field
+            let changes: Vec<String> = if res.len() > 2 {
+                res[2..].iter().map(|change| change.to_string()).collect()
+            } else {
+                prompt_changes(&contact_list[index])
+            };
+
+            if changes.is_empty() {
+                println!("Nothing changed");
+                return Ok(true);
+            }
+
+            let contact = contact_list[index].edited(&changes)?;
+            println!("Contact {} updated: {}", index + 1, contact);
+            contact_list[index] = contact;
         }
+        "tag" | "untag" => {
+            if res.len() < 3 {
+                return Err(format!("Usage: {} <number> <tag> [tag ...]", command));
+            }
+
+            let index = contact_index(res[1], contact_list.len())?;
+            let tags = res[2..]
+                .iter()
+                .map(|t| check_tag(t))
+                .collect::<Result<Vec<String>, String>>()?;
+
+            let contact = &mut contact_list[index];
+            for tag in tags {
+                let message = match command {
+                    "tag" if contact.tag(&tag) => "tagged",
+                    "tag" => "already tagged",
+                    _ if contact.untag(&tag) => "no longer tagged",
+                    _ => "wasn't tagged",
+                };
+                println!("Contact {} {} '{}'", index + 1, message, tag);
+            }
+        }
+        "tags" => {
+            let mut counts = BTreeMap::new();
+            for tag in contact_list.iter().flat_map(|contact| &contact.tags) {
+                *counts.entry(tag).or_insert(0) += 1;
+            }
+
+            if counts.is_empty() {
+                println!("No tags yet, add one with `tag <number> <tag>`");
+            }
+            for (tag, count) in counts {
+                println!("{} ({})", tag, count);
+            }
+        }
+        "birthdays" => {
+            let days = match res.get(1) {
+                None => birthday::DEFAULT_DAYS,
+                Some(days) => days
+                    .parse()
+                    .map_err(|_| "Usage: birthdays [days]".to_string())?,
+            };
+
+            let today = Local::now().date_naive();
+            let upcoming = birthday::upcoming(contact_list, today, days);
+            if upcoming.is_empty() {
+                println!("No birthdays in the next {} days", days);
+            }
+            for birthday in upcoming {
+                let when = match (birthday.date - today).num_days() {
+                    0 => "today".to_string(),
+                    1 => "tomorrow".to_string(),
+                    n => format!("in {} days", n),
+                };
+                println!(
+                    "{} ({}): {}. {} turns {}",
+                    birthday.date.format("%a %b %-d"),
+                    when,
+                    birthday.index + 1,
+                    contact_list[birthday.index].name,
+                    birthday.age
+                );
+            }
+        }
+        "dedupe" => dedupe_contacts(contact_list),
         "search" => {
             if res.len() < 2 {
-                println!("Usage: search <name>");
-                return true;
-            }
-
-            let name = res[1];
-            let mut found = false;
-            for (i, contact) in contact_list.iter().enumerate() {
-                if contact.name.contains(name) {
-                    println!(
-                        "{}. {} {} {}",
-                        i + 1,
-                        contact.name,
-                        contact.phone,
-                        contact.email
-                    );
-                    found = true;
-                }
+                return Err(SEARCH_USAGE.to_string());
             }
 
-            if !found {
-                print!("No contact found with name containing '{}'", name);
+            let query = res[1..].join(" ");
+            print_found(&query, &search::search(contact_list, &query));
+        }
+        "export" => {
+            let (Some(format), Some(path)) = (res.get(1), res.get(2)) else {
+                return Err("Usage: export csv|vcf <path>".to_string());
+            };
+
+            match *format {
+                "csv" => export::export_csv(contact_list, Path::new(path))?,
+                "vcf" => export::export_vcf(contact_list, Path::new(path))?,
+                _ => return Err("Usage: export csv|vcf <path>".to_string()),
             }
+            println!("Exported {} contacts to {}", contact_list.len(), path);
+        }
+        "import" => {
+            let (Some(format), Some(path)) = (res.get(1), res.get(2)) else {
+                return Err("Usage: import csv|vcf <path>".to_string());
+            };
 
-            return true;
+            let (contacts, errors) = match *format {
+                "csv" => export::import_csv(Path::new(path))?,
+                "vcf" => export::import_vcf(Path::new(path))?,
+                _ => return Err("Usage: import csv|vcf <path>".to_string()),
+            };
+            for e in &errors {
+                println!("Skipped {}", e);
+            }
+            println!(
+                "Imported {} contacts from {} ({} skipped)",
+                contacts.len(),
+                path,
+                errors.len()
+            );
+            contact_list.extend(contacts);
+        }
+        "exit" => return Ok(false),
+        _ => {}
+    }
+
+    Ok(true)
+}
+
+/// Adds a contact after checking it like `edit` would.
+fn add_contact(contact_list: &mut Vec<Contact>, contact: Contact) -> Result<(), String> {
+    contact.validate()?;
+    println!("Contact {} added: {}", contact_list.len() + 1, contact);
+    contact_list.push(contact);
+
+    Ok(())
+}
+
+/// Goes through the pairs `dedupe::find_duplicates` finds, asking before
+/// merging each one.
+fn dedupe_contacts(contact_list: &mut Vec<Contact>) {
+    // pairs already answered with no, as contacts since merging moves the
+    // numbers
+    let mut kept_apart: Vec<(Contact, Contact)> = Vec::new();
+    let mut shown = 0;
+    let mut merged = 0;
+
+    loop {
+        let next = dedupe::find_duplicates(contact_list).into_iter().find(|d| {
+            let pair = (
+                contact_list[d.first].clone(),
+                contact_list[d.second].clone(),
+            );
+            !kept_apart.contains(&pair)
+        });
+        let Some(duplicate) = next else {
+            break;
+        };
+        let (first, second) = (duplicate.first, duplicate.second);
+        shown += 1;
+
+        println!(
+            "Contacts {} and {} look like the same person ({}):",
+            first + 1,
+            second + 1,
+            duplicate.reason
+        );
+        println!(
+            "{}",
+            dedupe::side_by_side(
+                (first, &contact_list[first]),
+                (second, &contact_list[second])
+            )
+        );
+        print!("Merge {} into {}? (y/n/q): ", second + 1, first + 1);
+        io::stdout().flush().unwrap();
+
+        match get_input().to_lowercase().as_str() {
+            "y" | "yes" => {
+                contact_list[first] = dedupe::merge(&contact_list[first], &contact_list[second]);
+                contact_list.remove(second);
+                merged += 1;
+                println!("Merged into contact {}: {}", first + 1, contact_list[first]);
+            }
+            "q" | "quit" => break,
+            _ => kept_apart.push((contact_list[first].clone(), contact_list[second].clone())),
+        }
+    }
+
+    match shown {
+        0 => println!("No duplicates found"),
+        _ => println!("{} of {} duplicates merged", merged, shown),
+    }
+}
+
+/// The index of a contact from the number `list` shows.
+fn contact_index(number: &str, len: usize) -> Result<usize, String> {
+    match number.parse::<usize>() {
+        Ok(i) if i > 0 && i <= len => Ok(i - 1),
+        _ => Err("Invalid contact number".to_string()),
+    }
+}
+
+/// Like `print_contact`, with the field that matched.
+fn print_found(query: &str, found: &[(usize, &Contact, Field)]) {
+    for (i, contact, field) in found {
+        println!("{}. {} ({} match)", i + 1, contact, field);
+        for line in contact.details() {
+            println!("   {}", line);
+        }
+    }
+
+    if found.is_empty() {
+        println!("No contact matches '{}'", query);
+    }
+}
+
+/// Page `page` of `contacts`, with where it is when there's more than one.
+fn print_page(contacts: &[(usize, &Contact)], page: usize) {
+    for (i, contact) in sort::page(contacts, page) {
+        print_contact(i + 1, contact);
+    }
+
+    let pages = sort::page_count(contacts.len());
+    if pages > 1 {
+        println!("-- page {} of {} --", page, pages);
+    }
+}
+
+/// Shows `contacts` a page at a time, asking where to go after each one.
+fn page_through(contacts: &[(usize, &Contact)]) {
+    let pages = sort::page_count(contacts.len());
+    let mut page = 1;
+
+    loop {
+        print_page(contacts, page);
+        print!("(n)ext, (p)revious, a page number or (q)uit: ");
+        io::stdout().flush().unwrap();
+
+        page = match get_input().to_lowercase().as_str() {
+            "" | "n" | "next" if page < pages => page + 1,
+            "p" | "prev" | "previous" if page > 1 => page - 1,
+            "" | "n" | "next" => return,
+            "q" | "quit" => return,
+            input => match input.parse::<usize>() {
+                Ok(n) if (1..=pages).contains(&n) => n,
+                _ => page,
+            },
+        };
+    }
+}
+
+/// Asks a yes/no question, anything but yes is a no.
+fn confirm(question: &str) -> bool {
+    print!("{}", question);
+    io::stdout().flush().unwrap();
+
+    matches!(get_input().to_lowercase().as_str(), "y" | "yes")
+}
+
+/// `1. john 0908213 john@best.com`, then the optional fields that are set.
+fn print_contact(number: usize, contact: &Contact) {
+    println!("{}. {}", number, contact);
+    for line in contact.details() {
+        println!("   {}", line);
+    }
+}
+
+/// Asks for every field, an empty answer keeps the current value and `-`
+/// clears it.
+fn prompt_changes(contact: &Contact) -> Vec<String> {
+    let mut changes = Vec::new();
+    for field in FIELDS {
+        match contact.field(field).as_str() {
+            "" => print!("{}: ", field),
+            current => print!("{} ({}): ", field, current),
         }
-        "exit" => {
-            return false;
+        io::stdout().flush().unwrap();
+
+        match get_input().as_str() {
+            "" => {}
+            "-" => changes.push(format!("{}=", field)),
+            value => changes.push(format!("{}={}", field, value)),
         }
-        _ => true,
     }
+
+    changes
 }
 
 #[cfg(test)]
@@ -147,53 +639,221 @@ mod test {
     use super::*;
 
     fn get_john_contact() -> Contact {
-        Contact {
-            name: "john".to_string(),
-            phone: "090123".to_string(),
-            email: "john@best.com".to_string(),
-        }
+        Contact::new("john", "090123", "john@best.com")
     }
 
     fn get_mike_contact() -> Contact {
-        Contact {
-            name: "mike".to_string(),
-            phone: "090234".to_string(),
-            email: "mike@best.com".to_string(),
-        }
+        Contact::new("mike", "090234", "mike@best.com")
     }
 
     #[test]
     fn test_manage_contact() {
         let mut contact_list = Vec::<Contact>::new();
+        let mut history = History::default();
         let john_contact: Contact = get_john_contact();
         let mike_contact: Contact = get_mike_contact();
         let input_john: String = format!(
             "add {} {} {}",
-            john_contact.name, john_contact.phone, john_contact.email
+            john_contact.name, john_contact.phones[0], john_contact.emails[0]
         );
         let input_mike: String = format!(
             "add {} {} {}",
-            mike_contact.name, mike_contact.phone, mike_contact.email
+            mike_contact.name, mike_contact.phones[0], mike_contact.emails[0]
         );
 
         // add contact
-        assert!(manage_contact(&mut contact_list, input_john));
-        assert_eq!(*contact_list.get(0).unwrap(), get_john_contact());
+        assert!(manage_contact(&mut contact_list, &mut history, input_john));
+        assert_eq!(*contact_list.first().unwrap(), get_john_contact());
 
         // list contact
         assert!(contact_list.len() == 1, "Contact list len should be 1");
-        manage_contact(&mut contact_list, input_mike.clone());
+        manage_contact(&mut contact_list, &mut history, input_mike.clone());
         assert!(contact_list.len() == 2, "Contact list len should be 2");
 
+        // invalid phone or email
+        manage_contact(
+            &mut contact_list,
+            &mut history,
+            "add bob 09o8 bob@best.com".to_string(),
+        );
+        manage_contact(
+            &mut contact_list,
+            &mut history,
+            "add bob 0908 bob.best.com".to_string(),
+        );
+        assert!(contact_list.len() == 2, "Invalid contact added");
+
+        // a name with spaces, quoted or not
+        manage_contact(
+            &mut contact_list,
+            &mut history,
+            r#"add "John Doe" 0901 j@x.com"#.to_string(),
+        );
+        manage_contact(
+            &mut contact_list,
+            &mut history,
+            "add Mary Jane Watson 0902 mj@x.com".to_string(),
+        );
+        assert_eq!(contact_list[2].name, "John Doe");
+        assert_eq!(contact_list[3].name, "Mary Jane Watson");
+        assert_eq!(contact_list[3].phones, vec!["0902"]);
+        contact_list.truncate(2);
+
         // delete contact
-        assert!(manage_contact(&mut contact_list, "delete 1".to_string()));
+        assert!(manage_contact(
+            &mut contact_list,
+            &mut history,
+            "delete 1 --yes".to_string()
+        ));
         assert!(contact_list.len() == 1, "Contact not deleted");
 
         // search
-        manage_contact(&mut contact_list, input_mike);
-        assert!(manage_contact(&mut contact_list, "search mike".to_string()));
+        manage_contact(&mut contact_list, &mut history, input_mike);
+        assert!(manage_contact(
+            &mut contact_list,
+            &mut history,
+            "search mike".to_string()
+        ));
 
         // exist
-        assert!(!manage_contact(&mut contact_list, "exit".to_string()));
+        assert!(!manage_contact(
+            &mut contact_list,
+            &mut history,
+            "exit".to_string()
+        ));
+    }
+
+    #[test]
+    fn test_delete_last_contact() {
+        let mut contact_list = vec![get_john_contact(), get_mike_contact()];
+        let mut history = History::default();
+
+        manage_contact(
+            &mut contact_list,
+            &mut history,
+            "delete 3 --yes".to_string(),
+        );
+        assert_eq!(contact_list.len(), 2);
+        manage_contact(
+            &mut contact_list,
+            &mut history,
+            "delete 2 --yes".to_string(),
+        );
+        assert_eq!(contact_list, vec![get_john_contact()]);
+        manage_contact(
+            &mut contact_list,
+            &mut history,
+            "delete 1 --yes".to_string(),
+        );
+        assert!(contact_list.is_empty());
+    }
+
+    #[test]
+    fn test_edit_contact() {
+        let mut contact_list = vec![get_john_contact(), get_mike_contact()];
+        let mut history = History::default();
+
+        assert!(manage_contact(
+            &mut contact_list,
+            &mut history,
+            "edit 2 phone=555 EMAIL=mike@home.com".to_string()
+        ));
+        assert_eq!(contact_list[1].phones, vec!["555"]);
+        assert_eq!(contact_list[1].emails, vec!["mike@home.com"]);
+        assert_eq!(contact_list[1].name, "mike");
+
+        // a bad change leaves the contact as it was
+        manage_contact(
+            &mut contact_list,
+            &mut history,
+            "edit 1 phone=777 age=30".to_string(),
+        );
+        assert_eq!(contact_list[0], get_john_contact());
+        manage_contact(
+            &mut contact_list,
+            &mut history,
+            "edit 3 phone=777".to_string(),
+        );
+        assert_eq!(contact_list[0], get_john_contact());
+
+        let changes = vec!["phone".to_string()];
+        assert!(contact_list[0].edited(&changes).is_err());
+        let changes = vec!["name=".to_string()];
+        assert_eq!(
+            contact_list[0].edited(&changes),
+            Err("The name can't be empty".to_string())
+        );
+    }
+
+    #[test]
+    fn test_tag_contact() {
+        let mut contact_list = vec![get_john_contact(), get_mike_contact()];
+        let mut history = History::default();
+
+        manage_contact(
+            &mut contact_list,
+            &mut history,
+            "tag 2 Family work".to_string(),
+        );
+        assert_eq!(contact_list[1].tags, vec!["family", "work"]);
+        manage_contact(&mut contact_list, &mut history, "untag 2 work".to_string());
+        assert_eq!(contact_list[1].tags, vec!["family"]);
+
+        // nothing is tagged when one of the tags is invalid
+        manage_contact(
+            &mut contact_list,
+            &mut history,
+            "tag 1 friends no!".to_string(),
+        );
+        assert!(contact_list[0].tags.is_empty());
+
+        assert!(manage_contact(
+            &mut contact_list,
+            &mut history,
+            "list family".to_string()
+        ));
+    }
+
+    #[test]
+    fn test_undo() {
+        let mut contact_list = vec![get_john_contact()];
+        let mut history = History::default();
+
+        manage_contact(
+            &mut contact_list,
+            &mut history,
+            "add mike 090234 mike@best.com".to_string(),
+        );
+        manage_contact(
+            &mut contact_list,
+            &mut history,
+            "edit 1 name=johnny".to_string(),
+        );
+        manage_contact(
+            &mut contact_list,
+            &mut history,
+            "delete 1 --yes".to_string(),
+        );
+        // nothing changed, nothing to undo
+        manage_contact(&mut contact_list, &mut history, "list".to_string());
+        assert_eq!(contact_list, vec![get_mike_contact()]);
+
+        manage_contact(&mut contact_list, &mut history, "undo".to_string());
+        assert_eq!(contact_list[0].name, "johnny");
+        manage_contact(&mut contact_list, &mut history, "undo".to_string());
+        assert_eq!(contact_list[0], get_john_contact());
+        manage_contact(&mut contact_list, &mut history, "undo".to_string());
+        assert_eq!(contact_list, vec![get_john_contact()]);
+        assert!(history.undo().is_none());
+    }
+
+    #[test]
+    fn test_data_file() {
+        let dir = std::env::temp_dir().join(format!("contact_book_{}", process::id()));
+        let file = dir.join("nested").join("book.json");
+
+        assert_eq!(data_file(Some(file.clone()), Backend::Json), Ok(file));
+        assert!(dir.join("nested").is_dir());
+        fs::remove_dir_all(&dir).unwrap();
     }
 }
@@ -0,0 +1,377 @@
+use crate::contact::Contact;
+use std::fmt;
+
+/// The fields a search looks in, and that `name:`, `phone:` and `email:`
+/// limit it to.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Field {
+    Name,
+    Phone,
+    Email,
+}
+
+impl fmt::Display for Field {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Field::Name => "name",
+            Field::Phone => "phone",
+            Field::Email => "email",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// Contacts matching `query`, best match first, with their index in
+/// `contacts` and the field that matched.
+///
+/// Names are matched loosely: case is ignored, and small typos or left out
+/// letters still match, so `jon` finds John. Emails match when they contain
+/// the query, phones when their digits contain its digits, so `0803 555`
+/// finds `0803-555-1234`. `email:@gmail.com` or `phone:0803` looks in that
+/// field only.
+pub fn search<'a>(contacts: &'a [Contact], query: &str) -> Vec<(usize, &'a Contact, Field)> {
+    let (fields, query) = parse_query(query);
+    let query = query.trim().to_lowercase();
+    if query.is_empty() {
+        return Vec::new();
+    }
+
+    let mut found: Vec<(u32, usize, &Contact, Field)> = contacts
+        .iter()
+        .enumerate()
+        .filter_map(|(i, contact)| {
+            // the best field, the first one listed when they tie
+            let (score, field) = fields
+                .iter()
+                .filter_map(|&field| Some((score_field(field, &query, contact)?, field)))
+                .min_by_key(|(score, _)| *score)?;
+            Some((score, i, contact, field))
+        })
+        .collect();
+    // same score, keep the list order
+    found.sort_by_key(|(score, i, _, _)| (*score, *i));
+
+    found
+        .into_iter()
+        .map(|(_, i, contact, field)| (i, contact, field))
+        .collect()
+}
+
+/// The fields to look in and the text to look for: one field with a
+/// `field:` prefix, otherwise every field, phones only if the query looks
+/// like a phone number.
+fn parse_query(query: &str) -> (Vec<Field>, &str) {
+    let query = query.trim();
+    let prefixed = query.split_once(':').and_then(|(prefix, rest)| {
+        let field = match prefix.to_lowercase().as_str() {
+            "name" => Field::Name,
+            "phone" => Field::Phone,
+            "email" => Field::Email,
+            _ => return None,
+        };
+        Some((vec![field], rest))
+    });
+    if let Some(prefixed) = prefixed {
+        return prefixed;
+    }
+
+    let phone_like = query.chars().any(|c| c.is_ascii_digit())
+        && query
+            .chars()
+            .all(|c| c.is_ascii_digit() || "+-() .".contains(c));
+    let fields = match phone_like {
+        true => vec![Field::Name, Field::Phone, Field::Email],
+        false => vec![Field::Name, Field::Email],
+    };
+
+    (fields, query)
+}
+
+/// How well `field` of the contact matches the lowercase `query`, `None`
+/// if it doesn't.
+fn score_field(field: Field, query: &str, contact: &Contact) -> Option<u32> {
+    match field {
+        Field::Name => score(query, &contact.name),
+        Field::Phone => {
+            let query = digits(query);
+            if query.is_empty() {
+                return None;
+            }
+            contact
+                .phones
+                .iter()
+                .filter_map(|phone| plain_score(&query, &digits(phone)))
+                .min()
+        }
+        Field::Email => contact
+            .emails
+            .iter()
+            .filter_map(|email| plain_score(query, &email.to_lowercase()))
+            .min(),
+    }
+}
+
+/// Exact, then at the start, then anywhere in `value`.
+fn plain_score(query: &str, value: &str) -> Option<u32> {
+    if value == query {
+        Some(0)
+    } else if value.starts_with(query) {
+        Some(1)
+    } else if value.contains(query) {
+        Some(2)
+    } else {
+        None
+    }
+}
+
+fn digits(phone: &str) -> String {
+    phone.chars().filter(|c| c.is_ascii_digit()).collect()
+}
+
+/// How well `name` matches the lowercase `query`, lower is better and `None`
+/// is no match.
+fn score(query: &str, name: &str) -> Option<u32> {
+    let name = name.to_lowercase();
+    if name == query {
+        return Some(0);
+    }
+    if name.starts_with(query) {
+        return Some(1);
+    }
+    if name.contains(query) {
+        return Some(2);
+    }
+
+    // a typo in the name or one of its words, e.g. `jhon` or `smiht`
+    let length = query.chars().count();
+    if let Some(allowed) = typos_allowed(length) {
+        let distance = name
+            .split_whitespace()
+            .chain([name.as_str()])
+            .map(|word| levenshtein(query, word))
+            .min()
+            .unwrap_or(usize::MAX);
+        if distance <= allowed {
+            return Some(2 + distance as u32);
+        }
+    }
+
+    // letters left out, e.g. `jn` for `john`, the fewer the better
+    if is_subsequence(query, &name) {
+        let skipped = name.chars().count() - length;
+        return Some(10 + skipped as u32);
+    }
+
+    None
+}
+
+/// How many typos a query of `length` letters may have, `None` for very
+/// short queries, which would match almost anything.
+fn typos_allowed(length: usize) -> Option<usize> {
+    (length >= 3).then_some(1 + length / 5)
+}
+
+/// What a store keeps of `field` to prefilter searches with `like_patterns`:
+/// the name and emails lowercase, the phones as digits.
+pub fn field_key(field: Field, contact: &Contact) -> String {
+    match field {
+        Field::Name => contact.name.to_lowercase(),
+        Field::Phone => contact
+            .phones
+            .iter()
+            .map(|phone| digits(phone))
+            .collect::<Vec<_>>()
+            .join(" "),
+        Field::Email => contact.emails.join(" ").to_lowercase(),
+    }
+}
+
+/// SQL `LIKE` patterns (with `\` as the escape) for the `field_key`s, at
+/// least one of which matches every contact `search` would find. Stores use
+/// them to skip most contacts before ranking the rest.
+pub fn like_patterns(query: &str) -> Vec<(Field, String)> {
+    let (fields, query) = parse_query(query);
+    let query = query.trim().to_lowercase();
+    if query.is_empty() {
+        return Vec::new();
+    }
+    let contains = |text: &str| format!("%{}%", like_escaped(text));
+
+    let mut patterns = Vec::new();
+    for field in fields {
+        match field {
+            Field::Name => {
+                // letters left out, which also covers the name containing
+                // the query: `%j%n%`
+                let spread: String = query
+                    .chars()
+                    .map(|c| format!("%{}", like_escaped(&c.to_string())))
+                    .collect();
+                patterns.push((field, spread + "%"));
+
+                // `allowed` typos can break at most `allowed` of `allowed + 1`
+                // pieces of the query, so one piece is still in the name
+                let chars: Vec<char> = query.chars().collect();
+                if let Some(allowed) = typos_allowed(chars.len()) {
+                    let pieces = allowed + 1;
+                    for i in 0..pieces {
+                        let piece: String = chars
+                            [i * chars.len() / pieces..(i + 1) * chars.len() / pieces]
+                            .iter()
+                            .collect();
+                        patterns.push((field, contains(&piece)));
+                    }
+                }
+            }
+            Field::Phone => {
+                let query = digits(&query);
+                if !query.is_empty() {
+                    patterns.push((field, contains(&query)));
+                }
+            }
+            Field::Email => patterns.push((field, contains(&query))),
+        }
+    }
+
+    patterns
+}
+
+fn like_escaped(text: &str) -> String {
+    let mut escaped = String::new();
+    for c in text.chars() {
+        if matches!(c, '%' | '_' | '\\') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+
+    escaped
+}
+
+/// The number of single character insertions, deletions and substitutions
+/// turning `a` into `b`.
+pub fn levenshtein(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+
+    for (i, ca) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(ca != *cb);
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+
+    previous[b.len()]
+}
+
+fn is_subsequence(query: &str, name: &str) -> bool {
+    let mut name = name.chars();
+    query.chars().all(|q| name.any(|c| c == q))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn names(contacts: &[Contact], query: &str) -> Vec<String> {
+        search(contacts, query)
+            .into_iter()
+            .map(|(_, contact, _)| contact.name.clone())
+            .collect()
+    }
+
+    #[test]
+    fn test_search() {
+        let contacts: Vec<Contact> = ["Johnny", "Mike Smith", "John", "Joan", "Jo", "Sarah"]
+            .iter()
+            .map(|name| Contact::new(name, "090", "a@b.com"))
+            .collect();
+
+        // exact, then prefix, then one letter off
+        assert_eq!(names(&contacts, "JOHN"), vec!["John", "Johnny", "Joan"]);
+        assert_eq!(
+            names(&contacts, "jon"),
+            vec!["John", "Joan", "Jo", "Johnny"]
+        );
+        assert_eq!(names(&contacts, "smiht"), vec!["Mike Smith"]);
+        assert_eq!(names(&contacts, "mksmth"), vec!["Mike Smith"]);
+        assert_eq!(names(&contacts, "sarha"), vec!["Sarah"]);
+        assert_eq!(names(&contacts, "xyz"), Vec::<String>::new());
+        assert_eq!(names(&contacts, "  "), Vec::<String>::new());
+
+        assert_eq!(search(&contacts, "john")[0].0, 2);
+    }
+
+    #[test]
+    fn test_search_fields() {
+        let contacts = vec![
+            Contact::new("ann", "0803-555-1234", "ann@gmail.com"),
+            Contact::new("bob", "+44 20 0803", "bob@work.com"),
+            Contact::new("gmail fan", "090", "fan@yahoo.com"),
+        ];
+        let found = |query| -> Vec<(usize, Field)> {
+            search(&contacts, query)
+                .into_iter()
+                .map(|(i, _, field)| (i, field))
+                .collect()
+        };
+
+        assert_eq!(found("email:@GMAIL.com"), vec![(0, Field::Email)]);
+        assert_eq!(found("gmail"), vec![(2, Field::Name), (0, Field::Email)]);
+        // the digits are compared, a phone starting with them first
+        assert_eq!(
+            found("phone:0803"),
+            vec![(0, Field::Phone), (1, Field::Phone)]
+        );
+        assert_eq!(found("0803 555"), vec![(0, Field::Phone)]);
+        assert_eq!(found("name:bob"), vec![(1, Field::Name)]);
+        assert_eq!(found("name:work"), vec![]);
+        assert_eq!(found("phone:abc"), vec![]);
+        assert_eq!(found("email:"), vec![]);
+    }
+
+    #[test]
+    fn test_levenshtein() {
+        assert_eq!(levenshtein("jon", "john"), 1);
+        assert_eq!(levenshtein("kitten", "sitting"), 3);
+        assert_eq!(levenshtein("", "abc"), 3);
+        assert_eq!(levenshtein("é", "e"), 1);
+    }
+
+    #[test]
+    fn test_like_patterns() {
+        let owned = |list: &[(Field, &str)]| -> Vec<(Field, String)> {
+            list.iter().map(|(f, p)| (*f, p.to_string())).collect()
+        };
+
+        assert_eq!(
+            like_patterns("Jo"),
+            owned(&[(Field::Name, "%j%o%"), (Field::Email, "%jo%")])
+        );
+        // one typo allowed, so half of the query is still there
+        assert_eq!(
+            like_patterns("name:john"),
+            owned(&[
+                (Field::Name, "%j%o%h%n%"),
+                (Field::Name, "%jo%"),
+                (Field::Name, "%hn%")
+            ])
+        );
+        assert_eq!(
+            like_patterns("phone:0803-555"),
+            owned(&[(Field::Phone, "%0803555%")])
+        );
+        assert_eq!(
+            like_patterns("email:50%_off"),
+            owned(&[(Field::Email, "%50\\%\\_off%")])
+        );
+        assert!(like_patterns("  ").is_empty());
+
+        let contact = Contact::new("Élan", "+49 (30) 12", "Elan@X.de");
+        assert_eq!(field_key(Field::Name, &contact), "élan");
+        assert_eq!(field_key(Field::Phone, &contact), "493012");
+        assert_eq!(field_key(Field::Email, &contact), "elan@x.de");
+    }
+}
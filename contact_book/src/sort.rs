@@ -0,0 +1,185 @@
+use crate::contact::Contact;
+use std::cmp::Ordering;
+
+pub const LIST_USAGE: &str =
+    "Usage: list [tag] [--sort name|recent|email] [--asc|--desc] [--page <number>]";
+/// How many contacts a page of `list` shows.
+pub const PAGE_SIZE: usize = 20;
+
+/// The orders `list` can show contacts in.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SortBy {
+    /// As they were added, the order of the file.
+    Added,
+    Name,
+    /// Newest first, unless ascending is asked for.
+    Recent,
+    /// By first email, contacts without one come last.
+    Email,
+}
+
+/// What `list` shows.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ListOptions {
+    pub sort_by: SortBy,
+    pub descending: bool,
+    /// Only the contacts with this tag.
+    pub tag: Option<String>,
+    /// Only this page, counted from 1.
+    pub page: Option<usize>,
+}
+
+/// Reads the options after `list`: a tag, the order and whether to reverse
+/// it.
+pub fn parse_list_options(args: &[&str]) -> Result<ListOptions, String> {
+    let mut sort_by = SortBy::Added;
+    let mut descending = None;
+    let mut tag = None;
+    let mut page = None;
+    let mut args = args.iter();
+
+    while let Some(arg) = args.next() {
+        match arg.to_lowercase().as_str() {
+            "--sort" => {
+                sort_by = match args.next().map(|by| by.to_lowercase()).as_deref() {
+                    Some("name") => SortBy::Name,
+                    Some("recent") => SortBy::Recent,
+                    Some("email") => SortBy::Email,
+                    _ => return Err(LIST_USAGE.to_string()),
+                }
+            }
+            "--page" => match args.next().map(|n| n.parse::<usize>()) {
+                Some(Ok(n)) if n > 0 => page = Some(n),
+                _ => return Err(LIST_USAGE.to_string()),
+            },
+            "--asc" => descending = Some(false),
+            "--desc" => descending = Some(true),
+            word if tag.is_none() && !word.starts_with('-') => tag = Some(word.to_string()),
+            _ => return Err(LIST_USAGE.to_string()),
+        }
+    }
+
+    Ok(ListOptions {
+        sort_by,
+        descending: descending.unwrap_or(sort_by == SortBy::Recent),
+        tag,
+        page,
+    })
+}
+
+/// How many pages `len` contacts fill, at least one so an empty list still
+/// has a page to show.
+pub fn page_count(len: usize) -> usize {
+    len.div_ceil(PAGE_SIZE).max(1)
+}
+
+/// Page `page` of `items`, counted from 1, empty past the last one.
+pub fn page<T>(items: &[T], page: usize) -> &[T] {
+    let start = (page.saturating_sub(1) * PAGE_SIZE).min(items.len());
+    &items[start..(start + PAGE_SIZE).min(items.len())]
+}
+
+/// The contacts in the asked order, each with its index in `contacts` so
+/// the numbers shown still work with `edit` and `delete`.
+pub fn sorted(contacts: &[Contact], sort_by: SortBy, descending: bool) -> Vec<(usize, &Contact)> {
+    let mut sorted: Vec<(usize, &Contact)> = contacts.iter().enumerate().collect();
+    let compare = |a: &(usize, &Contact), b: &(usize, &Contact)| -> Ordering {
+        match sort_by {
+            SortBy::Added | SortBy::Recent => a.0.cmp(&b.0),
+            SortBy::Name => a.1.name.to_lowercase().cmp(&b.1.name.to_lowercase()),
+            SortBy::Email => match (a.1.emails.first(), b.1.emails.first()) {
+                (Some(a), Some(b)) => a.to_lowercase().cmp(&b.to_lowercase()),
+                (Some(_), None) => Ordering::Less,
+                (None, Some(_)) => Ordering::Greater,
+                (None, None) => Ordering::Equal,
+            },
+        }
+    };
+
+    // a stable sort, so contacts that compare equal stay in the order they
+    // were added
+    sorted.sort_by(|a, b| match descending {
+        true => compare(b, a),
+        false => compare(a, b),
+    });
+
+    sorted
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_sorted() {
+        let mut contacts = vec![
+            Contact::new("mike", "090", "mike@best.com"),
+            Contact::new("Anna", "091", "zed@best.com"),
+            Contact::new("bob", "092", "Bob@best.com"),
+        ];
+        contacts[0].emails.clear();
+
+        let names = |sort_by, descending| -> Vec<String> {
+            sorted(&contacts, sort_by, descending)
+                .into_iter()
+                .map(|(_, contact)| contact.name.clone())
+                .collect()
+        };
+        assert_eq!(names(SortBy::Name, false), vec!["Anna", "bob", "mike"]);
+        assert_eq!(names(SortBy::Name, true), vec!["mike", "bob", "Anna"]);
+        assert_eq!(names(SortBy::Recent, true), vec!["bob", "Anna", "mike"]);
+        assert_eq!(names(SortBy::Email, false), vec!["bob", "Anna", "mike"]);
+
+        // the numbers are the ones in the list
+        assert_eq!(sorted(&contacts, SortBy::Name, false)[0].0, 1);
+    }
+
+    #[test]
+    fn test_parse_list_options() {
+        let options = |sort_by, descending, tag: Option<&str>| {
+            Ok(ListOptions {
+                sort_by,
+                descending,
+                tag: tag.map(str::to_string),
+                page: None,
+            })
+        };
+        assert_eq!(parse_list_options(&[]), options(SortBy::Added, false, None));
+        assert_eq!(
+            parse_list_options(&["--sort", "NAME", "--desc"]),
+            options(SortBy::Name, true, None)
+        );
+        assert_eq!(
+            parse_list_options(&["--sort", "recent"]),
+            options(SortBy::Recent, true, None)
+        );
+        assert_eq!(
+            parse_list_options(&["--asc", "--sort", "recent"]),
+            options(SortBy::Recent, false, None)
+        );
+        assert_eq!(
+            parse_list_options(&["Family", "--sort", "name"]),
+            options(SortBy::Name, false, Some("family"))
+        );
+        assert!(parse_list_options(&["--sort"]).is_err());
+        assert!(parse_list_options(&["--sort", "phone"]).is_err());
+        assert!(parse_list_options(&["family", "work"]).is_err());
+        assert!(parse_list_options(&["--name"]).is_err());
+
+        assert_eq!(parse_list_options(&["--page", "2"]).unwrap().page, Some(2));
+        assert!(parse_list_options(&["--page", "0"]).is_err());
+        assert!(parse_list_options(&["--page"]).is_err());
+    }
+
+    #[test]
+    fn test_pages() {
+        let items: Vec<usize> = (0..45).collect();
+        assert_eq!(page_count(items.len()), 3);
+        assert_eq!(page_count(0), 1);
+        assert_eq!(page_count(PAGE_SIZE), 1);
+
+        assert_eq!(page(&items, 1), &items[..20]);
+        assert_eq!(page(&items, 3), &items[40..]);
+        assert!(page(&items, 4).is_empty());
+    }
+}
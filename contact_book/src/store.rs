@@ -0,0 +1,577 @@
+use crate::contact::Contact;
+use crate::crypto;
+use crate::search::{self, Field};
+use rusqlite::{Connection, params};
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{ErrorKind, Write};
+use std::path::{Path, PathBuf};
+
+/// Where the contacts live between runs.
+///
+/// The book works on the whole list in memory and saves it back, so a store
+/// only has to load and save it. `search` can be answered without loading
+/// every contact by stores able to do better.
+pub trait ContactStore {
+    fn load(&self) -> Result<Vec<Contact>, String>;
+
+    /// Replaces what is stored with `contacts`.
+    fn save(&mut self, contacts: &[Contact]) -> Result<(), String>;
+
+    /// Like the `search` command: matches best first, with their index in
+    /// the list and the field that matched.
+    fn search(&self, query: &str) -> Result<Vec<(usize, Contact, Field)>, String> {
+        let contacts = self.load()?;
+        Ok(search::search(&contacts, query)
+            .into_iter()
+            .map(|(i, contact, field)| (i, contact.clone(), field))
+            .collect())
+    }
+}
+
+/// How many earlier versions of the JSON file are kept.
+pub const BACKUPS: usize = 5;
+
+/// The contacts as a pretty printed JSON array, the original format,
+/// encrypted when there's a passphrase.
+///
+/// Saving never leaves a half written file: the new list goes to a
+/// temporary file that then replaces the old one. The file from before each
+/// save is kept as `contact.json.1`, the one before that as `.2`, and so on
+/// up to `BACKUPS`.
+pub struct JsonStore {
+    path: PathBuf,
+    passphrase: Option<String>,
+}
+
+impl JsonStore {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        JsonStore {
+            path: path.into(),
+            passphrase: None,
+        }
+    }
+
+    /// A store that decrypts the file with `passphrase` and saves it
+    /// encrypted (see `crypto`).
+    pub fn encrypted(path: impl Into<PathBuf>, passphrase: String) -> Self {
+        JsonStore {
+            path: path.into(),
+            passphrase: Some(passphrase),
+        }
+    }
+
+    /// Whether the file on disk is encrypted, false if there's none yet.
+    pub fn is_encrypted(&self) -> bool {
+        fs::read(&self.path).is_ok_and(|data| crypto::is_encrypted(&data))
+    }
+
+    /// Backup `number`, counted from 1 for the newest: `contact.json.1`.
+    pub fn backup_path(&self, number: usize) -> PathBuf {
+        self.with_suffix(&number.to_string())
+    }
+
+    /// The file's path with `.suffix` added to its name.
+    fn with_suffix(&self, suffix: &str) -> PathBuf {
+        let mut name = self.path.file_name().unwrap_or_default().to_os_string();
+        name.push(format!(".{}", suffix));
+        self.path.with_file_name(name)
+    }
+
+    /// The numbers of the backups there are, newest first.
+    pub fn backups(&self) -> Vec<usize> {
+        (1..=BACKUPS)
+            .filter(|&number| self.backup_path(number).exists())
+            .collect()
+    }
+
+    /// Deletes every backup.
+    pub fn remove_backups(&self) -> Result<(), String> {
+        for number in self.backups() {
+            let path = self.backup_path(number);
+            fs::remove_file(&path)
+                .map_err(|e| format!("Can't remove {}: {}", path.display(), e))?;
+        }
+
+        Ok(())
+    }
+
+    /// Puts backup `number` back as the file. The file it replaces becomes
+    /// backup 1, so a restore can be restored away too.
+    pub fn restore(&self, number: usize) -> Result<(), String> {
+        let backup = self.backup_path(number);
+        let data = fs::read(&backup).map_err(|e| match e.kind() {
+            ErrorKind::NotFound => format!("There's no backup {}", number),
+            _ => format!("Can't read {}: {}", backup.display(), e),
+        })?;
+
+        self.write(&data)
+    }
+
+    /// Replaces the file with `data`, after moving the backups up one and
+    /// copying the current file to backup 1.
+    fn write(&self, data: &[u8]) -> Result<(), String> {
+        let error =
+            |path: &Path, e: std::io::Error| format!("Can't write {}: {}", path.display(), e);
+
+        if self.path.exists() {
+            for number in (1..BACKUPS).rev() {
+                let from = self.backup_path(number);
+                if from.exists() {
+                    let to = self.backup_path(number + 1);
+                    fs::rename(&from, &to).map_err(|e| error(&to, e))?;
+                }
+            }
+            // copied rather than moved, so there's a complete file at
+            // `path` the whole time
+            let backup = self.backup_path(1);
+            fs::copy(&self.path, &backup).map_err(|e| error(&backup, e))?;
+        }
+
+        // in the same directory, as a rename can't cross file systems
+        let temp = self.with_suffix("tmp");
+        let write_temp = || -> std::io::Result<()> {
+            let mut file = File::create(&temp)?;
+            file.write_all(data)?;
+            // on the disk before it takes the place of the old file
+            file.sync_all()
+        };
+        if let Err(e) = write_temp() {
+            let _ = fs::remove_file(&temp);
+            return Err(error(&temp, e));
+        }
+
+        fs::rename(&temp, &self.path).map_err(|e| error(&self.path, e))
+    }
+}
+
+impl ContactStore for JsonStore {
+    fn load(&self) -> Result<Vec<Contact>, String> {
+        let mut data = match fs::read(&self.path) {
+            Ok(data) => data,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(format!("Can't read {}: {}", self.path.display(), e)),
+        };
+
+        if crypto::is_encrypted(&data) {
+            let Some(passphrase) = &self.passphrase else {
+                return Err(format!("{} is encrypted", self.path.display()));
+            };
+            data = crypto::decrypt(&data, passphrase)?;
+        }
+
+        // an unreadable file is an error rather than an empty book, which
+        // would be saved over it
+        serde_json::from_slice(&data)
+            .map_err(|e| format!("{} isn't a contact list: {}", self.path.display(), e))
+    }
+
+    fn save(&mut self, contacts: &[Contact]) -> Result<(), String> {
+        let mut data = serde_json::to_vec_pretty(contacts).map_err(|e| e.to_string())?;
+        if let Some(passphrase) = &self.passphrase {
+            data = crypto::encrypt(&data, passphrase)?;
+        }
+
+        self.write(&data)
+    }
+}
+
+/// The contacts in an SQLite database, one row each. Saving happens in a
+/// transaction, so a crash never leaves half a list behind, and only writes
+/// the rows that changed.
+///
+/// Next to each contact are the lowercase keys `search::like_patterns` is
+/// made for, the name one indexed, so a search only reads the rows that
+/// can match.
+pub struct SqliteStore {
+    connection: Connection,
+}
+
+/// The fields with a key column.
+const KEYS: [Field; 3] = [Field::Name, Field::Phone, Field::Email];
+
+/// The column holding `search::field_key` of `field`.
+fn key_column(field: Field) -> &'static str {
+    match field {
+        Field::Name => "name_key",
+        Field::Phone => "phone_key",
+        Field::Email => "email_key",
+    }
+}
+
+impl SqliteStore {
+    pub fn open(path: &Path) -> Result<Self, String> {
+        let connection =
+            Connection::open(path).map_err(|e| format!("Can't open {}: {}", path.display(), e))?;
+        Self::with_connection(connection)
+    }
+
+    fn with_connection(connection: Connection) -> Result<Self, String> {
+        // the lists are small, they are kept as JSON arrays
+        connection
+            .execute_batch(
+                "CREATE TABLE IF NOT EXISTS contacts (
+                    position INTEGER PRIMARY KEY,
+                    name TEXT NOT NULL,
+                    phones TEXT NOT NULL,
+                    emails TEXT NOT NULL,
+                    address TEXT,
+                    company TEXT,
+                    notes TEXT,
+                    tags TEXT NOT NULL,
+                    birthday TEXT,
+                    name_key TEXT NOT NULL DEFAULT '',
+                    phone_key TEXT NOT NULL DEFAULT '',
+                    email_key TEXT NOT NULL DEFAULT ''
+                );",
+            )
+            .map_err(sql_error)?;
+
+        // databases from before birthdays or the keys don't have the columns
+        let columns = [("birthday", "TEXT")]
+            .into_iter()
+            .chain(KEYS.map(|field| (key_column(field), "TEXT NOT NULL DEFAULT ''")));
+        let mut added_keys = false;
+        for (column, definition) in columns {
+            let exists = connection
+                .prepare("SELECT 1 FROM pragma_table_info('contacts') WHERE name = ?1")
+                .and_then(|mut statement| statement.exists([column]))
+                .map_err(sql_error)?;
+            if !exists {
+                connection
+                    .execute(
+                        &format!("ALTER TABLE contacts ADD COLUMN {} {}", column, definition),
+                        [],
+                    )
+                    .map_err(sql_error)?;
+                added_keys |= column.ends_with("_key");
+            }
+        }
+        if added_keys {
+            for (position, contact) in read_contacts(&connection, "", [])? {
+                write_contact(&connection, position, &contact)?;
+            }
+        }
+        connection
+            .execute(
+                "CREATE INDEX IF NOT EXISTS contacts_name_key ON contacts (name_key)",
+                [],
+            )
+            .map_err(sql_error)?;
+
+        Ok(SqliteStore { connection })
+    }
+}
+
+/// The contacts with their position, the ones passing `filter` (a `WHERE`
+/// clause, empty for all of them).
+fn read_contacts(
+    connection: &Connection,
+    filter: &str,
+    params: impl rusqlite::Params,
+) -> Result<Vec<(usize, Contact)>, String> {
+    let sql = format!("SELECT * FROM contacts {} ORDER BY position", filter);
+    let mut statement = connection.prepare(&sql).map_err(sql_error)?;
+    let list = |json: String| serde_json::from_str::<Vec<String>>(&json).unwrap_or_default();
+
+    let rows = statement
+        .query_map(params, |row| {
+            let contact = Contact {
+                name: row.get("name")?,
+                phones: list(row.get("phones")?),
+                emails: list(row.get("emails")?),
+                address: row.get("address")?,
+                company: row.get("company")?,
+                notes: row.get("notes")?,
+                birthday: row.get("birthday")?,
+                tags: list(row.get("tags")?),
+            };
+            Ok((row.get::<_, i64>("position")? as usize, contact))
+        })
+        .map_err(sql_error)?;
+
+    rows.collect::<Result<_, _>>().map_err(sql_error)
+}
+
+/// Stores `contact` at `position`, replacing what was there.
+fn write_contact(
+    connection: &Connection,
+    position: usize,
+    contact: &Contact,
+) -> Result<(), String> {
+    let json = |list: &Vec<String>| serde_json::to_string(list).unwrap_or_default();
+    let key = |field: Field| search::field_key(field, contact);
+
+    connection
+        .prepare_cached(
+            "INSERT OR REPLACE INTO contacts
+             (position, name, phones, emails, address, company, notes, tags, birthday,
+              name_key, phone_key, email_key)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)",
+        )
+        .and_then(|mut insert| {
+            insert.execute(params![
+                position as i64,
+                contact.name,
+                json(&contact.phones),
+                json(&contact.emails),
+                contact.address,
+                contact.company,
+                contact.notes,
+                json(&contact.tags),
+                contact.birthday,
+                key(Field::Name),
+                key(Field::Phone),
+                key(Field::Email),
+            ])
+        })
+        .map(drop)
+        .map_err(sql_error)
+}
+
+impl ContactStore for SqliteStore {
+    fn load(&self) -> Result<Vec<Contact>, String> {
+        Ok(read_contacts(&self.connection, "", [])?
+            .into_iter()
+            .map(|(_, contact)| contact)
+            .collect())
+    }
+
+    fn save(&mut self, contacts: &[Contact]) -> Result<(), String> {
+        let transaction = self.connection.transaction().map_err(sql_error)?;
+        let stored: HashMap<usize, Contact> =
+            read_contacts(&transaction, "", [])?.into_iter().collect();
+
+        for (position, contact) in contacts.iter().enumerate() {
+            if stored.get(&position) != Some(contact) {
+                write_contact(&transaction, position, contact)?;
+            }
+        }
+        transaction
+            .execute(
+                "DELETE FROM contacts WHERE position >= ?1",
+                [contacts.len() as i64],
+            )
+            .map_err(sql_error)?;
+
+        transaction.commit().map_err(sql_error)
+    }
+
+    /// Only the rows passing the `LIKE` prefilter are read, then ranked like
+    /// any other search.
+    fn search(&self, query: &str) -> Result<Vec<(usize, Contact, Field)>, String> {
+        let patterns = search::like_patterns(query);
+        if patterns.is_empty() {
+            return Ok(Vec::new());
+        }
+        let conditions: Vec<String> = patterns
+            .iter()
+            .map(|(field, _)| format!("{} LIKE ? ESCAPE '\\'", key_column(*field)))
+            .collect();
+        let filter = format!("WHERE {}", conditions.join(" OR "));
+        let (positions, candidates): (Vec<usize>, Vec<Contact>) = read_contacts(
+            &self.connection,
+            &filter,
+            rusqlite::params_from_iter(patterns.iter().map(|(_, pattern)| pattern)),
+        )?
+        .into_iter()
+        .unzip();
+
+        Ok(search::search(&candidates, query)
+            .into_iter()
+            .map(|(i, contact, field)| (positions[i], contact.clone(), field))
+            .collect())
+    }
+}
+
+fn sql_error(e: rusqlite::Error) -> String {
+    format!("Database error: {}", e)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn contacts() -> Vec<Contact> {
+        let mut john = Contact::new("john", "0908213", "john@best.com");
+        john.emails.push("john@home.com".to_string());
+        john.notes = Some("likes tea".to_string());
+        john.tag("family");
+        john.birthday = chrono::NaiveDate::from_ymd_opt(1990, 5, 17);
+        vec![
+            Contact::new("mike", "098732", "mike@best.com"),
+            john,
+            Contact::new("Johnny", "555", "johnny@x.com"),
+        ]
+    }
+
+    fn check_store(store: &mut dyn ContactStore) {
+        assert_eq!(store.load().unwrap(), Vec::<Contact>::new());
+
+        store.save(&contacts()).unwrap();
+        assert_eq!(store.load().unwrap(), contacts());
+
+        let found: Vec<(usize, String)> = store
+            .search("JOHN")
+            .unwrap()
+            .into_iter()
+            .map(|(i, contact, _)| (i, contact.name))
+            .collect();
+        assert_eq!(
+            found,
+            vec![(1, "john".to_string()), (2, "Johnny".to_string())]
+        );
+        assert_eq!(store.search("jon").unwrap()[0].0, 1);
+        let (i, contact, field) = store.search("phone:555").unwrap().remove(0);
+        assert_eq!(
+            (i, contact, field),
+            (2, contacts()[2].clone(), Field::Phone)
+        );
+
+        // saving replaces everything
+        store.save(&contacts()[..1]).unwrap();
+        assert_eq!(store.load().unwrap(), contacts()[..1]);
+    }
+
+    #[test]
+    fn test_json_store() {
+        // saving makes backups next to the file, they go with the directory
+        let dir = std::env::temp_dir().join(format!("contact_book_store_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("contact.json");
+        check_store(&mut JsonStore::new(&path));
+
+        fs::write(&path, "not json").unwrap();
+        assert!(JsonStore::new(&path).load().is_err());
+        fs::remove_file(&path).unwrap();
+
+        let mut store = JsonStore::encrypted(&path, "secret".to_string());
+        store.save(&contacts()).unwrap();
+        assert!(store.is_encrypted());
+        assert!(!fs::read(&path).unwrap().windows(4).any(|w| w == b"john"));
+        assert_eq!(store.load().unwrap(), contacts());
+        assert!(JsonStore::new(&path).load().is_err());
+        assert!(
+            JsonStore::encrypted(&path, "guess".to_string())
+                .load()
+                .is_err()
+        );
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_json_backups() {
+        let dir = std::env::temp_dir().join(format!("contact_book_backups_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("contact.json");
+        let mut store = JsonStore::new(&path);
+        let saved = |n: usize| contacts()[..n].to_vec();
+
+        store.save(&saved(1)).unwrap();
+        assert!(store.backups().is_empty());
+        for n in 2..=BACKUPS + 2 {
+            store.save(&saved(n % 3 + 1)).unwrap();
+        }
+        assert_eq!(store.backups(), (1..=BACKUPS).collect::<Vec<_>>());
+        // nothing but the file and its backups
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), BACKUPS + 1);
+
+        // backup 1 is the list from before the last save
+        let backup_1 = JsonStore::new(store.backup_path(1)).load().unwrap();
+        assert_eq!(backup_1, saved((BACKUPS + 1) % 3 + 1));
+        store.restore(1).unwrap();
+        assert_eq!(store.load().unwrap(), backup_1);
+        // and the restored-over list is the new backup 1
+        assert_eq!(
+            JsonStore::new(store.backup_path(1)).load().unwrap(),
+            saved((BACKUPS + 2) % 3 + 1)
+        );
+
+        assert_eq!(
+            store.restore(BACKUPS + 1),
+            Err(format!("There's no backup {}", BACKUPS + 1))
+        );
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_sqlite_store() {
+        let connection = Connection::open_in_memory().unwrap();
+        check_store(&mut SqliteStore::with_connection(connection).unwrap());
+    }
+
+    #[test]
+    fn test_sqlite_search_prefilter() {
+        let connection = Connection::open_in_memory().unwrap();
+        let mut store = SqliteStore::with_connection(connection).unwrap();
+        let mut list = contacts();
+        list.push(Contact::new("Élan Müller", "+49 (30) 1234", "ELAN@x.de"));
+        list.push(Contact::new("Anna_Smith", "0803-555-1234", "anna@x.com"));
+        store.save(&list).unwrap();
+
+        // the rows left out by SQL are never ones the search would find
+        for query in [
+            "john",
+            "jhon",
+            "jn",
+            "JOHNNY",
+            "élan",
+            "ÉLAN",
+            "mular",
+            "anna_",
+            "a%",
+            "_",
+            "0803 555",
+            "phone:555",
+            "email:@X.DE",
+            "mike@best",
+        ] {
+            let expected: Vec<(usize, Contact, Field)> = search::search(&list, query)
+                .into_iter()
+                .map(|(i, contact, field)| (i, contact.clone(), field))
+                .collect();
+            assert_eq!(store.search(query).unwrap(), expected, "{}", query);
+        }
+        assert_eq!(store.search("johm").unwrap()[0].1.name, "john");
+        assert!(store.search("zzz").unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_sqlite_writes_changed_rows() {
+        let connection = Connection::open_in_memory().unwrap();
+        let mut store = SqliteStore::with_connection(connection).unwrap();
+        let mut list = contacts();
+        store.save(&list).unwrap();
+
+        let before = store.connection.total_changes();
+        list[1].notes = None;
+        store.save(&list).unwrap();
+        assert_eq!(store.connection.total_changes() - before, 1);
+
+        let before = store.connection.total_changes();
+        store.save(&list[..1]).unwrap();
+        assert_eq!(store.connection.total_changes() - before, 2);
+        assert_eq!(store.load().unwrap(), contacts()[..1]);
+    }
+
+    #[test]
+    fn test_sqlite_adds_new_columns() {
+        let connection = Connection::open_in_memory().unwrap();
+        connection
+            .execute_batch(
+                "CREATE TABLE contacts (position INTEGER PRIMARY KEY, name TEXT NOT NULL,
+                    phones TEXT NOT NULL, emails TEXT NOT NULL, address TEXT, company TEXT,
+                    notes TEXT, tags TEXT NOT NULL);
+                 INSERT INTO contacts VALUES (0, 'mike', '[\"098732\"]', '[\"mike@best.com\"]',
+                    NULL, NULL, NULL, '[]');",
+            )
+            .unwrap();
+
+        let mut store = SqliteStore::with_connection(connection).unwrap();
+        assert_eq!(store.load().unwrap(), contacts()[..1]);
+        // the search keys are filled in for the rows already there
+        assert_eq!(store.search("MIKE").unwrap().len(), 1);
+        store.save(&contacts()).unwrap();
+        assert_eq!(store.load().unwrap(), contacts());
+    }
+}
@@ -0,0 +1,515 @@
+use crate::contact::{Contact, FIELDS};
+use crate::search;
+use crate::undo::History;
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use ratatui::layout::{Constraint, Flex, Layout, Rect};
+use ratatui::style::{Modifier, Style, Stylize};
+use ratatui::text::Line;
+use ratatui::widgets::{Block, Cell, Clear, Paragraph, Row, Table, TableState};
+use ratatui::{DefaultTerminal, Frame};
+
+/// How far PageUp and PageDown move.
+const PAGE_JUMP: usize = 10;
+const HELP: &str = "/ search  a add  e edit  d delete  u undo  q quit";
+
+/// Opens the full screen interface on `contacts` until it's quit. Changes
+/// are made to the list, saving it is up to the caller like for the
+/// prompt.
+pub fn run(contacts: &mut Vec<Contact>) -> Result<(), String> {
+    let mut terminal = ratatui::init();
+    let result = App::new(contacts).run(&mut terminal);
+    ratatui::restore();
+
+    result.map_err(|e| format!("Terminal error: {}", e))
+}
+
+/// What the keys do at the moment.
+#[derive(Debug, PartialEq)]
+enum Mode {
+    Browse,
+    /// Typing in the search box.
+    Search,
+    Form(Form),
+    /// Waiting for `y` to delete the contact at this index.
+    ConfirmDelete(usize),
+}
+
+/// The add and edit popup, one text box per field of `FIELDS`.
+#[derive(Debug, PartialEq)]
+struct Form {
+    /// The index of the contact being edited, `None` when adding.
+    editing: Option<usize>,
+    values: Vec<String>,
+    focus: usize,
+}
+
+impl Form {
+    fn new(editing: Option<usize>, contact: &Contact) -> Self {
+        Form {
+            editing,
+            values: FIELDS.iter().map(|field| contact.field(field)).collect(),
+            focus: 0,
+        }
+    }
+
+    /// The fields as the `field=value` changes `Contact::edited` takes,
+    /// so the form checks exactly what `edit` does.
+    fn changes(&self) -> Vec<String> {
+        FIELDS
+            .iter()
+            .zip(&self.values)
+            .map(|(field, value)| format!("{}={}", field, value))
+            .collect()
+    }
+}
+
+struct App<'a> {
+    contacts: &'a mut Vec<Contact>,
+    history: History,
+    query: String,
+    /// Indexes of the contacts in the table, in the order shown.
+    shown: Vec<usize>,
+    table: TableState,
+    mode: Mode,
+    /// What the last action did, shown instead of the key help.
+    status: Option<String>,
+    quit: bool,
+}
+
+impl<'a> App<'a> {
+    fn new(contacts: &'a mut Vec<Contact>) -> Self {
+        let mut app = App {
+            contacts,
+            history: History::default(),
+            query: String::new(),
+            shown: Vec::new(),
+            table: TableState::default(),
+            mode: Mode::Browse,
+            status: None,
+            quit: false,
+        };
+        app.refresh();
+
+        app
+    }
+
+    fn run(mut self, terminal: &mut DefaultTerminal) -> std::io::Result<()> {
+        while !self.quit {
+            terminal.draw(|frame| self.draw(frame))?;
+            if let Event::Key(key) = event::read()?
+                && key.kind == KeyEventKind::Press
+            {
+                self.handle_key(key);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// The index in `contacts` of the selected row.
+    fn selected(&self) -> Option<usize> {
+        self.table
+            .selected()
+            .and_then(|row| self.shown.get(row).copied())
+    }
+
+    /// Recomputes the rows after the search or the list changed, keeping
+    /// the selection on the same row if it still exists.
+    fn refresh(&mut self) {
+        self.shown = if self.query.trim().is_empty() {
+            (0..self.contacts.len()).collect()
+        } else {
+            search::search(self.contacts, &self.query)
+                .into_iter()
+                .map(|(i, _, _)| i)
+                .collect()
+        };
+
+        let row = match self.shown.len() {
+            0 => None,
+            len => Some(self.table.selected().unwrap_or(0).min(len - 1)),
+        };
+        self.table.select(row);
+    }
+
+    fn select(&mut self, row: usize) {
+        if !self.shown.is_empty() {
+            self.table.select(Some(row.min(self.shown.len() - 1)));
+        }
+    }
+
+    /// Changes the list and records it for undo.
+    fn change(&mut self, what: String, change: impl FnOnce(&mut Vec<Contact>)) {
+        let before = self.contacts.clone();
+        change(self.contacts);
+        self.history.record(&what, before);
+        self.status = Some(what);
+        self.refresh();
+    }
+
+    fn handle_key(&mut self, key: KeyEvent) {
+        let row = self.table.selected().unwrap_or(0);
+        match &mut self.mode {
+            Mode::Browse => {
+                self.status = None;
+                match key.code {
+                    KeyCode::Char('q') => self.quit = true,
+                    KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                        self.quit = true
+                    }
+                    KeyCode::Esc if !self.query.is_empty() => {
+                        self.query.clear();
+                        self.refresh();
+                    }
+                    KeyCode::Char('/') => self.mode = Mode::Search,
+                    KeyCode::Char('a') => {
+                        self.mode = Mode::Form(Form::new(None, &Contact::default()))
+                    }
+                    KeyCode::Char('e') | KeyCode::Enter => {
+                        if let Some(i) = self.selected() {
+                            self.mode = Mode::Form(Form::new(Some(i), &self.contacts[i]));
+                        }
+                    }
+                    KeyCode::Char('d') | KeyCode::Delete => {
+                        if let Some(i) = self.selected() {
+                            self.mode = Mode::ConfirmDelete(i);
+                        }
+                    }
+                    KeyCode::Char('u') => match self.history.undo() {
+                        Some((what, before)) => {
+                            *self.contacts = before;
+                            self.status = Some(format!("Undone: {}", what));
+                            self.refresh();
+                        }
+                        None => self.status = Some("Nothing to undo".to_string()),
+                    },
+                    KeyCode::Down | KeyCode::Char('j') => self.select(row + 1),
+                    KeyCode::Up | KeyCode::Char('k') => self.select(row.saturating_sub(1)),
+                    KeyCode::PageDown => self.select(row + PAGE_JUMP),
+                    KeyCode::PageUp => self.select(row.saturating_sub(PAGE_JUMP)),
+                    KeyCode::Home | KeyCode::Char('g') => self.select(0),
+                    KeyCode::End | KeyCode::Char('G') => self.select(usize::MAX),
+                    _ => {}
+                }
+            }
+            Mode::Search => match key.code {
+                KeyCode::Enter => self.mode = Mode::Browse,
+                KeyCode::Esc => {
+                    self.query.clear();
+                    self.mode = Mode::Browse;
+                    self.refresh();
+                }
+                KeyCode::Backspace => {
+                    self.query.pop();
+                    self.table.select(Some(0));
+                    self.refresh();
+                }
+                KeyCode::Down => self.select(row + 1),
+                KeyCode::Up => self.select(row.saturating_sub(1)),
+                KeyCode::Char(c) => {
+                    self.query.push(c);
+                    // the best match is first
+                    self.table.select(Some(0));
+                    self.refresh();
+                }
+                _ => {}
+            },
+            Mode::Form(form) => match key.code {
+                KeyCode::Esc => self.mode = Mode::Browse,
+                KeyCode::Tab | KeyCode::Down => form.focus = (form.focus + 1) % FIELDS.len(),
+                KeyCode::BackTab | KeyCode::Up => {
+                    form.focus = (form.focus + FIELDS.len() - 1) % FIELDS.len()
+                }
+                KeyCode::Backspace => {
+                    form.values[form.focus].pop();
+                }
+                KeyCode::Char(c) => form.values[form.focus].push(c),
+                KeyCode::Enter => self.submit(),
+                _ => {}
+            },
+            Mode::ConfirmDelete(i) => {
+                let i = *i;
+                self.mode = Mode::Browse;
+                if matches!(key.code, KeyCode::Char('y' | 'Y')) {
+                    let name = self.contacts[i].name.clone();
+                    self.change(format!("Deleted {}", name), |contacts| {
+                        contacts.remove(i);
+                    });
+                } else {
+                    self.status = Some("Nothing deleted".to_string());
+                }
+            }
+        }
+    }
+
+    /// Saves the form into the list, or leaves it open with the reason
+    /// it can't be.
+    fn submit(&mut self) {
+        let Mode::Form(form) = &self.mode else {
+            return;
+        };
+        let base = match form.editing {
+            Some(i) => &self.contacts[i],
+            None => &Contact::default(),
+        };
+
+        match (base.edited(&form.changes()), form.editing) {
+            (Err(e), _) => self.status = Some(e),
+            (Ok(contact), Some(i)) => {
+                self.mode = Mode::Browse;
+                self.change(format!("Updated {}", contact.name), |contacts| {
+                    contacts[i] = contact
+                });
+            }
+            (Ok(contact), None) => {
+                self.mode = Mode::Browse;
+                self.change(format!("Added {}", contact.name), |contacts| {
+                    contacts.push(contact)
+                });
+                // show the new contact, even if the search hides it
+                self.query.clear();
+                self.refresh();
+                self.select(usize::MAX);
+            }
+        }
+    }
+
+    fn draw(&mut self, frame: &mut Frame) {
+        let [search_area, table_area, details_area, status_area] = Layout::vertical([
+            Constraint::Length(3),
+            Constraint::Min(3),
+            Constraint::Length(5),
+            Constraint::Length(1),
+        ])
+        .areas(frame.area());
+
+        let searching = self.mode == Mode::Search;
+        let search = Paragraph::new(self.query.as_str()).block(
+            Block::bordered()
+                .title(" Search (/) ")
+                .border_style(highlight_if(searching)),
+        );
+        frame.render_widget(search, search_area);
+        if searching {
+            frame.set_cursor_position((
+                search_area.x + 1 + self.query.chars().count() as u16,
+                search_area.y + 1,
+            ));
+        }
+
+        let rows = self.shown.iter().map(|&i| {
+            let contact = &self.contacts[i];
+            Row::new([
+                Cell::from((i + 1).to_string()),
+                Cell::from(contact.name.as_str()),
+                Cell::from(contact.phones.join(", ")),
+                Cell::from(contact.emails.join(", ")),
+                Cell::from(contact.tags.join(", ")),
+            ])
+        });
+        let table = Table::new(
+            rows,
+            [
+                Constraint::Length(4),
+                Constraint::Percentage(25),
+                Constraint::Percentage(20),
+                Constraint::Percentage(30),
+                Constraint::Fill(1),
+            ],
+        )
+        .header(Row::new(["#", "Name", "Phones", "Emails", "Tags"]).bold())
+        .row_highlight_style(Style::new().add_modifier(Modifier::REVERSED))
+        .block(Block::bordered().title(format!(
+            " Contacts ({} of {}) ",
+            self.shown.len(),
+            self.contacts.len()
+        )));
+        frame.render_stateful_widget(table, table_area, &mut self.table);
+
+        let details: Vec<Line> = self
+            .selected()
+            .map(|i| self.contacts[i].details())
+            .unwrap_or_default()
+            .into_iter()
+            .map(Line::from)
+            .collect();
+        frame.render_widget(
+            Paragraph::new(details).block(Block::bordered()),
+            details_area,
+        );
+
+        let status = match (&self.mode, &self.status) {
+            (Mode::ConfirmDelete(i), _) => {
+                format!("Delete {}. {}? (y/n)", i + 1, self.contacts[*i].name)
+            }
+            (Mode::Form(_), Some(status)) => status.clone(),
+            (Mode::Form(_), None) => {
+                "Tab next field  Enter save  Esc cancel  (phones and emails comma separated)"
+                    .to_string()
+            }
+            (_, Some(status)) => status.clone(),
+            (_, None) => HELP.to_string(),
+        };
+        frame.render_widget(Paragraph::new(status), status_area);
+
+        if let Mode::Form(form) = &self.mode {
+            draw_form(frame, form);
+        }
+    }
+}
+
+fn draw_form(frame: &mut Frame, form: &Form) {
+    let area = centered(frame.area(), 60, FIELDS.len() as u16 + 2);
+    let title = match form.editing {
+        Some(i) => format!(" Edit contact {} ", i + 1),
+        None => " Add contact ".to_string(),
+    };
+    let lines: Vec<Line> = FIELDS
+        .iter()
+        .zip(&form.values)
+        .enumerate()
+        .map(|(i, (field, value))| {
+            Line::styled(
+                format!("{:>9}: {}", field, value),
+                highlight_if(i == form.focus),
+            )
+        })
+        .collect();
+
+    frame.render_widget(Clear, area);
+    frame.render_widget(
+        Paragraph::new(lines).block(Block::bordered().title(title)),
+        area,
+    );
+    frame.set_cursor_position((
+        area.x + 12 + form.values[form.focus].chars().count() as u16,
+        area.y + 1 + form.focus as u16,
+    ));
+}
+
+fn highlight_if(on: bool) -> Style {
+    if on {
+        Style::new().yellow()
+    } else {
+        Style::new()
+    }
+}
+
+/// A `width` x `height` box in the middle of `area`, smaller if it has to.
+fn centered(area: Rect, width: u16, height: u16) -> Rect {
+    let [area] = Layout::horizontal([Constraint::Length(width)])
+        .flex(Flex::Center)
+        .areas(area);
+    let [area] = Layout::vertical([Constraint::Length(height)])
+        .flex(Flex::Center)
+        .areas(area);
+
+    area
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use ratatui::Terminal;
+    use ratatui::backend::TestBackend;
+
+    fn contacts() -> Vec<Contact> {
+        vec![
+            Contact::new("john", "090123", "john@best.com"),
+            Contact::new("mike", "090234", "mike@best.com"),
+            Contact::new("sarah", "345432", "sarah@it.com"),
+        ]
+    }
+
+    fn press(app: &mut App, keys: &str) {
+        for c in keys.chars() {
+            app.handle_key(KeyEvent::from(KeyCode::Char(c)));
+        }
+    }
+
+    fn key(app: &mut App, code: KeyCode) {
+        app.handle_key(KeyEvent::from(code));
+    }
+
+    #[test]
+    fn test_search_and_move() {
+        let mut contacts = contacts();
+        let mut app = App::new(&mut contacts);
+        assert_eq!(app.shown, vec![0, 1, 2]);
+
+        key(&mut app, KeyCode::End);
+        assert_eq!(app.selected(), Some(2));
+
+        press(&mut app, "/mik");
+        assert_eq!(app.shown, vec![1]);
+        assert_eq!(app.selected(), Some(1));
+        key(&mut app, KeyCode::Enter);
+        // back to browsing, the search stays
+        press(&mut app, "j");
+        assert_eq!(app.shown, vec![1]);
+        key(&mut app, KeyCode::Esc);
+        assert_eq!(app.shown, vec![0, 1, 2]);
+    }
+
+    #[test]
+    fn test_add_edit_delete_undo() {
+        let mut contacts = contacts();
+        let mut app = App::new(&mut contacts);
+
+        // a bad email keeps the form open
+        press(&mut app, "aJane Doe");
+        key(&mut app, KeyCode::Tab);
+        press(&mut app, "555, 556");
+        key(&mut app, KeyCode::Tab);
+        press(&mut app, "jane");
+        key(&mut app, KeyCode::Enter);
+        assert!(matches!(app.mode, Mode::Form(_)));
+        assert!(app.status.as_deref().unwrap().starts_with("Invalid email"));
+        press(&mut app, "@x.com");
+        key(&mut app, KeyCode::Enter);
+        assert_eq!(app.mode, Mode::Browse);
+        assert_eq!(app.selected(), Some(3));
+
+        // the form starts with the current values
+        press(&mut app, "k");
+        key(&mut app, KeyCode::Enter);
+        press(&mut app, "y");
+        key(&mut app, KeyCode::Enter);
+
+        press(&mut app, "gdn");
+        assert_eq!(app.status.as_deref(), Some("Nothing deleted"));
+        press(&mut app, "dy");
+
+        assert_eq!(app.contacts.len(), 3);
+        assert_eq!(app.contacts[0].name, "mike");
+        assert_eq!(app.contacts[1].name, "sarahy");
+        assert_eq!(app.contacts[2].name, "Jane Doe");
+        assert_eq!(app.contacts[2].phones, vec!["555", "556"]);
+
+        press(&mut app, "uuu");
+        drop(app);
+        assert_eq!(contacts, self::contacts());
+    }
+
+    #[test]
+    fn test_draw() {
+        let mut contacts = contacts();
+        let mut app = App::new(&mut contacts);
+        press(&mut app, "/sar");
+        key(&mut app, KeyCode::Enter);
+        press(&mut app, "e");
+
+        let mut terminal = Terminal::new(TestBackend::new(80, 20)).unwrap();
+        terminal.draw(|frame| app.draw(frame)).unwrap();
+        let screen: String = terminal
+            .backend()
+            .buffer()
+            .content()
+            .iter()
+            .map(|cell| cell.symbol())
+            .collect();
+        assert!(screen.contains("Contacts (1 of 3)"));
+        assert!(screen.contains("Edit contact 3"));
+        assert!(screen.contains("email: sarah@it.com"));
+    }
+}
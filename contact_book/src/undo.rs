@@ -0,0 +1,31 @@
+use crate::contact::Contact;
+
+/// How many changes `undo` can go back.
+const MAX_STEPS: usize = 100;
+
+/// The changes made in an interactive session, newest last, so `undo` can
+/// take them back one at a time.
+///
+/// A step keeps the whole list from before the command rather than how to
+/// reverse it. Contact books are small, and the undo can't get out of step
+/// with what an add, delete, edit, merge or import did.
+#[derive(Debug, Default)]
+pub struct History {
+    steps: Vec<(String, Vec<Contact>)>,
+}
+
+impl History {
+    /// Remembers that `command` changed the list, which was `before`.
+    pub fn record(&mut self, command: &str, before: Vec<Contact>) {
+        if self.steps.len() == MAX_STEPS {
+            self.steps.remove(0);
+        }
+        self.steps.push((command.to_string(), before));
+    }
+
+    /// Forgets the last change, returning its command and the list from
+    /// before it.
+    pub fn undo(&mut self) -> Option<(String, Vec<Contact>)> {
+        self.steps.pop()
+    }
+}
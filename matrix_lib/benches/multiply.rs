@@ -0,0 +1,27 @@
+use criterion::{BenchmarkId, Criterion, black_box, criterion_group, criterion_main};
+use matrix_lib::Matrix;
+
+fn square(n: usize) -> Matrix<f64> {
+    Matrix::new(n, n, (0..n * n).map(|x| (x % 10) as f64).collect())
+}
+
+fn bench_multiply(c: &mut Criterion) {
+    let mut group = c.benchmark_group("multiply");
+
+    for n in [64, 128, 256, 512] {
+        let m1 = square(n);
+        let m2 = square(n);
+
+        group.bench_with_input(BenchmarkId::new("naive", n), &n, |b, _| {
+            b.iter(|| black_box(&m1).multiply_naive(black_box(&m2)))
+        });
+        group.bench_with_input(BenchmarkId::new("blocked", n), &n, |b, _| {
+            b.iter(|| black_box(&m1).multiply_blocked(black_box(&m2)))
+        });
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, bench_multiply);
+criterion_main!(benches);
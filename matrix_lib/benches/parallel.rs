@@ -0,0 +1,34 @@
+use criterion::{BenchmarkId, Criterion, black_box, criterion_group, criterion_main};
+use matrix_lib::Matrix;
+
+fn square(n: usize) -> Matrix<f64> {
+    Matrix::new(n, n, (0..n * n).map(|x| (x % 10) as f64).collect())
+}
+
+fn bench_parallel(c: &mut Criterion) {
+    let mut group = c.benchmark_group("parallel");
+    group.sample_size(10);
+
+    for n in [250, 500, 1000] {
+        let m1 = square(n);
+        let m2 = square(n);
+
+        group.bench_with_input(BenchmarkId::new("multiply", n), &n, |b, _| {
+            b.iter(|| black_box(&m1).multiply_blocked(black_box(&m2)))
+        });
+        group.bench_with_input(BenchmarkId::new("par_multiply", n), &n, |b, _| {
+            b.iter(|| black_box(&m1).par_multiply(black_box(&m2)))
+        });
+        group.bench_with_input(BenchmarkId::new("add", n), &n, |b, _| {
+            b.iter(|| black_box(&m1).add(black_box(&m2)))
+        });
+        group.bench_with_input(BenchmarkId::new("par_add", n), &n, |b, _| {
+            b.iter(|| black_box(&m1).par_add(black_box(&m2)))
+        });
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, bench_parallel);
+criterion_main!(benches);
@@ -0,0 +1,33 @@
+use criterion::{BenchmarkId, Criterion, black_box, criterion_group, criterion_main};
+use matrix_lib::Matrix;
+
+fn square(n: usize) -> Matrix<f64> {
+    Matrix::new(n, n, (0..n * n).map(|x| (x % 10) as f64).collect())
+}
+
+fn bench_simd(c: &mut Criterion) {
+    let mut group = c.benchmark_group("simd");
+
+    for n in [64, 256, 512] {
+        let m1 = square(n);
+        let m2 = square(n);
+
+        group.bench_with_input(BenchmarkId::new("multiply", n), &n, |b, _| {
+            b.iter(|| black_box(&m1).multiply(black_box(&m2)))
+        });
+        group.bench_with_input(BenchmarkId::new("simd_multiply", n), &n, |b, _| {
+            b.iter(|| black_box(&m1).simd_multiply(black_box(&m2)))
+        });
+        group.bench_with_input(BenchmarkId::new("add", n), &n, |b, _| {
+            b.iter(|| black_box(&m1).add(black_box(&m2)))
+        });
+        group.bench_with_input(BenchmarkId::new("simd_add", n), &n, |b, _| {
+            b.iter(|| black_box(&m1).simd_add(black_box(&m2)))
+        });
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, bench_simd);
+criterion_main!(benches);
@@ -0,0 +1,9 @@
+mod matrix;
+mod sparse;
+mod vector;
+
+#[cfg(feature = "simd")]
+pub use matrix::SimdElement;
+pub use matrix::{Matrix, hstack, vstack};
+pub use sparse::SparseMatrix;
+pub use vector::Vector;
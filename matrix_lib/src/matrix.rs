@@ -0,0 +1,1538 @@
+use num_traits::{Float, One, Zero};
+use std::cmp::Ordering;
+use std::ops::{Add, AddAssign, Div, Index, IndexMut, Mul, MulAssign, Neg, Sub, SubAssign};
+
+use crate::vector::Vector;
+
+#[cfg(feature = "parallel")]
+mod parallel;
+#[cfg(feature = "simd")]
+mod simd;
+
+#[cfg(feature = "simd")]
+pub use simd::SimdElement;
+
+const BLOCK_SIZE: usize = 64;
+// below this size the naive loop is already cache friendly
+const BLOCKED_THRESHOLD: usize = 32;
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Matrix<T> {
+    rows: usize,
+    cols: usize,
+    data: Vec<T>,
+}
+
+impl<T> Matrix<T> {
+    pub fn new(rows: usize, cols: usize, data: Vec<T>) -> Self {
+        assert!(data.len() == rows * cols, "Data does not match dimensions");
+
+        Matrix { rows, cols, data }
+    }
+
+    pub fn identity(n: usize) -> Self
+    where
+        T: Zero + One + Copy,
+    {
+        let mut data = vec![T::zero(); n * n];
+        for i in 0..n {
+            data[i * n + i] = T::one();
+        }
+
+        Matrix::new(n, n, data)
+    }
+
+    pub fn add(&self, other: &Matrix<T>) -> Matrix<T>
+    where
+        T: Add<Output = T> + Copy,
+    {
+        // check dimension
+        assert!(
+            self.rows == other.rows && self.cols == other.cols,
+            "Dimensions don't match"
+        );
+
+        let res = self
+            .data
+            .iter()
+            .zip(&other.data)
+            .map(|(a, b)| *a + *b)
+            .collect();
+
+        Matrix::new(self.rows, self.cols, res)
+    }
+
+    pub fn sub(&self, other: &Matrix<T>) -> Matrix<T>
+    where
+        T: Copy + Sub<Output = T>,
+    {
+        // check dimension
+        assert!(
+            self.rows == other.rows && self.cols == other.cols,
+            "Dimensions don't match"
+        );
+
+        let res = self
+            .data
+            .iter()
+            .zip(&other.data)
+            .map(|(a, b)| *a - *b)
+            .collect();
+
+        Matrix::new(self.rows, self.cols, res)
+    }
+
+    pub fn hadamard(&self, other: &Matrix<T>) -> Matrix<T>
+    where
+        T: Mul<Output = T> + Copy,
+    {
+        // check dimension
+        assert!(
+            self.rows == other.rows && self.cols == other.cols,
+            "Dimensions don't match"
+        );
+
+        let res = self
+            .data
+            .iter()
+            .zip(&other.data)
+            .map(|(a, b)| *a * *b)
+            .collect();
+
+        Matrix::new(self.rows, self.cols, res)
+    }
+
+    pub fn elementwise_div(&self, other: &Matrix<T>) -> Matrix<T>
+    where
+        T: Div<Output = T> + Copy,
+    {
+        // check dimension
+        assert!(
+            self.rows == other.rows && self.cols == other.cols,
+            "Dimensions don't match"
+        );
+
+        let res = self
+            .data
+            .iter()
+            .zip(&other.data)
+            .map(|(a, b)| *a / *b)
+            .collect();
+
+        Matrix::new(self.rows, self.cols, res)
+    }
+
+    // adds a 1 x cols matrix to every row
+    pub fn add_row_vector(&self, row: &Matrix<T>) -> Matrix<T>
+    where
+        T: Add<Output = T> + Copy,
+    {
+        assert!(
+            row.rows == 1 && row.cols == self.cols,
+            "Dimensions don't match"
+        );
+
+        let res = self
+            .data
+            .iter()
+            .enumerate()
+            .map(|(idx, a)| *a + row.data[idx % self.cols])
+            .collect();
+
+        Matrix::new(self.rows, self.cols, res)
+    }
+
+    // adds a rows x 1 matrix to every column
+    pub fn add_col_vector(&self, col: &Matrix<T>) -> Matrix<T>
+    where
+        T: Add<Output = T> + Copy,
+    {
+        assert!(
+            col.rows == self.rows && col.cols == 1,
+            "Dimensions don't match"
+        );
+
+        let res = self
+            .data
+            .iter()
+            .enumerate()
+            .map(|(idx, a)| *a + col.data[idx / self.cols])
+            .collect();
+
+        Matrix::new(self.rows, self.cols, res)
+    }
+
+    // multiplies row i by factors[i], `factors` is a rows x 1 matrix
+    pub fn scale_rows(&self, factors: &Matrix<T>) -> Matrix<T>
+    where
+        T: Mul<Output = T> + Copy,
+    {
+        assert!(
+            factors.rows == self.rows && factors.cols == 1,
+            "Dimensions don't match"
+        );
+
+        let res = self
+            .data
+            .iter()
+            .enumerate()
+            .map(|(idx, a)| *a * factors.data[idx / self.cols])
+            .collect();
+
+        Matrix::new(self.rows, self.cols, res)
+    }
+
+    // multiplies column j by factors[j], `factors` is a 1 x cols matrix
+    pub fn scale_cols(&self, factors: &Matrix<T>) -> Matrix<T>
+    where
+        T: Mul<Output = T> + Copy,
+    {
+        assert!(
+            factors.rows == 1 && factors.cols == self.cols,
+            "Dimensions don't match"
+        );
+
+        let res = self
+            .data
+            .iter()
+            .enumerate()
+            .map(|(idx, a)| *a * factors.data[idx % self.cols])
+            .collect();
+
+        Matrix::new(self.rows, self.cols, res)
+    }
+
+    // places `other` to the right of `self`
+    pub fn hstack(&self, other: &Matrix<T>) -> Matrix<T>
+    where
+        T: Copy,
+    {
+        hstack(&[self, other])
+    }
+
+    // places `other` below `self`
+    pub fn vstack(&self, other: &Matrix<T>) -> Matrix<T>
+    where
+        T: Copy,
+    {
+        vstack(&[self, other])
+    }
+
+    pub fn map<U, F>(&self, f: F) -> Matrix<U>
+    where
+        T: Copy,
+        F: FnMut(T) -> U,
+    {
+        let res = self.data.iter().copied().map(f).collect();
+
+        Matrix::new(self.rows, self.cols, res)
+    }
+
+    pub fn map_in_place<F>(&mut self, mut f: F)
+    where
+        T: Copy,
+        F: FnMut(T) -> T,
+    {
+        for a in self.data.iter_mut() {
+            *a = f(*a);
+        }
+    }
+
+    pub fn zip_with<U, F>(&self, other: &Matrix<T>, mut f: F) -> Matrix<U>
+    where
+        T: Copy,
+        F: FnMut(T, T) -> U,
+    {
+        // check dimension
+        assert!(
+            self.rows == other.rows && self.cols == other.cols,
+            "Dimensions don't match"
+        );
+
+        let res = self
+            .data
+            .iter()
+            .zip(&other.data)
+            .map(|(a, b)| f(*a, *b))
+            .collect();
+
+        Matrix::new(self.rows, self.cols, res)
+    }
+
+    pub fn transpose(&self) -> Self
+    where
+        T: Copy,
+    {
+        let mut res = Vec::new();
+
+        for i in 0..self.cols {
+            for j in 0..self.rows {
+                res.push(*self.get(j, i));
+            }
+        }
+
+        Matrix::new(self.cols, self.rows, res)
+    }
+
+    pub fn transpose_in_place(&mut self) {
+        assert!(self.is_square(), "Matrix must be square");
+
+        for i in 0..self.rows {
+            for j in (i + 1)..self.cols {
+                self.data.swap(i * self.cols + j, j * self.cols + i);
+            }
+        }
+    }
+
+    pub fn multiply(&self, other: &Matrix<T>) -> Self
+    where
+        T: Mul<Output = T> + Add<Output = T> + Copy + Zero,
+    {
+        let largest = self.rows.max(self.cols).max(other.cols);
+
+        if largest >= BLOCKED_THRESHOLD {
+            self.multiply_blocked(other)
+        } else {
+            self.multiply_naive(other)
+        }
+    }
+
+    pub fn multiply_naive(&self, other: &Matrix<T>) -> Self
+    where
+        T: Mul<Output = T> + Add<Output = T> + Copy + Zero,
+    {
+        assert!(self.cols == other.rows, "Incompatible dimensions");
+
+        let mut res = Vec::new();
+
+        for i in 0..self.rows {
+            for j in 0..other.cols {
+                let mut sum = T::zero();
+                for k in 0..self.cols {
+                    sum = sum + *self.get(i, k) * *other.get(k, j);
+                }
+
+                res.push(sum);
+            }
+        }
+
+        Matrix::new(self.rows, other.cols, res)
+    }
+
+    // works on BLOCK_SIZE x BLOCK_SIZE tiles so each tile of `self`, `other` and
+    // the result stays in cache, and walks `other` row by row (i-k-j order)
+    // instead of striding down its columns
+    pub fn multiply_blocked(&self, other: &Matrix<T>) -> Self
+    where
+        T: Mul<Output = T> + Add<Output = T> + Copy + Zero,
+    {
+        assert!(self.cols == other.rows, "Incompatible dimensions");
+
+        let (n, m, p) = (self.rows, self.cols, other.cols);
+        let mut res = vec![T::zero(); n * p];
+
+        for ii in (0..n).step_by(BLOCK_SIZE) {
+            for kk in (0..m).step_by(BLOCK_SIZE) {
+                for jj in (0..p).step_by(BLOCK_SIZE) {
+                    for i in ii..(ii + BLOCK_SIZE).min(n) {
+                        for k in kk..(kk + BLOCK_SIZE).min(m) {
+                            let a = self.data[i * m + k];
+                            let other_row = &other.data[k * p..(k + 1) * p];
+                            let res_row = &mut res[i * p..(i + 1) * p];
+
+                            for j in jj..(jj + BLOCK_SIZE).min(p) {
+                                res_row[j] = res_row[j] + a * other_row[j];
+                            }
+                        }
+                    }
+                }
+            }
+        }
+
+        Matrix::new(n, p, res)
+    }
+
+    // exponentiation by squaring, pow(0) is the identity
+    pub fn pow(&self, n: u32) -> Self
+    where
+        T: Mul<Output = T> + Add<Output = T> + Copy + Zero + One,
+    {
+        assert!(self.is_square(), "Matrix must be square");
+
+        let mut res = Matrix::identity(self.rows);
+        let mut base = self.clone();
+        let mut n = n;
+
+        while n > 0 {
+            if n % 2 == 1 {
+                res = res.multiply(&base);
+            }
+
+            n /= 2;
+            if n > 0 {
+                base = base.multiply(&base);
+            }
+        }
+
+        res
+    }
+
+    pub fn kronecker(&self, other: &Matrix<T>) -> Matrix<T>
+    where
+        T: Mul<Output = T> + Copy,
+    {
+        let rows = self.rows * other.rows;
+        let cols = self.cols * other.cols;
+        let mut res = Vec::with_capacity(rows * cols);
+
+        for i in 0..rows {
+            for j in 0..cols {
+                let a = *self.get(i / other.rows, j / other.cols);
+                let b = *other.get(i % other.rows, j % other.cols);
+                res.push(a * b);
+            }
+        }
+
+        Matrix::new(rows, cols, res)
+    }
+
+    pub fn scalar_mul(&self, rhs: T) -> Matrix<T>
+    where
+        T: Mul<Output = T> + Copy,
+    {
+        let mut new_data = Vec::new();
+
+        for i in 0..self.rows {
+            for j in 0..self.cols {
+                new_data.push(*self.get(i, j) * rhs);
+            }
+        }
+
+        Matrix::new(self.rows, self.cols, new_data)
+    }
+
+    pub fn get(&self, i: usize, j: usize) -> &T {
+        &self.data[i * self.cols + j]
+    }
+
+    pub fn set(&mut self, i: usize, j: usize, val: T) {
+        self[(i, j)] = val;
+    }
+
+    // reuses the data buffer, so no elements are copied,
+    // returns None when the number of elements differs
+    pub fn reshape(self, rows: usize, cols: usize) -> Option<Matrix<T>> {
+        if rows * cols != self.rows * self.cols {
+            return None;
+        }
+
+        Some(Matrix::new(rows, cols, self.data))
+    }
+
+    // the elements in row-major order
+    pub fn flatten(self) -> Vec<T> {
+        self.data
+    }
+
+    // element-wise comparison within `epsilon`, for floating-point results
+    // where `==` would fail on rounding noise
+    pub fn approx_eq(&self, other: &Matrix<T>, epsilon: T) -> bool
+    where
+        T: Float,
+    {
+        self.rows == other.rows
+            && self.cols == other.cols
+            && self
+                .data
+                .iter()
+                .zip(&other.data)
+                .all(|(a, b)| (*a - *b).abs() <= epsilon)
+    }
+
+    pub fn rows(&self) -> usize {
+        self.rows
+    }
+
+    pub fn cols(&self) -> usize {
+        self.cols
+    }
+
+    pub fn mul_vector(&self, vector: &Vector<T>) -> Vector<T>
+    where
+        T: Mul<Output = T> + Add<Output = T> + Copy + Zero,
+    {
+        assert!(self.cols == vector.len(), "Incompatible dimensions");
+
+        let mut res = Vec::new();
+
+        for i in 0..self.rows {
+            let mut sum = T::zero();
+            for j in 0..self.cols {
+                sum = sum + *self.get(i, j) * vector[j];
+            }
+
+            res.push(sum);
+        }
+
+        Vector::new(res)
+    }
+
+    pub fn is_square(&self) -> bool {
+        self.rows == self.cols
+    }
+
+    pub fn trace(&self) -> T
+    where
+        T: Add<Output = T> + Copy + Zero,
+    {
+        assert!(self.is_square(), "Matrix must be square");
+
+        (0..self.rows).fold(T::zero(), |acc, i| acc + *self.get(i, i))
+    }
+
+    pub fn rank(&self) -> usize
+    where
+        T: Float,
+    {
+        // scale the tolerance with the size and magnitude of the entries,
+        // otherwise rounding noise would count as an independent row
+        let max_abs = self.data.iter().fold(T::zero(), |acc, x| acc.max(x.abs()));
+        let size = T::from(self.rows.max(self.cols)).unwrap();
+
+        self.rank_with_tolerance(T::epsilon() * size * max_abs)
+    }
+
+    pub fn rank_with_tolerance(&self, epsilon: T) -> usize
+    where
+        T: Float,
+    {
+        let mut m = self.clone();
+        let mut rank = 0;
+
+        // reduce to row echelon form, counting the pivots
+        for col in 0..m.cols {
+            if rank == m.rows {
+                break;
+            }
+
+            let pivot = m.pivot_row(rank, col);
+
+            if m.get(pivot, col).abs() <= epsilon {
+                continue;
+            }
+
+            m.swap_rows(pivot, rank);
+
+            for r in (rank + 1)..m.rows {
+                let factor = *m.get(r, col) / *m.get(rank, col);
+
+                for c in col..m.cols {
+                    let val = *m.get(r, c) - factor * *m.get(rank, c);
+                    m.set(r, c, val);
+                }
+            }
+
+            rank += 1;
+        }
+
+        rank
+    }
+
+    pub fn frobenius_norm(&self) -> T
+    where
+        T: Float,
+    {
+        self.data
+            .iter()
+            .fold(T::zero(), |acc, x| acc + *x * *x)
+            .sqrt()
+    }
+
+    // maximum absolute column sum
+    pub fn norm_l1(&self) -> T
+    where
+        T: Float,
+    {
+        (0..self.cols)
+            .map(|j| (0..self.rows).fold(T::zero(), |acc, i| acc + self.get(i, j).abs()))
+            .fold(T::zero(), T::max)
+    }
+
+    // maximum absolute row sum
+    pub fn norm_inf(&self) -> T
+    where
+        T: Float,
+    {
+        (0..self.rows)
+            .map(|i| (0..self.cols).fold(T::zero(), |acc, j| acc + self.get(i, j).abs()))
+            .fold(T::zero(), T::max)
+    }
+
+    // Gauss-Jordan elimination with partial pivoting,
+    // returns None when the matrix is singular
+    pub fn inverse(&self) -> Option<Matrix<T>>
+    where
+        T: Float,
+    {
+        assert!(self.is_square(), "Matrix must be square");
+
+        let n = self.rows;
+        let mut m = self.clone();
+        let mut inv = Matrix::identity(n);
+
+        let max_abs = self.data.iter().fold(T::zero(), |acc, x| acc.max(x.abs()));
+        let epsilon = T::epsilon() * T::from(n).unwrap() * max_abs;
+
+        for col in 0..n {
+            let pivot = m.pivot_row(col, col);
+
+            if m.get(pivot, col).abs() <= epsilon {
+                return None;
+            }
+
+            m.swap_rows(pivot, col);
+            inv.swap_rows(pivot, col);
+
+            let p = *m.get(col, col);
+            for j in 0..n {
+                m.set(col, j, *m.get(col, j) / p);
+                inv.set(col, j, *inv.get(col, j) / p);
+            }
+
+            for r in 0..n {
+                if r == col {
+                    continue;
+                }
+
+                let factor = *m.get(r, col);
+                for j in 0..n {
+                    m.set(r, j, *m.get(r, j) - factor * *m.get(col, j));
+                    inv.set(r, j, *inv.get(r, j) - factor * *inv.get(col, j));
+                }
+            }
+        }
+
+        Some(inv)
+    }
+
+    // condition number in the L1 norm, None when the matrix is singular
+    pub fn condition_number(&self) -> Option<T>
+    where
+        T: Float,
+    {
+        self.inverse().map(|inv| self.norm_l1() * inv.norm_l1())
+    }
+
+    // row (from `from` downwards) with the largest absolute value in `col`
+    fn pivot_row(&self, from: usize, col: usize) -> usize
+    where
+        T: Float,
+    {
+        (from..self.rows)
+            .max_by(|&a, &b| {
+                self.get(a, col)
+                    .abs()
+                    .partial_cmp(&self.get(b, col).abs())
+                    .unwrap_or(Ordering::Equal)
+            })
+            .unwrap()
+    }
+
+    fn swap_rows(&mut self, a: usize, b: usize) {
+        if a == b {
+            return;
+        }
+
+        for j in 0..self.cols {
+            self.data.swap(a * self.cols + j, b * self.cols + j);
+        }
+    }
+}
+
+pub fn hstack<T: Copy>(matrices: &[&Matrix<T>]) -> Matrix<T> {
+    assert!(!matrices.is_empty(), "No matrices to stack");
+
+    let rows = matrices[0].rows;
+    assert!(
+        matrices.iter().all(|m| m.rows == rows),
+        "All matrices must have the same number of rows"
+    );
+
+    let cols = matrices.iter().map(|m| m.cols).sum();
+    let mut res = Vec::with_capacity(rows * cols);
+
+    for i in 0..rows {
+        for m in matrices {
+            res.extend_from_slice(&m.data[i * m.cols..(i + 1) * m.cols]);
+        }
+    }
+
+    Matrix::new(rows, cols, res)
+}
+
+pub fn vstack<T: Copy>(matrices: &[&Matrix<T>]) -> Matrix<T> {
+    assert!(!matrices.is_empty(), "No matrices to stack");
+
+    let cols = matrices[0].cols;
+    assert!(
+        matrices.iter().all(|m| m.cols == cols),
+        "All matrices must have the same number of columns"
+    );
+
+    let rows = matrices.iter().map(|m| m.rows).sum();
+    let mut res = Vec::with_capacity(rows * cols);
+
+    // row-major storage, so stacking vertically is plain concatenation
+    for m in matrices {
+        res.extend_from_slice(&m.data);
+    }
+
+    Matrix::new(rows, cols, res)
+}
+
+impl<T> Add for &Matrix<T>
+where
+    T: Add<Output = T> + Copy,
+{
+    type Output = Matrix<T>;
+
+    fn add(self, other: &Matrix<T>) -> Matrix<T> {
+        self.add(other)
+    }
+}
+
+impl<T> Sub for &Matrix<T>
+where
+    T: Sub<Output = T> + Copy,
+{
+    type Output = Matrix<T>;
+
+    fn sub(self, other: &Matrix<T>) -> Matrix<T> {
+        self.sub(other)
+    }
+}
+
+impl<T> Mul for &Matrix<T>
+where
+    T: Mul<Output = T> + Copy + Zero,
+{
+    type Output = Matrix<T>;
+
+    fn mul(self, rhs: &Matrix<T>) -> Self::Output {
+        self.multiply(rhs)
+    }
+}
+
+impl<T> Mul<T> for &Matrix<T>
+where
+    T: Mul<Output = T> + Copy,
+{
+    type Output = Matrix<T>;
+
+    fn mul(self, rhs: T) -> Self::Output {
+        self.scalar_mul(rhs)
+    }
+}
+
+impl<T> Mul<&Vector<T>> for &Matrix<T>
+where
+    T: Mul<Output = T> + Add<Output = T> + Copy + Zero,
+{
+    type Output = Vector<T>;
+
+    fn mul(self, rhs: &Vector<T>) -> Self::Output {
+        self.mul_vector(rhs)
+    }
+}
+
+impl<T> Neg for Matrix<T>
+where
+    T: Neg<Output = T>,
+{
+    type Output = Matrix<T>;
+
+    fn neg(self) -> Self::Output {
+        let res = self.data.into_iter().map(|e| -e).collect();
+
+        Matrix::new(self.rows, self.cols, res)
+    }
+}
+
+impl<T> Index<(usize, usize)> for Matrix<T> {
+    type Output = T;
+
+    fn index(&self, index: (usize, usize)) -> &Self::Output {
+        let (i, j) = index;
+        self.get(i, j)
+    }
+}
+
+impl<T> IndexMut<(usize, usize)> for Matrix<T> {
+    fn index_mut(&mut self, index: (usize, usize)) -> &mut Self::Output {
+        let (i, j) = index;
+        &mut self.data[i * self.cols + j]
+    }
+}
+
+impl<T> Div<T> for &Matrix<T>
+where
+    T: Div<Output = T> + Copy,
+{
+    type Output = Matrix<T>;
+
+    fn div(self, rhs: T) -> Self::Output {
+        let data = self.data.iter().map(|x| *x / rhs).collect();
+        Matrix::new(self.rows, self.cols, data)
+    }
+}
+
+impl<T> AddAssign<&Matrix<T>> for Matrix<T>
+where
+    T: AddAssign + Copy,
+{
+    fn add_assign(&mut self, other: &Matrix<T>) {
+        // check dimension
+        assert!(
+            self.rows == other.rows && self.cols == other.cols,
+            "Dimensions don't match"
+        );
+
+        for (a, b) in self.data.iter_mut().zip(&other.data) {
+            *a += *b;
+        }
+    }
+}
+
+impl<T> SubAssign<&Matrix<T>> for Matrix<T>
+where
+    T: SubAssign + Copy,
+{
+    fn sub_assign(&mut self, other: &Matrix<T>) {
+        // check dimension
+        assert!(
+            self.rows == other.rows && self.cols == other.cols,
+            "Dimensions don't match"
+        );
+
+        for (a, b) in self.data.iter_mut().zip(&other.data) {
+            *a -= *b;
+        }
+    }
+}
+
+impl<T> MulAssign<T> for Matrix<T>
+where
+    T: MulAssign + Copy,
+{
+    fn mul_assign(&mut self, rhs: T) {
+        for a in self.data.iter_mut() {
+            *a *= rhs;
+        }
+    }
+}
+
+#[cfg(feature = "approx")]
+impl<T> approx::AbsDiffEq for Matrix<T>
+where
+    T: approx::AbsDiffEq,
+    T::Epsilon: Copy,
+{
+    type Epsilon = T::Epsilon;
+
+    fn default_epsilon() -> Self::Epsilon {
+        T::default_epsilon()
+    }
+
+    fn abs_diff_eq(&self, other: &Self, epsilon: Self::Epsilon) -> bool {
+        self.rows == other.rows
+            && self.cols == other.cols
+            && self
+                .data
+                .iter()
+                .zip(&other.data)
+                .all(|(a, b)| a.abs_diff_eq(b, epsilon))
+    }
+}
+
+#[cfg(feature = "approx")]
+impl<T> approx::RelativeEq for Matrix<T>
+where
+    T: approx::RelativeEq,
+    T::Epsilon: Copy,
+{
+    fn default_max_relative() -> Self::Epsilon {
+        T::default_max_relative()
+    }
+
+    fn relative_eq(
+        &self,
+        other: &Self,
+        epsilon: Self::Epsilon,
+        max_relative: Self::Epsilon,
+    ) -> bool {
+        self.rows == other.rows
+            && self.cols == other.cols
+            && self
+                .data
+                .iter()
+                .zip(&other.data)
+                .all(|(a, b)| a.relative_eq(b, epsilon, max_relative))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_new_ok() {
+        let data = vec![1.0, 2.0, 3.0, 4.0, 5.0, 6.0];
+        let matrix = Matrix::new(2, 3, data.clone());
+
+        assert_eq!(matrix.rows, 2);
+        assert_eq!(matrix.cols, 3);
+        assert_eq!(data.len(), 6);
+
+        let data = vec![1, 2, 3, 4, 5, 6, 7, 8];
+        let matrix = Matrix::new(2, 4, data.clone());
+
+        assert_eq!(matrix.rows, 2);
+        assert_eq!(matrix.cols, 4);
+        assert_eq!(matrix.data.len(), data.len());
+
+        let m3 = Matrix::new(2, 2, vec!["a", "b", "c", "d"]);
+
+        assert_eq!(m3.rows, 2);
+        assert_eq!(m3.cols, 2);
+        assert_eq!(m3.data.len(), 4);
+    }
+
+    #[test]
+    #[should_panic(expected = "Data does not match dimensions")]
+    fn test_new_fail() {
+        let data = vec![1.0, 2.0, 3.0, 4.0, 5.0];
+        Matrix::new(3, 3, data);
+    }
+
+    #[test]
+    fn test_add_matrix() {
+        let matrix_1 = Matrix::new(2, 2, vec![1.0, 2.0, 3.0, 4.0]);
+        let matrix_2 = Matrix::new(2, 2, vec![5.0, 6.0, 7.0, 8.0]);
+
+        let expected_data = vec![6.0, 8.0, 10.0, 12.0];
+        let res = matrix_1.add(&matrix_2);
+
+        assert!(res.data == expected_data, "wrong matrix data");
+    }
+
+    #[test]
+    #[should_panic(expected = "Dimensions don't match")]
+    fn test_add_matrix_wrong_dimensions() {
+        let matrix_1 = Matrix::new(2, 2, vec![1.0, 2.0, 3.0, 4.0]);
+        let matrix_2 = Matrix::new(3, 2, vec![5.0, 6.0, 7.0, 8.0, 9.0, 10.0]);
+
+        matrix_1.add(&matrix_2);
+    }
+
+    #[test]
+    fn test_hadamard() {
+        let matrix_1 = Matrix::new(2, 2, vec![1, 2, 3, 4]);
+        let matrix_2 = Matrix::new(2, 2, vec![5, 6, 7, 8]);
+
+        assert_eq!(
+            matrix_1.hadamard(&matrix_2),
+            Matrix::new(2, 2, vec![5, 12, 21, 32])
+        );
+
+        let mask = Matrix::new(2, 2, vec![1, 0, 0, 1]);
+        assert_eq!(
+            matrix_1.hadamard(&mask),
+            Matrix::new(2, 2, vec![1, 0, 0, 4])
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Dimensions don't match")]
+    fn test_hadamard_wrong_dimensions() {
+        let matrix_1 = Matrix::new(2, 2, vec![1, 2, 3, 4]);
+        let matrix_2 = Matrix::new(1, 4, vec![5, 6, 7, 8]);
+
+        matrix_1.hadamard(&matrix_2);
+    }
+
+    #[test]
+    fn test_elementwise_div() {
+        let matrix_1 = Matrix::new(2, 2, vec![2.0, 6.0, 12.0, 20.0]);
+        let matrix_2 = Matrix::new(2, 2, vec![1.0, 2.0, 3.0, 4.0]);
+
+        assert_eq!(
+            matrix_1.elementwise_div(&matrix_2),
+            Matrix::new(2, 2, vec![2.0, 3.0, 4.0, 5.0])
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Dimensions don't match")]
+    fn test_elementwise_div_wrong_dimensions() {
+        let matrix_1 = Matrix::new(2, 2, vec![1.0, 2.0, 3.0, 4.0]);
+        let matrix_2 = Matrix::new(2, 1, vec![1.0, 2.0]);
+
+        matrix_1.elementwise_div(&matrix_2);
+    }
+
+    #[test]
+    fn test_add_row_and_col_vector() {
+        let matrix = Matrix::new(2, 3, vec![1, 2, 3, 4, 5, 6]);
+
+        let row = Matrix::new(1, 3, vec![10, 20, 30]);
+        assert_eq!(
+            matrix.add_row_vector(&row),
+            Matrix::new(2, 3, vec![11, 22, 33, 14, 25, 36])
+        );
+
+        let col = Matrix::new(2, 1, vec![100, 200]);
+        assert_eq!(
+            matrix.add_col_vector(&col),
+            Matrix::new(2, 3, vec![101, 102, 103, 204, 205, 206])
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Dimensions don't match")]
+    fn test_add_row_vector_wrong_dimensions() {
+        let matrix = Matrix::new(2, 3, vec![1, 2, 3, 4, 5, 6]);
+
+        // a column vector where a row vector is expected
+        matrix.add_row_vector(&Matrix::new(3, 1, vec![1, 2, 3]));
+    }
+
+    #[test]
+    fn test_scale_rows_and_cols() {
+        let matrix = Matrix::new(2, 3, vec![1.0, 2.0, 3.0, 4.0, 5.0, 6.0]);
+
+        let row_factors = Matrix::new(2, 1, vec![2.0, 0.5]);
+        assert_eq!(
+            matrix.scale_rows(&row_factors),
+            Matrix::new(2, 3, vec![2.0, 4.0, 6.0, 2.0, 2.5, 3.0])
+        );
+
+        let col_factors = Matrix::new(1, 3, vec![1.0, 0.0, -1.0]);
+        assert_eq!(
+            matrix.scale_cols(&col_factors),
+            Matrix::new(2, 3, vec![1.0, 0.0, -3.0, 4.0, 0.0, -6.0])
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Dimensions don't match")]
+    fn test_scale_cols_wrong_dimensions() {
+        let matrix = Matrix::new(2, 2, vec![1, 2, 3, 4]);
+
+        matrix.scale_cols(&Matrix::new(1, 3, vec![1, 2, 3]));
+    }
+
+    #[test]
+    fn test_hstack_vstack() {
+        let a = Matrix::new(2, 2, vec![1, 2, 3, 4]);
+        let b = Matrix::new(2, 1, vec![5, 6]);
+        let c = Matrix::new(1, 2, vec![7, 8]);
+
+        assert_eq!(a.hstack(&b), Matrix::new(2, 3, vec![1, 2, 5, 3, 4, 6]));
+        assert_eq!(a.vstack(&c), Matrix::new(3, 2, vec![1, 2, 3, 4, 7, 8]));
+    }
+
+    #[test]
+    fn test_block_matrix() {
+        let identity: Matrix<i32> = Matrix::identity(2);
+        let zeros = Matrix::new(2, 2, vec![0; 4]);
+        let twos = Matrix::new(2, 2, vec![2; 4]);
+
+        let top = hstack(&[&identity, &zeros]);
+        let bottom = hstack(&[&twos, &identity]);
+        let block = vstack(&[&top, &bottom]);
+
+        let expected_matrix =
+            Matrix::new(4, 4, vec![1, 0, 0, 0, 0, 1, 0, 0, 2, 2, 1, 0, 2, 2, 0, 1]);
+
+        assert_eq!(block, expected_matrix);
+    }
+
+    #[test]
+    #[should_panic(expected = "All matrices must have the same number of rows")]
+    fn test_hstack_wrong_dimensions() {
+        let a = Matrix::new(2, 2, vec![1, 2, 3, 4]);
+        let b = Matrix::new(1, 2, vec![5, 6]);
+
+        a.hstack(&b);
+    }
+
+    #[test]
+    #[should_panic(expected = "All matrices must have the same number of columns")]
+    fn test_vstack_wrong_dimensions() {
+        let a = Matrix::new(2, 2, vec![1, 2, 3, 4]);
+        let b = Matrix::new(2, 1, vec![5, 6]);
+
+        a.vstack(&b);
+    }
+
+    #[test]
+    #[should_panic(expected = "No matrices to stack")]
+    fn test_stack_empty() {
+        vstack::<i32>(&[]);
+    }
+
+    #[test]
+    fn test_map() {
+        let matrix = Matrix::new(2, 2, vec![1, -2, 3, -4]);
+
+        assert_eq!(matrix.map(|x| x * x), Matrix::new(2, 2, vec![1, 4, 9, 16]));
+        assert_eq!(
+            matrix.map(|x| x > 0),
+            Matrix::new(2, 2, vec![true, false, true, false])
+        );
+    }
+
+    #[test]
+    fn test_map_in_place() {
+        let mut matrix = Matrix::new(2, 2, vec![1.0, -2.0, 3.0, -4.0]);
+
+        matrix.map_in_place(f64::abs);
+
+        assert_eq!(matrix, Matrix::new(2, 2, vec![1.0, 2.0, 3.0, 4.0]));
+    }
+
+    #[test]
+    fn test_zip_with() {
+        let matrix_1 = Matrix::new(2, 2, vec![1, 5, 3, 8]);
+        let matrix_2 = Matrix::new(2, 2, vec![4, 2, 3, 9]);
+
+        assert_eq!(
+            matrix_1.zip_with(&matrix_2, i32::max),
+            Matrix::new(2, 2, vec![4, 5, 3, 9])
+        );
+        assert_eq!(
+            matrix_1.zip_with(&matrix_2, |a, b| a == b),
+            Matrix::new(2, 2, vec![false, false, true, false])
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Dimensions don't match")]
+    fn test_zip_with_wrong_dimensions() {
+        let matrix_1 = Matrix::new(2, 2, vec![1, 2, 3, 4]);
+        let matrix_2 = Matrix::new(1, 4, vec![1, 2, 3, 4]);
+
+        matrix_1.zip_with(&matrix_2, |a, b| a + b);
+    }
+
+    #[test]
+    fn test_transpose() {
+        let matrix = Matrix::new(2, 3, vec![1.0, 2.0, 3.0, 4.0, 5.0, 6.0]);
+        let expected_matrix = Matrix::new(3, 2, vec![1.0, 4.0, 2.0, 5.0, 3.0, 6.0]);
+
+        assert!(matrix.transpose() == expected_matrix, "Wrongly transposed");
+    }
+
+    #[test]
+    fn test_transpose_in_place() {
+        let mut matrix = Matrix::new(3, 3, vec![1, 2, 3, 4, 5, 6, 7, 8, 9]);
+        let expected_matrix = matrix.transpose();
+
+        matrix.transpose_in_place();
+
+        assert_eq!(matrix, expected_matrix);
+    }
+
+    #[test]
+    #[should_panic(expected = "Matrix must be square")]
+    fn test_transpose_in_place_not_square() {
+        let mut matrix = Matrix::new(2, 3, vec![1, 2, 3, 4, 5, 6]);
+
+        matrix.transpose_in_place();
+    }
+
+    #[test]
+    fn test_multiply() {
+        let matrix_1 = Matrix::new(2, 2, vec![1.0, 2.0, 3.0, 4.0]);
+        let matrix_2 = Matrix::new(2, 2, vec![5.0, 6.0, 7.0, 8.0]);
+
+        let expected_matrix = Matrix::new(2, 2, vec![19.0, 22.0, 43.0, 50.0]);
+
+        assert_eq!(matrix_1.multiply(&matrix_2), expected_matrix);
+
+        assert_eq!(&matrix_1 * &matrix_2, expected_matrix);
+    }
+
+    #[test]
+    fn test_multiply_blocked() {
+        let matrix_1 = Matrix::new(2, 2, vec![1.0, 2.0, 3.0, 4.0]);
+        let matrix_2 = Matrix::new(2, 2, vec![5.0, 6.0, 7.0, 8.0]);
+
+        let expected_matrix = Matrix::new(2, 2, vec![19.0, 22.0, 43.0, 50.0]);
+
+        assert_eq!(matrix_1.multiply_blocked(&matrix_2), expected_matrix);
+    }
+
+    #[test]
+    fn test_multiply_blocked_matches_naive() {
+        // sizes that don't divide evenly into blocks
+        let (n, m, p) = (150, 70, 131);
+        let matrix_1 = Matrix::new(n, m, (0..n * m).map(|x| (x % 17) as i64 - 8).collect());
+        let matrix_2 = Matrix::new(m, p, (0..m * p).map(|x| (x % 13) as i64 - 6).collect());
+
+        let expected_matrix = matrix_1.multiply_naive(&matrix_2);
+
+        assert_eq!(matrix_1.multiply_blocked(&matrix_2), expected_matrix);
+        assert_eq!(&matrix_1 * &matrix_2, expected_matrix);
+    }
+
+    #[test]
+    #[should_panic(expected = "Incompatible dimensions")]
+    fn test_multiply_blocked_wrong_dimensions() {
+        let matrix_1 = Matrix::new(2, 3, vec![1, 2, 3, 4, 5, 6]);
+        let matrix_2 = Matrix::new(2, 3, vec![1, 2, 3, 4, 5, 6]);
+
+        matrix_1.multiply_blocked(&matrix_2);
+    }
+
+    #[test]
+    fn test_pow() {
+        let matrix = Matrix::new(2, 2, vec![1, 2, 3, 4]);
+
+        assert_eq!(matrix.pow(0), Matrix::identity(2));
+        assert_eq!(matrix.pow(1), matrix);
+        assert_eq!(matrix.pow(3), &(&matrix * &matrix) * &matrix);
+
+        // Fibonacci numbers
+        let fib = Matrix::new(2, 2, vec![1u64, 1, 1, 0]);
+        assert_eq!(fib.pow(10), Matrix::new(2, 2, vec![89, 55, 55, 34]));
+    }
+
+    #[test]
+    fn test_pow_path_counting() {
+        // adjacency matrix of a directed 3-cycle: 0 -> 1 -> 2 -> 0
+        let graph = Matrix::new(3, 3, vec![0, 1, 0, 0, 0, 1, 1, 0, 0]);
+
+        assert_eq!(graph.pow(3), Matrix::identity(3));
+        assert_eq!(*graph.pow(4).get(0, 1), 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "Matrix must be square")]
+    fn test_pow_not_square() {
+        let matrix = Matrix::new(1, 2, vec![1, 2]);
+
+        matrix.pow(2);
+    }
+
+    #[test]
+    fn test_kronecker() {
+        let matrix_1 = Matrix::new(2, 2, vec![1, 2, 3, 4]);
+        let matrix_2 = Matrix::new(2, 2, vec![0, 5, 6, 7]);
+
+        let expected_matrix = Matrix::new(
+            4,
+            4,
+            vec![
+                0, 5, 0, 10, //
+                6, 7, 12, 14, //
+                0, 15, 0, 20, //
+                18, 21, 24, 28,
+            ],
+        );
+
+        assert_eq!(matrix_1.kronecker(&matrix_2), expected_matrix);
+    }
+
+    #[test]
+    fn test_kronecker_non_square() {
+        let matrix_1 = Matrix::new(1, 2, vec![1, 2]);
+        let matrix_2 = Matrix::new(2, 1, vec![3, 4]);
+
+        let res = matrix_1.kronecker(&matrix_2);
+
+        assert_eq!(res, Matrix::new(2, 2, vec![3, 6, 4, 8]));
+
+        let identity = Matrix::new(2, 2, vec![1, 0, 0, 1]);
+        let block = Matrix::new(1, 2, vec![7, 9]);
+
+        assert_eq!(
+            identity.kronecker(&block),
+            Matrix::new(2, 4, vec![7, 9, 0, 0, 0, 0, 7, 9])
+        );
+    }
+
+    #[test]
+    fn test_add_trait() {
+        let matrix_1 = Matrix::new(2, 2, vec![1.0, 2.0, 3.0, 4.0]);
+        let matrix_2 = Matrix::new(2, 2, vec![5.0, 6.0, 7.0, 8.0]);
+
+        let expected_data = vec![6.0, 8.0, 10.0, 12.0];
+
+        assert!(
+            &matrix_1 + &matrix_2 == Matrix::new(2, 2, expected_data),
+            "wrong matrix data"
+        );
+    }
+
+    #[test]
+    fn test_mul_trait() {
+        let matrix_1 = Matrix::new(2, 2, vec![1.0, 2.0, 3.0, 4.0]);
+        let matrix_2 = Matrix::new(2, 2, vec![5.0, 6.0, 7.0, 8.0]);
+
+        let expected_matrix = Matrix::new(2, 2, vec![19.0, 22.0, 43.0, 50.0]);
+
+        assert_eq!(&matrix_1 * &matrix_2, expected_matrix);
+    }
+
+    #[test]
+    fn test_scalar_mul() {
+        let matrix = Matrix::new(2, 2, vec![1.0, 2.0, 3.0, 4.0]);
+
+        let expected_matrix = Matrix::new(2, 2, vec![2.0, 4.0, 6.0, 8.0]);
+
+        assert_eq!(matrix.scalar_mul(2.0), expected_matrix);
+        assert_eq!(&matrix * 2.0, expected_matrix);
+    }
+
+    #[test]
+    fn test_sub_matrix() {
+        let matrix_1 = Matrix::new(2, 2, vec![1.0, 2.0, 3.0, 4.0]);
+        let matrix_2 = Matrix::new(2, 2, vec![5.0, 6.0, 7.0, 8.0]);
+
+        let expected_data = vec![-4.0, -4.0, -4.0, -4.0];
+        let res = matrix_1.sub(&matrix_2);
+
+        assert!(res.data == expected_data, "wrong matrix data");
+        assert_eq!(&matrix_1 - &matrix_2, Matrix::new(2, 2, expected_data));
+
+        let m1 = Matrix::new(2, 2, vec![1, 2, 3, 4]);
+        let m2 = Matrix::new(2, 2, vec![5, 6, 7, 8]);
+        let exp_m = Matrix::new(2, 2, vec![-4, -4, -4, -4]);
+        assert_eq!(&m1 - &m2, exp_m);
+    }
+
+    #[test]
+    fn test_neg_trait() {
+        let matrix_1 = Matrix::new(2, 2, vec![-1.0, 2.0, 3.0, -4.0]);
+
+        let exp_matrix = Matrix::new(2, 2, vec![1.0, -2.0, -3.0, 4.0]);
+
+        assert_eq!(-matrix_1, exp_matrix);
+    }
+
+    #[test]
+    fn test_matrix_index() {
+        let matrix: Matrix<f64> = Matrix::new(2, 2, vec![1.0, 2.0, 3.0, 4.0]);
+
+        assert_eq!(matrix[(0, 0)], matrix.data[0]);
+        assert_eq!(matrix[(0, 1)], matrix.data[1]);
+        assert_eq!(matrix[(1, 0)], matrix.data[2]);
+        assert_eq!(matrix[(1, 1)], matrix.data[3]);
+    }
+
+    #[test]
+    fn test_matrix_index_mut() {
+        let mut matrix: Matrix<f64> = Matrix::new(2, 2, vec![1.0, 2.0, 3.0, 4.0]);
+
+        matrix[(0, 0)] = 6.0;
+        assert_eq!(matrix[(0, 0)], 6.0);
+
+        matrix.set(0, 1, 19.0);
+        assert_eq!(matrix[(0, 1)], 19.0);
+    }
+
+    #[test]
+    fn test_mul_vector() {
+        let matrix = Matrix::new(2, 3, vec![1, 2, 3, 4, 5, 6]);
+        let vector = Vector::new(vec![1, 0, -1]);
+
+        assert_eq!(matrix.mul_vector(&vector), Vector::new(vec![-2, -2]));
+        assert_eq!(&matrix * &vector, Vector::new(vec![-2, -2]));
+
+        let matrix = Matrix::new(2, 2, vec![1.0, 2.0, 3.0, 4.0]);
+        let vector = Vector::new(vec![1.0, 1.0]);
+        assert_eq!(&matrix * &vector, Vector::new(vec![3.0, 7.0]));
+    }
+
+    #[test]
+    #[should_panic(expected = "Incompatible dimensions")]
+    fn test_mul_vector_wrong_dimensions() {
+        let matrix = Matrix::new(2, 2, vec![1, 2, 3, 4]);
+        let vector = Vector::new(vec![1, 2, 3]);
+
+        matrix.mul_vector(&vector);
+    }
+
+    #[test]
+    fn test_trace() {
+        let matrix = Matrix::new(3, 3, vec![1, 2, 3, 4, 5, 6, 7, 8, 9]);
+
+        assert_eq!(matrix.trace(), 15);
+    }
+
+    #[test]
+    #[should_panic(expected = "Matrix must be square")]
+    fn test_trace_not_square() {
+        let matrix = Matrix::new(2, 3, vec![1, 2, 3, 4, 5, 6]);
+
+        matrix.trace();
+    }
+
+    #[test]
+    fn test_rank() {
+        let full = Matrix::new(2, 2, vec![1.0, 2.0, 3.0, 4.0]);
+        assert_eq!(full.rank(), 2);
+
+        // third row is the sum of the first two
+        let singular = Matrix::new(3, 3, vec![1.0, 2.0, 3.0, 4.0, 5.0, 6.0, 5.0, 7.0, 9.0]);
+        assert_eq!(singular.rank(), 2);
+
+        let wide = Matrix::new(2, 4, vec![1.0, 2.0, 3.0, 4.0, 2.0, 4.0, 6.0, 8.0]);
+        assert_eq!(wide.rank(), 1);
+
+        let zero: Matrix<f64> = Matrix::new(2, 3, vec![0.0; 6]);
+        assert_eq!(zero.rank(), 0);
+    }
+
+    #[test]
+    fn test_rank_with_tolerance() {
+        let nearly_singular = Matrix::new(2, 2, vec![1.0, 1.0, 1.0, 1.0 + 1e-9]);
+
+        assert_eq!(nearly_singular.rank(), 2);
+        assert_eq!(nearly_singular.rank_with_tolerance(1e-6), 1);
+    }
+
+    #[test]
+    fn test_identity() {
+        let identity: Matrix<i32> = Matrix::identity(3);
+
+        assert_eq!(identity, Matrix::new(3, 3, vec![1, 0, 0, 0, 1, 0, 0, 0, 1]));
+    }
+
+    #[test]
+    fn test_norms() {
+        let matrix = Matrix::new(2, 2, vec![1.0, -2.0, -3.0, 4.0]);
+
+        assert_eq!(matrix.frobenius_norm(), 30.0_f64.sqrt());
+        assert_eq!(matrix.norm_l1(), 6.0);
+        assert_eq!(matrix.norm_inf(), 7.0);
+    }
+
+    #[test]
+    fn test_inverse() {
+        let matrix = Matrix::new(2, 2, vec![4.0, 7.0, 2.0, 6.0]);
+        let expected_matrix = Matrix::new(2, 2, vec![0.6, -0.7, -0.2, 0.4]);
+
+        assert!(matrix.inverse().unwrap().approx_eq(&expected_matrix, 1e-12));
+
+        // needs a row swap to find a non-zero pivot
+        let permutation = Matrix::new(2, 2, vec![0.0, 1.0, 1.0, 0.0]);
+        assert_eq!(permutation.inverse().unwrap(), permutation);
+
+        let singular = Matrix::new(2, 2, vec![1.0, 2.0, 2.0, 4.0]);
+        assert_eq!(singular.inverse(), None);
+    }
+
+    #[test]
+    fn test_condition_number() {
+        let identity: Matrix<f64> = Matrix::identity(3);
+        assert_eq!(identity.condition_number(), Some(1.0));
+
+        let matrix = Matrix::new(2, 2, vec![4.0, 7.0, 2.0, 6.0]);
+        // ||A||_1 = 13, ||A^-1||_1 = 1.1
+        assert!((matrix.condition_number().unwrap() - 14.3).abs() < 1e-9);
+
+        let singular = Matrix::new(2, 2, vec![1.0, 2.0, 2.0, 4.0]);
+        assert_eq!(singular.condition_number(), None);
+    }
+
+    #[test]
+    fn test_assign_ops() {
+        let mut matrix = Matrix::new(2, 2, vec![1, 2, 3, 4]);
+        let other = Matrix::new(2, 2, vec![5, 6, 7, 8]);
+
+        matrix += &other;
+        assert_eq!(matrix, Matrix::new(2, 2, vec![6, 8, 10, 12]));
+
+        matrix -= &other;
+        assert_eq!(matrix, Matrix::new(2, 2, vec![1, 2, 3, 4]));
+
+        matrix *= 3;
+        assert_eq!(matrix, Matrix::new(2, 2, vec![3, 6, 9, 12]));
+    }
+
+    #[test]
+    #[should_panic(expected = "Dimensions don't match")]
+    fn test_add_assign_wrong_dimensions() {
+        let mut matrix = Matrix::new(2, 2, vec![1, 2, 3, 4]);
+
+        matrix += &Matrix::new(1, 4, vec![1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn test_reshape() {
+        let matrix = Matrix::new(2, 3, vec![1, 2, 3, 4, 5, 6]);
+
+        let reshaped = matrix.reshape(3, 2).unwrap();
+        assert_eq!(reshaped, Matrix::new(3, 2, vec![1, 2, 3, 4, 5, 6]));
+
+        let row = reshaped.reshape(1, 6).unwrap();
+        assert_eq!(row.rows(), 1);
+        assert_eq!(row.cols(), 6);
+    }
+
+    #[test]
+    fn test_reshape_wrong_size() {
+        let matrix = Matrix::new(2, 3, vec![1, 2, 3, 4, 5, 6]);
+
+        assert_eq!(matrix.clone().reshape(4, 2), None);
+        assert_eq!(matrix.reshape(6, 0), None);
+    }
+
+    #[test]
+    fn test_flatten() {
+        let matrix = Matrix::new(2, 2, vec![1, 2, 3, 4]);
+
+        assert_eq!(matrix.transpose().flatten(), vec![1, 3, 2, 4]);
+    }
+
+    #[test]
+    fn test_approx_eq() {
+        let matrix = Matrix::new(2, 2, vec![0.1 + 0.2, 1.0, 2.0, 3.0]);
+        let expected_matrix = Matrix::new(2, 2, vec![0.3, 1.0, 2.0, 3.0]);
+
+        assert_ne!(matrix, expected_matrix);
+        assert!(matrix.approx_eq(&expected_matrix, 1e-12));
+        assert!(!matrix.approx_eq(&Matrix::new(2, 2, vec![0.3, 1.0, 2.0, 3.1]), 1e-12));
+
+        // different shapes are never equal
+        let reshaped = expected_matrix.clone().reshape(1, 4).unwrap();
+        assert!(!reshaped.approx_eq(&expected_matrix, 1e-12));
+    }
+
+    #[test]
+    fn test_inverse_roundtrip() {
+        let matrix = Matrix::new(3, 3, vec![2.0, -1.0, 0.0, -1.0, 2.0, -1.0, 0.0, -1.0, 2.0]);
+        let inv = matrix.inverse().unwrap();
+
+        assert!((&matrix * &inv).approx_eq(&Matrix::identity(3), 1e-12));
+    }
+
+    #[cfg(feature = "approx")]
+    #[test]
+    fn test_approx_traits() {
+        let matrix = Matrix::new(1, 2, vec![0.1 + 0.2, 1.0]);
+        let expected_matrix = Matrix::new(1, 2, vec![0.3, 1.0]);
+
+        approx::assert_abs_diff_eq!(matrix, expected_matrix, epsilon = 1e-12);
+        approx::assert_relative_eq!(matrix, expected_matrix);
+    }
+
+    #[test]
+    fn test_div_trait() {
+        let m1 = Matrix::new(2, 2, vec![4, 4, 4, 4]);
+
+        assert_eq!(&m1 / 2, Matrix::new(2, 2, vec![2, 2, 2, 2]));
+    }
+}
@@ -0,0 +1,188 @@
+use num_traits::Zero;
+use rayon::prelude::*;
+use std::ops::{Add, Mul, Sub};
+
+use super::Matrix;
+use crate::vector::Vector;
+
+impl<T> Matrix<T>
+where
+    T: Copy + Send + Sync,
+{
+    pub fn par_multiply(&self, other: &Matrix<T>) -> Self
+    where
+        T: Mul<Output = T> + Add<Output = T> + Zero,
+    {
+        assert!(self.cols == other.rows, "Incompatible dimensions");
+
+        let (m, p) = (self.cols, other.cols);
+        let mut res = vec![T::zero(); self.rows * p];
+
+        // every row of the result only depends on one row of `self`,
+        // so rows can be computed independently
+        res.par_chunks_mut(p.max(1))
+            .enumerate()
+            .for_each(|(i, res_row)| {
+                for k in 0..m {
+                    let a = self.data[i * m + k];
+                    let other_row = &other.data[k * p..(k + 1) * p];
+
+                    for (r, b) in res_row.iter_mut().zip(other_row) {
+                        *r = *r + a * *b;
+                    }
+                }
+            });
+
+        Matrix::new(self.rows, p, res)
+    }
+
+    pub fn par_mul_vector(&self, vector: &Vector<T>) -> Vector<T>
+    where
+        T: Mul<Output = T> + Add<Output = T> + Zero,
+    {
+        assert!(self.cols == vector.len(), "Incompatible dimensions");
+
+        let m = self.cols;
+        // one entry per row, even when there are no columns to chunk
+        let res = (0..self.rows)
+            .into_par_iter()
+            .map(|i| {
+                self.data[i * m..(i + 1) * m]
+                    .iter()
+                    .zip(vector.as_slice())
+                    .fold(T::zero(), |acc, (a, b)| acc + *a * *b)
+            })
+            .collect();
+
+        Vector::new(res)
+    }
+
+    pub fn par_add(&self, other: &Matrix<T>) -> Matrix<T>
+    where
+        T: Add<Output = T>,
+    {
+        self.par_zip(other, |a, b| a + b)
+    }
+
+    pub fn par_sub(&self, other: &Matrix<T>) -> Matrix<T>
+    where
+        T: Sub<Output = T>,
+    {
+        self.par_zip(other, |a, b| a - b)
+    }
+
+    pub fn par_hadamard(&self, other: &Matrix<T>) -> Matrix<T>
+    where
+        T: Mul<Output = T>,
+    {
+        self.par_zip(other, |a, b| a * b)
+    }
+
+    pub fn par_scalar_mul(&self, rhs: T) -> Matrix<T>
+    where
+        T: Mul<Output = T>,
+    {
+        let res = self.data.par_iter().map(|x| *x * rhs).collect();
+
+        Matrix::new(self.rows, self.cols, res)
+    }
+
+    pub fn par_transpose(&self) -> Self {
+        let res = (0..self.cols)
+            .into_par_iter()
+            .flat_map_iter(|j| (0..self.rows).map(move |i| *self.get(i, j)))
+            .collect();
+
+        Matrix::new(self.cols, self.rows, res)
+    }
+
+    fn par_zip<F>(&self, other: &Matrix<T>, f: F) -> Matrix<T>
+    where
+        F: Fn(T, T) -> T + Sync,
+    {
+        // check dimension
+        assert!(
+            self.rows == other.rows && self.cols == other.cols,
+            "Dimensions don't match"
+        );
+
+        let res = self
+            .data
+            .par_iter()
+            .zip(&other.data)
+            .map(|(a, b)| f(*a, *b))
+            .collect();
+
+        Matrix::new(self.rows, self.cols, res)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn sample(rows: usize, cols: usize) -> Matrix<i64> {
+        Matrix::new(
+            rows,
+            cols,
+            (0..rows * cols).map(|x| (x % 11) as i64 - 5).collect(),
+        )
+    }
+
+    #[test]
+    fn test_par_multiply() {
+        let matrix_1 = Matrix::new(2, 2, vec![1.0, 2.0, 3.0, 4.0]);
+        let matrix_2 = Matrix::new(2, 2, vec![5.0, 6.0, 7.0, 8.0]);
+
+        let expected_matrix = Matrix::new(2, 2, vec![19.0, 22.0, 43.0, 50.0]);
+        assert_eq!(matrix_1.par_multiply(&matrix_2), expected_matrix);
+
+        let matrix_1 = sample(90, 45);
+        let matrix_2 = sample(45, 70);
+        assert_eq!(
+            matrix_1.par_multiply(&matrix_2),
+            matrix_1.multiply_naive(&matrix_2)
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Incompatible dimensions")]
+    fn test_par_multiply_wrong_dimensions() {
+        sample(2, 3).par_multiply(&sample(2, 3));
+    }
+
+    #[test]
+    fn test_par_mul_vector() {
+        let matrix = sample(40, 30);
+        let vector = Vector::new((0..30).collect());
+
+        assert_eq!(matrix.par_mul_vector(&vector), matrix.mul_vector(&vector));
+
+        // no columns: a zero for every row
+        let matrix = sample(3, 0);
+        let vector = Vector::new(Vec::new());
+        assert_eq!(matrix.par_mul_vector(&vector), matrix.mul_vector(&vector));
+        assert_eq!(matrix.par_mul_vector(&vector), Vector::new(vec![0; 3]));
+    }
+
+    #[test]
+    fn test_par_elementwise() {
+        let matrix_1 = sample(30, 20);
+        let matrix_2 = &sample(30, 20) * 3;
+
+        assert_eq!(matrix_1.par_add(&matrix_2), matrix_1.add(&matrix_2));
+        assert_eq!(matrix_1.par_sub(&matrix_2), matrix_1.sub(&matrix_2));
+        assert_eq!(
+            matrix_1.par_hadamard(&matrix_2),
+            matrix_1.hadamard(&matrix_2)
+        );
+        assert_eq!(matrix_1.par_scalar_mul(7), matrix_1.scalar_mul(7));
+        assert_eq!(matrix_1.par_transpose(), matrix_1.transpose());
+    }
+
+    #[test]
+    #[should_panic(expected = "Dimensions don't match")]
+    fn test_par_add_wrong_dimensions() {
+        sample(2, 3).par_add(&sample(3, 2));
+    }
+}
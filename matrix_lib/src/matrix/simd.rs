@@ -0,0 +1,216 @@
+use num_traits::Zero;
+use std::ops::{Add, Mul, Sub};
+use wide::{f32x8, f64x4};
+
+use super::{BLOCK_SIZE, Matrix};
+
+// slice kernels for the float types, working a full SIMD register at a time
+// and falling back to scalar code for the leftover elements
+pub trait SimdElement:
+    Copy + Zero + Add<Output = Self> + Sub<Output = Self> + Mul<Output = Self>
+{
+    fn add_slices(a: &[Self], b: &[Self], out: &mut [Self]);
+
+    fn sub_slices(a: &[Self], b: &[Self], out: &mut [Self]);
+
+    // y += alpha * x
+    fn axpy(alpha: Self, x: &[Self], y: &mut [Self]);
+}
+
+macro_rules! impl_simd_element {
+    ($t:ty, $simd:ty, $lanes:expr) => {
+        impl SimdElement for $t {
+            fn add_slices(a: &[$t], b: &[$t], out: &mut [$t]) {
+                let mut a_chunks = a.chunks_exact($lanes);
+                let mut b_chunks = b.chunks_exact($lanes);
+                let mut out_chunks = out.chunks_exact_mut($lanes);
+
+                for ((a, b), out) in (&mut a_chunks).zip(&mut b_chunks).zip(&mut out_chunks) {
+                    let sum = <$simd>::from(<[$t; $lanes]>::try_from(a).unwrap())
+                        + <$simd>::from(<[$t; $lanes]>::try_from(b).unwrap());
+                    out.copy_from_slice(&sum.to_array());
+                }
+
+                for ((a, b), out) in a_chunks
+                    .remainder()
+                    .iter()
+                    .zip(b_chunks.remainder())
+                    .zip(out_chunks.into_remainder())
+                {
+                    *out = *a + *b;
+                }
+            }
+
+            fn sub_slices(a: &[$t], b: &[$t], out: &mut [$t]) {
+                let mut a_chunks = a.chunks_exact($lanes);
+                let mut b_chunks = b.chunks_exact($lanes);
+                let mut out_chunks = out.chunks_exact_mut($lanes);
+
+                for ((a, b), out) in (&mut a_chunks).zip(&mut b_chunks).zip(&mut out_chunks) {
+                    let diff = <$simd>::from(<[$t; $lanes]>::try_from(a).unwrap())
+                        - <$simd>::from(<[$t; $lanes]>::try_from(b).unwrap());
+                    out.copy_from_slice(&diff.to_array());
+                }
+
+                for ((a, b), out) in a_chunks
+                    .remainder()
+                    .iter()
+                    .zip(b_chunks.remainder())
+                    .zip(out_chunks.into_remainder())
+                {
+                    *out = *a - *b;
+                }
+            }
+
+            fn axpy(alpha: $t, x: &[$t], y: &mut [$t]) {
+                let alpha_v = <$simd>::splat(alpha);
+                let mut x_chunks = x.chunks_exact($lanes);
+                let mut y_chunks = y.chunks_exact_mut($lanes);
+
+                for (x, y) in (&mut x_chunks).zip(&mut y_chunks) {
+                    let x_v = <$simd>::from(<[$t; $lanes]>::try_from(x).unwrap());
+                    let y_v = <$simd>::from(<[$t; $lanes]>::try_from(&*y).unwrap());
+                    y.copy_from_slice(&(alpha_v * x_v + y_v).to_array());
+                }
+
+                for (x, y) in x_chunks.remainder().iter().zip(y_chunks.into_remainder()) {
+                    *y += alpha * *x;
+                }
+            }
+        }
+    };
+}
+
+impl_simd_element!(f32, f32x8, 8);
+impl_simd_element!(f64, f64x4, 4);
+
+impl<T: SimdElement> Matrix<T> {
+    pub fn simd_add(&self, other: &Matrix<T>) -> Matrix<T> {
+        // check dimension
+        assert!(
+            self.rows == other.rows && self.cols == other.cols,
+            "Dimensions don't match"
+        );
+
+        let mut res = vec![T::zero(); self.data.len()];
+        T::add_slices(&self.data, &other.data, &mut res);
+
+        Matrix::new(self.rows, self.cols, res)
+    }
+
+    pub fn simd_sub(&self, other: &Matrix<T>) -> Matrix<T> {
+        // check dimension
+        assert!(
+            self.rows == other.rows && self.cols == other.cols,
+            "Dimensions don't match"
+        );
+
+        let mut res = vec![T::zero(); self.data.len()];
+        T::sub_slices(&self.data, &other.data, &mut res);
+
+        Matrix::new(self.rows, self.cols, res)
+    }
+
+    // same tiling as `multiply_blocked`, with the innermost loop replaced
+    // by a vectorized axpy over a contiguous slice of the row
+    pub fn simd_multiply(&self, other: &Matrix<T>) -> Matrix<T> {
+        assert!(self.cols == other.rows, "Incompatible dimensions");
+
+        let (n, m, p) = (self.rows, self.cols, other.cols);
+        let mut res = vec![T::zero(); n * p];
+
+        for ii in (0..n).step_by(BLOCK_SIZE) {
+            for kk in (0..m).step_by(BLOCK_SIZE) {
+                for jj in (0..p).step_by(BLOCK_SIZE) {
+                    let j_end = (jj + BLOCK_SIZE).min(p);
+
+                    for i in ii..(ii + BLOCK_SIZE).min(n) {
+                        for k in kk..(kk + BLOCK_SIZE).min(m) {
+                            let a = self.data[i * m + k];
+                            let other_row = &other.data[k * p + jj..k * p + j_end];
+                            let res_row = &mut res[i * p + jj..i * p + j_end];
+
+                            T::axpy(a, other_row, res_row);
+                        }
+                    }
+                }
+            }
+        }
+
+        Matrix::new(n, p, res)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn sample(rows: usize, cols: usize) -> Matrix<f64> {
+        Matrix::new(
+            rows,
+            cols,
+            (0..rows * cols).map(|x| (x % 11) as f64 - 5.0).collect(),
+        )
+    }
+
+    #[test]
+    fn test_simd_add_sub() {
+        // 3x3 leaves a remainder after the 4/8 lane chunks
+        let matrix_1 = Matrix::new(3, 3, vec![1.0, 2.0, 3.0, 4.0, 5.0, 6.0, 7.0, 8.0, 9.0]);
+        let matrix_2 = Matrix::new(3, 3, vec![9.0, 8.0, 7.0, 6.0, 5.0, 4.0, 3.0, 2.0, 1.0]);
+
+        assert_eq!(
+            matrix_1.simd_add(&matrix_2),
+            Matrix::new(3, 3, vec![10.0; 9])
+        );
+        assert_eq!(matrix_1.simd_sub(&matrix_2), matrix_1.sub(&matrix_2));
+
+        let matrix_1: Matrix<f32> = Matrix::new(3, 3, vec![1.5; 9]);
+        let matrix_2: Matrix<f32> = Matrix::new(3, 3, vec![0.5; 9]);
+
+        assert_eq!(
+            matrix_1.simd_add(&matrix_2),
+            Matrix::new(3, 3, vec![2.0; 9])
+        );
+        assert_eq!(
+            matrix_1.simd_sub(&matrix_2),
+            Matrix::new(3, 3, vec![1.0; 9])
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Dimensions don't match")]
+    fn test_simd_add_wrong_dimensions() {
+        sample(2, 3).simd_add(&sample(3, 2));
+    }
+
+    #[test]
+    fn test_simd_multiply() {
+        let matrix_1 = Matrix::new(2, 2, vec![1.0, 2.0, 3.0, 4.0]);
+        let matrix_2 = Matrix::new(2, 2, vec![5.0, 6.0, 7.0, 8.0]);
+
+        let expected_matrix = Matrix::new(2, 2, vec![19.0, 22.0, 43.0, 50.0]);
+        assert_eq!(matrix_1.simd_multiply(&matrix_2), expected_matrix);
+
+        // small integers are exact in f64, so the summation order doesn't matter
+        let matrix_1 = sample(70, 67);
+        let matrix_2 = sample(67, 81);
+        assert_eq!(
+            matrix_1.simd_multiply(&matrix_2),
+            matrix_1.multiply_naive(&matrix_2)
+        );
+
+        let matrix_1: Matrix<f32> = Matrix::new(1, 3, vec![1.0, 2.0, 3.0]);
+        let matrix_2: Matrix<f32> = Matrix::new(3, 1, vec![4.0, 5.0, 6.0]);
+        assert_eq!(
+            matrix_1.simd_multiply(&matrix_2),
+            Matrix::new(1, 1, vec![32.0])
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Incompatible dimensions")]
+    fn test_simd_multiply_wrong_dimensions() {
+        sample(2, 3).simd_multiply(&sample(2, 3));
+    }
+}
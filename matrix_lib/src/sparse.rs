@@ -0,0 +1,369 @@
+use num_traits::Zero;
+use std::ops::{Add, Mul};
+
+use crate::matrix::Matrix;
+use crate::vector::Vector;
+
+// Compressed Sparse Row (CSR) storage: only the non-zero values are kept.
+// The values of row `i` are `values[row_ptr[i]..row_ptr[i + 1]]`, and
+// `col_indices` holds the column of each of those values.
+#[derive(Debug, PartialEq, Clone)]
+pub struct SparseMatrix<T> {
+    rows: usize,
+    cols: usize,
+    row_ptr: Vec<usize>,
+    col_indices: Vec<usize>,
+    values: Vec<T>,
+}
+
+impl<T> SparseMatrix<T> {
+    pub fn new(rows: usize, cols: usize) -> Self {
+        SparseMatrix {
+            rows,
+            cols,
+            row_ptr: vec![0; rows + 1],
+            col_indices: Vec::new(),
+            values: Vec::new(),
+        }
+    }
+
+    // builds the matrix from (row, col, value) entries in any order,
+    // duplicate entries are summed and zeros are dropped
+    pub fn from_triplets(rows: usize, cols: usize, mut triplets: Vec<(usize, usize, T)>) -> Self
+    where
+        T: Add<Output = T> + Copy + Zero,
+    {
+        assert!(
+            triplets.iter().all(|(i, j, _)| *i < rows && *j < cols),
+            "Index out of bounds"
+        );
+
+        triplets.sort_by_key(|(i, j, _)| (*i, *j));
+
+        let mut merged: Vec<(usize, usize, T)> = Vec::with_capacity(triplets.len());
+        for (i, j, val) in triplets {
+            match merged.last_mut() {
+                Some((li, lj, lval)) if *li == i && *lj == j => *lval = *lval + val,
+                _ => merged.push((i, j, val)),
+            }
+        }
+
+        let mut sparse = SparseMatrix::new(rows, cols);
+        for (i, j, val) in merged {
+            if val.is_zero() {
+                continue;
+            }
+
+            sparse.col_indices.push(j);
+            sparse.values.push(val);
+            sparse.row_ptr[i + 1] += 1;
+        }
+
+        // turn the per-row counts into offsets
+        for i in 0..rows {
+            sparse.row_ptr[i + 1] += sparse.row_ptr[i];
+        }
+
+        sparse
+    }
+
+    pub fn from_dense(matrix: &Matrix<T>) -> Self
+    where
+        T: Copy + Zero,
+    {
+        let mut sparse = SparseMatrix::new(matrix.rows(), matrix.cols());
+
+        for i in 0..matrix.rows() {
+            for j in 0..matrix.cols() {
+                let val = *matrix.get(i, j);
+                if !val.is_zero() {
+                    sparse.col_indices.push(j);
+                    sparse.values.push(val);
+                }
+            }
+
+            sparse.row_ptr[i + 1] = sparse.values.len();
+        }
+
+        sparse
+    }
+
+    pub fn to_dense(&self) -> Matrix<T>
+    where
+        T: Copy + Zero,
+    {
+        let mut data = vec![T::zero(); self.rows * self.cols];
+
+        for i in 0..self.rows {
+            for (j, val) in self.row(i) {
+                data[i * self.cols + j] = *val;
+            }
+        }
+
+        Matrix::new(self.rows, self.cols, data)
+    }
+
+    pub fn rows(&self) -> usize {
+        self.rows
+    }
+
+    pub fn cols(&self) -> usize {
+        self.cols
+    }
+
+    // number of stored (non-zero) values
+    pub fn nnz(&self) -> usize {
+        self.values.len()
+    }
+
+    pub fn get(&self, i: usize, j: usize) -> T
+    where
+        T: Copy + Zero,
+    {
+        assert!(i < self.rows && j < self.cols, "Index out of bounds");
+
+        self.row(i)
+            .find(|(col, _)| *col == j)
+            .map(|(_, val)| *val)
+            .unwrap_or_else(T::zero)
+    }
+
+    pub fn mul_dense(&self, other: &Matrix<T>) -> Matrix<T>
+    where
+        T: Mul<Output = T> + Add<Output = T> + Copy + Zero,
+    {
+        assert!(self.cols == other.rows(), "Incompatible dimensions");
+
+        let p = other.cols();
+        let mut res = vec![T::zero(); self.rows * p];
+
+        for i in 0..self.rows {
+            for (k, a) in self.row(i) {
+                for j in 0..p {
+                    res[i * p + j] = res[i * p + j] + *a * *other.get(k, j);
+                }
+            }
+        }
+
+        Matrix::new(self.rows, p, res)
+    }
+
+    pub fn multiply(&self, other: &SparseMatrix<T>) -> SparseMatrix<T>
+    where
+        T: Mul<Output = T> + Add<Output = T> + Copy + Zero,
+    {
+        assert!(self.cols == other.rows, "Incompatible dimensions");
+
+        let mut res = SparseMatrix::new(self.rows, other.cols);
+
+        // accumulate one row of the result at a time in a dense buffer,
+        // remembering which columns were touched so it can be reset cheaply
+        let mut acc = vec![T::zero(); other.cols];
+        let mut touched = vec![false; other.cols];
+        let mut touched_cols = Vec::new();
+
+        for i in 0..self.rows {
+            for (k, a) in self.row(i) {
+                for (j, b) in other.row(k) {
+                    if !touched[j] {
+                        touched[j] = true;
+                        touched_cols.push(j);
+                    }
+                    acc[j] = acc[j] + *a * *b;
+                }
+            }
+
+            touched_cols.sort_unstable();
+            for &j in &touched_cols {
+                if !acc[j].is_zero() {
+                    res.col_indices.push(j);
+                    res.values.push(acc[j]);
+                }
+
+                acc[j] = T::zero();
+                touched[j] = false;
+            }
+            touched_cols.clear();
+
+            res.row_ptr[i + 1] = res.values.len();
+        }
+
+        res
+    }
+
+    pub fn mul_vector(&self, vector: &Vector<T>) -> Vector<T>
+    where
+        T: Mul<Output = T> + Add<Output = T> + Copy + Zero,
+    {
+        assert!(self.cols == vector.len(), "Incompatible dimensions");
+
+        let res = (0..self.rows)
+            .map(|i| {
+                self.row(i)
+                    .fold(T::zero(), |acc, (j, val)| acc + *val * vector[j])
+            })
+            .collect();
+
+        Vector::new(res)
+    }
+
+    // (column, value) pairs of the stored entries in row `i`
+    fn row(&self, i: usize) -> impl Iterator<Item = (usize, &T)> {
+        let range = self.row_ptr[i]..self.row_ptr[i + 1];
+
+        self.col_indices[range.clone()]
+            .iter()
+            .copied()
+            .zip(&self.values[range])
+    }
+}
+
+impl<T> From<&Matrix<T>> for SparseMatrix<T>
+where
+    T: Copy + Zero,
+{
+    fn from(matrix: &Matrix<T>) -> Self {
+        SparseMatrix::from_dense(matrix)
+    }
+}
+
+impl<T> From<&SparseMatrix<T>> for Matrix<T>
+where
+    T: Copy + Zero,
+{
+    fn from(sparse: &SparseMatrix<T>) -> Self {
+        sparse.to_dense()
+    }
+}
+
+impl<T> Mul for &SparseMatrix<T>
+where
+    T: Mul<Output = T> + Copy + Zero,
+{
+    type Output = SparseMatrix<T>;
+
+    fn mul(self, rhs: &SparseMatrix<T>) -> Self::Output {
+        self.multiply(rhs)
+    }
+}
+
+impl<T> Mul<&Matrix<T>> for &SparseMatrix<T>
+where
+    T: Mul<Output = T> + Copy + Zero,
+{
+    type Output = Matrix<T>;
+
+    fn mul(self, rhs: &Matrix<T>) -> Self::Output {
+        self.mul_dense(rhs)
+    }
+}
+
+impl<T> Mul<&Vector<T>> for &SparseMatrix<T>
+where
+    T: Mul<Output = T> + Copy + Zero,
+{
+    type Output = Vector<T>;
+
+    fn mul(self, rhs: &Vector<T>) -> Self::Output {
+        self.mul_vector(rhs)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_from_dense() {
+        let dense = Matrix::new(3, 3, vec![1, 0, 0, 0, 0, 2, 3, 4, 0]);
+        let sparse = SparseMatrix::from_dense(&dense);
+
+        assert_eq!(sparse.nnz(), 4);
+        assert_eq!(sparse.row_ptr, vec![0, 1, 2, 4]);
+        assert_eq!(sparse.col_indices, vec![0, 2, 0, 1]);
+        assert_eq!(sparse.values, vec![1, 2, 3, 4]);
+
+        assert_eq!(sparse.to_dense(), dense);
+        assert_eq!(Matrix::from(&sparse), dense);
+    }
+
+    #[test]
+    fn test_from_triplets() {
+        let sparse = SparseMatrix::from_triplets(
+            2,
+            3,
+            vec![(1, 2, 5), (0, 1, 2), (1, 0, 1), (0, 1, 3), (1, 1, 0)],
+        );
+
+        assert_eq!(sparse.nnz(), 3);
+        assert_eq!(sparse.to_dense(), Matrix::new(2, 3, vec![0, 5, 0, 1, 0, 5]));
+    }
+
+    #[test]
+    #[should_panic(expected = "Index out of bounds")]
+    fn test_from_triplets_out_of_bounds() {
+        SparseMatrix::from_triplets(2, 2, vec![(2, 0, 1)]);
+    }
+
+    #[test]
+    fn test_get() {
+        let sparse = SparseMatrix::from_triplets(2, 2, vec![(0, 1, 7.0)]);
+
+        assert_eq!(sparse.get(0, 1), 7.0);
+        assert_eq!(sparse.get(1, 1), 0.0);
+    }
+
+    #[test]
+    fn test_empty_rows() {
+        let sparse: SparseMatrix<i32> = SparseMatrix::new(3, 2);
+
+        assert_eq!(sparse.nnz(), 0);
+        assert_eq!(sparse.to_dense(), Matrix::new(3, 2, vec![0; 6]));
+    }
+
+    #[test]
+    fn test_mul_dense() {
+        let a = Matrix::new(2, 3, vec![1, 0, 2, 0, 3, 0]);
+        let b = Matrix::new(3, 2, vec![1, 2, 3, 4, 5, 6]);
+        let sparse = SparseMatrix::from_dense(&a);
+
+        assert_eq!(sparse.mul_dense(&b), &a * &b);
+        assert_eq!(&sparse * &b, &a * &b);
+    }
+
+    #[test]
+    fn test_multiply_sparse() {
+        let a = Matrix::new(3, 3, vec![1, 0, 0, 0, 0, 2, -1, 0, 0]);
+        let b = Matrix::new(3, 3, vec![0, 4, 0, 0, 0, 0, 5, 0, 6]);
+
+        let product = SparseMatrix::from_dense(&a).multiply(&SparseMatrix::from_dense(&b));
+
+        assert_eq!(product.to_dense(), &a * &b);
+        assert_eq!(product.nnz(), 4);
+
+        // entries that cancel out are not stored
+        let c = Matrix::new(1, 2, vec![1, 1]);
+        let d = Matrix::new(2, 1, vec![1, -1]);
+        let product = &SparseMatrix::from_dense(&c) * &SparseMatrix::from_dense(&d);
+
+        assert_eq!(product.nnz(), 0);
+    }
+
+    #[test]
+    #[should_panic(expected = "Incompatible dimensions")]
+    fn test_multiply_wrong_dimensions() {
+        let a: SparseMatrix<i32> = SparseMatrix::new(2, 3);
+        let b: SparseMatrix<i32> = SparseMatrix::new(2, 3);
+
+        a.multiply(&b);
+    }
+
+    #[test]
+    fn test_mul_vector() {
+        let sparse = SparseMatrix::from_triplets(2, 3, vec![(0, 0, 2), (1, 2, 3)]);
+        let vector = Vector::new(vec![1, 2, 3]);
+
+        assert_eq!(sparse.mul_vector(&vector), Vector::new(vec![2, 9]));
+        assert_eq!(&sparse * &vector, Vector::new(vec![2, 9]));
+    }
+}
@@ -0,0 +1,190 @@
+use num_traits::{Float, Zero};
+use std::ops::{Add, Index, IndexMut, Mul, Sub};
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Vector<T> {
+    data: Vec<T>,
+}
+
+impl<T> Vector<T> {
+    pub fn new(data: Vec<T>) -> Self {
+        Vector { data }
+    }
+
+    pub fn len(&self) -> usize {
+        self.data.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    pub fn as_slice(&self) -> &[T] {
+        &self.data
+    }
+
+    pub fn dot(&self, other: &Vector<T>) -> T
+    where
+        T: Mul<Output = T> + Add<Output = T> + Copy + Zero,
+    {
+        assert!(self.len() == other.len(), "Dimensions don't match");
+
+        self.data
+            .iter()
+            .zip(&other.data)
+            .fold(T::zero(), |acc, (a, b)| acc + *a * *b)
+    }
+
+    pub fn cross(&self, other: &Vector<T>) -> Vector<T>
+    where
+        T: Mul<Output = T> + Sub<Output = T> + Copy,
+    {
+        assert!(
+            self.len() == 3 && other.len() == 3,
+            "Cross product is only defined for 3D vectors"
+        );
+
+        let (a, b) = (&self.data, &other.data);
+
+        Vector::new(vec![
+            a[1] * b[2] - a[2] * b[1],
+            a[2] * b[0] - a[0] * b[2],
+            a[0] * b[1] - a[1] * b[0],
+        ])
+    }
+
+    pub fn norm(&self) -> T
+    where
+        T: Float,
+    {
+        self.dot(self).sqrt()
+    }
+}
+
+impl<T> Add for &Vector<T>
+where
+    T: Add<Output = T> + Copy,
+{
+    type Output = Vector<T>;
+
+    fn add(self, other: &Vector<T>) -> Vector<T> {
+        assert!(self.len() == other.len(), "Dimensions don't match");
+
+        let res = self
+            .data
+            .iter()
+            .zip(&other.data)
+            .map(|(a, b)| *a + *b)
+            .collect();
+
+        Vector::new(res)
+    }
+}
+
+impl<T> Sub for &Vector<T>
+where
+    T: Sub<Output = T> + Copy,
+{
+    type Output = Vector<T>;
+
+    fn sub(self, other: &Vector<T>) -> Vector<T> {
+        assert!(self.len() == other.len(), "Dimensions don't match");
+
+        let res = self
+            .data
+            .iter()
+            .zip(&other.data)
+            .map(|(a, b)| *a - *b)
+            .collect();
+
+        Vector::new(res)
+    }
+}
+
+impl<T> Mul<T> for &Vector<T>
+where
+    T: Mul<Output = T> + Copy,
+{
+    type Output = Vector<T>;
+
+    fn mul(self, rhs: T) -> Self::Output {
+        let res = self.data.iter().map(|x| *x * rhs).collect();
+        Vector::new(res)
+    }
+}
+
+impl<T> Index<usize> for Vector<T> {
+    type Output = T;
+
+    fn index(&self, index: usize) -> &Self::Output {
+        &self.data[index]
+    }
+}
+
+impl<T> IndexMut<usize> for Vector<T> {
+    fn index_mut(&mut self, index: usize) -> &mut Self::Output {
+        &mut self.data[index]
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_dot() {
+        let v1 = Vector::new(vec![1, 2, 3]);
+        let v2 = Vector::new(vec![4, 5, 6]);
+
+        assert_eq!(v1.dot(&v2), 32);
+    }
+
+    #[test]
+    #[should_panic(expected = "Dimensions don't match")]
+    fn test_dot_wrong_dimensions() {
+        let v1 = Vector::new(vec![1, 2, 3]);
+        let v2 = Vector::new(vec![4, 5]);
+
+        v1.dot(&v2);
+    }
+
+    #[test]
+    fn test_cross() {
+        let x = Vector::new(vec![1, 0, 0]);
+        let y = Vector::new(vec![0, 1, 0]);
+
+        assert_eq!(x.cross(&y), Vector::new(vec![0, 0, 1]));
+        assert_eq!(y.cross(&x), Vector::new(vec![0, 0, -1]));
+
+        let v1 = Vector::new(vec![1.0, 2.0, 3.0]);
+        let v2 = Vector::new(vec![4.0, 5.0, 6.0]);
+        assert_eq!(v1.cross(&v2), Vector::new(vec![-3.0, 6.0, -3.0]));
+    }
+
+    #[test]
+    #[should_panic(expected = "Cross product is only defined for 3D vectors")]
+    fn test_cross_not_3d() {
+        let v1 = Vector::new(vec![1, 2]);
+        let v2 = Vector::new(vec![3, 4]);
+
+        v1.cross(&v2);
+    }
+
+    #[test]
+    fn test_norm() {
+        let v = Vector::new(vec![3.0, 4.0]);
+
+        assert_eq!(v.norm(), 5.0);
+    }
+
+    #[test]
+    fn test_vector_ops() {
+        let v1 = Vector::new(vec![1, 2, 3]);
+        let v2 = Vector::new(vec![4, 5, 6]);
+
+        assert_eq!(&v1 + &v2, Vector::new(vec![5, 7, 9]));
+        assert_eq!(&v2 - &v1, Vector::new(vec![3, 3, 3]));
+        assert_eq!(&v1 * 2, Vector::new(vec![2, 4, 6]));
+        assert_eq!(v1[1], 2);
+    }
+}
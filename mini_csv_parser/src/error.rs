@@ -0,0 +1,78 @@
+use std::fmt;
+use std::io;
+
+/// Why a file couldn't be read.
+#[derive(Debug)]
+pub enum CsvError {
+    Io(io::Error),
+    /// A malformed record, in strict mode
+    Parse(ParseError),
+}
+
+impl fmt::Display for CsvError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CsvError::Io(e) => write!(f, "{}", e),
+            CsvError::Parse(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for CsvError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            CsvError::Io(e) => Some(e),
+            CsvError::Parse(e) => Some(e),
+        }
+    }
+}
+
+impl From<io::Error> for CsvError {
+    fn from(e: io::Error) -> Self {
+        CsvError::Io(e)
+    }
+}
+
+impl From<ParseError> for CsvError {
+    fn from(e: ParseError) -> Self {
+        CsvError::Parse(e)
+    }
+}
+
+/// Where a record is malformed and how.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseError {
+    /// Counting from 1, the header included
+    pub line: usize,
+    /// The field, counting from 1
+    pub column: usize,
+    pub kind: ParseErrorKind,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ParseErrorKind {
+    /// A quoted field is still open at the end of the file
+    UnterminatedQuote,
+    /// A quote in the middle of a field that doesn't start with one
+    StrayQuote,
+    /// Text between a closing quote and the next delimiter
+    TextAfterQuote,
+    /// The record doesn't have as many fields as there are headers
+    FieldCount { expected: usize, found: usize },
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}, column {}: ", self.line, self.column)?;
+        match &self.kind {
+            ParseErrorKind::UnterminatedQuote => write!(f, "quoted field is never closed"),
+            ParseErrorKind::StrayQuote => write!(f, "quote inside an unquoted field"),
+            ParseErrorKind::TextAfterQuote => write!(f, "text after the closing quote"),
+            ParseErrorKind::FieldCount { expected, found } => {
+                write!(f, "expected {} fields, found {}", expected, found)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ParseError {}
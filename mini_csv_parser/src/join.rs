@@ -0,0 +1,102 @@
+use crate::Csv;
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JoinKind {
+    /// Only the rows with a match on both sides
+    Inner,
+    /// Every row of the left side, with empty values where the right side
+    /// has no match
+    Left,
+}
+
+/// The columns of both sides, the key once. A right column named like a
+/// left one gets a `_right` suffix, and a number after that if needed
+pub(crate) fn joined_headers(left: &[String], right: &[String], key: usize) -> Vec<String> {
+    let mut headers = left.to_vec();
+    for (idx, name) in right.iter().enumerate() {
+        if idx == key {
+            continue;
+        }
+
+        let mut renamed = name.clone();
+        if headers.contains(&renamed) {
+            renamed = format!("{}_right", name);
+            let mut n = 2;
+            while headers.contains(&renamed) {
+                renamed = format!("{}_right{}", name, n);
+                n += 1;
+            }
+        }
+        headers.push(renamed);
+    }
+    headers
+}
+
+/// Rows of `left` and `right` with the same value in their key columns,
+/// one row per matching pair, in the order of `left` then `right`
+pub(crate) fn join_rows(
+    left: &Csv,
+    right: &Csv,
+    keys: (usize, usize),
+    kind: JoinKind,
+) -> Vec<Vec<String>> {
+    let (left_key, right_key) = keys;
+    let key = |row: &[String], idx: usize| row.get(idx).map(|value| value.trim().to_string());
+
+    let mut index = HashMap::<String, Vec<&Vec<String>>>::new();
+    for row in &right.rows {
+        if let Some(value) = key(row, right_key) {
+            index.entry(value).or_default().push(row);
+        }
+    }
+
+    let width = left.headers.len();
+    let other_width = right.headers.len();
+    let mut rows = Vec::new();
+    for row in &left.rows {
+        // one value per header, so the right side's values line up
+        let mut padded = row.clone();
+        padded.resize(width, String::new());
+
+        let matches = key(row, left_key).and_then(|value| index.get(&value));
+        match matches {
+            Some(matches) => {
+                for other in matches {
+                    let mut joined = padded.clone();
+                    joined.extend(
+                        (0..other_width)
+                            .filter(|&idx| idx != right_key)
+                            .map(|idx| other.get(idx).cloned().unwrap_or_default()),
+                    );
+                    rows.push(joined);
+                }
+            }
+            None if kind == JoinKind::Left => {
+                padded.resize(padded.len() + other_width.saturating_sub(1), String::new());
+                rows.push(padded);
+            }
+            None => {}
+        }
+    }
+    rows
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn strings(values: &[&str]) -> Vec<String> {
+        values.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn test_joined_headers() {
+        let left = strings(&["id", "name", "name_right"]);
+        let right = strings(&["name", "id", "city"]);
+        assert_eq!(
+            joined_headers(&left, &right, 1),
+            ["id", "name", "name_right", "name_right2", "city"]
+        );
+    }
+}
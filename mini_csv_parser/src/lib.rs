@@ -0,0 +1,617 @@
+mod error;
+mod join;
+mod options;
+mod parser;
+mod query;
+mod row;
+mod schema;
+mod sniff;
+mod sort;
+mod stats;
+mod stream;
+mod writer;
+
+pub use error::{CsvError, ParseError, ParseErrorKind};
+pub use join::JoinKind;
+pub use options::{Mode, ReadOptions};
+pub use query::{Query, QueryError};
+pub use row::{FieldError, FromCsvRow, Row, RowError};
+pub use schema::{ColumnSchema, ColumnType, Date, SAMPLE_SIZE, Schema, Value};
+pub use sort::Order;
+pub use stats::{NumericStats, Stats, TextStats};
+pub use stream::{Rows, StreamRow};
+pub use writer::CsvWriter;
+
+use std::fmt;
+use std::fs::File;
+use std::io::{BufReader, BufWriter};
+use std::str::FromStr;
+
+#[derive(Debug, Clone)]
+pub struct Csv {
+    pub headers: Vec<String>,
+    pub rows: Vec<Vec<String>>,
+    /// `false` when the headers were generated for a file without them
+    pub has_headers: bool,
+    /// The malformed records met while reading, in lenient mode
+    pub errors: Vec<ParseError>,
+}
+
+impl Csv {
+    pub fn from_file(path: &str) -> Result<Csv, CsvError> {
+        ReadOptions::new().read(path)
+    }
+
+    /// A tab-separated file, with a header record
+    pub fn from_tsv(path: &str) -> Result<Csv, CsvError> {
+        ReadOptions::new().tsv().read(path)
+    }
+
+    /// Guess how the file at `path` is to be read from its first lines:
+    /// the delimiter among `,`, tab, `;` and `|`, and whether the first
+    /// record is a header
+    ///
+    /// ```no_run
+    /// use mini_csv_parser::Csv;
+    ///
+    /// let path = "export.txt";
+    /// let csv = Csv::sniff(path)?.read(path)?;
+    /// # Ok::<(), mini_csv_parser::CsvError>(())
+    /// ```
+    pub fn sniff(path: &str) -> Result<ReadOptions, CsvError> {
+        let f = File::open(path)?;
+        Ok(sniff::sniff(BufReader::new(f))?)
+    }
+
+    /// The rows of the file at `path` one at a time, for files too big to
+    /// load whole. The header record is read right away
+    pub fn stream(path: &str) -> Result<Rows<BufReader<File>>, CsvError> {
+        ReadOptions::new().stream(path)
+    }
+
+    /// Write the headers and rows back out as CSV, replacing the file at
+    /// `path`. Generated headers aren't written
+    pub fn to_file(&self, path: &str) -> std::io::Result<()> {
+        self.write(path, ',')
+    }
+
+    /// Like `to_file`, separating the fields with tabs
+    pub fn to_tsv(&self, path: &str) -> std::io::Result<()> {
+        self.write(path, '\t')
+    }
+
+    fn write(&self, path: &str, delimiter: char) -> std::io::Result<()> {
+        let f = File::create(path)?;
+        let mut writer = CsvWriter::with_delimiter(BufWriter::new(f), delimiter);
+
+        if self.has_headers {
+            writer.write_record(&self.headers)?;
+        }
+        for row in &self.rows {
+            writer.write_record(row)?;
+        }
+        writer.flush()
+    }
+
+    pub fn get(&self, row: usize, cols: &str) -> Option<&str> {
+        let idx = self.headers.iter().position(|x| x == cols)?;
+        self.rows.get(row)?.get(idx).map(|s| s.as_str())
+    }
+
+    /// The value in the column at `col`, counting from 0, for files whose
+    /// columns have no meaningful names
+    pub fn at(&self, row: usize, col: usize) -> Option<&str> {
+        self.rows.get(row)?.get(col).map(|s| s.as_str())
+    }
+
+    /// The row at `index` with its values addressable by header name
+    pub fn row(&self, index: usize) -> Option<Row<'_>> {
+        let values = self.rows.get(index)?;
+        Some(Row::new(&self.headers, values))
+    }
+
+    /// A new `Csv` with only the given columns, in the given order. `None`
+    /// when one of them doesn't exist. A row too short for a column gets an
+    /// empty value there, so every row has the same width
+    pub fn select(&self, columns: &[&str]) -> Option<Csv> {
+        let indexes = columns
+            .iter()
+            .map(|column| self.headers.iter().position(|x| x == column))
+            .collect::<Option<Vec<usize>>>()?;
+
+        let headers = indexes.iter().map(|&i| self.headers[i].clone()).collect();
+        let rows = self
+            .rows
+            .iter()
+            .map(|row| {
+                indexes
+                    .iter()
+                    .map(|&i| row.get(i).cloned().unwrap_or_default())
+                    .collect()
+            })
+            .collect();
+
+        Some(Csv {
+            headers,
+            rows,
+            has_headers: self.has_headers,
+            errors: self.errors.clone(),
+        })
+    }
+
+    /// A new `Csv` with only the rows `keep` is true for:
+    ///
+    /// ```
+    /// # let csv = mini_csv_parser::Csv::from_file("text.csv").unwrap();
+    /// let over_30 = csv.filter(|row| row.parse::<u32>("age").is_ok_and(|age| age > 30));
+    /// ```
+    pub fn filter<F>(&self, keep: F) -> Csv
+    where
+        F: Fn(&Row) -> bool,
+    {
+        let rows = self
+            .rows
+            .iter()
+            .filter(|values| keep(&Row::new(&self.headers, values)))
+            .cloned()
+            .collect();
+
+        Csv {
+            headers: self.headers.clone(),
+            rows,
+            has_headers: self.has_headers,
+            errors: self.errors.clone(),
+        }
+    }
+
+    /// The rows matching a query like `age > 30 && city == London`. A
+    /// column that isn't in the headers is an error rather than no match
+    pub fn query(&self, query: &str) -> Result<Csv, QueryError> {
+        let query = Query::parse(query)?;
+        if let Some(column) = query
+            .columns()
+            .find(|column| !self.headers.iter().any(|x| x == column))
+        {
+            return Err(QueryError::unknown_column(column));
+        }
+
+        Ok(self.filter(|row| query.matches(row)))
+    }
+
+    /// Every value of `name` parsed as a `T`, one result per row. `None`
+    /// when there is no such column
+    pub fn column<T>(&self, name: &str) -> Option<Vec<Result<T, RowError>>>
+    where
+        T: FromStr,
+        T::Err: fmt::Display,
+    {
+        if !self.headers.iter().any(|x| x == name) {
+            return None;
+        }
+
+        let values = self
+            .rows
+            .iter()
+            .enumerate()
+            .map(|(index, values)| {
+                Row::new(&self.headers, values)
+                    .parse(name)
+                    .map_err(|error| RowError { row: index, error })
+            })
+            .collect();
+        Some(values)
+    }
+
+    /// A new `Csv` with the rows sorted on the column `name`, as numbers
+    /// when all its values are. Equal values keep their order and rows
+    /// without a value come last. `None` when there is no such column
+    pub fn sort_by(&self, name: &str, order: Order) -> Option<Csv> {
+        let idx = self.headers.iter().position(|x| x == name)?;
+        let mut sorted = self.clone();
+        sort::sort_rows(&mut sorted.rows, idx, order);
+        Some(sorted)
+    }
+
+    /// The likely type of each column and how many values it lacks, from
+    /// the first `SAMPLE_SIZE` rows
+    pub fn infer_schema(&self) -> Schema {
+        Schema::infer(&self.headers, &self.rows)
+    }
+
+    /// Every value of `name` read as the column's type, as inferred from
+    /// all of them. `None` when there is no such column
+    pub fn values(&self, name: &str) -> Option<Vec<Value>> {
+        let idx = self.headers.iter().position(|x| x == name)?;
+        let column = || {
+            self.rows
+                .iter()
+                .map(move |row| row.get(idx).map_or("", |s| s.as_str()))
+        };
+
+        let kind = ColumnType::of(column());
+        Some(column().map(|value| Value::parse(value, kind)).collect())
+    }
+
+    /// The rows of both files side by side where their `key` columns hold
+    /// the same value, the key column once. A column of `other` named like
+    /// one of this file gets a `_right` suffix. `None` when either file
+    /// has no `key` column
+    pub fn join(&self, other: &Csv, key: &str, kind: JoinKind) -> Option<Csv> {
+        let left_key = self.headers.iter().position(|x| x == key)?;
+        let right_key = other.headers.iter().position(|x| x == key)?;
+
+        Some(Csv {
+            headers: join::joined_headers(&self.headers, &other.headers, right_key),
+            rows: join::join_rows(self, other, (left_key, right_key), kind),
+            has_headers: self.has_headers && other.has_headers,
+            // lines of two files, which couldn't be told apart
+            errors: Vec::new(),
+        })
+    }
+
+    /// A summary of the column `name`, numeric when its type is integer or
+    /// float. `None` when there is no such column
+    pub fn stats(&self, name: &str) -> Option<Stats> {
+        let idx = self.headers.iter().position(|x| x == name)?;
+        let values = self
+            .rows
+            .iter()
+            .filter_map(|row| row.get(idx).map(|s| s.as_str()));
+        Some(Stats::of(values))
+    }
+
+    /// Every row turned into a `T`, in order. A row that doesn't fit is an
+    /// error in its place, so one bad row doesn't cost the others
+    pub fn deserialize<T: FromCsvRow>(&self) -> Vec<Result<T, RowError>> {
+        self.rows
+            .iter()
+            .enumerate()
+            .map(|(index, values)| {
+                T::from_row(&Row::new(&self.headers, values))
+                    .map_err(|error| RowError { row: index, error })
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_from_file() {
+        let csv = match Csv::from_file("text.csv") {
+            Ok(csv) => csv,
+            Err(e) => panic!("Fail to load Csv: {}", e),
+        };
+
+        assert!(!csv.rows.is_empty(), "Csv should not be empty");
+    }
+
+    #[test]
+    fn test_get() {
+        let csv = match Csv::from_file("text.csv") {
+            Ok(csv) => csv,
+            Err(e) => panic!("Fail to load Csv: {}", e),
+        };
+
+        assert!(
+            csv.get(0, "name").unwrap() == "Alice",
+            "wrong name on row 1"
+        );
+        assert!(
+            csv.get(0, "city").unwrap() == "London",
+            "wrong city on row 1"
+        );
+
+        assert!(csv.get(0, "local").is_none(), "wrong city on row 1");
+
+        assert!(csv.get(6, "local").is_none(), "wrong city on row 1");
+    }
+
+    #[test]
+    fn test_modes() {
+        let csv = Csv::from_file("text.csv").unwrap();
+        // mike's row is a field short
+        assert_eq!(csv.rows.len(), 5);
+        assert_eq!(
+            csv.errors,
+            [ParseError {
+                line: 4,
+                column: 3,
+                kind: ParseErrorKind::FieldCount {
+                    expected: 3,
+                    found: 2
+                }
+            }]
+        );
+
+        let strict = ReadOptions::new().mode(Mode::Strict).read("text.csv");
+        assert!(matches!(
+            strict,
+            Err(CsvError::Parse(ParseError { line: 4, .. }))
+        ));
+
+        assert!(matches!(
+            Csv::from_file("missing.csv"),
+            Err(CsvError::Io(_))
+        ));
+    }
+
+    #[test]
+    fn test_stream() {
+        let csv = Csv::from_file("text.csv").unwrap();
+        let rows = Csv::stream("text.csv").unwrap();
+        assert_eq!(rows.headers(), csv.headers);
+
+        let streamed: Vec<Vec<String>> = rows.map(|row| row.unwrap().into_values()).collect();
+        assert_eq!(streamed, csv.rows);
+
+        assert!(Csv::stream("missing.csv").is_err());
+    }
+
+    #[test]
+    fn test_without_headers() {
+        let csv = ReadOptions::new()
+            .has_headers(false)
+            .read("text.csv")
+            .unwrap();
+
+        assert_eq!(csv.headers, ["col0", "col1", "col2"]);
+        assert_eq!(csv.rows.len(), 6);
+        assert_eq!(csv.get(0, "col0"), Some("name"));
+        assert_eq!(csv.at(1, 2), Some("London"));
+        assert_eq!(csv.at(1, 3), None);
+
+        let path = std::env::temp_dir().join(format!("mini_csv_raw_{}.csv", std::process::id()));
+        let path = path.to_str().unwrap();
+        csv.to_file(path).unwrap();
+        let written = std::fs::read_to_string(path).unwrap();
+        std::fs::remove_file(path).unwrap();
+        assert!(written.starts_with("name,age,city\n"));
+    }
+
+    #[test]
+    fn test_select() {
+        let csv = Csv::from_file("text.csv").unwrap();
+        let selected = csv.select(&["city", "name"]).unwrap();
+
+        assert_eq!(selected.headers, ["city", "name"]);
+        assert_eq!(selected.rows[0], ["London", "Alice"]);
+        // "mike, 30" has no city
+        assert_eq!(selected.rows[2], ["", "mike"]);
+        assert_eq!(selected.rows.len(), csv.rows.len());
+
+        assert!(csv.select(&["name", "zip"]).is_none());
+    }
+
+    #[test]
+    fn test_column() {
+        let csv = Csv::from_file("text.csv").unwrap();
+        let ages = csv.column::<u32>("age").unwrap();
+
+        assert_eq!(ages[0], Ok(30));
+        assert_eq!(ages[2], Ok(30));
+        assert_eq!(ages[3].as_ref().unwrap_err().row, 3);
+        assert_eq!(ages[4], Ok(41));
+
+        let cities = csv.column::<String>("city").unwrap();
+        assert!(matches!(
+            &cities[2],
+            Err(RowError {
+                row: 2,
+                error: FieldError::Missing(_)
+            })
+        ));
+
+        assert!(csv.column::<u32>("zip").is_none());
+    }
+
+    #[test]
+    fn test_filter() {
+        let csv = Csv::from_file("text.csv").unwrap();
+
+        let over_26 = csv.filter(|row| row.parse::<u32>("age").is_ok_and(|age| age > 26));
+        let names: Vec<_> = over_26.rows.iter().map(|row| row[0].as_str()).collect();
+        assert_eq!(names, ["Alice", "mike", "Smith, John"]);
+        assert_eq!(over_26.headers, csv.headers);
+
+        assert!(csv.filter(|_| false).rows.is_empty());
+    }
+
+    #[test]
+    fn test_query() {
+        let csv = Csv::from_file("text.csv").unwrap();
+
+        let found = csv.query("age > 26 && city == London").unwrap();
+        assert_eq!(found.rows, [["Alice", "30", "London"]]);
+
+        let found = csv.query("city == \"New York\" || name == Bob").unwrap();
+        assert_eq!(found.rows.len(), 2);
+
+        assert_eq!(
+            csv.query("zip == 1").unwrap_err().to_string(),
+            "invalid query: no column `zip`"
+        );
+        assert!(csv.query("age >").is_err());
+    }
+
+    #[test]
+    fn test_sort_by() {
+        let csv = Csv::from_file("text.csv").unwrap();
+        let names =
+            |csv: &Csv| -> Vec<String> { csv.rows.iter().map(|row| row[0].clone()).collect() };
+
+        let ages = csv.filter(|row| row.parse::<u32>("age").is_ok());
+        let oldest = ages.sort_by("age", Order::Desc).unwrap();
+        assert_eq!(names(&oldest), ["Smith, John", "Alice", "mike", "Bob"]);
+
+        // mike has no city
+        let by_city = csv.sort_by("city", Order::Asc).unwrap();
+        assert_eq!(
+            names(&by_city),
+            ["Alice", "Smith, John", "Bob", "sarah", "mike"]
+        );
+
+        assert!(csv.sort_by("zip", Order::Asc).is_none());
+    }
+
+    #[test]
+    fn test_stats() {
+        let csv = Csv::from_file("text.csv").unwrap();
+
+        let ages = csv.filter(|row| row.parse::<u32>("age").is_ok());
+        let Some(Stats::Numeric(stats)) = ages.stats("age") else {
+            panic!("ages should be numbers");
+        };
+        assert_eq!(stats.count, 4);
+        assert_eq!(stats.min, 25.0);
+        assert_eq!(stats.max, 41.0);
+        assert_eq!(stats.median, 30.0);
+
+        // "r" isn't a number, and mike has no city
+        assert!(matches!(csv.stats("age"), Some(Stats::Text(_))));
+        assert_eq!(csv.stats("city").unwrap().count(), 4);
+        assert!(csv.stats("zip").is_none());
+    }
+
+    #[test]
+    fn test_infer_schema() {
+        let csv = Csv::from_file("text.csv").unwrap();
+        let schema = csv.infer_schema();
+
+        assert_eq!(schema.columns.len(), 3);
+        assert_eq!(schema.get("name").unwrap().kind, ColumnType::String);
+        // "r" isn't a number
+        assert_eq!(schema.get("age").unwrap().kind, ColumnType::String);
+        let city = schema.get("city").unwrap();
+        assert_eq!(city.nulls, 1);
+        assert_eq!(city.sampled, 5);
+
+        let ages = csv.filter(|row| row.parse::<u32>("age").is_ok());
+        assert_eq!(
+            ages.infer_schema().get("age").unwrap().kind,
+            ColumnType::Integer
+        );
+        let values = ages.values("age").unwrap();
+        assert_eq!(values[0], Value::Integer(30));
+        assert!(csv.values("zip").is_none());
+    }
+
+    #[test]
+    fn test_tsv() {
+        let csv = Csv::from_file("text.csv").unwrap();
+        let path = std::env::temp_dir().join(format!("mini_csv_{}.tsv", std::process::id()));
+        let path = path.to_str().unwrap();
+
+        csv.to_tsv(path).unwrap();
+        let written = std::fs::read_to_string(path).unwrap();
+        let sniffed = Csv::sniff(path).unwrap();
+        let copy = Csv::from_tsv(path).unwrap();
+        std::fs::remove_file(path).unwrap();
+
+        assert!(written.contains("Smith, John\t41\tNew York\n"));
+        assert_eq!(sniffed, ReadOptions::new().tsv());
+        assert_eq!(copy.rows, csv.rows);
+        assert_eq!(Csv::sniff("text.csv").unwrap(), ReadOptions::new());
+    }
+
+    fn csv(headers: &[&str], rows: &[&[&str]]) -> Csv {
+        let strings = |values: &[&str]| values.iter().map(|s| s.to_string()).collect();
+        Csv {
+            headers: strings(headers),
+            rows: rows.iter().map(|row| strings(row)).collect(),
+            has_headers: true,
+            errors: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_join() {
+        let people = csv(
+            &["id", "name", "city"],
+            &[
+                &["1", "Alice", "London"],
+                &["2", "Bob", "Paris"],
+                &["3", "mike"],
+            ],
+        );
+        let orders = csv(
+            &["item", "id", "city"],
+            &[
+                &["tea", "1", "Leeds"],
+                &["cake", " 3", ""],
+                &["jam", "1", "York"],
+                &["pie", "9", ""],
+            ],
+        );
+
+        let inner = people.join(&orders, "id", JoinKind::Inner).unwrap();
+        assert_eq!(inner.headers, ["id", "name", "city", "item", "city_right"]);
+        assert_eq!(
+            inner.rows,
+            [
+                ["1", "Alice", "London", "tea", "Leeds"],
+                ["1", "Alice", "London", "jam", "York"],
+                ["3", "mike", "", "cake", ""],
+            ]
+        );
+
+        let left = people.join(&orders, "id", JoinKind::Left).unwrap();
+        assert_eq!(left.rows.len(), 4);
+        assert_eq!(left.rows[2], ["2", "Bob", "Paris", "", ""]);
+
+        assert!(people.join(&orders, "item", JoinKind::Inner).is_none());
+    }
+
+    #[test]
+    fn test_to_file() {
+        let csv = Csv::from_file("text.csv").unwrap();
+        let path = std::env::temp_dir().join(format!("mini_csv_{}.csv", std::process::id()));
+        let path = path.to_str().unwrap();
+
+        csv.to_file(path).unwrap();
+        let copy = Csv::from_file(path).unwrap();
+        std::fs::remove_file(path).unwrap();
+
+        assert_eq!(copy.headers, csv.headers);
+        assert_eq!(copy.rows, csv.rows);
+        assert_eq!(copy.get(4, "name"), Some("Smith, John"));
+    }
+
+    #[derive(Debug, PartialEq)]
+    struct Person {
+        name: String,
+        age: u32,
+    }
+
+    impl FromCsvRow for Person {
+        fn from_row(row: &Row) -> Result<Self, FieldError> {
+            Ok(Person {
+                name: row.parse("name")?,
+                age: row.parse("age")?,
+            })
+        }
+    }
+
+    #[test]
+    fn test_deserialize() {
+        let csv = Csv::from_file("text.csv").unwrap();
+        let people = csv.deserialize::<Person>();
+
+        assert_eq!(people.len(), csv.rows.len());
+        assert_eq!(
+            people[0],
+            Ok(Person {
+                name: "Alice".to_string(),
+                age: 30
+            })
+        );
+        // "mike, 30" is trimmed before parsing
+        assert_eq!(people[2].as_ref().unwrap().age, 30);
+
+        let error = people[3].as_ref().unwrap_err();
+        assert_eq!(error.row, 3);
+        assert!(matches!(&error.error, FieldError::Invalid { column, .. } if column == "age"));
+    }
+}
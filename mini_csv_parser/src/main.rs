@@ -1,6 +1,5 @@
-use std::fs::File;
-use std::io::BufReader;
-use std::io::prelude::*;
+use mini_csv_parser::{Csv, FieldError, FromCsvRow, Row};
+use std::error::Error;
 
 #[derive(Debug)]
 struct Record {
@@ -9,111 +8,41 @@ struct Record {
     city: String,
 }
 
-impl Record {
-    fn new(name: String, age: u32, city: String) -> Self {
-        Record { name, age, city }
+impl FromCsvRow for Record {
+    fn from_row(row: &Row) -> Result<Self, FieldError> {
+        Ok(Record {
+            name: row.parse("name")?,
+            age: row.parse("age")?,
+            city: row.parse("city")?,
+        })
     }
 }
 
-struct Csv {
-    headers: Vec<String>,
-    rows: Vec<Vec<String>>,
+/// `cargo run -- "age > 30 && city == London"` shows only the matching rows
+fn main() {
+    if let Err(e) = run() {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    }
 }
 
-impl Csv {
-    fn from_file(path: &str) -> std::io::Result<Csv> {
-        let f = File::open(path)?;
-        let reader = BufReader::new(f);
-
-        let mut headers = Vec::<String>::new();
-        let mut rows = Vec::<Vec<String>>::new();
-
-        for (i, res) in reader.lines().enumerate() {
-            let line = res?;
-            let cols = line.split(",").map(|x| x.to_string()).collect();
-            if i == 0 {
-                headers = cols;
-            } else {
-                rows.push(cols);
-            }
-        }
-
-        let csv = Csv {
-            headers,
-            rows,
-        };
-        Ok(csv)
+fn run() -> Result<(), Box<dyn Error>> {
+    let path = "text.csv";
+    let mut csv = Csv::from_file(path)?;
+    for error in &csv.errors {
+        eprintln!("{}: {}", path, error);
     }
 
-    fn get(&self, row: usize, cols: &str) -> Option<&str> {
-        let idx = self.headers.iter().position(|x| x == cols)?;
-        self.rows.get(row)?.get(idx).map(|s| s.as_str())
+    if let Some(query) = std::env::args().nth(1) {
+        csv = csv.query(&query)?;
     }
 
-    fn iter_records(&self) -> Vec<Record> {
-        let mut records = Vec::<Record>::new();
-        for i in 1..self.rows.len() {
-            let data: Vec<String> = self.rows[i].iter().map(|a| a.trim().to_string()).collect();
-            if data.len() < 3 {
-                continue;
-            }
-            if let Ok(n) = data[1].parse::<u32>() {
-                records.push(Record::new(data[0].clone(), n, data[2].clone()));
-            }
+    for result in csv.deserialize::<Record>() {
+        match result {
+            Ok(record) => println!("{}, {}, lives in {}", record.name, record.age, record.city),
+            Err(e) => eprintln!("Skipped {}", e),
         }
-        records
     }
-}
-
-fn main() -> std::io::Result<()> {
-    let path = "text.csv";
-    let csv = Csv::from_file(path)?;
-
-    let records = csv.iter_records();
-
-    println!("Records: {:?}", records);
 
     Ok(())
 }
-
-#[cfg(test)]
-mod test {
-    use super::*;
-
-    #[test]
-    fn test_from_file() {
-        let csv = match Csv::from_file("text.csv") {
-            Ok(csv) => csv,
-            Err(e) => panic!("Fail to load Csv: {}", e),
-        };
-
-        assert!(!csv.rows.is_empty(), "Csv should not be empty");
-    }
-
-    #[test]
-    fn test_get() {
-        let csv = match Csv::from_file("text.csv") {
-            Ok(csv) => csv,
-            Err(e) => panic!("Fail to load Csv: {}", e),
-        };
-
-        assert!(
-            csv.get(0, "name").unwrap() == "Alice",
-            "wrong name on row 1"
-        );
-        assert!(
-            csv.get(0, "city").unwrap() == "London",
-            "wrong city on row 1"
-        );
-
-        assert!(
-            csv.get(0, "local").is_none(),
-            "wrong city on row 1"
-        );
-
-        assert!(
-            csv.get(6, "local").is_none(),
-            "wrong city on row 1"
-        );
-    }
-}
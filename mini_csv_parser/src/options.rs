@@ -0,0 +1,113 @@
+use crate::Csv;
+use crate::error::CsvError;
+use crate::stream::Rows;
+use std::fs::File;
+use std::io::BufReader;
+
+/// How a file is read. The defaults suit a CSV file whose first record is
+/// the header:
+///
+/// ```no_run
+/// use mini_csv_parser::ReadOptions;
+///
+/// let csv = ReadOptions::new().has_headers(false).read("points.csv")?;
+/// let x = csv.get(0, "col0");
+/// # Ok::<(), mini_csv_parser::CsvError>(())
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReadOptions {
+    has_headers: bool,
+    mode: Mode,
+    delimiter: char,
+}
+
+/// What to do with a malformed record.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Mode {
+    /// Fail on the first one
+    Strict,
+    /// Read it as well as possible and keep going, listing what was wrong
+    /// in `Csv::errors`
+    #[default]
+    Lenient,
+}
+
+impl Default for ReadOptions {
+    fn default() -> Self {
+        ReadOptions {
+            has_headers: true,
+            mode: Mode::default(),
+            delimiter: ',',
+        }
+    }
+}
+
+impl ReadOptions {
+    pub fn new() -> Self {
+        ReadOptions::default()
+    }
+
+    /// Whether the first record names the columns. When it doesn't, it is
+    /// data like the others and the columns are named `col0`, `col1`, …
+    pub fn has_headers(mut self, has_headers: bool) -> Self {
+        self.has_headers = has_headers;
+        self
+    }
+
+    /// The character between fields, `,` unless told otherwise
+    pub fn delimiter(mut self, delimiter: char) -> Self {
+        self.delimiter = delimiter;
+        self
+    }
+
+    /// Tab-separated values
+    pub fn tsv(self) -> Self {
+        self.delimiter('\t')
+    }
+
+    pub fn mode(mut self, mode: Mode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    pub fn read(&self, path: &str) -> Result<Csv, CsvError> {
+        let stream = self.stream(path)?;
+        let headers = stream.headers().to_vec();
+        let mut errors = stream.header_errors().to_vec();
+        let mut rows = Vec::new();
+        for row in stream {
+            let (values, row_errors) = row?.into_parts();
+            rows.push(values);
+            errors.extend(row_errors);
+        }
+
+        Ok(Csv {
+            headers,
+            rows,
+            has_headers: self.has_headers,
+            errors,
+        })
+    }
+
+    pub fn stream(&self, path: &str) -> Result<Rows<BufReader<File>>, CsvError> {
+        let f = File::open(path)?;
+        Rows::with_options(BufReader::new(f), self)
+    }
+
+    pub(crate) fn reads_headers(&self) -> bool {
+        self.has_headers
+    }
+
+    pub(crate) fn read_mode(&self) -> Mode {
+        self.mode
+    }
+
+    pub(crate) fn read_delimiter(&self) -> char {
+        self.delimiter
+    }
+}
+
+/// Names for the columns of a file without a header record
+pub(crate) fn generated_headers(count: usize) -> Vec<String> {
+    (0..count).map(|i| format!("col{}", i)).collect()
+}
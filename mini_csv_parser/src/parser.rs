@@ -0,0 +1,256 @@
+use crate::error::{ParseError, ParseErrorKind};
+use std::io::BufRead;
+use std::mem;
+
+/// Where the parser is within a record, following RFC 4180.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum State {
+    /// Nothing read yet for the current field
+    FieldStart,
+    /// Inside a field without quotes
+    Unquoted,
+    /// Inside a quoted field, where commas and line breaks are data
+    Quoted,
+    /// Just saw a quote inside a quoted field: the end of the field, or the
+    /// first half of an escaped `""`
+    QuoteInQuoted,
+}
+
+/// One record and what was wrong with it, if anything.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Record {
+    pub values: Vec<String>,
+    /// The line it starts on, counting from 1
+    pub line: usize,
+    pub errors: Vec<ParseError>,
+}
+
+/// Reads records, keeping count of the lines for error messages.
+pub struct Parser<R> {
+    reader: R,
+    delimiter: char,
+    /// Lines read so far
+    line: usize,
+}
+
+impl<R: BufRead> Parser<R> {
+    pub fn with_delimiter(reader: R, delimiter: char) -> Self {
+        Parser {
+            reader,
+            delimiter,
+            line: 0,
+        }
+    }
+
+    /// Read the next record, `None` at the end of the input.
+    ///
+    /// A record spans several lines when a quoted field holds a line break.
+    /// Blank lines between records are skipped. Malformed input is read as
+    /// well as it can be, with an error saying where: a quote in the middle
+    /// of an unquoted field is kept as is, and so is text after a closing
+    /// quote.
+    pub fn read_record(&mut self) -> std::io::Result<Option<Record>> {
+        let mut fields = Vec::new();
+        let mut field = String::new();
+        let mut state = State::FieldStart;
+        let mut line = String::new();
+        let mut start = self.line + 1;
+        let mut quote_line = start;
+        let mut errors = Vec::new();
+        let delimiter = self.delimiter;
+
+        loop {
+            line.clear();
+            if self.reader.read_line(&mut line)? == 0 {
+                // end of input, possibly inside an unterminated quoted field
+                if fields.is_empty() && field.is_empty() && state == State::FieldStart {
+                    return Ok(None);
+                }
+                if state == State::Quoted {
+                    errors.push(ParseError {
+                        line: quote_line,
+                        column: fields.len() + 1,
+                        kind: ParseErrorKind::UnterminatedQuote,
+                    });
+                }
+                fields.push(field);
+                return Ok(Some(Record {
+                    values: fields,
+                    line: start,
+                    errors,
+                }));
+            }
+            self.line += 1;
+
+            let mut chars = line.chars().peekable();
+            while let Some(c) = chars.next() {
+                match (state, c) {
+                    (State::Quoted, '"') => state = State::QuoteInQuoted,
+                    (State::Quoted, c) => field.push(c),
+                    (State::QuoteInQuoted, '"') => {
+                        field.push('"');
+                        state = State::Quoted;
+                    }
+                    (State::FieldStart, '"') => {
+                        quote_line = self.line;
+                        state = State::Quoted;
+                    }
+                    (_, c) if c == delimiter => {
+                        fields.push(mem::take(&mut field));
+                        state = State::FieldStart;
+                    }
+                    // the \n of a \r\n line ending comes next
+                    (_, '\r') if chars.peek() == Some(&'\n') => {}
+                    (_, '\n') => break,
+                    (current, c) => {
+                        let kind = if current == State::QuoteInQuoted {
+                            Some(ParseErrorKind::TextAfterQuote)
+                        } else if c == '"' {
+                            Some(ParseErrorKind::StrayQuote)
+                        } else {
+                            None
+                        };
+                        if let Some(kind) = kind {
+                            errors.push(ParseError {
+                                line: self.line,
+                                column: fields.len() + 1,
+                                kind,
+                            });
+                        }
+                        field.push(c);
+                        state = State::Unquoted;
+                    }
+                }
+            }
+
+            if state == State::Quoted {
+                continue;
+            }
+            if fields.is_empty() && field.is_empty() && state == State::FieldStart {
+                start = self.line + 1;
+                continue;
+            }
+
+            fields.push(field);
+            return Ok(Some(Record {
+                values: fields,
+                line: start,
+                errors,
+            }));
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn parsed(input: &str) -> Vec<Record> {
+        let mut parser = Parser::with_delimiter(input.as_bytes(), ',');
+        let mut records = Vec::new();
+        while let Some(record) = parser.read_record().unwrap() {
+            records.push(record);
+        }
+        records
+    }
+
+    fn records(input: &str) -> Vec<Vec<String>> {
+        parsed(input)
+            .into_iter()
+            .map(|record| record.values)
+            .collect()
+    }
+
+    fn errors(input: &str) -> Vec<(usize, usize, ParseErrorKind)> {
+        parsed(input)
+            .into_iter()
+            .flat_map(|record| record.errors)
+            .map(|e| (e.line, e.column, e.kind))
+            .collect()
+    }
+
+    #[test]
+    fn test_plain_fields() {
+        assert_eq!(
+            records("name,age\nAlice,30\n"),
+            vec![vec!["name", "age"], vec!["Alice", "30"]]
+        );
+        assert_eq!(records("a,,c"), vec![vec!["a", "", "c"]]);
+        assert_eq!(records("a,b,\n"), vec![vec!["a", "b", ""]]);
+        assert!(records("").is_empty());
+    }
+
+    #[test]
+    fn test_quoted_fields() {
+        assert_eq!(
+            records("\"Smith, John\",42\n"),
+            vec![vec!["Smith, John", "42"]]
+        );
+        assert_eq!(
+            records("\"She said \"\"hi\"\"\",x\n"),
+            vec![vec!["She said \"hi\"", "x"]]
+        );
+        assert_eq!(records("\"\",b\n"), vec![vec!["", "b"]]);
+    }
+
+    #[test]
+    fn test_embedded_newlines() {
+        assert_eq!(
+            records("id,note\n1,\"first line\nsecond line\"\n2,done\n"),
+            vec![
+                vec!["id", "note"],
+                vec!["1", "first line\nsecond line"],
+                vec!["2", "done"],
+            ]
+        );
+    }
+
+    #[test]
+    fn test_line_endings_and_blank_lines() {
+        assert_eq!(
+            records("a,b\r\n\r\n\"x\r\ny\",z\r\n"),
+            vec![vec!["a", "b"], vec!["x\r\ny", "z"]]
+        );
+    }
+
+    #[test]
+    fn test_malformed_input() {
+        // stray quotes are kept, an unterminated field runs to the end
+        assert_eq!(records("ab\"c,d\n"), vec![vec!["ab\"c", "d"]]);
+        assert_eq!(records("\"ab\"c,d\n"), vec![vec!["abc", "d"]]);
+        assert_eq!(records("\"open,end\n"), vec![vec!["open,end\n"]]);
+    }
+
+    #[test]
+    fn test_delimiter() {
+        let mut parser = Parser::with_delimiter("a\tb,c\t\"d\te\"\n".as_bytes(), '\t');
+        let record = parser.read_record().unwrap().unwrap();
+        assert_eq!(record.values, ["a", "b,c", "d\te"]);
+    }
+
+    #[test]
+    fn test_errors() {
+        assert!(errors("a,\"b\"\n\"c\nd\",e\n").is_empty());
+        assert_eq!(
+            errors("a,b\nab\"c,d\n"),
+            [(2, 1, ParseErrorKind::StrayQuote)]
+        );
+        assert_eq!(
+            errors("a,\"b\"c\n"),
+            [(1, 2, ParseErrorKind::TextAfterQuote)]
+        );
+        assert_eq!(
+            errors("a\n\nb,\"open\nc\n"),
+            [(3, 2, ParseErrorKind::UnterminatedQuote)]
+        );
+    }
+
+    #[test]
+    fn test_lines() {
+        let lines: Vec<usize> = parsed("a\n\n\"b\nc\"\n\r\nd\n")
+            .iter()
+            .map(|record| record.line)
+            .collect();
+        assert_eq!(lines, [1, 3, 6]);
+    }
+}
@@ -0,0 +1,267 @@
+use crate::row::Row;
+use std::cmp::Ordering;
+use std::fmt;
+
+/// A filter written as text, like `age > 30 && city == London`.
+///
+/// Conditions compare a column with a value using `==`, `!=`, `<`, `<=`, `>`
+/// or `>=`, and are joined with `&&` and `||`, `&&` binding tighter. Values
+/// with spaces or operators in them go in double quotes. Two numbers compare
+/// as numbers, anything else as text.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Query {
+    /// Any of these groups matching is a match, a group matches when all
+    /// its conditions do
+    any: Vec<Vec<Condition>>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Condition {
+    column: String,
+    op: Op,
+    value: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Op {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Word(String),
+    Op(Op),
+    And,
+    Or,
+}
+
+/// Why a query couldn't be parsed.
+#[derive(Debug, Clone, PartialEq)]
+pub struct QueryError(String);
+
+impl fmt::Display for QueryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid query: {}", self.0)
+    }
+}
+
+impl std::error::Error for QueryError {}
+
+impl QueryError {
+    pub(crate) fn unknown_column(column: &str) -> Self {
+        QueryError(format!("no column `{}`", column))
+    }
+}
+
+impl Query {
+    pub fn parse(text: &str) -> Result<Query, QueryError> {
+        let mut tokens = tokenize(text)?.into_iter();
+        let mut any = vec![Vec::new()];
+
+        loop {
+            let condition = match (tokens.next(), tokens.next(), tokens.next()) {
+                (Some(Token::Word(column)), Some(Token::Op(op)), Some(Token::Word(value))) => {
+                    Condition { column, op, value }
+                }
+                (None, _, _) => return Err(QueryError("expected a condition".to_string())),
+                _ => {
+                    return Err(QueryError(
+                        "expected a condition like `age > 30`".to_string(),
+                    ));
+                }
+            };
+            if let Some(group) = any.last_mut() {
+                group.push(condition);
+            }
+
+            match tokens.next() {
+                None => return Ok(Query { any }),
+                Some(Token::And) => {}
+                Some(Token::Or) => any.push(Vec::new()),
+                Some(_) => return Err(QueryError("expected `&&` or `||`".to_string())),
+            }
+        }
+    }
+
+    /// The columns the query refers to, to check them against the headers
+    pub fn columns(&self) -> impl Iterator<Item = &str> {
+        self.any
+            .iter()
+            .flatten()
+            .map(|condition| condition.column.as_str())
+    }
+
+    /// Whether `row` matches. A condition on a value the row doesn't have
+    /// doesn't match
+    pub fn matches(&self, row: &Row) -> bool {
+        self.any
+            .iter()
+            .any(|group| group.iter().all(|condition| condition.matches(row)))
+    }
+}
+
+impl Condition {
+    fn matches(&self, row: &Row) -> bool {
+        let Some(value) = row.get(&self.column) else {
+            return false;
+        };
+        let Some(ordering) = compare(value.trim(), &self.value) else {
+            return false;
+        };
+
+        match self.op {
+            Op::Eq => ordering == Ordering::Equal,
+            Op::Ne => ordering != Ordering::Equal,
+            Op::Lt => ordering == Ordering::Less,
+            Op::Le => ordering != Ordering::Greater,
+            Op::Gt => ordering == Ordering::Greater,
+            Op::Ge => ordering != Ordering::Less,
+        }
+    }
+}
+
+/// Numbers by value, anything else as text. `None` when a number is
+/// compared with something that isn't one, or for NaN
+fn compare(value: &str, other: &str) -> Option<Ordering> {
+    match (value.parse::<f64>(), other.parse::<f64>()) {
+        (Ok(a), Ok(b)) => a.partial_cmp(&b),
+        (Err(_), Ok(_)) => None,
+        _ => Some(value.cmp(other)),
+    }
+}
+
+fn tokenize(text: &str) -> Result<Vec<Token>, QueryError> {
+    let mut tokens = Vec::new();
+    let mut chars = text.chars().peekable();
+
+    while let Some(&c) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+            continue;
+        }
+
+        if c == '"' {
+            chars.next();
+            let mut word = String::new();
+            loop {
+                match chars.next() {
+                    Some('"') => break,
+                    Some(c) => word.push(c),
+                    None => return Err(QueryError("unterminated quote".to_string())),
+                }
+            }
+            tokens.push(Token::Word(word));
+            continue;
+        }
+
+        if is_operator(c) {
+            let mut symbol = String::new();
+            while let Some(&c) = chars.peek()
+                && is_operator(c)
+            {
+                symbol.push(c);
+                chars.next();
+            }
+            let token = match symbol.as_str() {
+                "==" | "=" => Token::Op(Op::Eq),
+                "!=" => Token::Op(Op::Ne),
+                "<" => Token::Op(Op::Lt),
+                "<=" => Token::Op(Op::Le),
+                ">" => Token::Op(Op::Gt),
+                ">=" => Token::Op(Op::Ge),
+                "&&" => Token::And,
+                "||" => Token::Or,
+                _ => return Err(QueryError(format!("unknown operator `{}`", symbol))),
+            };
+            tokens.push(token);
+            continue;
+        }
+
+        let mut word = String::new();
+        while let Some(&c) = chars.peek()
+            && !c.is_whitespace()
+            && !is_operator(c)
+            && c != '"'
+        {
+            word.push(c);
+            chars.next();
+        }
+        tokens.push(Token::Word(word));
+    }
+
+    Ok(tokens)
+}
+
+fn is_operator(c: char) -> bool {
+    matches!(c, '=' | '!' | '<' | '>' | '&' | '|')
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn strings(values: &[&str]) -> Vec<String> {
+        values.iter().map(|s| s.to_string()).collect()
+    }
+
+    fn matches(query: &str, values: &[&str]) -> bool {
+        let headers = strings(&["name", "age", "city"]);
+        let values = strings(values);
+        Query::parse(query)
+            .unwrap()
+            .matches(&Row::new(&headers, &values))
+    }
+
+    #[test]
+    fn test_conditions() {
+        let alice = ["Alice", "30", "London"];
+        assert!(matches("age == 30", &alice));
+        assert!(matches("age >= 30", &alice));
+        assert!(!matches("age > 30", &alice));
+        assert!(matches("age < 100", &alice));
+        assert!(matches("age != 31", &alice));
+        assert!(matches("city == London", &alice));
+        assert!(matches("name < Bob", &alice));
+        // numbers compare as numbers, not text
+        assert!(matches("age > 4", &alice));
+        assert!(matches("age>29&&city==London", &alice));
+        assert!(!matches("age > 26", &["sarah", "r", "lagos"]));
+        assert!(!matches("age != 26", &["sarah", "r", "lagos"]));
+
+        assert!(matches("city == \"New York\"", &["John", "41", "New York"]));
+        // the value is trimmed, a missing one never matches
+        assert!(matches("age == 30", &["mike", " 30"]));
+        assert!(!matches("city != London", &["mike", " 30"]));
+        assert!(!matches("zip == 1", &alice));
+    }
+
+    #[test]
+    fn test_and_or() {
+        let alice = ["Alice", "30", "London"];
+        let bob = ["Bob", "25", "Paris"];
+        let query = "age > 26 && city == London || city == Paris";
+        assert!(matches(query, &alice));
+        assert!(matches(query, &bob));
+        assert!(!matches("age > 26 && city == Paris", &bob));
+        assert!(!matches("age > 26 && city == Paris", &alice));
+    }
+
+    #[test]
+    fn test_parse() {
+        let query = Query::parse("age > 30 || city == London").unwrap();
+        assert_eq!(query.columns().collect::<Vec<_>>(), ["age", "city"]);
+
+        assert!(Query::parse("").is_err());
+        assert!(Query::parse("age >").is_err());
+        assert!(Query::parse("age 30").is_err());
+        assert!(Query::parse("age > 30 &&").is_err());
+        assert!(Query::parse("age > 30 city == London").is_err());
+        assert!(Query::parse("age => 30").is_err());
+        assert!(Query::parse("city == \"London").is_err());
+    }
+}
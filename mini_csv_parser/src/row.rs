@@ -0,0 +1,154 @@
+use std::fmt;
+use std::str::FromStr;
+
+/// One row of a `Csv`, with its values looked up by header name.
+#[derive(Debug, Clone, Copy)]
+pub struct Row<'a> {
+    headers: &'a [String],
+    values: &'a [String],
+}
+
+impl<'a> Row<'a> {
+    pub fn new(headers: &'a [String], values: &'a [String]) -> Self {
+        Row { headers, values }
+    }
+
+    /// The raw value under `column`, `None` when there is no such column
+    /// or the row is too short to have it
+    pub fn get(&self, column: &str) -> Option<&'a str> {
+        let idx = self.headers.iter().position(|x| x == column)?;
+        self.values.get(idx).map(|s| s.as_str())
+    }
+
+    /// The raw value in the column at `index`, counting from 0
+    pub fn at(&self, index: usize) -> Option<&'a str> {
+        self.values.get(index).map(|s| s.as_str())
+    }
+
+    /// The value under `column` parsed as a `T`, after trimming the spaces
+    /// around it
+    pub fn parse<T>(&self, column: &str) -> Result<T, FieldError>
+    where
+        T: FromStr,
+        T::Err: fmt::Display,
+    {
+        let value = self
+            .get(column)
+            .ok_or_else(|| FieldError::Missing(column.to_string()))?
+            .trim();
+
+        value.parse().map_err(|e: T::Err| FieldError::Invalid {
+            column: column.to_string(),
+            value: value.to_string(),
+            reason: e.to_string(),
+        })
+    }
+}
+
+/// A type that can be built from a row, like a serde `Deserialize` without
+/// the derive:
+///
+/// ```
+/// use mini_csv_parser::{FieldError, FromCsvRow, Row};
+///
+/// struct City {
+///     name: String,
+///     population: u64,
+/// }
+///
+/// impl FromCsvRow for City {
+///     fn from_row(row: &Row) -> Result<Self, FieldError> {
+///         Ok(City {
+///             name: row.parse("name")?,
+///             population: row.parse("population")?,
+///         })
+///     }
+/// }
+/// ```
+pub trait FromCsvRow: Sized {
+    fn from_row(row: &Row) -> Result<Self, FieldError>;
+}
+
+/// Why a field couldn't be read.
+#[derive(Debug, Clone, PartialEq)]
+pub enum FieldError {
+    /// No such column, or the row ends before it
+    Missing(String),
+    /// The value doesn't parse as the field's type
+    Invalid {
+        column: String,
+        value: String,
+        reason: String,
+    },
+}
+
+impl fmt::Display for FieldError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FieldError::Missing(column) => write!(f, "no value for `{}`", column),
+            FieldError::Invalid {
+                column,
+                value,
+                reason,
+            } => write!(f, "`{}` is not a valid {}: {}", value, column, reason),
+        }
+    }
+}
+
+impl std::error::Error for FieldError {}
+
+/// A row that couldn't be turned into a record.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RowError {
+    /// The index of the row, as used by `Csv::get`
+    pub row: usize,
+    pub error: FieldError,
+}
+
+impl fmt::Display for RowError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "row {}: {}", self.row, self.error)
+    }
+}
+
+impl std::error::Error for RowError {}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn strings(values: &[&str]) -> Vec<String> {
+        values.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn test_parse() {
+        let headers = strings(&["name", "age", "city"]);
+        let values = strings(&["Alice", " 30 ", "London"]);
+        let row = Row::new(&headers, &values);
+
+        assert_eq!(row.get("age"), Some(" 30 "));
+        assert_eq!(row.at(2), Some("London"));
+        assert_eq!(row.at(3), None);
+        assert_eq!(row.parse::<u32>("age"), Ok(30));
+        assert_eq!(row.parse::<String>("city"), Ok("London".to_string()));
+        assert_eq!(
+            row.parse::<u32>("zip"),
+            Err(FieldError::Missing("zip".to_string()))
+        );
+
+        let short = strings(&["Bob"]);
+        let row = Row::new(&headers, &short);
+        assert_eq!(
+            row.parse::<String>("city"),
+            Err(FieldError::Missing("city".to_string()))
+        );
+
+        let bad = strings(&["Carol", "old"]);
+        let error = Row::new(&headers, &bad).parse::<u32>("age").unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "`old` is not a valid age: invalid digit found in string"
+        );
+    }
+}
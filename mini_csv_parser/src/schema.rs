@@ -0,0 +1,291 @@
+use std::fmt;
+
+/// How many rows `Csv::infer_schema` looks at, from the top.
+pub const SAMPLE_SIZE: usize = 1000;
+
+/// The likely type of a column, from its values.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColumnType {
+    Integer,
+    /// Some values have a fraction, the rest are integers
+    Float,
+    /// `true` or `false`, in any case
+    Bool,
+    /// `YYYY-MM-DD`
+    Date,
+    String,
+}
+
+impl ColumnType {
+    /// The narrowest type every value fits, ignoring empty ones. A column
+    /// with nothing but empty values is a `String`
+    pub fn of<'a>(values: impl IntoIterator<Item = &'a str>) -> ColumnType {
+        let mut kind: Option<ColumnType> = None;
+        for value in values.into_iter().map(str::trim) {
+            if value.is_empty() {
+                continue;
+            }
+            let value_kind = ColumnType::of_value(value);
+            kind = Some(match kind {
+                None => value_kind,
+                Some(kind) => kind.widen(value_kind),
+            });
+            if kind == Some(ColumnType::String) {
+                break;
+            }
+        }
+
+        kind.unwrap_or(ColumnType::String)
+    }
+
+    pub fn is_numeric(self) -> bool {
+        matches!(self, ColumnType::Integer | ColumnType::Float)
+    }
+
+    fn of_value(value: &str) -> ColumnType {
+        if value.parse::<i64>().is_ok() {
+            ColumnType::Integer
+        } else if is_float(value) {
+            ColumnType::Float
+        } else if value.eq_ignore_ascii_case("true") || value.eq_ignore_ascii_case("false") {
+            ColumnType::Bool
+        } else if Date::parse(value).is_some() {
+            ColumnType::Date
+        } else {
+            ColumnType::String
+        }
+    }
+
+    /// The type that holds values of both
+    fn widen(self, other: ColumnType) -> ColumnType {
+        match (self, other) {
+            (a, b) if a == b => a,
+            (ColumnType::Integer | ColumnType::Float, ColumnType::Integer | ColumnType::Float) => {
+                ColumnType::Float
+            }
+            _ => ColumnType::String,
+        }
+    }
+}
+
+impl fmt::Display for ColumnType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            ColumnType::Integer => "integer",
+            ColumnType::Float => "float",
+            ColumnType::Bool => "bool",
+            ColumnType::Date => "date",
+            ColumnType::String => "string",
+        };
+        f.pad(name)
+    }
+}
+
+/// A number written out, not `inf` or `NaN`, which `f64` also parses
+fn is_float(value: &str) -> bool {
+    value.parse::<f64>().is_ok_and(f64::is_finite) && value.chars().any(|c| c.is_ascii_digit())
+}
+
+/// What `Csv::infer_schema` found out about one column.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ColumnSchema {
+    pub name: String,
+    pub kind: ColumnType,
+    /// Empty or missing values among the sampled rows
+    pub nulls: usize,
+    /// Rows sampled
+    pub sampled: usize,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Schema {
+    pub columns: Vec<ColumnSchema>,
+}
+
+impl Schema {
+    pub fn infer(headers: &[String], rows: &[Vec<String>]) -> Schema {
+        let sample = &rows[..rows.len().min(SAMPLE_SIZE)];
+        let columns = headers
+            .iter()
+            .enumerate()
+            .map(|(idx, name)| {
+                let values: Vec<&str> = sample
+                    .iter()
+                    .map(|row| row.get(idx).map_or("", |s| s.as_str()))
+                    .collect();
+                ColumnSchema {
+                    name: name.clone(),
+                    kind: ColumnType::of(values.iter().copied()),
+                    nulls: values.iter().filter(|v| v.trim().is_empty()).count(),
+                    sampled: sample.len(),
+                }
+            })
+            .collect();
+
+        Schema { columns }
+    }
+
+    pub fn get(&self, name: &str) -> Option<&ColumnSchema> {
+        self.columns.iter().find(|column| column.name == name)
+    }
+}
+
+impl fmt::Display for Schema {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, column) in self.columns.iter().enumerate() {
+            if i > 0 {
+                writeln!(f)?;
+            }
+            write!(
+                f,
+                "{:<12} {:<8} {} empty of {}",
+                column.name, column.kind, column.nulls, column.sampled
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// A calendar date, as written `YYYY-MM-DD`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Date {
+    pub year: i32,
+    pub month: u8,
+    pub day: u8,
+}
+
+impl Date {
+    /// `None` unless `text` is a date that exists, like `2024-02-29`
+    pub fn parse(text: &str) -> Option<Date> {
+        let mut parts = text.split('-');
+        let (year, month, day) = (parts.next()?, parts.next()?, parts.next()?);
+        if parts.next().is_some() || year.len() != 4 || month.len() != 2 || day.len() != 2 {
+            return None;
+        }
+        if !(year.chars().chain(month.chars()).chain(day.chars())).all(|c| c.is_ascii_digit()) {
+            return None;
+        }
+
+        let date = Date {
+            year: year.parse().ok()?,
+            month: month.parse().ok()?,
+            day: day.parse().ok()?,
+        };
+        let days = match date.month {
+            1 | 3 | 5 | 7 | 8 | 10 | 12 => 31,
+            4 | 6 | 9 | 11 => 30,
+            2 if is_leap(date.year) => 29,
+            2 => 28,
+            _ => return None,
+        };
+        (1..=days).contains(&date.day).then_some(date)
+    }
+}
+
+impl fmt::Display for Date {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:04}-{:02}-{:02}", self.year, self.month, self.day)
+    }
+}
+
+fn is_leap(year: i32) -> bool {
+    (year % 4 == 0 && year % 100 != 0) || year % 400 == 0
+}
+
+/// A value read as its column's type.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    /// Empty, missing, or not of the column's type
+    Null,
+    Integer(i64),
+    Float(f64),
+    Bool(bool),
+    Date(Date),
+    String(String),
+}
+
+impl Value {
+    pub fn parse(value: &str, kind: ColumnType) -> Value {
+        let value = value.trim();
+        if value.is_empty() {
+            return Value::Null;
+        }
+
+        let parsed = match kind {
+            ColumnType::Integer => value.parse().ok().map(Value::Integer),
+            ColumnType::Float => value.parse().ok().map(Value::Float),
+            ColumnType::Bool => value.to_ascii_lowercase().parse().ok().map(Value::Bool),
+            ColumnType::Date => Date::parse(value).map(Value::Date),
+            ColumnType::String => Some(Value::String(value.to_string())),
+        };
+        parsed.unwrap_or(Value::Null)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_column_type() {
+        assert_eq!(ColumnType::of(["1", " 2", "", "-3"]), ColumnType::Integer);
+        assert_eq!(ColumnType::of(["1", "2.5"]), ColumnType::Float);
+        assert_eq!(ColumnType::of(["true", "FALSE"]), ColumnType::Bool);
+        assert_eq!(
+            ColumnType::of(["2024-02-29", "1999-12-31"]),
+            ColumnType::Date
+        );
+        assert_eq!(ColumnType::of(["1", "true"]), ColumnType::String);
+        assert_eq!(ColumnType::of(["30", "r"]), ColumnType::String);
+        assert_eq!(ColumnType::of(["inf", "NaN"]), ColumnType::String);
+        assert_eq!(ColumnType::of(["", " "]), ColumnType::String);
+    }
+
+    #[test]
+    fn test_date() {
+        assert_eq!(
+            Date::parse("2024-02-29"),
+            Some(Date {
+                year: 2024,
+                month: 2,
+                day: 29
+            })
+        );
+        assert!(Date::parse("2023-02-29").is_none());
+        assert!(Date::parse("1900-02-29").is_none());
+        assert!(Date::parse("2000-02-29").is_some());
+        assert!(Date::parse("2024-13-01").is_none());
+        assert!(Date::parse("2024-1-01").is_none());
+        assert!(Date::parse("+024-01-01").is_none());
+        assert_eq!(Date::parse("2024-04-30").unwrap().to_string(), "2024-04-30");
+    }
+
+    #[test]
+    fn test_schema() {
+        let headers = vec!["id".to_string(), "score".to_string(), "note".to_string()];
+        let mut rows: Vec<Vec<String>> = [["1", "2.5", "x"], ["2", "", ""], ["3", "4", "y"]]
+            .iter()
+            .map(|row| row.iter().map(|s| s.to_string()).collect())
+            .collect();
+        // a short row lacks the last two values
+        rows.push(vec!["4".to_string()]);
+
+        let schema = Schema::infer(&headers, &rows);
+        let score = schema.get("score").unwrap();
+        assert_eq!(score.kind, ColumnType::Float);
+        assert_eq!(score.nulls, 2);
+        assert_eq!(score.sampled, 4);
+        assert_eq!(schema.get("id").unwrap().kind, ColumnType::Integer);
+        assert_eq!(schema.get("note").unwrap().nulls, 2);
+        assert!(schema.get("zip").is_none());
+    }
+
+    #[test]
+    fn test_value() {
+        assert_eq!(Value::parse(" 30", ColumnType::Integer), Value::Integer(30));
+        assert_eq!(Value::parse("30", ColumnType::Float), Value::Float(30.0));
+        assert_eq!(Value::parse("True", ColumnType::Bool), Value::Bool(true));
+        assert_eq!(Value::parse("", ColumnType::String), Value::Null);
+        assert_eq!(Value::parse("r", ColumnType::Integer), Value::Null);
+    }
+}
@@ -0,0 +1,157 @@
+use crate::options::ReadOptions;
+use crate::parser::Parser;
+use crate::schema::ColumnType;
+use std::io::{self, BufRead};
+
+/// How many records are looked at to guess the format.
+const SAMPLE_RECORDS: usize = 20;
+
+/// The delimiters tried, in order of preference on a tie.
+const DELIMITERS: [char; 4] = [',', '\t', ';', '|'];
+
+/// Guess the delimiter and whether there is a header record from the start
+/// of a file. A file that gives no clue is taken for CSV with headers
+pub fn sniff<R: BufRead>(mut reader: R) -> io::Result<ReadOptions> {
+    let mut sample = String::new();
+    let mut lines = 0;
+    let mut line = String::new();
+    // more lines than records, for blank lines and quoted line breaks
+    while lines < SAMPLE_RECORDS * 2 && reader.read_line(&mut line)? > 0 {
+        sample.push_str(&line);
+        line.clear();
+        lines += 1;
+    }
+
+    let mut best = None;
+    for delimiter in DELIMITERS {
+        let records = parse(&sample, delimiter)?;
+        let (count, width) = score(&records);
+        // ties go to the delimiter tried first
+        if width > 1
+            && best
+                .as_ref()
+                .is_none_or(|(_, _, best)| (count, width) > *best)
+        {
+            best = Some((delimiter, records, (count, width)));
+        }
+    }
+
+    let Some((delimiter, records, _)) = best else {
+        return Ok(ReadOptions::new());
+    };
+    Ok(ReadOptions::new()
+        .delimiter(delimiter)
+        .has_headers(has_headers(&records)))
+}
+
+fn parse(sample: &str, delimiter: char) -> io::Result<Vec<Vec<String>>> {
+    let mut parser = Parser::with_delimiter(sample.as_bytes(), delimiter);
+    let mut records = Vec::new();
+    while records.len() < SAMPLE_RECORDS
+        && let Some(record) = parser.read_record()?
+    {
+        records.push(record.values);
+    }
+    Ok(records)
+}
+
+/// How many records have the most common number of fields, and that
+/// number. The right delimiter splits every record the same way, into more
+/// than one field
+fn score(records: &[Vec<String>]) -> (usize, usize) {
+    let mut counts: Vec<(usize, usize)> = Vec::new();
+    for record in records {
+        match counts.iter_mut().find(|(width, _)| *width == record.len()) {
+            Some((_, count)) => *count += 1,
+            None => counts.push((record.len(), 1)),
+        }
+    }
+
+    counts
+        .into_iter()
+        .map(|(width, count)| (count, width))
+        .max()
+        .unwrap_or_default()
+}
+
+/// The first record is a header when, in some column, it doesn't fit the
+/// type of the values below it, like `age` over numbers. When every column
+/// is text there is no telling, and headers are the more likely
+fn has_headers(records: &[Vec<String>]) -> bool {
+    let Some((first, rest)) = records.split_first() else {
+        return true;
+    };
+    if rest.is_empty() {
+        return true;
+    }
+
+    let column = |idx: usize| {
+        rest.iter()
+            .map(move |record| record.get(idx).map_or("", |s| s.as_str()))
+    };
+
+    let mut typed_columns = 0;
+    for (idx, value) in first.iter().enumerate() {
+        let below = ColumnType::of(column(idx));
+        if below == ColumnType::String {
+            continue;
+        }
+        typed_columns += 1;
+
+        // an integer over floats still fits, a word over numbers doesn't
+        let with_first = ColumnType::of(column(idx).chain([value.as_str()]));
+        if with_first == ColumnType::String {
+            return true;
+        }
+    }
+
+    typed_columns == 0
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn sniffed(text: &str) -> ReadOptions {
+        sniff(text.as_bytes()).unwrap()
+    }
+
+    #[test]
+    fn test_delimiter() {
+        assert_eq!(sniffed("name,age\nAlice,30\nBob,25\n"), ReadOptions::new());
+        assert_eq!(
+            sniffed("name\tage\nSmith, John\t41\nBob\t25\n"),
+            ReadOptions::new().tsv()
+        );
+        assert_eq!(
+            sniffed("name;price\nTea;1,50\nCake;3,20\n"),
+            ReadOptions::new().delimiter(';')
+        );
+        assert_eq!(sniffed("a|b|c\n1|2|3\n"), ReadOptions::new().delimiter('|'));
+        // a field short on one line still counts
+        assert_eq!(
+            sniffed("name,age,city\nAlice,30,London\nmike, 30\nBob,25,Paris\n"),
+            ReadOptions::new()
+        );
+    }
+
+    #[test]
+    fn test_headers() {
+        assert_eq!(
+            sniffed("1,2.5\n2,3\n3,4.5\n"),
+            ReadOptions::new().has_headers(false)
+        );
+        assert_eq!(
+            sniffed("2024-01-01,true\n2024-01-02,false\n"),
+            ReadOptions::new().has_headers(false)
+        );
+        assert_eq!(
+            sniffed("day,open\n2024-01-01,true\n2024-01-02,false\n"),
+            ReadOptions::new()
+        );
+        // all text: can't tell, so headers
+        assert_eq!(sniffed("a,b\nc,d\n"), ReadOptions::new());
+        assert_eq!(sniffed(""), ReadOptions::new());
+        assert_eq!(sniffed("just one column\n"), ReadOptions::new());
+    }
+}
@@ -0,0 +1,87 @@
+use std::cmp::Ordering;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Order {
+    Asc,
+    Desc,
+}
+
+/// Sort `rows` on the column at `idx`. The values compare as numbers when
+/// they all are, as text otherwise. Rows without a value come last either
+/// way, and rows with equal values keep their order
+pub(crate) fn sort_rows(rows: &mut [Vec<String>], idx: usize, order: Order) {
+    let value = |row: &Vec<String>| -> Option<String> {
+        row.get(idx)
+            .map(|value| value.trim().to_string())
+            .filter(|value| !value.is_empty())
+    };
+
+    let numeric = rows
+        .iter()
+        .filter_map(value)
+        .all(|value| value.parse::<f64>().is_ok());
+
+    rows.sort_by(|a, b| match (value(a), value(b)) {
+        (Some(a), Some(b)) => {
+            let ordering = if numeric {
+                let a: f64 = a.parse().unwrap_or_default();
+                let b: f64 = b.parse().unwrap_or_default();
+                a.total_cmp(&b)
+            } else {
+                a.cmp(&b)
+            };
+            match order {
+                Order::Asc => ordering,
+                Order::Desc => ordering.reverse(),
+            }
+        }
+        (Some(_), None) => Ordering::Less,
+        (None, Some(_)) => Ordering::Greater,
+        (None, None) => Ordering::Equal,
+    });
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn sorted(values: &[&str], order: Order) -> Vec<String> {
+        let mut rows: Vec<Vec<String>> = values
+            .iter()
+            .enumerate()
+            .map(|(i, value)| vec![value.to_string(), i.to_string()])
+            .collect();
+        sort_rows(&mut rows, 0, order);
+        rows.into_iter()
+            .map(|row| format!("{}#{}", row[0], row[1]))
+            .collect()
+    }
+
+    #[test]
+    fn test_numeric() {
+        // 9 before 10, as numbers
+        assert_eq!(
+            sorted(&["10", "9", "-1.5", "9"], Order::Asc),
+            ["-1.5#2", "9#1", "9#3", "10#0"]
+        );
+        assert_eq!(
+            sorted(&["10", "9", "-1.5", "9"], Order::Desc),
+            ["10#0", "9#1", "9#3", "-1.5#2"]
+        );
+    }
+
+    #[test]
+    fn test_text() {
+        assert_eq!(
+            sorted(&["b", "10", "a", "9"], Order::Asc),
+            ["10#1", "9#3", "a#2", "b#0"]
+        );
+        assert_eq!(sorted(&["a", "b", "a"], Order::Desc), ["b#1", "a#0", "a#2"]);
+    }
+
+    #[test]
+    fn test_missing_last() {
+        assert_eq!(sorted(&["", "2", "1"], Order::Asc), ["1#2", "2#1", "#0"]);
+        assert_eq!(sorted(&["", "2", "1"], Order::Desc), ["2#1", "1#2", "#0"]);
+    }
+}
@@ -0,0 +1,179 @@
+use crate::schema::ColumnType;
+use std::collections::HashMap;
+use std::fmt;
+
+/// A summary of one column. Empty and missing values are left out.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Stats {
+    /// An integer or float column
+    Numeric(NumericStats),
+    /// Any other type, or an empty column
+    Text(TextStats),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct NumericStats {
+    pub count: usize,
+    pub min: f64,
+    pub max: f64,
+    pub mean: f64,
+    pub median: f64,
+    pub distinct: usize,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TextStats {
+    pub count: usize,
+    pub distinct: usize,
+    /// Each value with how often it occurs, the most frequent first
+    pub values: Vec<(String, usize)>,
+}
+
+impl Stats {
+    pub fn of<'a>(values: impl IntoIterator<Item = &'a str>) -> Stats {
+        let values: Vec<&str> = values
+            .into_iter()
+            .map(str::trim)
+            .filter(|value| !value.is_empty())
+            .collect();
+
+        if values.is_empty() || !ColumnType::of(values.iter().copied()).is_numeric() {
+            return Stats::Text(TextStats::of(&values));
+        }
+
+        let numbers = values
+            .iter()
+            .map(|value| value.parse().unwrap_or_default())
+            .collect();
+        Stats::Numeric(NumericStats::of(numbers))
+    }
+
+    pub fn count(&self) -> usize {
+        match self {
+            Stats::Numeric(stats) => stats.count,
+            Stats::Text(stats) => stats.count,
+        }
+    }
+
+    pub fn distinct(&self) -> usize {
+        match self {
+            Stats::Numeric(stats) => stats.distinct,
+            Stats::Text(stats) => stats.distinct,
+        }
+    }
+}
+
+impl NumericStats {
+    /// `numbers` isn't empty
+    fn of(mut numbers: Vec<f64>) -> Self {
+        numbers.sort_by(f64::total_cmp);
+
+        let count = numbers.len();
+        let middle = count / 2;
+        let median = if count.is_multiple_of(2) {
+            (numbers[middle - 1] + numbers[middle]) / 2.0
+        } else {
+            numbers[middle]
+        };
+        let mean = numbers.iter().sum::<f64>() / count as f64;
+
+        let mut distinct = numbers.clone();
+        distinct.dedup();
+
+        NumericStats {
+            count,
+            min: numbers[0],
+            max: numbers[count - 1],
+            mean,
+            median,
+            distinct: distinct.len(),
+        }
+    }
+}
+
+impl TextStats {
+    fn of(values: &[&str]) -> Self {
+        let mut counts = HashMap::<&str, usize>::new();
+        for value in values {
+            *counts.entry(value).or_default() += 1;
+        }
+
+        let mut counts: Vec<(String, usize)> = counts
+            .into_iter()
+            .map(|(value, count)| (value.to_string(), count))
+            .collect();
+        // ties in alphabetical order, so the output doesn't change run to run
+        counts.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+
+        TextStats {
+            count: values.len(),
+            distinct: counts.len(),
+            values: counts,
+        }
+    }
+}
+
+impl fmt::Display for Stats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Stats::Numeric(stats) => {
+                writeln!(f, "count:    {}", stats.count)?;
+                writeln!(f, "min:      {}", stats.min)?;
+                writeln!(f, "max:      {}", stats.max)?;
+                writeln!(f, "mean:     {:.2}", stats.mean)?;
+                writeln!(f, "median:   {}", stats.median)?;
+                write!(f, "distinct: {}", stats.distinct)
+            }
+            Stats::Text(stats) => {
+                writeln!(f, "count:    {}", stats.count)?;
+                write!(f, "distinct: {}", stats.distinct)?;
+                for (value, count) in &stats.values {
+                    write!(f, "\n  {:<12} {}", value, count)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_numeric() {
+        let Stats::Numeric(stats) = Stats::of(["30", "25", " 30", "", "41"]) else {
+            panic!("expected numbers");
+        };
+        assert_eq!(stats.count, 4);
+        assert_eq!(stats.min, 25.0);
+        assert_eq!(stats.max, 41.0);
+        assert_eq!(stats.mean, 31.5);
+        assert_eq!(stats.median, 30.0);
+        assert_eq!(stats.distinct, 3);
+
+        let Stats::Numeric(stats) = Stats::of(["1.5", "-2", "4"]) else {
+            panic!("expected numbers");
+        };
+        assert_eq!(stats.median, 1.5);
+        assert_eq!(stats.min, -2.0);
+    }
+
+    #[test]
+    fn test_text() {
+        let stats = Stats::of(["London", "Paris", "London", "30", "Lagos"]);
+        let Stats::Text(text) = &stats else {
+            panic!("expected text");
+        };
+        assert_eq!(text.count, 5);
+        assert_eq!(text.distinct, 4);
+        assert_eq!(text.values[0], ("London".to_string(), 2));
+        assert_eq!(text.values[1], ("30".to_string(), 1));
+
+        assert!(matches!(Stats::of(["inf", "1"]), Stats::Text(_)));
+
+        let empty = Stats::of(["", " "]);
+        assert_eq!(empty.count(), 0);
+        assert_eq!(empty.distinct(), 0);
+    }
+}
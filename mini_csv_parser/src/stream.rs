@@ -0,0 +1,263 @@
+use crate::error::{CsvError, ParseError, ParseErrorKind};
+use crate::options::{Mode, ReadOptions, generated_headers};
+use crate::parser::{Parser, Record};
+use crate::row::{FieldError, FromCsvRow, Row, RowError};
+use std::io::BufRead;
+use std::str::FromStr;
+use std::sync::Arc;
+
+/// Rows read one at a time, as the iterator is advanced. Only the current
+/// record is in memory, however big the file.
+pub struct Rows<R: BufRead> {
+    parser: Parser<R>,
+    headers: Arc<[String]>,
+    /// What was wrong with the header record, in lenient mode
+    header_errors: Vec<ParseError>,
+    /// The first row of a file without headers, read to count its columns
+    pending: Option<Record>,
+    mode: Mode,
+    /// Index of the next row, as used by `Csv::get`
+    index: usize,
+    /// Set after an error, past which nothing is read
+    failed: bool,
+}
+
+impl<R: BufRead> Rows<R> {
+    /// Read the header record, the rows are left for the iterator
+    pub fn new(reader: R) -> Result<Self, CsvError> {
+        Rows::with_options(reader, &ReadOptions::new())
+    }
+
+    pub fn with_options(reader: R, options: &ReadOptions) -> Result<Self, CsvError> {
+        let mut parser = Parser::with_delimiter(reader, options.read_delimiter());
+        let first = parser.read_record()?;
+
+        let (headers, header_errors, pending) = match first {
+            Some(record) if options.reads_headers() => {
+                if options.read_mode() == Mode::Strict
+                    && let Some(error) = record.errors.first()
+                {
+                    return Err(error.clone().into());
+                }
+                (record.values, record.errors, None)
+            }
+            Some(record) => (
+                generated_headers(record.values.len()),
+                Vec::new(),
+                Some(record),
+            ),
+            None => (Vec::new(), Vec::new(), None),
+        };
+
+        Ok(Rows {
+            parser,
+            headers: headers.into(),
+            header_errors,
+            pending,
+            mode: options.read_mode(),
+            index: 0,
+            failed: false,
+        })
+    }
+
+    pub fn headers(&self) -> &[String] {
+        &self.headers
+    }
+
+    /// What was wrong with the header record, in lenient mode
+    pub fn header_errors(&self) -> &[ParseError] {
+        &self.header_errors
+    }
+}
+
+impl<R: BufRead> Iterator for Rows<R> {
+    type Item = Result<StreamRow, CsvError>;
+
+    /// In strict mode, the first malformed record is an error and ends the
+    /// iteration. In lenient mode it is read as well as it can be, and its
+    /// row carries the errors
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed {
+            return None;
+        }
+
+        let record = match self.pending.take() {
+            Some(record) => Ok(Some(record)),
+            None => self.parser.read_record(),
+        };
+        let mut record = match record {
+            Ok(Some(record)) => record,
+            Ok(None) => return None,
+            Err(e) => {
+                self.failed = true;
+                return Some(Err(e.into()));
+            }
+        };
+
+        let (expected, found) = (self.headers.len(), record.values.len());
+        if expected != found {
+            record.errors.push(ParseError {
+                line: record.line,
+                column: expected.min(found) + 1,
+                kind: ParseErrorKind::FieldCount { expected, found },
+            });
+        }
+        if self.mode == Mode::Strict
+            && let Some(error) = record.errors.first()
+        {
+            self.failed = true;
+            return Some(Err(error.clone().into()));
+        }
+
+        let row = StreamRow {
+            index: self.index,
+            line: record.line,
+            headers: Arc::clone(&self.headers),
+            values: record.values,
+            errors: record.errors,
+        };
+        self.index += 1;
+        Some(Ok(row))
+    }
+}
+
+/// A row that owns its values, as it outlives the reader's buffer. The
+/// headers are shared by every row of the stream.
+#[derive(Debug, Clone)]
+pub struct StreamRow {
+    pub index: usize,
+    /// The line it starts on, counting from 1
+    pub line: usize,
+    headers: Arc<[String]>,
+    values: Vec<String>,
+    /// What was wrong with it, in lenient mode
+    pub errors: Vec<ParseError>,
+}
+
+impl StreamRow {
+    /// Borrow it as a `Row`, e.g. for `FromCsvRow::from_row`
+    pub fn row(&self) -> Row<'_> {
+        Row::new(&self.headers, &self.values)
+    }
+
+    pub fn get(&self, column: &str) -> Option<&str> {
+        self.row().get(column)
+    }
+
+    pub fn at(&self, index: usize) -> Option<&str> {
+        self.values.get(index).map(|s| s.as_str())
+    }
+
+    pub fn parse<T>(&self, column: &str) -> Result<T, FieldError>
+    where
+        T: FromStr,
+        T::Err: std::fmt::Display,
+    {
+        self.row().parse(column)
+    }
+
+    pub fn deserialize<T: FromCsvRow>(&self) -> Result<T, RowError> {
+        T::from_row(&self.row()).map_err(|error| RowError {
+            row: self.index,
+            error,
+        })
+    }
+
+    pub fn into_values(self) -> Vec<String> {
+        self.values
+    }
+
+    /// The values and the errors, without the headers
+    pub(crate) fn into_parts(self) -> (Vec<String>, Vec<ParseError>) {
+        (self.values, self.errors)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_rows() {
+        let input = "name,age\nAlice,30\n\"Smith, John\",41\n";
+        let mut rows = Rows::new(input.as_bytes()).unwrap();
+        assert_eq!(rows.headers(), ["name", "age"]);
+
+        let alice = rows.next().unwrap().unwrap();
+        assert_eq!(alice.index, 0);
+        assert_eq!(alice.line, 2);
+        assert_eq!(alice.get("name"), Some("Alice"));
+        assert_eq!(alice.parse::<u32>("age"), Ok(30));
+
+        let john = rows.next().unwrap().unwrap();
+        assert_eq!(john.index, 1);
+        assert_eq!(john.into_values(), ["Smith, John", "41"]);
+
+        assert!(rows.next().is_none());
+    }
+
+    #[test]
+    fn test_without_headers() {
+        let options = ReadOptions::new().has_headers(false);
+        let mut rows = Rows::with_options("1,2,3\n4,5,6\n".as_bytes(), &options).unwrap();
+        assert_eq!(rows.headers(), ["col0", "col1", "col2"]);
+
+        let first = rows.next().unwrap().unwrap();
+        assert_eq!(first.index, 0);
+        assert_eq!(first.get("col1"), Some("2"));
+        assert_eq!(first.at(2), Some("3"));
+        assert_eq!(rows.next().unwrap().unwrap().at(0), Some("4"));
+        assert!(rows.next().is_none());
+
+        let mut empty = Rows::with_options("".as_bytes(), &options).unwrap();
+        assert!(empty.headers().is_empty());
+        assert!(empty.next().is_none());
+    }
+
+    #[test]
+    fn test_modes() {
+        let input = "name,age\nAlice,30\nmike\nBob,25\n";
+
+        let rows: Vec<_> = Rows::new(input.as_bytes()).unwrap().collect();
+        assert_eq!(rows.len(), 3);
+        let mike = rows[1].as_ref().unwrap();
+        assert_eq!(
+            mike.errors,
+            [ParseError {
+                line: 3,
+                column: 2,
+                kind: ParseErrorKind::FieldCount {
+                    expected: 2,
+                    found: 1
+                }
+            }]
+        );
+        assert!(rows[2].as_ref().unwrap().errors.is_empty());
+
+        let strict = ReadOptions::new().mode(Mode::Strict);
+        let rows: Vec<_> = Rows::with_options(input.as_bytes(), &strict)
+            .unwrap()
+            .collect();
+        assert_eq!(rows.len(), 2);
+        assert!(rows[0].is_ok());
+        assert_eq!(
+            rows[1].as_ref().unwrap_err().to_string(),
+            "line 3, column 2: expected 2 fields, found 1"
+        );
+
+        let bad_header = "a\"b,c\n1,2\n";
+        assert!(Rows::with_options(bad_header.as_bytes(), &strict).is_err());
+        let rows = Rows::new(bad_header.as_bytes()).unwrap();
+        assert_eq!(rows.header_errors().len(), 1);
+    }
+
+    #[test]
+    fn test_read_error() {
+        // invalid UTF-8 fails the read, and the iterator stops there
+        let input: &[u8] = b"name\nAlice\n\xff\nBob\n";
+        let rows: Vec<_> = Rows::new(input).unwrap().collect();
+        assert_eq!(rows.len(), 2);
+        assert!(rows[0].is_ok());
+        assert!(matches!(rows[1], Err(CsvError::Io(_))));
+    }
+}
@@ -0,0 +1,115 @@
+use std::io::{self, Write};
+
+/// Writes records as CSV, quoting the fields that need it so that
+/// the parser gives back exactly what was written.
+pub struct CsvWriter<W: Write> {
+    inner: W,
+    delimiter: char,
+}
+
+impl<W: Write> CsvWriter<W> {
+    pub fn new(inner: W) -> Self {
+        CsvWriter::with_delimiter(inner, ',')
+    }
+
+    /// A writer separating fields with `delimiter`, e.g. `'\t'` for TSV
+    pub fn with_delimiter(inner: W, delimiter: char) -> Self {
+        CsvWriter { inner, delimiter }
+    }
+
+    /// Write one record, ended by a line break
+    pub fn write_record<S: AsRef<str>>(&mut self, fields: &[S]) -> io::Result<()> {
+        // a lone empty field would be a blank line, which readers skip
+        if let [field] = fields
+            && field.as_ref().is_empty()
+        {
+            return self.inner.write_all(b"\"\"\n");
+        }
+
+        for (i, field) in fields.iter().enumerate() {
+            if i > 0 {
+                write!(self.inner, "{}", self.delimiter)?;
+            }
+            self.write_field(field.as_ref())?;
+        }
+        self.inner.write_all(b"\n")
+    }
+
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+
+    /// The underlying writer, e.g. to get a `Vec<u8>` back
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
+
+    fn write_field(&mut self, field: &str) -> io::Result<()> {
+        if !needs_quotes(field, self.delimiter) {
+            return self.inner.write_all(field.as_bytes());
+        }
+
+        // a quote inside a quoted field is doubled
+        write!(self.inner, "\"{}\"", field.replace('"', "\"\""))
+    }
+}
+
+/// Delimiters, quotes and line breaks only keep their meaning as data
+/// inside quotes
+fn needs_quotes(field: &str, delimiter: char) -> bool {
+    field.contains([delimiter, '"', '\r', '\n'])
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::parser::Parser;
+
+    fn written(records: &[&[&str]]) -> String {
+        let mut writer = CsvWriter::new(Vec::new());
+        for record in records {
+            writer.write_record(record).unwrap();
+        }
+        String::from_utf8(writer.into_inner()).unwrap()
+    }
+
+    #[test]
+    fn test_quoting() {
+        assert_eq!(written(&[&["a", "b"]]), "a,b\n");
+        assert_eq!(
+            written(&[&["Smith, John", "She said \"hi\"", "two\nlines"]]),
+            "\"Smith, John\",\"She said \"\"hi\"\"\",\"two\nlines\"\n"
+        );
+        assert_eq!(written(&[&["", "b", ""]]), ",b,\n");
+        assert_eq!(written(&[&[""]]), "\"\"\n");
+    }
+
+    #[test]
+    fn test_tsv() {
+        let mut writer = CsvWriter::with_delimiter(Vec::new(), '\t');
+        writer.write_record(&["Smith, John", "a\tb"]).unwrap();
+        let text = String::from_utf8(writer.into_inner()).unwrap();
+        assert_eq!(text, "Smith, John\t\"a\tb\"\n");
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let records: &[&[&str]] = &[
+            &["id", "note"],
+            &["1", "Smith, John"],
+            &["2", "\"quoted\""],
+            &["3", "first line\r\nsecond line"],
+            &[""],
+            &[" spaced ", ""],
+        ];
+        let text = written(records);
+
+        let mut parser = Parser::with_delimiter(text.as_bytes(), ',');
+        for record in records {
+            let read = parser.read_record().unwrap().unwrap();
+            assert_eq!(read.values, *record);
+            assert!(read.errors.is_empty());
+        }
+        assert!(parser.read_record().unwrap().is_none());
+    }
+}
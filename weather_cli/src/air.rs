@@ -0,0 +1,185 @@
+use crate::error::WeatherError;
+use crate::location::GeoPlace;
+use crate::view::{AirView, ComponentView};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Response of the air pollution endpoint.
+#[derive(Debug, Deserialize)]
+pub struct AirResponse {
+    pub list: Vec<AirEntry>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AirEntry {
+    pub main: AirIndex,
+    pub components: Components,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AirIndex {
+    /// From 1 (good) to 5 (very poor)
+    pub aqi: u8,
+}
+
+/// Concentrations in μg/m³.
+#[derive(Debug, Deserialize)]
+pub struct Components {
+    pub pm2_5: f64,
+    pub pm10: f64,
+    pub no2: f64,
+    pub o3: f64,
+}
+
+/// The qualitative scale OpenWeather uses for the index and each pollutant.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Level {
+    Good,
+    Fair,
+    Moderate,
+    Poor,
+    VeryPoor,
+}
+
+const LEVELS: [Level; 5] = [
+    Level::Good,
+    Level::Fair,
+    Level::Moderate,
+    Level::Poor,
+    Level::VeryPoor,
+];
+
+/// Where each pollutant moves to the next level, in μg/m³
+const PM2_5_LIMITS: [f64; 4] = [10.0, 25.0, 50.0, 75.0];
+const PM10_LIMITS: [f64; 4] = [20.0, 50.0, 100.0, 200.0];
+const NO2_LIMITS: [f64; 4] = [40.0, 70.0, 150.0, 200.0];
+const O3_LIMITS: [f64; 4] = [60.0, 100.0, 140.0, 180.0];
+
+impl Level {
+    pub fn from_aqi(aqi: u8) -> Option<Level> {
+        LEVELS.get(usize::from(aqi).checked_sub(1)?).copied()
+    }
+
+    /// The level of a concentration, given where the next levels start
+    pub fn from_concentration(value: f64, limits: &[f64; 4]) -> Level {
+        let passed = limits.iter().filter(|limit| value >= **limit).count();
+        LEVELS[passed]
+    }
+}
+
+impl fmt::Display for Level {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let label = match self {
+            Level::Good => "Good",
+            Level::Fair => "Fair",
+            Level::Moderate => "Moderate",
+            Level::Poor => "Poor",
+            Level::VeryPoor => "Very poor",
+        };
+        write!(f, "{}", label)
+    }
+}
+
+/// The current air quality of a place.
+#[derive(Debug)]
+pub struct AirQuality {
+    pub place: GeoPlace,
+    pub aqi: u8,
+    pub components: Components,
+}
+
+impl AirQuality {
+    pub fn new(place: GeoPlace, response: AirResponse) -> Result<Self, WeatherError> {
+        let entry = response
+            .list
+            .into_iter()
+            .next()
+            .ok_or(WeatherError::Unknown)?;
+
+        Ok(AirQuality {
+            place,
+            aqi: entry.main.aqi,
+            components: entry.components,
+        })
+    }
+
+    pub fn view(&self) -> AirView {
+        let c = &self.components;
+        let component = |name: &'static str, value: f64, limits: &[f64; 4]| ComponentView {
+            name,
+            value,
+            level: Level::from_concentration(value, limits),
+        };
+
+        AirView {
+            city: self.place.name.clone(),
+            country: self.place.country.clone(),
+            aqi: self.aqi,
+            quality: Level::from_aqi(self.aqi),
+            components: vec![
+                component("PM2.5", c.pm2_5, &PM2_5_LIMITS),
+                component("PM10", c.pm10, &PM10_LIMITS),
+                component("NO2", c.no2, &NO2_LIMITS),
+                component("O3", c.o3, &O3_LIMITS),
+            ],
+        }
+    }
+
+    pub fn display(&self) {
+        print!("{}", self.view().to_text());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_levels() {
+        assert_eq!(Level::from_aqi(1), Some(Level::Good));
+        assert_eq!(Level::from_aqi(5), Some(Level::VeryPoor));
+        assert_eq!(Level::from_aqi(0), None);
+        assert_eq!(Level::from_aqi(6), None);
+
+        assert_eq!(Level::from_concentration(0.0, &PM2_5_LIMITS), Level::Good);
+        assert_eq!(Level::from_concentration(10.0, &PM2_5_LIMITS), Level::Fair);
+        assert_eq!(Level::from_concentration(74.9, &PM2_5_LIMITS), Level::Poor);
+        assert_eq!(
+            Level::from_concentration(300.0, &PM10_LIMITS),
+            Level::VeryPoor
+        );
+        assert_eq!(Level::VeryPoor.to_string(), "Very poor");
+    }
+
+    #[test]
+    fn test_air_quality() {
+        let response: AirResponse = serde_json::from_str(
+            r#"{"coord": {"lon": -0.1, "lat": 51.5}, "list": [{"dt": 1760600000,
+                "main": {"aqi": 2},
+                "components": {"co": 201.9, "no": 0.0, "no2": 45.2, "o3": 61.0,
+                               "so2": 1.2, "pm2_5": 8.3, "pm10": 12.1, "nh3": 0.7}}]}"#,
+        )
+        .unwrap();
+        let place = GeoPlace {
+            name: "London".to_string(),
+            lat: 51.5,
+            lon: -0.1,
+            country: Some("GB".to_string()),
+        };
+
+        let view = AirQuality::new(place, response).unwrap().view();
+        assert_eq!(view.quality, Some(Level::Fair));
+        let levels: Vec<(&str, Level)> =
+            view.components.iter().map(|c| (c.name, c.level)).collect();
+        assert_eq!(
+            levels,
+            vec![
+                ("PM2.5", Level::Good),
+                ("PM10", Level::Good),
+                ("NO2", Level::Fair),
+                ("O3", Level::Fair),
+            ]
+        );
+    }
+}
@@ -0,0 +1,179 @@
+use crate::error::WeatherError;
+use crate::location::GeoPlace;
+use crate::view::{AlertView, AlertsView};
+use chrono::DateTime;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// The exit code of `alerts --fail-on-alert` when there are alerts.
+pub const ALERT_EXIT_CODE: u8 = 3;
+
+/// The One Call response with everything but the alerts excluded.
+#[derive(Debug, Deserialize)]
+pub struct AlertsResponse {
+    /// Shift in seconds from UTC
+    #[serde(default)]
+    pub timezone_offset: i64,
+    /// Left out entirely when there are none
+    #[serde(default)]
+    pub alerts: Vec<Alert>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Alert {
+    pub sender_name: String,
+    pub event: String,
+    /// Unix timestamps
+    pub start: i64,
+    pub end: i64,
+    pub description: String,
+    #[serde(default)]
+    pub tags: Vec<String>,
+}
+
+/// How serious an alert is. OpenWeather doesn't send one, so it comes from
+/// the usual naming of alerts: warnings before watches before advisories.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Severity {
+    Severe,
+    Moderate,
+    Minor,
+    Unknown,
+}
+
+impl Severity {
+    pub fn of(event: &str) -> Severity {
+        let event = event.to_lowercase();
+        if event.contains("warning") || event.contains("emergency") {
+            Severity::Severe
+        } else if event.contains("watch") {
+            Severity::Moderate
+        } else if event.contains("advisory") || event.contains("statement") {
+            Severity::Minor
+        } else {
+            Severity::Unknown
+        }
+    }
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let label = match self {
+            Severity::Severe => "Severe",
+            Severity::Moderate => "Moderate",
+            Severity::Minor => "Minor",
+            Severity::Unknown => "Unknown severity",
+        };
+        write!(f, "{}", label)
+    }
+}
+
+/// The active alerts of a place.
+#[derive(Debug)]
+pub struct Alerts {
+    pub place: GeoPlace,
+    pub timezone_offset: i64,
+    pub alerts: Vec<Alert>,
+}
+
+impl Alerts {
+    pub fn new(place: GeoPlace, response: AlertsResponse) -> Self {
+        Alerts {
+            place,
+            timezone_offset: response.timezone_offset,
+            alerts: response.alerts,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.alerts.is_empty()
+    }
+
+    pub fn view(&self) -> Result<AlertsView, WeatherError> {
+        let alerts = self
+            .alerts
+            .iter()
+            .map(|alert| {
+                Ok(AlertView {
+                    event: alert.event.clone(),
+                    severity: Severity::of(&alert.event),
+                    sender: alert.sender_name.clone(),
+                    start: self.local_time(alert.start)?,
+                    end: self.local_time(alert.end)?,
+                    description: alert.description.trim().to_string(),
+                })
+            })
+            .collect::<Result<_, WeatherError>>()?;
+
+        Ok(AlertsView {
+            city: self.place.name.clone(),
+            country: self.place.country.clone(),
+            alerts,
+        })
+    }
+
+    pub fn display(&self) -> Result<(), WeatherError> {
+        print!("{}", self.view()?.to_text());
+        Ok(())
+    }
+
+    /// A timestamp as `YYYY-MM-DD HH:MM` in the place's own time
+    fn local_time(&self, timestamp: i64) -> Result<String, WeatherError> {
+        let local = timestamp + self.timezone_offset;
+        let date_time =
+            DateTime::from_timestamp(local, 0).ok_or(WeatherError::InvalidTimestamp(timestamp))?;
+        Ok(date_time.format("%Y-%m-%d %H:%M").to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn miami() -> GeoPlace {
+        GeoPlace {
+            name: "Miami".to_string(),
+            lat: 25.8,
+            lon: -80.2,
+            country: Some("US".to_string()),
+        }
+    }
+
+    #[test]
+    fn test_severity() {
+        assert_eq!(Severity::of("Hurricane Warning"), Severity::Severe);
+        assert_eq!(Severity::of("Flood Watch"), Severity::Moderate);
+        assert_eq!(Severity::of("Heat Advisory"), Severity::Minor);
+        assert_eq!(Severity::of("Fog"), Severity::Unknown);
+    }
+
+    #[test]
+    fn test_alerts() {
+        let response: AlertsResponse = serde_json::from_str(
+            r#"{"lat": 25.8, "lon": -80.2, "timezone": "America/New_York",
+                "timezone_offset": -14400,
+                "alerts": [{"sender_name": "NWS Miami", "event": "Hurricane Warning",
+                            "start": 1760623200, "end": 1760680800,
+                            "description": "Hurricane conditions are expected.\n",
+                            "tags": ["Wind"]}]}"#,
+        )
+        .unwrap();
+
+        let alerts = Alerts::new(miami(), response);
+        assert!(!alerts.is_empty());
+        let view = alerts.view().unwrap();
+        assert_eq!(view.alerts[0].severity, Severity::Severe);
+        // 14:00 UTC is 10:00 in Miami
+        assert_eq!(view.alerts[0].start, "2025-10-16 10:00");
+        assert_eq!(
+            view.alerts[0].description,
+            "Hurricane conditions are expected."
+        );
+
+        let calm: AlertsResponse = serde_json::from_str(r#"{"timezone_offset": 0}"#).unwrap();
+        let calm = Alerts::new(miami(), calm);
+        assert!(calm.is_empty());
+        assert!(calm.view().unwrap().to_text().contains("No active alerts"));
+    }
+}
@@ -0,0 +1,95 @@
+use crate::error::WeatherError;
+use keyring::Entry;
+
+/// Where `auth set` keeps the OpenWeatherMap key in the OS keyring
+const SERVICE: &str = "weather_cli";
+const USER: &str = "openweather";
+
+/// The OpenWeatherMap key from, in order: --api-key, the
+/// `OPENWEATHER_API_KEY` environment variable, the config file and the OS
+/// keyring
+pub fn api_key(flag: Option<String>, config: Option<String>) -> Result<String, WeatherError> {
+    choose(
+        flag,
+        std::env::var("OPENWEATHER_API_KEY").ok(),
+        config,
+        stored_key,
+    )
+}
+
+/// The first key that isn't blank. The keyring is only asked when nothing
+/// else has one, as it may show a prompt
+fn choose(
+    flag: Option<String>,
+    env: Option<String>,
+    config: Option<String>,
+    keyring: impl FnOnce() -> Option<String>,
+) -> Result<String, WeatherError> {
+    [flag, env, config]
+        .into_iter()
+        .flatten()
+        .find(|key| !key.trim().is_empty())
+        .or_else(keyring)
+        .ok_or(WeatherError::InvalidApiKey)
+}
+
+/// The key saved with `auth set`. A keyring that can't be reached counts
+/// as no key, so the other sources still work on a machine without one
+pub fn stored_key() -> Option<String> {
+    entry().ok()?.get_password().ok()
+}
+
+pub fn store_key(key: &str) -> Result<(), WeatherError> {
+    entry()?.set_password(key).map_err(keyring_error)
+}
+
+/// Forget the saved key, `false` if there was none
+pub fn delete_key() -> Result<bool, WeatherError> {
+    match entry()?.delete_credential() {
+        Ok(()) => Ok(true),
+        Err(keyring::Error::NoEntry) => Ok(false),
+        Err(e) => Err(keyring_error(e)),
+    }
+}
+
+fn entry() -> Result<Entry, WeatherError> {
+    Entry::new(SERVICE, USER).map_err(keyring_error)
+}
+
+fn keyring_error(e: keyring::Error) -> WeatherError {
+    WeatherError::Keyring(e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(value: &str) -> Option<String> {
+        Some(value.to_string())
+    }
+
+    #[test]
+    fn test_choose() {
+        let keyring = || panic!("the keyring shouldn't be asked");
+        assert_eq!(
+            choose(key("flag"), key("env"), key("config"), keyring).unwrap(),
+            "flag"
+        );
+        assert_eq!(
+            choose(None, key("env"), key("config"), keyring).unwrap(),
+            "env"
+        );
+        assert_eq!(
+            choose(None, key(" "), key("config"), keyring).unwrap(),
+            "config"
+        );
+        assert_eq!(
+            choose(None, None, None, || key("stored")).unwrap(),
+            "stored"
+        );
+        assert!(matches!(
+            choose(None, None, None, || None),
+            Err(WeatherError::InvalidApiKey)
+        ));
+    }
+}
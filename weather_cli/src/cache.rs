@@ -0,0 +1,176 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// How long a cached response is served before asking the API again.
+pub const DEFAULT_TTL_MINUTES: u64 = 10;
+
+/// API responses kept on disk, one file per endpoint, location and units,
+/// so running the CLI again doesn't burn the API quota.
+#[derive(Debug, Clone)]
+pub struct Cache {
+    dir: PathBuf,
+    ttl: Duration,
+    mode: CacheMode,
+}
+
+/// How cached responses are used.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CacheMode {
+    /// Served while younger than the TTL
+    Fresh,
+    /// Ignore what is cached but still store the new responses
+    Refresh,
+    /// Served whatever their age, nothing is fetched
+    Offline,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct CacheEntry {
+    /// Seconds since the Unix epoch
+    fetched_at: u64,
+    /// The response body, as the API sent it
+    body: String,
+}
+
+impl Cache {
+    pub fn new(dir: PathBuf, ttl: Duration, mode: CacheMode) -> Self {
+        Cache { dir, ttl, mode }
+    }
+
+    /// The cache in the user's cache directory, `None` if there is none
+    pub fn open(ttl: Duration, mode: CacheMode) -> Option<Self> {
+        let dir = dirs::cache_dir()?.join("weather_cli");
+        Some(Cache::new(dir, ttl, mode))
+    }
+
+    pub fn is_offline(&self) -> bool {
+        self.mode == CacheMode::Offline
+    }
+
+    fn path(&self, key: &str) -> PathBuf {
+        self.dir.join(format!("{}.json", key))
+    }
+
+    /// The cached body for `key` if it is younger than the TTL
+    pub fn get(&self, key: &str) -> Option<String> {
+        if self.mode == CacheMode::Refresh {
+            return None;
+        }
+
+        let (body, age) = self.get_any(key)?;
+        if age >= self.ttl {
+            return None;
+        }
+
+        Some(body)
+    }
+
+    /// The cached body for `key` however old it is, with its age
+    pub fn get_any(&self, key: &str) -> Option<(String, Duration)> {
+        let text = fs::read_to_string(self.path(key)).ok()?;
+        let entry: CacheEntry = serde_json::from_str(&text).ok()?;
+        let age = Duration::from_secs(now().saturating_sub(entry.fetched_at));
+
+        Some((entry.body, age))
+    }
+
+    pub fn put(&self, key: &str, body: &str) -> io::Result<()> {
+        fs::create_dir_all(&self.dir)?;
+        let entry = CacheEntry {
+            fetched_at: now(),
+            body: body.to_string(),
+        };
+        fs::write(self.path(key), serde_json::to_string(&entry)?)
+    }
+}
+
+/// A file name for one endpoint, location and units, like
+/// `weather-new_york-metric`
+pub fn key(endpoint: &str, location: &str, units: &str) -> String {
+    format!("{}-{}-{}", endpoint, location, units)
+        .to_lowercase()
+        .chars()
+        .map(|c| match c {
+            'a'..='z' | '0'..='9' | '-' | '.' | ',' => c,
+            _ => '_',
+        })
+        .collect()
+}
+
+/// A rough age like `5 min` or `3 days`, for notices
+pub fn format_age(age: Duration) -> String {
+    let minutes = age.as_secs() / 60;
+    match minutes {
+        0 => "less than a minute".to_string(),
+        1..60 => format!("{} min", minutes),
+        60..1440 => format!("{} h {} min", minutes / 60, minutes % 60),
+        _ => match minutes / 1440 {
+            1 => "1 day".to_string(),
+            days => format!("{} days", days),
+        },
+    }
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_key() {
+        assert_eq!(
+            key("weather", "New York", "Metric"),
+            "weather-new_york-metric"
+        );
+        assert_eq!(
+            key("forecast", "52.5,-13.4", "kelvin"),
+            "forecast-52.5,-13.4-kelvin"
+        );
+        assert_eq!(key("weather", "../etc", "metric"), "weather-.._etc-metric");
+    }
+
+    #[test]
+    fn test_format_age() {
+        assert_eq!(format_age(Duration::from_secs(30)), "less than a minute");
+        assert_eq!(format_age(Duration::from_secs(25 * 60)), "25 min");
+        assert_eq!(format_age(Duration::from_secs(125 * 60)), "2 h 5 min");
+        assert_eq!(format_age(Duration::from_secs(86_400)), "1 day");
+        assert_eq!(format_age(Duration::from_secs(3 * 86_400 + 10)), "3 days");
+    }
+
+    #[test]
+    fn test_cache() {
+        let dir = std::env::temp_dir().join(format!("weather_cli_cache_{}", std::process::id()));
+        let cache = Cache::new(dir.clone(), Duration::from_secs(600), CacheMode::Fresh);
+        assert_eq!(cache.get("weather-paris-metric"), None);
+
+        cache
+            .put("weather-paris-metric", r#"{"name":"Paris"}"#)
+            .unwrap();
+        assert_eq!(
+            cache.get("weather-paris-metric").as_deref(),
+            Some(r#"{"name":"Paris"}"#)
+        );
+        assert_eq!(cache.get("weather-paris-imperial"), None);
+
+        let refreshing = Cache::new(dir.clone(), Duration::from_secs(600), CacheMode::Refresh);
+        assert_eq!(refreshing.get("weather-paris-metric"), None);
+
+        let expired = Cache::new(dir.clone(), Duration::ZERO, CacheMode::Fresh);
+        assert_eq!(expired.get("weather-paris-metric"), None);
+        let (body, age) = expired.get_any("weather-paris-metric").unwrap();
+        assert_eq!(body, r#"{"name":"Paris"}"#);
+        assert!(age < Duration::from_secs(60));
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
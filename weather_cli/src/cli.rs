@@ -1,31 +1,482 @@
-use clap::Parser;
+use crate::cache::{Cache, CacheMode, DEFAULT_TTL_MINUTES};
+use crate::config::Config;
+use crate::error::WeatherError;
+use crate::favorites::Favorites;
+use crate::location::{Location, check_latitude, check_longitude};
+use crate::notify::parse_interval;
+use crate::onecall;
+use crate::provider::ProviderKind;
+use crate::retry::{DEFAULT_CONCURRENCY, DEFAULT_RETRIES, Retry};
+use crate::units::Units;
+use chrono::NaiveDate;
+use clap::parser::ValueSource;
+use clap::{ArgMatches, Parser, Subcommand, ValueEnum};
+use std::io::IsTerminal;
+use std::path::PathBuf;
+use std::time::Duration;
 
 #[derive(Debug, Parser)]
-#[command(name = "weather_cli", about = "A simple CLI to fetch weather data")]
+#[command(
+    name = "weather_cli",
+    about = "A simple CLI to fetch weather data",
+    args_conflicts_with_subcommands = true,
+    subcommand_negates_reqs = true
+)]
 pub struct Cli {
-    /// City name to fetch weather for
-    #[arg(required = true)]
-    pub city: Vec<String>,
+    #[command(subcommand)]
+    pub command: Option<Command>,
 
-    /// Temperature units: metric (Celsius), imperial (Fahrenheit), or kelvin
-    #[arg(short, long, default_value = "metric")]
-    pub units: String,
+    /// City name to fetch weather for, or coordinates like "52.5,13.4".
+    /// Guessed from your IP address when left out
+    pub city: Vec<Location>,
+
+    /// Latitude of a place to fetch weather for, used with --lon
+    #[arg(long, requires = "lon", allow_negative_numbers = true, value_parser = latitude, global = true)]
+    pub lat: Option<f64>,
+
+    /// Longitude of a place to fetch weather for, used with --lat
+    #[arg(long, requires = "lat", allow_negative_numbers = true, value_parser = longitude, global = true)]
+    pub lon: Option<f64>,
+
+    /// Don't guess the location from your IP address when no city is given
+    #[arg(long, global = true)]
+    pub no_auto_locate: bool,
+
+    /// OpenWeatherMap API key, instead of OPENWEATHER_API_KEY, the config
+    /// file or the one saved with `auth set`
+    #[arg(long, value_name = "KEY", global = true)]
+    pub api_key: Option<String>,
+
+    /// Where the weather comes from
+    #[arg(long, value_enum, default_value_t = ProviderKind::OpenWeather, global = true)]
+    pub provider: ProviderKind,
+
+    /// Language of the conditions and labels, like "de", "fr" or "pt_br"
+    #[arg(long, value_name = "CODE", value_parser = language, global = true)]
+    pub lang: Option<String>,
+
+    /// Units for temperatures and wind speeds
+    #[arg(short, long, value_enum, ignore_case = true, default_value_t = Units::Metric, global = true)]
+    pub units: Units,
+
+    /// Always ask the API, without reading or writing the cache
+    #[arg(long, global = true)]
+    pub no_cache: bool,
+
+    /// Ask the API even if a cached response is still fresh
+    #[arg(long, global = true)]
+    pub refresh: bool,
+
+    /// Don't use the network, show the last cached responses whatever their age
+    #[arg(long, conflicts_with_all = ["no_cache", "refresh"], global = true)]
+    pub offline: bool,
+
+    /// Minutes a cached response stays fresh
+    #[arg(long, value_name = "MINUTES", default_value_t = DEFAULT_TTL_MINUTES, global = true)]
+    pub cache_ttl: u64,
+
+    /// How many times a request is tried again after a network error, a
+    /// rate limit or a server error
+    #[arg(long, value_name = "N", default_value_t = DEFAULT_RETRIES, global = true)]
+    pub retries: u32,
+
+    /// How many requests are sent at the same time, 1 to send them one
+    /// after another. Lower it when a long list of cities hits rate limits
+    #[arg(long, value_name = "N", default_value_t = DEFAULT_CONCURRENCY, value_parser = clap::value_parser!(u32).range(1..), global = true)]
+    pub concurrency: u32,
+
+    /// Print what happens behind the scenes, like retried requests, to stderr
+    #[arg(short, long, global = true)]
+    pub verbose: bool,
+
+    /// How to print the weather
+    #[arg(short, long, value_enum, default_value_t = OutputFormat::Text, global = true)]
+    pub output: OutputFormat,
+
+    /// Write the report to this file instead of stdout, replacing it in
+    /// one step
+    #[arg(long, value_name = "PATH", global = true)]
+    pub out: Option<PathBuf>,
 
     /// Show detailed weather information
-    #[arg(short, long)]
+    #[arg(short, long, overrides_with = "no_detailed")]
     pub detailed: bool,
+
+    /// Leave the details out, even when the config file asks for them
+    #[arg(long, overrides_with = "detailed")]
+    pub no_detailed: bool,
+
+    /// Refresh the weather every few minutes, 5 unless given like --watch=10
+    #[arg(
+        long,
+        value_name = "MINUTES",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "5",
+        value_parser = clap::value_parser!(u64).range(1..),
+        conflicts_with = "offline"
+    )]
+    pub watch: Option<u64>,
+
+    /// Show the next hours after the current weather, 12 unless given like
+    /// --hourly=24. Implies --onecall
+    #[arg(
+        long,
+        value_name = "HOURS",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "12",
+        value_parser = clap::value_parser!(u8).range(1..=onecall::MAX_HOURS as i64),
+        conflicts_with = "compare"
+    )]
+    pub hourly: Option<u8>,
+
+    /// Get everything from the One Call API 3.0 in one request per place:
+    /// the current weather with its alerts, and the forecast up to 8 days
+    #[arg(long, global = true)]
+    pub onecall: bool,
+
+    /// Show the cities side by side in one table
+    #[arg(long, conflicts_with = "detailed")]
+    pub compare: bool,
+
+    /// Print the text output without colors and icons
+    #[arg(long, global = true)]
+    pub no_color: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+pub enum OutputFormat {
+    /// Readable text, the details only with --detailed
+    Text,
+    /// Every field, as a JSON array with one object per city
+    Json,
+    /// Every field, one row per city after a header row
+    Csv,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Show the daily forecast for the next days
+    Forecast {
+        /// City name to fetch the forecast for, or coordinates like "52.5,13.4".
+        /// Guessed from your IP address when left out
+        #[arg(conflicts_with = "lat")]
+        city: Option<Location>,
+
+        /// How many days to show, up to 5, or up to 8 with --onecall
+        #[arg(long, default_value_t = 5, value_parser = clap::value_parser!(u8).range(1..=onecall::MAX_DAYS as i64))]
+        days: u8,
+    },
+    /// Show the air quality index and main pollutants
+    Air {
+        /// City name, or coordinates like "52.5,13.4".
+        /// Guessed from your IP address when left out
+        #[arg(conflicts_with = "lat")]
+        city: Option<Location>,
+    },
+    /// Show the active weather alerts
+    Alerts {
+        /// City name, or coordinates like "52.5,13.4".
+        /// Guessed from your IP address when left out
+        #[arg(conflicts_with = "lat")]
+        city: Option<Location>,
+
+        /// Exit with code 3 when there are alerts, for scripts
+        #[arg(long)]
+        fail_on_alert: bool,
+    },
+    /// Show the temperature range and conditions of a past day
+    History {
+        /// City name, or coordinates like "52.5,13.4".
+        /// Guessed from your IP address when left out
+        #[arg(conflicts_with = "lat")]
+        city: Option<Location>,
+
+        /// The day, as YYYY-MM-DD
+        #[arg(long, value_parser = date)]
+        date: NaiveDate,
+    },
+    /// Keep running and show desktop notifications when the temperature
+    /// crosses a threshold or a weather alert is issued
+    Notify {
+        /// City name, or coordinates like "52.5,13.4".
+        /// Guessed from your IP address when left out
+        #[arg(conflicts_with = "lat")]
+        city: Option<Location>,
+
+        /// How often to check, like 1h, 30m or 1h30m
+        #[arg(long, value_name = "DURATION", default_value = "1h", value_parser = parse_interval)]
+        interval: Duration,
+
+        /// Notify when the temperature drops below this, in --units
+        #[arg(long, value_name = "TEMP", allow_negative_numbers = true)]
+        alert_temp_below: Option<f64>,
+
+        /// Notify when the temperature rises above this, in --units
+        #[arg(long, value_name = "TEMP", allow_negative_numbers = true)]
+        alert_temp_above: Option<f64>,
+    },
+    /// Manage the cities shown when no city is given
+    Favorites {
+        #[command(subcommand)]
+        action: FavoritesAction,
+    },
+    /// Manage the OpenWeatherMap API key saved in the OS keyring
+    Auth {
+        #[command(subcommand)]
+        action: AuthAction,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum FavoritesAction {
+    /// Add cities or coordinates to the favorites
+    Add {
+        #[arg(required = true)]
+        cities: Vec<Location>,
+    },
+    /// Remove cities from the favorites
+    Remove {
+        #[arg(required = true)]
+        cities: Vec<Location>,
+    },
+    /// Show the favorites
+    List,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum AuthAction {
+    /// Save an OpenWeatherMap API key in the OS keyring
+    Set {
+        /// The key, asked for when left out so it stays out of your shell
+        /// history
+        key: Option<String>,
+    },
+    /// Remove the saved API key from the OS keyring
+    Remove,
 }
 
 impl Cli {
-    pub fn is_metric(&self) -> bool {
-        self.units.to_lowercase() == "metric"
+    /// Fill in what the command line left out from the config file
+    pub fn apply_config(
+        &mut self,
+        config: &Config,
+        matches: &ArgMatches,
+    ) -> Result<(), WeatherError> {
+        if matches.value_source("units") != Some(ValueSource::CommandLine)
+            && let Some(units) = config.units
+        {
+            self.units = units;
+        }
+        if !self.no_detailed {
+            self.detailed |= config.detailed;
+        }
+
+        if self.lat.is_some() {
+            return Ok(());
+        }
+        match &mut self.command {
+            Some(
+                Command::Forecast { city, .. }
+                | Command::Air { city }
+                | Command::Alerts { city, .. }
+                | Command::History { city, .. }
+                | Command::Notify { city, .. },
+            ) if city.is_none() => {
+                *city = config.locations()?.into_iter().next();
+            }
+            None if self.city.is_empty() => self.city = config.locations()?,
+            _ => {}
+        }
+
+        Ok(())
+    }
+
+    /// Show all the favorites when no place is given at all. Applied before
+    /// the config, so favorites win over its default cities
+    pub fn apply_favorites(&mut self, favorites: &Favorites) -> Result<(), WeatherError> {
+        if self.command.is_none() && self.city.is_empty() && self.lat.is_none() {
+            self.city = favorites.locations()?;
+        }
+
+        Ok(())
+    }
+
+    /// The place given with --lat and --lon
+    pub fn coordinates(&self) -> Option<Location> {
+        match (self.lat, self.lon) {
+            (Some(lat), Some(lon)) => Some(Location::Coordinates { lat, lon }),
+            _ => None,
+        }
+    }
+
+    /// The on-disk cache, unless turned off with --no-cache
+    pub fn cache(&self) -> Option<Cache> {
+        if self.no_cache {
+            return None;
+        }
+
+        // watching is for fresh weather, whatever the TTL
+        let mode = if self.offline {
+            CacheMode::Offline
+        } else if self.refresh
+            || self.watch.is_some()
+            || matches!(self.command, Some(Command::Notify { .. }))
+        {
+            CacheMode::Refresh
+        } else {
+            CacheMode::Fresh
+        };
+
+        Cache::open(Duration::from_secs(self.cache_ttl * 60), mode)
+    }
+
+    /// Whether the weather comes from the One Call endpoint
+    pub fn uses_one_call(&self) -> bool {
+        self.onecall || self.hourly.is_some()
     }
 
-    pub fn is_imperial(&self) -> bool {
-        self.units.to_lowercase() == "imperial"
+    /// How failed requests are tried again
+    pub fn retry(&self) -> Retry {
+        Retry::new(self.retries, self.concurrency, self.verbose)
+    }
+
+    /// Whether the text output gets colors and icons: not with --no-color,
+    /// `NO_COLOR` set, --out, or stdout going somewhere else than a terminal
+    pub fn use_color(&self) -> bool {
+        !self.no_color
+            && self.output == OutputFormat::Text
+            && self.out.is_none()
+            && std::env::var_os("NO_COLOR").is_none()
+            && std::io::stdout().is_terminal()
+    }
+
+    /// Every place to fetch the current weather for, in order. Empty when
+    /// the location is to be guessed
+    pub fn locations(&self) -> Vec<Location> {
+        let mut locations = self.city.clone();
+        locations.extend(self.coordinates());
+        locations
+    }
+}
+
+fn date(s: &str) -> Result<NaiveDate, String> {
+    NaiveDate::parse_from_str(s, "%Y-%m-%d").map_err(|_| "use a date like 2024-03-01".to_string())
+}
+
+fn language(s: &str) -> Result<String, String> {
+    let valid = (2..=5).contains(&s.len())
+        && s.chars()
+            .all(|c| c.is_ascii_alphabetic() || c == '_' || c == '-');
+    if !valid {
+        return Err("use a language code like de or pt_br".to_string());
+    }
+
+    Ok(s.to_lowercase().replace('-', "_"))
+}
+
+fn latitude(s: &str) -> Result<f64, String> {
+    let lat = s.parse::<f64>().map_err(|e| e.to_string())?;
+    check_latitude(lat)
+}
+
+fn longitude(s: &str) -> Result<f64, String> {
+    let lon = s.parse::<f64>().map_err(|e| e.to_string())?;
+    check_longitude(lon)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::{CommandFactory, FromArgMatches};
+
+    fn parse(args: &[&str], config: &Config) -> Cli {
+        let matches = Cli::command().get_matches_from(args);
+        let mut cli = Cli::from_arg_matches(&matches).unwrap();
+        cli.apply_config(config, &matches).unwrap();
+        cli
+    }
+
+    #[test]
+    fn test_apply_config() {
+        let config = Config {
+            cities: vec!["Lagos".to_string()],
+            units: Some(Units::Imperial),
+            detailed: true,
+            ..Config::default()
+        };
+        let lagos = Location::City("Lagos".to_string());
+
+        let cli = parse(&["weather_cli"], &config);
+        assert_eq!(cli.units, Units::Imperial);
+        assert!(cli.detailed);
+        assert_eq!(cli.city, vec![lagos.clone()]);
+
+        let cli = parse(&["weather_cli", "--no-detailed"], &config);
+        assert!(!cli.detailed);
+        // the last of the two flags wins
+        let cli = parse(&["weather_cli", "--no-detailed", "-d"], &config);
+        assert!(cli.detailed);
+        let cli = parse(&["weather_cli", "-d", "--no-detailed"], &Config::default());
+        assert!(!cli.detailed);
+
+        let cli = parse(&["weather_cli", "Paris", "-u", "Metric"], &config);
+        assert_eq!(cli.units, Units::Metric);
+        assert_eq!(cli.city, vec![Location::City("Paris".to_string())]);
+
+        let cli = parse(&["weather_cli", "--lat", "1", "--lon", "2"], &config);
+        assert_eq!(cli.locations().len(), 1);
+
+        let cli = parse(&["weather_cli", "forecast", "-u", "kelvin"], &config);
+        assert_eq!(cli.units, Units::Kelvin);
+        assert!(
+            matches!(cli.command, Some(Command::Forecast { city: Some(city), .. }) if city == lagos)
+        );
+
+        let cli = parse(&["weather_cli", "Paris"], &Config::default());
+        assert_eq!(cli.units, Units::Metric);
+    }
+
+    #[test]
+    fn test_apply_favorites() {
+        let favorites = Favorites {
+            cities: vec!["Accra".to_string()],
+        };
+        let config = Config {
+            city: Some("Lagos".to_string()),
+            ..Config::default()
+        };
+        let apply = |args: &[&str]| {
+            let matches = Cli::command().get_matches_from(args);
+            let mut cli = Cli::from_arg_matches(&matches).unwrap();
+            cli.apply_favorites(&favorites).unwrap();
+            cli.apply_config(&config, &matches).unwrap();
+            cli
+        };
+
+        let accra = Location::City("Accra".to_string());
+        assert_eq!(apply(&["weather_cli"]).city, vec![accra]);
+        assert_eq!(apply(&["weather_cli", "Paris"]).city.len(), 1);
+        assert!(
+            apply(&["weather_cli", "--lat", "1", "--lon", "2"])
+                .city
+                .is_empty()
+        );
+        assert!(matches!(
+            apply(&["weather_cli", "air"]).command,
+            Some(Command::Air { city: Some(Location::City(city)) }) if city == "Lagos"
+        ));
     }
 
-    pub fn is_kelvin(&self) -> bool {
-        self.units.to_lowercase() == "kelvin"
+    #[test]
+    fn test_lang() {
+        let cli = parse(
+            &["weather_cli", "Paris", "--lang", "PT-br"],
+            &Config::default(),
+        );
+        assert_eq!(cli.lang.as_deref(), Some("pt_br"));
+        assert!(Cli::try_parse_from(["weather_cli", "--lang", "german"]).is_err());
+        assert!(Cli::try_parse_from(["weather_cli", "--lang", "d"]).is_err());
     }
 }
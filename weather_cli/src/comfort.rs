@@ -0,0 +1,88 @@
+/// Below this the heat index isn't defined (80°F).
+const HEAT_INDEX_FROM: f64 = 26.7;
+
+/// Above this temperature, or below this wind speed, there is no wind chill.
+const WIND_CHILL_UP_TO: f64 = 10.0;
+const WIND_CHILL_MIN_WIND: f64 = 4.8;
+
+/// The temperature at which the air would be saturated, with the Magnus
+/// formula. Temperatures here are all in Celsius, unrounded
+pub fn dew_point(celsius: f64, humidity: u8) -> f64 {
+    const A: f64 = 17.62;
+    const B: f64 = 243.12;
+
+    // ln(0) is -inf, and no air is really that dry
+    let humidity = f64::from(humidity.max(1)) / 100.0;
+    let gamma = humidity.ln() + A * celsius / (B + celsius);
+    B * gamma / (A - gamma)
+}
+
+/// What hot and humid air feels like, with the US National Weather
+/// Service's regression. `None` when it isn't hot enough to matter
+pub fn heat_index(celsius: f64, humidity: u8) -> Option<f64> {
+    if celsius < HEAT_INDEX_FROM {
+        return None;
+    }
+
+    let t = celsius * 9.0 / 5.0 + 32.0;
+    let rh = f64::from(humidity);
+    let mut index = -42.379 + 2.04901523 * t + 10.14333127 * rh
+        - 0.22475541 * t * rh
+        - 0.00683783 * t * t
+        - 0.05481717 * rh * rh
+        + 0.00122874 * t * t * rh
+        + 0.00085282 * t * rh * rh
+        - 0.00000199 * t * t * rh * rh;
+
+    if rh < 13.0 && (80.0..=112.0).contains(&t) {
+        index -= (13.0 - rh) / 4.0 * ((17.0 - (t - 95.0).abs()) / 17.0).sqrt();
+    } else if rh > 85.0 && (80.0..=87.0).contains(&t) {
+        index += (rh - 85.0) / 10.0 * (87.0 - t) / 5.0;
+    }
+
+    Some((index - 32.0) * 5.0 / 9.0)
+}
+
+/// What cold air feels like in a wind of `wind_kmh`, with the formula used
+/// in North America and the UK. `None` when it's too warm or too calm
+pub fn wind_chill(celsius: f64, wind_kmh: f64) -> Option<f64> {
+    if celsius > WIND_CHILL_UP_TO || wind_kmh < WIND_CHILL_MIN_WIND {
+        return None;
+    }
+
+    let v = wind_kmh.powf(0.16);
+    Some(13.12 + 0.6215 * celsius - 11.37 * v + 0.3965 * celsius * v)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn round(value: f64) -> f64 {
+        (value * 10.0).round() / 10.0
+    }
+
+    #[test]
+    fn test_dew_point() {
+        assert_eq!(round(dew_point(20.0, 50)), 9.3);
+        assert_eq!(round(dew_point(25.0, 100)), 25.0);
+        assert!(dew_point(30.0, 0).is_finite());
+    }
+
+    #[test]
+    fn test_heat_index() {
+        assert_eq!(heat_index(25.0, 90), None);
+        // 90°F at 60% is 100°F on the NWS chart
+        assert_eq!(
+            heat_index(32.22, 60).map(|c| (c * 9.0 / 5.0 + 32.0).round()),
+            Some(100.0)
+        );
+    }
+
+    #[test]
+    fn test_wind_chill() {
+        assert_eq!(wind_chill(15.0, 30.0), None);
+        assert_eq!(wind_chill(-5.0, 3.0), None);
+        assert_eq!(wind_chill(-10.0, 30.0).map(round), Some(-19.5));
+    }
+}
@@ -0,0 +1,109 @@
+use crate::error::WeatherError;
+use crate::location::Location;
+use crate::units::Units;
+use serde::Deserialize;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Defaults read from `~/.config/weather_cli/config.toml`, the command line
+/// wins over all of them.
+///
+/// ```toml
+/// cities = ["London", "52.5,13.4"]
+/// units = "imperial"
+/// detailed = true
+/// api_key = "..."
+/// ```
+#[derive(Debug, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// One default city, same as a one-entry `cities`
+    pub city: Option<String>,
+    pub cities: Vec<String>,
+    pub units: Option<Units>,
+    pub detailed: bool,
+    /// Used when `OPENWEATHER_API_KEY` isn't set
+    pub api_key: Option<String>,
+}
+
+impl Config {
+    /// Where the config file is looked for
+    pub fn path() -> Option<PathBuf> {
+        Some(dirs::config_dir()?.join("weather_cli").join("config.toml"))
+    }
+
+    /// The config file, or the defaults when there is none
+    pub fn load() -> Result<Self, WeatherError> {
+        match Config::path() {
+            Some(path) => Config::load_from(&path),
+            None => Ok(Config::default()),
+        }
+    }
+
+    pub fn load_from(path: &Path) -> Result<Self, WeatherError> {
+        let invalid =
+            |reason: String| WeatherError::InvalidConfig(path.display().to_string(), reason);
+
+        match fs::read_to_string(path) {
+            Ok(text) => toml::from_str(&text).map_err(|e| invalid(e.message().to_string())),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Config::default()),
+            Err(e) => Err(invalid(e.to_string())),
+        }
+    }
+
+    /// The default places, `city` first
+    pub fn locations(&self) -> Result<Vec<Location>, WeatherError> {
+        self.city
+            .iter()
+            .chain(&self.cities)
+            .map(|city| {
+                city.parse().map_err(|reason| {
+                    let path = Config::path().unwrap_or_default();
+                    WeatherError::InvalidConfig(path.display().to_string(), reason)
+                })
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_config() {
+        let config: Config = toml::from_str(
+            r#"
+            city = "Lagos"
+            cities = ["London", "52.5,13.4"]
+            units = "imperial"
+            detailed = true
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config.units, Some(Units::Imperial));
+        assert!(config.detailed);
+        assert_eq!(config.api_key, None);
+        assert_eq!(
+            config.locations().unwrap(),
+            vec![
+                Location::City("Lagos".to_string()),
+                Location::City("London".to_string()),
+                Location::Coordinates {
+                    lat: 52.5,
+                    lon: 13.4
+                },
+            ]
+        );
+
+        assert_eq!(toml::from_str::<Config>("").unwrap(), Config::default());
+        assert!(toml::from_str::<Config>("unit = \"metric\"").is_err());
+    }
+
+    #[test]
+    fn test_load_missing_file() {
+        let path = std::env::temp_dir().join("weather_cli_no_such_config.toml");
+        assert_eq!(Config::load_from(&path).unwrap(), Config::default());
+    }
+}
@@ -5,14 +5,55 @@ pub enum WeatherError {
     #[error("City not found: {0}")]
     CityNotFound(String),
 
-    #[error("Invalid API key. Please check your OPENWEATHER_API_KEY environment variable")]
+    #[error(
+        "Invalid API key. Pass --api-key, set OPENWEATHER_API_KEY, add api_key to your config file or save one with `weather_cli auth set`"
+    )]
     InvalidApiKey,
 
     #[error("Network error: {0}")]
     NetworkError(#[from] reqwest::Error),
 
-    #[error("Invalid units '{0}'. Use: metric, imperial, or kelvin")]
-    InvalidUnits(String),
+    #[error("Unexpected response from the API: {0}")]
+    InvalidResponse(#[from] serde_json::Error),
+
+    #[error("Could not write the output: {0}")]
+    Output(String),
+
+    #[error("The API is unavailable ({0}), try again later")]
+    Unavailable(u16),
+
+    #[error("Too many requests to the API, try again {}", retry_in(.0))]
+    RateLimited(Option<u64>),
+
+    #[error("Nothing cached for {0}, it can't be shown offline")]
+    NotCached(String),
+
+    #[error("Invalid config file {0}: {1}")]
+    InvalidConfig(String, String),
+
+    #[error("Could not save {0}: {1}")]
+    SaveFailed(String, String),
+
+    #[error("{0} is not one of your favorites")]
+    NotFavorite(String),
+
+    #[error("Could not use the OS keyring: {0}")]
+    Keyring(String),
+
+    #[error("Could not show a notification: {0}")]
+    Notification(String),
+
+    #[error("Could not guess your location: {0}. Pass a city or --lat/--lon instead")]
+    AutoLocateFailed(String),
+
+    #[error("No city given. Pass a city or --lat/--lon, or drop --no-auto-locate")]
+    NoLocation,
+
+    #[error("No weather history for {0}, pick a date from {1} to {2}")]
+    DateOutOfRange(chrono::NaiveDate, chrono::NaiveDate, chrono::NaiveDate),
+
+    #[error("`{0}` is only available with --provider openweather")]
+    Unsupported(String),
 
     #[error("Invalid timestamp: {0}")]
     InvalidTimestamp(i64),
@@ -20,3 +61,11 @@ pub enum WeatherError {
     #[error("Unknown error occurred")]
     Unknown,
 }
+
+/// When a rate limited request can be tried again, as the API put it
+fn retry_in(seconds: &Option<u64>) -> String {
+    match seconds {
+        Some(seconds) => format!("in {}s", seconds),
+        None => "in a minute".to_string(),
+    }
+}
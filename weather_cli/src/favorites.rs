@@ -0,0 +1,148 @@
+use crate::error::WeatherError;
+use crate::location::Location;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// The cities saved with `favorites add`, in
+/// `~/.config/weather_cli/favorites.toml`. Running the CLI without a city
+/// shows all of them.
+#[derive(Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Favorites {
+    pub cities: Vec<String>,
+}
+
+impl Favorites {
+    /// Where the favorites are kept
+    pub fn path() -> Option<PathBuf> {
+        Some(
+            dirs::config_dir()?
+                .join("weather_cli")
+                .join("favorites.toml"),
+        )
+    }
+
+    /// The saved favorites, none when there is no file yet
+    pub fn load() -> Result<Self, WeatherError> {
+        match Favorites::path() {
+            Some(path) => Favorites::load_from(&path),
+            None => Ok(Favorites::default()),
+        }
+    }
+
+    pub fn load_from(path: &Path) -> Result<Self, WeatherError> {
+        let invalid =
+            |reason: String| WeatherError::InvalidConfig(path.display().to_string(), reason);
+
+        match fs::read_to_string(path) {
+            Ok(text) => toml::from_str(&text).map_err(|e| invalid(e.message().to_string())),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Favorites::default()),
+            Err(e) => Err(invalid(e.to_string())),
+        }
+    }
+
+    pub fn save(&self) -> Result<(), WeatherError> {
+        let path = Favorites::path().ok_or_else(|| {
+            WeatherError::SaveFailed("favorites".to_string(), "no config directory".to_string())
+        })?;
+        self.save_to(&path)
+    }
+
+    pub fn save_to(&self, path: &Path) -> Result<(), WeatherError> {
+        let failed = |reason: String| WeatherError::SaveFailed(path.display().to_string(), reason);
+
+        let text = toml::to_string(self).map_err(|e| failed(e.to_string()))?;
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).map_err(|e| failed(e.to_string()))?;
+        }
+        fs::write(path, text).map_err(|e| failed(e.to_string()))
+    }
+
+    /// Add a place at the end, `false` if it was already there
+    pub fn add(&mut self, location: &Location) -> bool {
+        if self.position(location).is_some() {
+            return false;
+        }
+
+        self.cities.push(location.to_string());
+        true
+    }
+
+    pub fn remove(&mut self, location: &Location) -> Result<(), WeatherError> {
+        let index = self
+            .position(location)
+            .ok_or_else(|| WeatherError::NotFavorite(location.to_string()))?;
+        self.cities.remove(index);
+        Ok(())
+    }
+
+    /// The favorites as places to fetch, in order
+    pub fn locations(&self) -> Result<Vec<Location>, WeatherError> {
+        self.cities
+            .iter()
+            .map(|city| {
+                city.parse().map_err(|reason| {
+                    let path = Favorites::path().unwrap_or_default();
+                    WeatherError::InvalidConfig(path.display().to_string(), reason)
+                })
+            })
+            .collect()
+    }
+
+    /// Where a place is in the list, city names matched case-insensitively
+    fn position(&self, location: &Location) -> Option<usize> {
+        let wanted = location.to_string().to_lowercase();
+        self.cities
+            .iter()
+            .position(|city| city.to_lowercase() == wanted)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn city(name: &str) -> Location {
+        Location::City(name.to_string())
+    }
+
+    #[test]
+    fn test_add_remove() {
+        let mut favorites = Favorites::default();
+        assert!(favorites.add(&city("London")));
+        assert!(favorites.add(&"52.5,13.4".parse().unwrap()));
+        assert!(!favorites.add(&city("london")));
+        assert_eq!(favorites.cities, vec!["London", "52.5,13.4"]);
+
+        favorites.remove(&city("LONDON")).unwrap();
+        assert_eq!(favorites.cities, vec!["52.5,13.4"]);
+        assert!(matches!(
+            favorites.remove(&city("Paris")),
+            Err(WeatherError::NotFavorite(_))
+        ));
+    }
+
+    #[test]
+    fn test_save_and_load() {
+        let path = std::env::temp_dir()
+            .join(format!("weather_cli_favorites_{}", std::process::id()))
+            .join("favorites.toml");
+        assert_eq!(Favorites::load_from(&path).unwrap(), Favorites::default());
+
+        let mut favorites = Favorites::default();
+        favorites.add(&city("Lagos"));
+        favorites.add(&city("New York"));
+        favorites.save_to(&path).unwrap();
+
+        let loaded = Favorites::load_from(&path).unwrap();
+        assert_eq!(loaded, favorites);
+        assert_eq!(
+            loaded.locations().unwrap(),
+            vec![city("Lagos"), city("New York")]
+        );
+
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+}
@@ -0,0 +1,219 @@
+use crate::error::WeatherError;
+use crate::units::Units;
+use crate::view::{DayView, ForecastView, convert_temp};
+use chrono::{DateTime, NaiveDate};
+use serde::Deserialize;
+
+/// Response of the 5 day / 3 hour forecast endpoint.
+#[derive(Debug, Deserialize)]
+pub struct ForecastResponse {
+    pub list: Vec<ForecastEntry>,
+    pub city: ForecastCity,
+}
+
+/// One 3-hour step of the forecast.
+#[derive(Debug, Deserialize)]
+pub struct ForecastEntry {
+    pub dt: i64,
+    pub main: ForecastMain,
+    pub weather: Vec<ForecastCondition>,
+    /// Probability of precipitation, from 0 to 1
+    #[serde(default)]
+    pub pop: f64,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ForecastMain {
+    pub temp_min: f64,
+    pub temp_max: f64,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ForecastCondition {
+    pub description: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ForecastCity {
+    pub name: String,
+    pub country: Option<String>,
+    /// Shift in seconds from UTC
+    #[serde(default)]
+    pub timezone: i64,
+}
+
+/// The 3-hour entries of one local day folded together.
+#[derive(Debug, PartialEq)]
+pub struct DailyForecast {
+    pub date: NaiveDate,
+    /// Lowest temperature in Kelvin
+    pub min: f64,
+    /// Highest temperature in Kelvin
+    pub max: f64,
+    /// The most frequent description of the day
+    pub conditions: String,
+    /// Highest probability of precipitation, in percent
+    pub precipitation: u8,
+}
+
+impl ForecastResponse {
+    /// Group the entries by day in the city's local time, in order.
+    pub fn daily(&self) -> Result<Vec<DailyForecast>, WeatherError> {
+        let mut days: Vec<(NaiveDate, Vec<&ForecastEntry>)> = Vec::new();
+
+        for entry in &self.list {
+            let timestamp = entry.dt + self.city.timezone;
+            let date = DateTime::from_timestamp(timestamp, 0)
+                .ok_or(WeatherError::InvalidTimestamp(timestamp))?
+                .date_naive();
+
+            match days.last_mut() {
+                Some((day, entries)) if *day == date => entries.push(entry),
+                _ => days.push((date, vec![entry])),
+            }
+        }
+
+        Ok(days
+            .into_iter()
+            .map(|(date, entries)| summarize(date, &entries))
+            .collect())
+    }
+
+    /// The first `days` days, converted to `units`
+    pub fn view(&self, units: Units, days: usize) -> Result<ForecastView, WeatherError> {
+        let days = self
+            .daily()?
+            .into_iter()
+            .take(days)
+            .map(|day| DayView {
+                date: day.date,
+                min: convert_temp(day.min, units).0,
+                max: convert_temp(day.max, units).0,
+                conditions: day.conditions,
+                precipitation: day.precipitation,
+            })
+            .collect();
+
+        Ok(ForecastView {
+            city: self.city.name.clone(),
+            country: self.city.country.clone(),
+            temperature_unit: units.temperature_unit(),
+            days,
+        })
+    }
+
+    /// Print the first `days` days of the forecast
+    pub fn display(&self, units: Units, days: usize) -> Result<(), WeatherError> {
+        print!("{}", self.view(units, days)?.to_text());
+        Ok(())
+    }
+}
+
+fn summarize(date: NaiveDate, entries: &[&ForecastEntry]) -> DailyForecast {
+    let min = entries
+        .iter()
+        .map(|e| e.main.temp_min)
+        .fold(f64::INFINITY, f64::min);
+    let max = entries
+        .iter()
+        .map(|e| e.main.temp_max)
+        .fold(f64::NEG_INFINITY, f64::max);
+    let pop = entries.iter().map(|e| e.pop).fold(0.0, f64::max);
+
+    let conditions = most_common(
+        entries
+            .iter()
+            .filter_map(|e| e.weather.first())
+            .map(|c| c.description.as_str()),
+    );
+
+    DailyForecast {
+        date,
+        min,
+        max,
+        conditions,
+        precipitation: (pop * 100.0).round() as u8,
+    }
+}
+
+/// The description seen the most, the first one seen wins a tie
+pub fn most_common<'a>(descriptions: impl Iterator<Item = &'a str>) -> String {
+    let mut counts: Vec<(&str, usize)> = Vec::new();
+    for description in descriptions {
+        match counts.iter_mut().find(|(seen, _)| *seen == description) {
+            Some((_, count)) => *count += 1,
+            None => counts.push((description, 1)),
+        }
+    }
+
+    counts
+        .iter()
+        .rev()
+        .max_by_key(|(_, count)| *count)
+        .map(|(description, _)| description.to_string())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(dt: i64, min: f64, max: f64, description: &str, pop: f64) -> String {
+        format!(
+            r#"{{"dt": {}, "main": {{"temp": {}, "temp_min": {}, "temp_max": {}}},
+                "weather": [{{"main": "", "description": "{}"}}], "pop": {}}}"#,
+            dt, min, min, max, description, pop
+        )
+    }
+
+    #[test]
+    fn test_daily_groups_by_local_day() {
+        // 2024-03-01 21:00 UTC onwards, every 3 hours, in a city 2 hours ahead
+        let start = 1_709_326_800;
+        let entries = [
+            entry(start, 280.0, 281.0, "clear sky", 0.0),
+            entry(start + 3 * 3600, 278.0, 279.5, "light rain", 0.35),
+            entry(start + 6 * 3600, 276.5, 278.0, "light rain", 0.6),
+            entry(start + 9 * 3600, 279.0, 283.0, "broken clouds", 0.1),
+        ];
+        let json = format!(
+            r#"{{"list": [{}], "city": {{"name": "Cairo", "country": "EG", "timezone": 7200}}}}"#,
+            entries.join(",")
+        );
+        let forecast: ForecastResponse = serde_json::from_str(&json).unwrap();
+
+        let days = forecast.daily().unwrap();
+        assert_eq!(days.len(), 2);
+        assert_eq!(days[0].date, NaiveDate::from_ymd_opt(2024, 3, 1).unwrap());
+        assert_eq!(days[0].conditions, "clear sky");
+        assert_eq!(
+            days[1],
+            DailyForecast {
+                date: NaiveDate::from_ymd_opt(2024, 3, 2).unwrap(),
+                min: 276.5,
+                max: 283.0,
+                conditions: "light rain".to_string(),
+                precipitation: 60,
+            }
+        );
+    }
+
+    #[test]
+    fn test_view() {
+        let json = format!(
+            r#"{{"list": [{}], "city": {{"name": "Oslo", "timezone": 3600}}}}"#,
+            [
+                entry(1_709_326_800, 270.0, 271.0, "snow", 0.9),
+                entry(1_709_337_600, 272.15, 273.15, "snow", 0.4),
+            ]
+            .join(",")
+        );
+        let forecast: ForecastResponse = serde_json::from_str(&json).unwrap();
+
+        let view = forecast.view(Units::Imperial, 5).unwrap();
+        assert_eq!(view.temperature_unit, "°F");
+        assert_eq!(view.days.len(), 2);
+        assert_eq!(view.days[1].max, 32.0);
+        assert_eq!(forecast.view(Units::Metric, 1).unwrap().days.len(), 1);
+    }
+}
@@ -0,0 +1,95 @@
+use crate::error::WeatherError;
+use crate::location::Location;
+use serde::Deserialize;
+
+const IPINFO_URL: &str = "https://ipinfo.io/json";
+
+/// Where ipinfo thinks the machine's public IP address is.
+#[derive(Debug, Deserialize)]
+pub struct IpLocation {
+    pub city: Option<String>,
+    pub region: Option<String>,
+    pub country: Option<String>,
+    /// Latitude and longitude as `"52.5200,13.4050"`
+    pub loc: Option<String>,
+}
+
+impl IpLocation {
+    /// The coordinates when given, they are more precise than the city name
+    pub fn location(&self) -> Option<Location> {
+        if let Some(Ok(location @ Location::Coordinates { .. })) =
+            self.loc.as_deref().map(str::parse::<Location>)
+        {
+            return Some(location);
+        }
+
+        self.city
+            .clone()
+            .filter(|c| !c.is_empty())
+            .map(Location::City)
+    }
+
+    /// A readable name for the place, like "Berlin, Berlin, DE"
+    pub fn place(&self) -> String {
+        let parts: Vec<&str> = [&self.city, &self.region, &self.country]
+            .into_iter()
+            .filter_map(|p| p.as_deref())
+            .filter(|p| !p.is_empty())
+            .collect();
+
+        if parts.is_empty() {
+            "an unknown place".to_string()
+        } else {
+            parts.join(", ")
+        }
+    }
+}
+
+/// Guess the location from the public IP address
+pub async fn locate() -> Result<IpLocation, WeatherError> {
+    let failed = |e: reqwest::Error| WeatherError::AutoLocateFailed(e.to_string());
+
+    let response = reqwest::get(IPINFO_URL).await.map_err(failed)?;
+    if !response.status().is_success() {
+        return Err(WeatherError::AutoLocateFailed(format!(
+            "ipinfo.io answered {}",
+            response.status()
+        )));
+    }
+
+    response.json().await.map_err(failed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ip_location() {
+        let found: IpLocation = serde_json::from_str(
+            r#"{"ip": "203.0.113.7", "city": "Berlin", "region": "Berlin",
+                "country": "DE", "loc": "52.5244,13.4105", "timezone": "Europe/Berlin"}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            found.location(),
+            Some(Location::Coordinates {
+                lat: 52.5244,
+                lon: 13.4105
+            })
+        );
+        assert_eq!(found.place(), "Berlin, Berlin, DE");
+
+        let city_only: IpLocation =
+            serde_json::from_str(r#"{"city": "Lagos", "country": "NG"}"#).unwrap();
+        assert_eq!(
+            city_only.location(),
+            Some(Location::City("Lagos".to_string()))
+        );
+        assert_eq!(city_only.place(), "Lagos, NG");
+
+        let nothing: IpLocation = serde_json::from_str(r#"{"bogon": true}"#).unwrap();
+        assert_eq!(nothing.location(), None);
+        assert_eq!(nothing.place(), "an unknown place");
+    }
+}
@@ -0,0 +1,173 @@
+use crate::error::WeatherError;
+use crate::forecast::most_common;
+use crate::model::WeatherCondition;
+use crate::units::Units;
+use crate::view::{HistoryView, convert_temp};
+use chrono::{Days, NaiveDate};
+use serde::Deserialize;
+
+/// How many days past today the timemachine endpoint answers for.
+pub const MAX_DAYS_AHEAD: u64 = 4;
+
+/// How many moments of the day are asked for, one every 3 hours like the
+/// forecast.
+pub const SAMPLES: i64 = 8;
+
+/// Response of the One Call timemachine endpoint, the weather at one moment.
+#[derive(Debug, Deserialize)]
+pub struct TimeMachineResponse {
+    /// Shift in seconds from UTC
+    #[serde(default)]
+    pub timezone_offset: i64,
+    pub data: Vec<TimeMachinePoint>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TimeMachinePoint {
+    pub dt: i64,
+    /// In Kelvin
+    pub temp: f64,
+    pub weather: Vec<WeatherCondition>,
+}
+
+/// The weather of one past (or nearly here) day.
+#[derive(Debug, PartialEq)]
+pub struct HistoryDay {
+    pub city: String,
+    pub country: Option<String>,
+    pub date: NaiveDate,
+    /// Lowest temperature in Kelvin
+    pub min: f64,
+    /// Highest temperature in Kelvin
+    pub max: f64,
+    /// The most frequent description of the day
+    pub conditions: String,
+}
+
+/// The first day with data
+pub fn earliest_date() -> NaiveDate {
+    NaiveDate::from_ymd_opt(1979, 1, 1).expect("valid date")
+}
+
+/// Make sure the API has data for `date`
+pub fn check_date(date: NaiveDate, today: NaiveDate) -> Result<(), WeatherError> {
+    let latest = today + Days::new(MAX_DAYS_AHEAD);
+    if date < earliest_date() || date > latest {
+        return Err(WeatherError::DateOutOfRange(date, earliest_date(), latest));
+    }
+
+    Ok(())
+}
+
+/// The Unix timestamps to ask for: every 3 hours of the local day, for a
+/// place `timezone_offset` seconds ahead of UTC
+pub fn sample_times(date: NaiveDate, timezone_offset: i64) -> Vec<i64> {
+    let midnight = date.and_time(chrono::NaiveTime::MIN).and_utc().timestamp() - timezone_offset;
+    (0..SAMPLES).map(|i| midnight + i * 3 * 3600).collect()
+}
+
+impl HistoryDay {
+    pub fn new(
+        city: String,
+        country: Option<String>,
+        date: NaiveDate,
+        points: &[TimeMachinePoint],
+    ) -> Self {
+        let min = points.iter().map(|p| p.temp).fold(f64::INFINITY, f64::min);
+        let max = points
+            .iter()
+            .map(|p| p.temp)
+            .fold(f64::NEG_INFINITY, f64::max);
+        let conditions = most_common(
+            points
+                .iter()
+                .filter_map(|p| p.weather.first())
+                .map(|c| c.description.as_str()),
+        );
+
+        HistoryDay {
+            city,
+            country,
+            date,
+            min,
+            max,
+            conditions,
+        }
+    }
+
+    /// The day converted to `units`
+    pub fn view(&self, units: Units) -> HistoryView {
+        let (min, temperature_unit) = convert_temp(self.min, units);
+        HistoryView {
+            city: self.city.clone(),
+            country: self.country.clone(),
+            date: self.date,
+            min,
+            max: convert_temp(self.max, units).0,
+            temperature_unit,
+            conditions: self.conditions.clone(),
+        }
+    }
+
+    pub fn display(&self, units: Units) {
+        print!("{}", self.view(units).to_text());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(y: i32, m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, d).unwrap()
+    }
+
+    #[test]
+    fn test_check_date() {
+        let today = date(2025, 10, 16);
+        assert!(check_date(date(1979, 1, 1), today).is_ok());
+        assert!(check_date(date(2025, 10, 20), today).is_ok());
+        assert!(matches!(
+            check_date(date(2025, 10, 21), today),
+            Err(WeatherError::DateOutOfRange(_, _, latest)) if latest == date(2025, 10, 20)
+        ));
+        assert!(check_date(date(1978, 12, 31), today).is_err());
+    }
+
+    #[test]
+    fn test_sample_times() {
+        // 2024-03-01 00:00 UTC
+        let times = sample_times(date(2024, 3, 1), 0);
+        assert_eq!(times.len(), 8);
+        assert_eq!(times[0], 1_709_251_200);
+        assert_eq!(times[7], 1_709_251_200 + 21 * 3600);
+
+        // local midnight is earlier in UTC for a place ahead of it
+        assert_eq!(sample_times(date(2024, 3, 1), 3600)[0], 1_709_247_600);
+    }
+
+    #[test]
+    fn test_history_day() {
+        // two moments of the day, each from its own response
+        let points: Vec<TimeMachinePoint> = [
+            r#"{"lat": 51.5, "lon": -0.1, "timezone": "Europe/London", "timezone_offset": 0,
+                "data": [{"dt": 1709251200, "temp": 278.4, "humidity": 90,
+                          "weather": [{"main": "Rain", "description": "light rain"}]}]}"#,
+            r#"{"data": [{"dt": 1709272800, "temp": 284.9,
+                          "weather": [{"main": "Clouds", "description": "few clouds"}]}]}"#,
+        ]
+        .iter()
+        .flat_map(|json| {
+            serde_json::from_str::<TimeMachineResponse>(json)
+                .unwrap()
+                .data
+        })
+        .collect();
+
+        let day = HistoryDay::new("London".to_string(), None, date(2024, 3, 1), &points);
+        assert_eq!(day.min, 278.4);
+        assert_eq!(day.max, 284.9);
+        assert_eq!(day.conditions, "light rain");
+        assert_eq!(day.view(Units::Metric).max, 11.8);
+    }
+}
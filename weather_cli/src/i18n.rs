@@ -0,0 +1,219 @@
+use std::sync::OnceLock;
+
+/// The words around the values in the text output. The conditions
+/// themselves come translated from the API.
+#[derive(Debug, PartialEq)]
+pub struct Labels {
+    pub weather_in: &'static str,
+    pub temperature: &'static str,
+    pub feels_like: &'static str,
+    pub conditions: &'static str,
+    pub humidity: &'static str,
+    pub additional_details: &'static str,
+    pub country: &'static str,
+    pub pressure: &'static str,
+    pub visibility: &'static str,
+    pub wind: &'static str,
+    pub speed: &'static str,
+    pub cloudiness: &'static str,
+    pub sun_times: &'static str,
+    pub sunrise: &'static str,
+    pub sunset: &'static str,
+    pub comfort: &'static str,
+    pub dew_point: &'static str,
+    pub heat_index: &'static str,
+    pub wind_chill: &'static str,
+    pub city: &'static str,
+    pub temp: &'static str,
+    pub forecast_for: &'static str,
+    pub precipitation: &'static str,
+    /// Between the place and the date of a past day
+    pub on: &'static str,
+    /// Between the two ends of a range
+    pub to: &'static str,
+    pub air_quality_in: &'static str,
+    pub alerts_for: &'static str,
+    pub no_alerts: &'static str,
+    pub from: &'static str,
+    pub next_hours: &'static str,
+    pub active_alerts: &'static str,
+    pub until: &'static str,
+}
+
+pub const EN: Labels = Labels {
+    weather_in: "Weather in",
+    temperature: "Temperature",
+    feels_like: "Feels like",
+    conditions: "Conditions",
+    humidity: "Humidity",
+    additional_details: "Additional Details",
+    country: "Country",
+    pressure: "Pressure",
+    visibility: "Visibility",
+    wind: "Wind",
+    speed: "Speed",
+    cloudiness: "Cloudiness",
+    sun_times: "Sun Times",
+    sunrise: "Sunrise",
+    sunset: "Sunset",
+    comfort: "Comfort",
+    dew_point: "Dew point",
+    heat_index: "Heat index",
+    wind_chill: "Wind chill",
+    city: "City",
+    temp: "Temp",
+    forecast_for: "Forecast for",
+    precipitation: "precipitation",
+    on: "on",
+    to: "to",
+    air_quality_in: "Air quality in",
+    alerts_for: "Weather alerts for",
+    no_alerts: "No active alerts",
+    from: "From",
+    next_hours: "Next hours",
+    active_alerts: "Active alerts",
+    until: "until",
+};
+
+const DE: Labels = Labels {
+    weather_in: "Wetter in",
+    temperature: "Temperatur",
+    feels_like: "Gefühlt",
+    conditions: "Wetterlage",
+    humidity: "Luftfeuchtigkeit",
+    additional_details: "Weitere Details",
+    country: "Land",
+    pressure: "Luftdruck",
+    visibility: "Sichtweite",
+    wind: "Wind",
+    speed: "Geschwindigkeit",
+    cloudiness: "Bewölkung",
+    sun_times: "Sonnenzeiten",
+    sunrise: "Sonnenaufgang",
+    sunset: "Sonnenuntergang",
+    comfort: "Empfinden",
+    dew_point: "Taupunkt",
+    heat_index: "Hitzeindex",
+    wind_chill: "Windkühle",
+    city: "Stadt",
+    temp: "Temp.",
+    forecast_for: "Vorhersage für",
+    precipitation: "Niederschlag",
+    on: "am",
+    to: "bis",
+    air_quality_in: "Luftqualität in",
+    alerts_for: "Wetterwarnungen für",
+    no_alerts: "Keine aktiven Warnungen",
+    from: "Von",
+    next_hours: "Nächste Stunden",
+    active_alerts: "Aktive Warnungen",
+    until: "bis",
+};
+
+const ES: Labels = Labels {
+    weather_in: "Tiempo en",
+    temperature: "Temperatura",
+    feels_like: "Sensación térmica",
+    conditions: "Condiciones",
+    humidity: "Humedad",
+    additional_details: "Más detalles",
+    country: "País",
+    pressure: "Presión",
+    visibility: "Visibilidad",
+    wind: "Viento",
+    speed: "Velocidad",
+    cloudiness: "Nubosidad",
+    sun_times: "Sol",
+    sunrise: "Amanecer",
+    sunset: "Atardecer",
+    comfort: "Confort",
+    dew_point: "Punto de rocío",
+    heat_index: "Índice de calor",
+    wind_chill: "Sensación por viento",
+    city: "Ciudad",
+    temp: "Temp.",
+    forecast_for: "Pronóstico para",
+    precipitation: "precipitación",
+    on: "el",
+    to: "a",
+    air_quality_in: "Calidad del aire en",
+    alerts_for: "Alertas meteorológicas para",
+    no_alerts: "No hay alertas activas",
+    from: "Desde",
+    next_hours: "Próximas horas",
+    active_alerts: "Alertas activas",
+    until: "hasta",
+};
+
+const FR: Labels = Labels {
+    weather_in: "Météo à",
+    temperature: "Température",
+    feels_like: "Ressenti",
+    conditions: "Conditions",
+    humidity: "Humidité",
+    additional_details: "Plus de détails",
+    country: "Pays",
+    pressure: "Pression",
+    visibility: "Visibilité",
+    wind: "Vent",
+    speed: "Vitesse",
+    cloudiness: "Nébulosité",
+    sun_times: "Soleil",
+    sunrise: "Lever",
+    sunset: "Coucher",
+    comfort: "Confort",
+    dew_point: "Point de rosée",
+    heat_index: "Indice de chaleur",
+    wind_chill: "Refroidissement éolien",
+    city: "Ville",
+    temp: "Temp.",
+    forecast_for: "Prévisions pour",
+    precipitation: "précipitations",
+    on: "le",
+    to: "à",
+    air_quality_in: "Qualité de l'air à",
+    alerts_for: "Alertes météo pour",
+    no_alerts: "Aucune alerte en cours",
+    from: "Du",
+    next_hours: "Prochaines heures",
+    active_alerts: "Alertes en cours",
+    until: "jusqu'à",
+};
+
+static LABELS: OnceLock<&'static Labels> = OnceLock::new();
+
+/// Use the labels of `lang` for everything printed after, English until
+/// then
+pub fn set_language(lang: &str) {
+    LABELS.set(labels_for(lang)).ok();
+}
+
+pub fn labels() -> &'static Labels {
+    LABELS.get().copied().unwrap_or(&EN)
+}
+
+/// The labels for a language code like `de` or `pt_br`, English for the
+/// languages without a translation
+pub fn labels_for(lang: &str) -> &'static Labels {
+    let primary = lang.split(['_', '-']).next().unwrap_or_default();
+    match primary.to_lowercase().as_str() {
+        "de" => &DE,
+        "es" => &ES,
+        "fr" => &FR,
+        _ => &EN,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_labels_for() {
+        assert_eq!(labels_for("de").temperature, "Temperatur");
+        assert_eq!(labels_for("FR").weather_in, "Météo à");
+        assert_eq!(labels_for("es-MX").humidity, "Humedad");
+        assert_eq!(labels_for("pt_br"), &EN);
+        assert_eq!(labels_for(""), &EN);
+    }
+}
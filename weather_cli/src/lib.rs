@@ -1,3 +1,25 @@
+pub mod air;
+pub mod alerts;
+pub mod auth;
+pub mod cache;
 pub mod cli;
+pub mod comfort;
+pub mod config;
 pub mod error;
+pub mod favorites;
+pub mod forecast;
+pub mod geolocate;
+pub mod history;
+pub mod i18n;
+pub mod location;
 pub mod model;
+pub mod notify;
+pub mod onecall;
+pub mod open_meteo;
+pub mod openweather;
+pub mod output;
+pub mod provider;
+pub mod retry;
+pub mod style;
+pub mod units;
+pub mod view;
@@ -0,0 +1,126 @@
+use serde::Deserialize;
+use std::fmt;
+use std::str::FromStr;
+
+/// Where to fetch the weather for: a city name, or coordinates for places
+/// whose name is ambiguous or unknown to the API.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Location {
+    City(String),
+    Coordinates { lat: f64, lon: f64 },
+}
+
+impl Location {
+    pub fn coordinates(lat: f64, lon: f64) -> Result<Self, String> {
+        Ok(Location::Coordinates {
+            lat: check_latitude(lat)?,
+            lon: check_longitude(lon)?,
+        })
+    }
+
+    /// The query string parameters selecting this location
+    pub fn query(&self) -> String {
+        match self {
+            Location::City(name) => format!("q={}", name),
+            Location::Coordinates { lat, lon } => format!("lat={}&lon={}", lat, lon),
+        }
+    }
+}
+
+/// A match of the geocoding endpoint.
+#[derive(Debug, Clone, Deserialize)]
+pub struct GeoPlace {
+    pub name: String,
+    pub lat: f64,
+    pub lon: f64,
+    pub country: Option<String>,
+}
+
+pub fn check_latitude(lat: f64) -> Result<f64, String> {
+    if (-90.0..=90.0).contains(&lat) {
+        Ok(lat)
+    } else {
+        Err(format!("latitude {} is not between -90 and 90", lat))
+    }
+}
+
+pub fn check_longitude(lon: f64) -> Result<f64, String> {
+    if (-180.0..=180.0).contains(&lon) {
+        Ok(lon)
+    } else {
+        Err(format!("longitude {} is not between -180 and 180", lon))
+    }
+}
+
+impl FromStr for Location {
+    type Err = String;
+
+    /// `"52.5,13.4"` is read as latitude and longitude, anything else as a
+    /// city name.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        if s.is_empty() {
+            return Err("the city name is empty".to_string());
+        }
+
+        if let Some((lat, lon)) = s.split_once(',')
+            && let (Ok(lat), Ok(lon)) = (lat.trim().parse::<f64>(), lon.trim().parse::<f64>())
+        {
+            return Location::coordinates(lat, lon);
+        }
+
+        Ok(Location::City(s.to_string()))
+    }
+}
+
+impl fmt::Display for Location {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Location::City(name) => write!(f, "{}", name),
+            Location::Coordinates { lat, lon } => write!(f, "{},{}", lat, lon),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_location() {
+        assert_eq!(
+            "52.5,13.4".parse(),
+            Ok(Location::Coordinates {
+                lat: 52.5,
+                lon: 13.4
+            })
+        );
+        assert_eq!(
+            " -33.9, 18.4 ".parse(),
+            Ok(Location::Coordinates {
+                lat: -33.9,
+                lon: 18.4
+            })
+        );
+        assert_eq!(
+            "Paris, FR".parse(),
+            Ok(Location::City("Paris, FR".to_string()))
+        );
+        assert_eq!(
+            "New York".parse(),
+            Ok(Location::City("New York".to_string()))
+        );
+        assert!("91,0".parse::<Location>().is_err());
+        assert!("0,181".parse::<Location>().is_err());
+        assert!("  ".parse::<Location>().is_err());
+    }
+
+    #[test]
+    fn test_query() {
+        assert_eq!(Location::City("London".to_string()).query(), "q=London");
+        assert_eq!(
+            Location::coordinates(52.5, -0.1).unwrap().query(),
+            "lat=52.5&lon=-0.1"
+        );
+    }
+}
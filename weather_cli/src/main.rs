@@ -1,65 +1,449 @@
 use anyhow::Result;
-use clap::Parser;
-use weather_cli::cli::Cli;
+use chrono::{Local, NaiveDate};
+use clap::{CommandFactory, FromArgMatches};
+use std::io::{self, IsTerminal};
+use std::process::ExitCode;
+use std::time::Duration;
+use weather_cli::alerts::ALERT_EXIT_CODE;
+use weather_cli::auth;
+use weather_cli::cli::{AuthAction, Cli, Command, FavoritesAction, OutputFormat};
+use weather_cli::config::Config;
 use weather_cli::error::WeatherError;
-use weather_cli::model::WeatherResponse;
+use weather_cli::favorites::Favorites;
+use weather_cli::geolocate;
+use weather_cli::history;
+use weather_cli::i18n;
+use weather_cli::location::Location;
+use weather_cli::notify::Watcher;
+use weather_cli::open_meteo::OpenMeteo;
+use weather_cli::openweather::OpenWeather;
+use weather_cli::output;
+use weather_cli::provider::{Provider, ProviderKind, WeatherProvider};
+use weather_cli::style;
+use weather_cli::view::{self, WeatherView};
+
+/// Clears the terminal and moves the cursor to the top left.
+const CLEAR_SCREEN: &str = "\x1b[2J\x1b[H";
 
 #[tokio::main]
-async fn main() -> Result<()> {
-    let cli = Cli::parse();
+async fn main() -> Result<ExitCode> {
+    let matches = Cli::command().get_matches();
+    let mut cli = Cli::from_arg_matches(&matches)?;
+    let config = Config::load()?;
+    cli.apply_favorites(&Favorites::load()?)?;
+    cli.apply_config(&config, &matches)?;
+
+    // favorites need neither the network nor an API key
+    if let Some(Command::Favorites { action }) = &cli.command {
+        favorites(action)?;
+        return Ok(ExitCode::SUCCESS);
+    }
+    if let Some(Command::Auth { action }) = &cli.command {
+        auth(action)?;
+        return Ok(ExitCode::SUCCESS);
+    }
 
-    validate_units(&cli.units)?;
+    style::set_enabled(cli.use_color());
+    if let Some(lang) = &cli.lang {
+        i18n::set_language(lang);
+    }
 
     dotenv::dotenv().ok();
 
-    let api_key = std::env::var("OPENWEATHER_API_KEY").map_err(|_| WeatherError::InvalidApiKey)?;
+    let cache = cli.cache();
+    let retry = cli.retry();
+    let provider = match cli.provider {
+        ProviderKind::OpenWeather => {
+            let api_key = auth::api_key(cli.api_key.clone(), config.api_key)?;
+            Provider::OpenWeather(OpenWeather::new(api_key, cli.lang.clone(), cache, retry))
+        }
+        ProviderKind::OpenMeteo => {
+            Provider::OpenMeteo(OpenMeteo::new(cli.lang.clone(), cache, retry))
+        }
+    };
+
+    let mut exit_code = ExitCode::SUCCESS;
+    let report = match &cli.command {
+        None => match cli.watch {
+            Some(minutes) => return watch(&cli, &provider, minutes).await,
+            None => current(&cli, &provider).await?,
+        },
+        Some(Command::Forecast { city, days }) => {
+            forecast(&cli, &provider, city, *days as usize).await?
+        }
+        Some(Command::Air { city }) => air(&cli, provider.openweather("air")?, city).await?,
+        Some(Command::Alerts {
+            city,
+            fail_on_alert,
+        }) => {
+            let (report, active) = alerts(&cli, provider.openweather("alerts")?, city).await?;
+            if active && *fail_on_alert {
+                exit_code = ExitCode::from(ALERT_EXIT_CODE);
+            }
+            report
+        }
+        Some(Command::History { city, date }) => {
+            history(&cli, provider.openweather("history")?, city, *date).await?
+        }
+        Some(Command::Notify {
+            city,
+            interval,
+            alert_temp_below,
+            alert_temp_above,
+        }) => {
+            let watcher = Watcher::new(*alert_temp_below, *alert_temp_above);
+            return notify(&cli, &provider, city, *interval, watcher).await;
+        }
+        Some(Command::Favorites { .. } | Command::Auth { .. }) => String::new(),
+    };
+    emit(&cli, &report)?;
+
+    Ok(exit_code)
+}
+
+/// Print the report, or write it to the --out file
+fn emit(cli: &Cli, report: &str) -> Result<()> {
+    match &cli.out {
+        Some(path) => output::write_atomic(path, report)?,
+        None => print!("{}", report),
+    }
+
+    Ok(())
+}
+
+async fn current(cli: &Cli, provider: &Provider) -> Result<String> {
+    let locations = current_locations(cli).await?;
+    current_report(cli, provider, &locations).await
+}
+
+/// Clear the screen and show the current weather again every `minutes`,
+/// until interrupted. A failed refresh is reported and the next one tried
+async fn watch(cli: &Cli, provider: &Provider, minutes: u64) -> Result<ExitCode> {
+    let locations = current_locations(cli).await?;
+    let mut timer = tokio::time::interval(Duration::from_secs(minutes * 60));
+
+    loop {
+        timer.tick().await;
+        let report = current_report(cli, provider, &locations).await;
+        // a file is rewritten instead
+        if cli.out.is_none() {
+            print!("{}", CLEAR_SCREEN);
+        }
+        if let Err(e) = report.and_then(|report| emit(cli, &report)) {
+            eprintln!("Error: {}", e);
+        }
+        println!(
+            "\nLast updated at {}, every {} min (Ctrl+C to stop)",
+            Local::now().format("%H:%M:%S"),
+            minutes
+        );
+    }
+}
+
+/// Check the weather every `interval` until interrupted, and show a
+/// desktop notification for what changed. The alerts come with the weather
+/// from One Call, so it's still one request per check
+async fn notify(
+    cli: &Cli,
+    provider: &Provider,
+    city: &Option<Location>,
+    interval: Duration,
+    mut watcher: Watcher,
+) -> Result<ExitCode> {
+    if matches!(provider, Provider::OpenMeteo(_)) && !watcher.has_thresholds() {
+        return Err(WeatherError::Unsupported(
+            "notify without --alert-temp-below or --alert-temp-above".to_string(),
+        )
+        .into());
+    }
+
+    let location = pick_location(city, cli).await?;
+    let mut timer = tokio::time::interval(interval);
+    eprintln!(
+        "Checking the weather in {} every {} min (Ctrl+C to stop)",
+        location,
+        interval.as_secs() / 60
+    );
+
+    loop {
+        timer.tick().await;
+        let view = match provider {
+            Provider::OpenWeather(openweather) => openweather
+                .one_call(&location, cli.units)
+                .await
+                .and_then(|one_call| one_call.view(cli.units, 0)),
+            Provider::OpenMeteo(_) => provider
+                .current(&location, cli.units)
+                .await
+                .and_then(|weather| weather.view(cli.units)),
+        };
+
+        let view = match view {
+            Ok(view) => view,
+            Err(e) => {
+                eprintln!("Error: {}", e);
+                continue;
+            }
+        };
+        if cli.verbose {
+            eprintln!(
+                "{}: {:.1}{}, {}",
+                Local::now().format("%H:%M:%S"),
+                view.temperature,
+                view.temperature_unit,
+                view.conditions
+            );
+        }
+
+        for notice in watcher.check(&view) {
+            println!(
+                "[{}] {}: {}",
+                Local::now().format("%Y-%m-%d %H:%M"),
+                notice.summary,
+                notice.body.replace('\n', " ")
+            );
+            if let Err(e) = notice.send() {
+                eprintln!("Error: {}", e);
+            }
+        }
+    }
+}
+
+/// The places given, or the guessed one
+async fn current_locations(cli: &Cli) -> Result<Vec<Location>, WeatherError> {
+    let mut locations = cli.locations();
+    if locations.is_empty() {
+        locations.push(guess_location(cli.no_auto_locate || cli.offline).await?);
+    }
+
+    Ok(locations)
+}
+
+/// The current weather of every place, fetched concurrently
+async fn current_report(cli: &Cli, provider: &Provider, locations: &[Location]) -> Result<String> {
+    if cli.uses_one_call() {
+        let flag = if cli.hourly.is_some() {
+            "--hourly"
+        } else {
+            "--onecall"
+        };
+        provider.openweather(flag)?;
+    }
 
-    let cities = cli.city;
     let mut handles = Vec::new();
 
-    for city in cities {
-        let api_key_clone = api_key.clone();
+    for location in locations {
+        let provider = provider.clone();
+        let location = location.clone();
+        let units = cli.units;
+        let one_call = cli.uses_one_call();
+        let hours = cli.hourly.unwrap_or(0) as usize;
 
-        let handle = tokio::spawn(async move { fetch_weather(&city, &api_key_clone).await });
+        let handle = tokio::spawn(async move {
+            match &provider {
+                Provider::OpenWeather(openweather) if one_call => openweather
+                    .one_call(&location, units)
+                    .await?
+                    .view(units, hours),
+                _ => provider.current(&location, units).await?.view(units),
+            }
+        });
         handles.push(handle);
     }
 
+    let mut report = String::new();
+    let mut views = Vec::new();
     for handle in handles {
-        let weather = handle.await??;
+        let view = handle.await??;
 
         // Display based on flags
-        if cli.detailed {
-            weather.display_detailed(&cli.units);
-        } else {
-            weather.display(&cli.units);
+        match cli.output {
+            OutputFormat::Text if !cli.compare => report.push_str(&view.to_text(cli.detailed)),
+            // formatted once every city is in
+            OutputFormat::Text | OutputFormat::Json | OutputFormat::Csv => views.push(view),
+        }
+    }
+
+    report.push_str(&views_report(&views, cli.output)?);
+    Ok(report)
+}
+
+async fn forecast(
+    cli: &Cli,
+    provider: &Provider,
+    city: &Option<Location>,
+    days: usize,
+) -> Result<String> {
+    let location = pick_location(city, cli).await?;
+    let forecast = if cli.onecall {
+        let openweather = provider.openweather("--onecall")?;
+        openweather
+            .one_call(&location, cli.units)
+            .await?
+            .forecast(cli.units, days)?
+    } else {
+        provider
+            .forecast(&location, cli.units)
+            .await?
+            .view(cli.units, days)?
+    };
+    Ok(match cli.output {
+        OutputFormat::Text => forecast.to_text(),
+        OutputFormat::Json => json(&forecast)?,
+        OutputFormat::Csv => view::forecast_csv(&forecast)?,
+    })
+}
+
+async fn air(cli: &Cli, provider: &OpenWeather, city: &Option<Location>) -> Result<String> {
+    let location = pick_location(city, cli).await?;
+    let air = provider.air(&location).await?.view();
+    Ok(match cli.output {
+        OutputFormat::Text => air.to_text(),
+        OutputFormat::Json => json(&air)?,
+        OutputFormat::Csv => view::air_csv(&air)?,
+    })
+}
+
+/// The alerts, and `true` if there are any
+async fn alerts(
+    cli: &Cli,
+    provider: &OpenWeather,
+    city: &Option<Location>,
+) -> Result<(String, bool)> {
+    let location = pick_location(city, cli).await?;
+    let alerts = provider.alerts(&location).await?.view()?;
+    let report = match cli.output {
+        OutputFormat::Text => alerts.to_text(),
+        OutputFormat::Json => json(&alerts)?,
+        OutputFormat::Csv => view::alerts_csv(&alerts)?,
+    };
+
+    Ok((report, !alerts.alerts.is_empty()))
+}
+
+async fn history(
+    cli: &Cli,
+    provider: &OpenWeather,
+    city: &Option<Location>,
+    date: NaiveDate,
+) -> Result<String> {
+    history::check_date(date, chrono::Local::now().date_naive())?;
+    let location = pick_location(city, cli).await?;
+    let day = provider
+        .history(&location, date, cli.units)
+        .await?
+        .view(cli.units);
+    Ok(match cli.output {
+        OutputFormat::Text => day.to_text(),
+        OutputFormat::Json => json(&day)?,
+        OutputFormat::Csv => view::history_csv(&day)?,
+    })
+}
+
+fn favorites(action: &FavoritesAction) -> Result<()> {
+    let mut favorites = Favorites::load()?;
+    match action {
+        FavoritesAction::Add { cities } => {
+            for city in cities {
+                if favorites.add(city) {
+                    println!("Added {}", city);
+                } else {
+                    println!("{} is already a favorite", city);
+                }
+            }
+            favorites.save()?;
+        }
+        FavoritesAction::Remove { cities } => {
+            for city in cities {
+                favorites.remove(city)?;
+                println!("Removed {}", city);
+            }
+            favorites.save()?;
+        }
+        FavoritesAction::List if favorites.cities.is_empty() => {
+            println!("No favorites yet, add some with `weather_cli favorites add <CITY>`")
+        }
+        FavoritesAction::List => {
+            for city in &favorites.cities {
+                println!("{}", city);
+            }
         }
     }
 
     Ok(())
 }
 
-fn validate_units(units: &str) -> Result<(), WeatherError> {
-    match units.to_lowercase().as_str() {
-        "metric" | "imperial" | "kelvin" => Ok(()),
-        _ => Err(WeatherError::InvalidUnits(units.to_string())),
+fn auth(action: &AuthAction) -> Result<()> {
+    match action {
+        AuthAction::Set { key } => {
+            let key = match key {
+                Some(key) => key.clone(),
+                None => read_key()?,
+            };
+            let key = key.trim();
+            if key.is_empty() {
+                return Err(WeatherError::InvalidApiKey.into());
+            }
+
+            auth::store_key(key)?;
+            println!("Saved the API key in the OS keyring");
+        }
+        AuthAction::Remove if auth::delete_key()? => println!("Removed the saved API key"),
+        AuthAction::Remove => println!("No API key saved"),
     }
+
+    Ok(())
 }
 
-async fn fetch_weather(city: &str, api_key: &str) -> Result<WeatherResponse, WeatherError> {
-    let url = format!(
-        "https://api.openweathermap.org/data/2.5/weather?q={}&APPID={}",
-        city, api_key
-    );
+/// The key typed without echo, or the first line piped in
+fn read_key() -> Result<String> {
+    if io::stdin().is_terminal() {
+        return Ok(rpassword::prompt_password("OpenWeatherMap API key: ")?);
+    }
 
-    let response = reqwest::get(&url).await?;
+    let mut key = String::new();
+    io::stdin().read_line(&mut key)?;
+    Ok(key)
+}
 
-    match response.status() {
-        reqwest::StatusCode::OK => {
-            let weather: WeatherResponse = response.json().await?;
-            Ok(weather)
-        }
-        reqwest::StatusCode::NOT_FOUND => Err(WeatherError::CityNotFound(city.to_string())),
-        reqwest::StatusCode::UNAUTHORIZED => Err(WeatherError::InvalidApiKey),
-        _ => Err(WeatherError::Unknown),
+fn views_report(views: &[WeatherView], output: OutputFormat) -> Result<String> {
+    Ok(match output {
+        // only collected for text with --compare
+        OutputFormat::Text if views.is_empty() => String::new(),
+        OutputFormat::Text => view::compare_table(views),
+        OutputFormat::Json => json(views)?,
+        OutputFormat::Csv => view::weather_csv(views)?,
+    })
+}
+
+/// Pretty JSON ending with a newline
+fn json<T: serde::Serialize + ?Sized>(value: &T) -> Result<String> {
+    Ok(format!("{}\n", serde_json::to_string_pretty(value)?))
+}
+
+/// The place a subcommand is about: its city argument, --lat/--lon, or a
+/// guess
+async fn pick_location(city: &Option<Location>, cli: &Cli) -> Result<Location, WeatherError> {
+    match city.clone().or_else(|| cli.coordinates()) {
+        Some(location) => Ok(location),
+        None => guess_location(cli.no_auto_locate || cli.offline).await,
+    }
+}
+
+/// Where the machine seems to be, unless the user opted out or is offline
+async fn guess_location(no_auto_locate: bool) -> Result<Location, WeatherError> {
+    if no_auto_locate {
+        return Err(WeatherError::NoLocation);
     }
+
+    let found = geolocate::locate().await?;
+    let location = found
+        .location()
+        .ok_or_else(|| WeatherError::AutoLocateFailed("no city for your IP address".to_string()))?;
+    eprintln!(
+        "No city given, guessed {} from your IP address",
+        found.place()
+    );
+
+    Ok(location)
 }
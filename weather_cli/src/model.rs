@@ -1,3 +1,8 @@
+use crate::comfort;
+use crate::error::WeatherError;
+use crate::units::Units;
+use crate::view::{WeatherView, convert_temp, round};
+use chrono::DateTime;
 use serde::Deserialize;
 
 #[derive(Debug, Deserialize)]
@@ -9,6 +14,9 @@ pub struct WeatherResponse {
     pub clouds: Option<Clouds>,
     pub sys: Option<Sys>,
     pub visibility: Option<u32>,
+    /// Shift in seconds from UTC
+    #[serde(default)]
+    pub timezone: i64,
 }
 
 #[derive(Debug, Deserialize)]
@@ -80,99 +88,49 @@ impl WeatherResponse {
         &self.weather[0].description
     }
 
-    pub fn display(&self, units: &str) {
-        println!("\n Weather in {}", self.name);
-
-        match units.to_lowercase().as_str() {
-            "metric" => {
-                println!("Temperature: {:.1}°C", self.temp_celsius());
-                println!("Feels like: {:.1}°C", self.feels_like_celsius());
-            }
-            "imperial" => {
-                println!("Temperature: {:.1}°F", self.temp_fahrenheit());
-                println!("Feels like: {:.1}°F", self.feels_like_fahrenheit());
-            }
-            "kelvin" => {
-                println!("Temperature: {:.1}K", self.temp());
-                println!("Feels like: {:.1}K", self.feels_like());
-            }
-            _ => {
-                println!("Invalid units, defaulting to Celsius");
-                println!("Temperature: {:.1}°C", self.temp_celsius());
-            }
-        }
+    /// Everything worth showing, converted to `units`
+    pub fn view(&self, units: Units) -> Result<WeatherView, WeatherError> {
+        let (temperature, temperature_unit) = convert_temp(self.temp(), units);
+        let (feels_like, _) = convert_temp(self.feels_like(), units);
+        let wind_speed = match units {
+            Units::Metric => self.wind_speed_kmh(),
+            Units::Imperial => self.wind_speed_mph(),
+            Units::Kelvin => self.wind_speed_ms(),
+        };
+
+        Ok(WeatherView {
+            city: self.name().to_string(),
+            country: self.country().map(str::to_string),
+            temperature,
+            feels_like,
+            temperature_unit,
+            conditions: self.description().to_string(),
+            humidity: self.humidity(),
+            pressure: self.pressure(),
+            visibility_km: self.visibility_km(),
+            wind_speed: wind_speed.map(round),
+            wind_unit: units.wind_unit(),
+            wind_direction: self.wind_direction(),
+            cloudiness: self.cloud_coverage(),
+            sunrise: self.sunrise_time()?,
+            sunset: self.sunset_time()?,
+            dew_point: convert_temp(self.dew_point(), units).0,
+            heat_index: self.heat_index().map(|k| convert_temp(k, units).0),
+            wind_chill: self.wind_chill().map(|k| convert_temp(k, units).0),
+            hourly: None,
+            alerts: None,
+        })
+    }
 
-        println!("Conditions: {}", self.description());
-        println!("Humidity: {}%", self.humidity());
+    pub fn display(&self, units: Units) -> Result<(), WeatherError> {
+        print!("{}", self.view(units)?.to_text(false));
+        Ok(())
     }
 
     /// Display detailed weather information
-    pub fn display_detailed(&self, units: &str) {
-        // Basic info
-        self.display(units);
-
-        println!("\n Additional Details:");
-
-        // Country
-        if let Some(country) = self.country() {
-            println!("Country: {}", country);
-        }
-
-        // Pressure
-        if let Some(pressure) = self.pressure() {
-            println!("Pressure: {} hPa", pressure);
-        }
-
-        // Visibility
-        if let Some(visibility) = self.visibility_km() {
-            println!("Visibility: {:.1} km", visibility);
-        }
-
-        // Wind
-        println!("\n Wind:");
-        match units {
-            "imperial" => {
-                if let Some(speed) = self.wind_speed_mph() {
-                    print!("  Speed: {:.1} mph", speed);
-                    if let Some(dir) = self.wind_direction() {
-                        print!(" ({})", dir);
-                    }
-                    println!();
-                }
-            }
-            "metric" => {
-                if let Some(speed) = self.wind_speed_kmh() {
-                    print!("  Speed: {:.1} km/h", speed);
-                    if let Some(dir) = self.wind_direction() {
-                        print!(" ({})", dir);
-                    }
-                    println!();
-                }
-            }
-            _ => {
-                if let Some(speed) = self.wind_speed_ms() {
-                    print!("  Speed: {:.1} m/s", speed);
-                    if let Some(dir) = self.wind_direction() {
-                        print!(" ({})", dir);
-                    }
-                    println!();
-                }
-            }
-        }
-
-        // Clouds
-        if let Some(clouds) = self.cloud_coverage() {
-            println!("\n  Cloudiness: {}%", clouds);
-        }
-
-        // Sunrise/Sunset
-        println!("\n Sun Times:");
-        if let Some(sunrise) = self.sunrise_time() {
-            println!("  Sunrise: {}", sunrise);
-        }
-        if let Some(sunset) = self.sunset_time() {
-            println!("  Sunset: {}", sunset);
-        }
+    pub fn display_detailed(&self, units: Units) -> Result<(), WeatherError> {
+        print!("{}", self.view(units)?.to_text(true));
+        Ok(())
     }
 
     /// Get wind speed in different units
@@ -226,25 +184,86 @@ impl WeatherResponse {
         self.visibility.map(|v| v as f64 / 1000.0)
     }
 
-    /// Format sunrise time (returns HH:MM or None)
-    pub fn sunrise_time(&self) -> Option<String> {
-        let timestamp = self.sys.as_ref()?.sunrise?;
-        format_timestamp(timestamp)
+    /// Dew point in Kelvin
+    pub fn dew_point(&self) -> f64 {
+        comfort::dew_point(self.temp_celsius(), self.humidity()) + 273.15
+    }
+
+    /// Heat index in Kelvin, when it's hot
+    pub fn heat_index(&self) -> Option<f64> {
+        comfort::heat_index(self.temp_celsius(), self.humidity()).map(|c| c + 273.15)
+    }
+
+    /// Wind chill in Kelvin, when it's cold and windy
+    pub fn wind_chill(&self) -> Option<f64> {
+        comfort::wind_chill(self.temp_celsius(), self.wind_speed_kmh()?).map(|c| c + 273.15)
     }
 
-    /// Format sunset time (returns HH:MM or None)
-    pub fn sunset_time(&self) -> Option<String> {
-        let timestamp = self.sys.as_ref()?.sunset?;
-        format_timestamp(timestamp)
+    /// Format sunrise time in the city's own time (HH:MM, or None when
+    /// not sent)
+    pub fn sunrise_time(&self) -> Result<Option<String>, WeatherError> {
+        self.sys
+            .as_ref()
+            .and_then(|sys| sys.sunrise)
+            .map(|timestamp| format_timestamp(timestamp, self.timezone))
+            .transpose()
+    }
+
+    /// Format sunset time in the city's own time (HH:MM, or None when not
+    /// sent)
+    pub fn sunset_time(&self) -> Result<Option<String>, WeatherError> {
+        self.sys
+            .as_ref()
+            .and_then(|sys| sys.sunset)
+            .map(|timestamp| format_timestamp(timestamp, self.timezone))
+            .transpose()
     }
 }
 
-/// Helper function to format Unix timestamp to HH:MM
-fn format_timestamp(timestamp: u64) -> Option<String> {
-    use chrono::DateTime;
+/// Helper function to format Unix timestamp to HH:MM, shifted by
+/// `offset` seconds from UTC
+fn format_timestamp(timestamp: u64, offset: i64) -> Result<String, WeatherError> {
+    let invalid = WeatherError::InvalidTimestamp(timestamp as i64);
+    let local = i64::try_from(timestamp)
+        .ok()
+        .and_then(|t| t.checked_add(offset))
+        .ok_or(invalid)?;
+    let date_time =
+        DateTime::from_timestamp(local, 0).ok_or(WeatherError::InvalidTimestamp(local))?;
+
+    Ok(date_time.format("%H:%M").to_string())
+}
 
-    let date_time = DateTime::from_timestamp(timestamp as i64, 0).expect("Invalid timestamp");
-    let formatted_time = date_time.format("%H:%M").to_string();
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn weather(sunrise: u64, timezone: i64) -> WeatherResponse {
+        let json = format!(
+            r#"{{"name": "Tokyo", "main": {{"temp": 290.0, "feels_like": 289.0, "humidity": 60}},
+                "weather": [{{"main": "Clear", "description": "clear sky"}}],
+                "sys": {{"country": "JP", "sunrise": {}}}, "timezone": {}}}"#,
+            sunrise, timezone
+        );
+        serde_json::from_str(&json).unwrap()
+    }
 
-    Some(formatted_time)
+    #[test]
+    fn test_local_sun_times() {
+        // 2024-03-01 21:05 UTC is 06:05 the next morning in Tokyo
+        let tokyo = weather(1_709_327_100, 9 * 3600);
+        assert_eq!(tokyo.sunrise_time().unwrap().as_deref(), Some("06:05"));
+        assert_eq!(tokyo.sunset_time().unwrap(), None);
+        assert_eq!(
+            weather(1_709_327_100, 0).sunrise_time().unwrap().as_deref(),
+            Some("21:05")
+        );
+
+        let broken = weather(u64::MAX, 0);
+        assert!(matches!(
+            broken.sunrise_time(),
+            Err(WeatherError::InvalidTimestamp(_))
+        ));
+        assert!(broken.view(Units::Metric).is_err());
+    }
 }
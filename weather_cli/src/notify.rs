@@ -0,0 +1,260 @@
+use crate::error::WeatherError;
+use crate::view::WeatherView;
+use notify_rust::Notification;
+use std::collections::HashSet;
+use std::time::Duration;
+
+/// The shortest interval between polls, to spare the API quota.
+pub const MIN_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Alert descriptions are cut to this many characters in a notification.
+const MAX_BODY: usize = 200;
+
+/// One desktop notification.
+#[derive(Debug, PartialEq)]
+pub struct Notice {
+    pub summary: String,
+    pub body: String,
+}
+
+impl Notice {
+    pub fn send(&self) -> Result<(), WeatherError> {
+        Notification::new()
+            .appname("weather_cli")
+            .summary(&self.summary)
+            .body(&self.body)
+            .show()
+            .map(|_| ())
+            .map_err(|e| WeatherError::Notification(e.to_string()))
+    }
+}
+
+/// Remembers what the last polls saw, so a notification goes out when a
+/// threshold is crossed or an alert issued, not on every poll after.
+#[derive(Debug, Default)]
+pub struct Watcher {
+    /// In the units the weather is fetched in
+    below: Option<f64>,
+    above: Option<f64>,
+    was_below: bool,
+    was_above: bool,
+    /// The alerts already notified, by event and start
+    seen_alerts: HashSet<String>,
+}
+
+impl Watcher {
+    pub fn new(below: Option<f64>, above: Option<f64>) -> Self {
+        Watcher {
+            below,
+            above,
+            ..Watcher::default()
+        }
+    }
+
+    pub fn has_thresholds(&self) -> bool {
+        self.below.is_some() || self.above.is_some()
+    }
+
+    /// The notifications due for the latest weather
+    pub fn check(&mut self, view: &WeatherView) -> Vec<Notice> {
+        let mut notices = Vec::new();
+        let temperature = format!("{:.1}{}", view.temperature, view.temperature_unit);
+
+        let is_below = self.below.is_some_and(|below| view.temperature < below);
+        if is_below && !self.was_below {
+            notices.push(Notice {
+                summary: format!("{} is down to {}", view.city, temperature),
+                body: format!(
+                    "Below {:.1}{}, {}",
+                    self.below.unwrap_or_default(),
+                    view.temperature_unit,
+                    view.conditions
+                ),
+            });
+        }
+        self.was_below = is_below;
+
+        let is_above = self.above.is_some_and(|above| view.temperature > above);
+        if is_above && !self.was_above {
+            notices.push(Notice {
+                summary: format!("{} is up to {}", view.city, temperature),
+                body: format!(
+                    "Above {:.1}{}, {}",
+                    self.above.unwrap_or_default(),
+                    view.temperature_unit,
+                    view.conditions
+                ),
+            });
+        }
+        self.was_above = is_above;
+
+        let mut active = HashSet::new();
+        for alert in view.alerts.as_deref().unwrap_or_default() {
+            let key = format!("{}@{}", alert.event, alert.start);
+            let seen = self.seen_alerts.contains(&key);
+            // notified already, or twice in the same response
+            if !active.insert(key) || seen {
+                continue;
+            }
+            notices.push(Notice {
+                summary: format!("{}: {}", view.city, alert.event),
+                body: format!(
+                    "{}, until {}\n{}",
+                    alert.sender,
+                    alert.end,
+                    shorten(&alert.description)
+                ),
+            });
+        }
+        // an alert issued again after it ended is new again
+        self.seen_alerts = active;
+
+        notices
+    }
+}
+
+/// The first line of a description, cut short
+fn shorten(description: &str) -> String {
+    let line = description.trim().lines().next().unwrap_or_default();
+    if line.chars().count() <= MAX_BODY {
+        return line.to_string();
+    }
+
+    let cut: String = line.chars().take(MAX_BODY - 1).collect();
+    format!("{}…", cut.trim_end())
+}
+
+/// An interval like `1h`, `30m`, `90s` or `1h30m`, at least a minute
+pub fn parse_interval(text: &str) -> Result<Duration, String> {
+    let invalid = || format!("`{}` isn't an interval like 1h, 30m or 1h30m", text);
+
+    let mut seconds = 0u64;
+    let mut number = String::new();
+    for c in text.trim().chars() {
+        if c.is_ascii_digit() {
+            number.push(c);
+            continue;
+        }
+
+        let unit = match c {
+            'h' => 3600,
+            'm' => 60,
+            's' => 1,
+            _ => return Err(invalid()),
+        };
+        let value: u64 = number.parse().map_err(|_| invalid())?;
+        seconds = value
+            .checked_mul(unit)
+            .and_then(|value| seconds.checked_add(value))
+            .ok_or_else(invalid)?;
+        number.clear();
+    }
+    if !number.is_empty() || seconds == 0 {
+        return Err(invalid());
+    }
+
+    let interval = Duration::from_secs(seconds);
+    if interval < MIN_INTERVAL {
+        return Err(format!("{} is too often, poll at most once a minute", text));
+    }
+
+    Ok(interval)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::alerts::Severity;
+    use crate::view::AlertView;
+
+    fn weather(temperature: f64, alerts: &[&str]) -> WeatherView {
+        let alerts = alerts
+            .iter()
+            .map(|event| AlertView {
+                event: event.to_string(),
+                severity: Severity::of(event),
+                sender: "Met Office".to_string(),
+                start: "2025-01-10 06:00".to_string(),
+                end: "2025-01-10 18:00".to_string(),
+                description: "Icy patches.\nTake care.".to_string(),
+            })
+            .collect();
+
+        WeatherView {
+            city: "London".to_string(),
+            country: Some("GB".to_string()),
+            temperature,
+            feels_like: temperature,
+            temperature_unit: "°C",
+            conditions: "light snow".to_string(),
+            humidity: 90,
+            pressure: None,
+            visibility_km: None,
+            wind_speed: None,
+            wind_unit: "km/h",
+            wind_direction: None,
+            cloudiness: None,
+            sunrise: None,
+            sunset: None,
+            dew_point: temperature,
+            heat_index: None,
+            wind_chill: None,
+            hourly: None,
+            alerts: Some(alerts),
+        }
+    }
+
+    #[test]
+    fn test_thresholds() {
+        let mut watcher = Watcher::new(Some(0.0), Some(30.0));
+        assert_eq!(watcher.check(&weather(2.0, &[])), vec![]);
+
+        let notices = watcher.check(&weather(-1.5, &[]));
+        assert_eq!(
+            notices,
+            vec![Notice {
+                summary: "London is down to -1.5°C".to_string(),
+                body: "Below 0.0°C, light snow".to_string(),
+            }]
+        );
+        // still below, already notified
+        assert_eq!(watcher.check(&weather(-3.0, &[])), vec![]);
+
+        watcher.check(&weather(1.0, &[]));
+        assert_eq!(watcher.check(&weather(-0.5, &[])).len(), 1);
+        assert_eq!(watcher.check(&weather(31.0, &[])).len(), 1);
+    }
+
+    #[test]
+    fn test_alerts() {
+        let mut watcher = Watcher::new(None, None);
+        let notices = watcher.check(&weather(1.0, &["Yellow ice warning"]));
+        assert_eq!(notices.len(), 1);
+        assert_eq!(notices[0].summary, "London: Yellow ice warning");
+        assert_eq!(
+            notices[0].body,
+            "Met Office, until 2025-01-10 18:00\nIcy patches."
+        );
+
+        let both = weather(1.0, &["Yellow ice warning", "Amber snow warning"]);
+        let notices = watcher.check(&both);
+        assert_eq!(notices.len(), 1);
+        assert_eq!(notices[0].summary, "London: Amber snow warning");
+
+        watcher.check(&weather(1.0, &[]));
+        assert_eq!(watcher.check(&both).len(), 2);
+    }
+
+    #[test]
+    fn test_parse_interval() {
+        assert_eq!(parse_interval("1h"), Ok(Duration::from_secs(3600)));
+        assert_eq!(parse_interval("30m"), Ok(Duration::from_secs(1800)));
+        assert_eq!(parse_interval("1h30m"), Ok(Duration::from_secs(5400)));
+        assert_eq!(parse_interval("90s"), Ok(Duration::from_secs(90)));
+        assert!(parse_interval("30s").is_err());
+        assert!(parse_interval("1d").is_err());
+        assert!(parse_interval("60").is_err());
+        assert!(parse_interval("h").is_err());
+        assert!(parse_interval("").is_err());
+    }
+}
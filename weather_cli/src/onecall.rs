@@ -0,0 +1,270 @@
+use crate::alerts::{Alert, Alerts};
+use crate::error::WeatherError;
+use crate::forecast::ForecastCondition;
+use crate::location::GeoPlace;
+use crate::model::{Clouds, MainWeather, Sys, WeatherCondition, WeatherResponse, Wind};
+use crate::units::Units;
+use crate::view::{DayView, ForecastView, HourView, WeatherView, convert_temp};
+use chrono::{DateTime, NaiveDateTime};
+use serde::Deserialize;
+
+/// How many hours ahead the One Call endpoint answers for.
+pub const MAX_HOURS: u8 = 48;
+
+/// How many days ahead it answers for, today included.
+pub const MAX_DAYS: u8 = 8;
+
+/// Response of the One Call endpoint: the current weather, the next 48
+/// hours, the next 8 days and the alerts of a place, all in one request.
+#[derive(Debug, Deserialize)]
+pub struct OneCallResponse {
+    /// Shift in seconds from UTC
+    #[serde(default)]
+    pub timezone_offset: i64,
+    pub current: Current,
+    #[serde(default)]
+    pub hourly: Vec<Hour>,
+    #[serde(default)]
+    pub daily: Vec<Day>,
+    /// Left out entirely when there are none
+    #[serde(default)]
+    pub alerts: Vec<Alert>,
+}
+
+/// The current weather, flat where `/weather` nests it.
+#[derive(Debug, Deserialize)]
+pub struct Current {
+    pub sunrise: Option<u64>,
+    pub sunset: Option<u64>,
+    /// In Kelvin
+    pub temp: f64,
+    pub feels_like: f64,
+    pub pressure: Option<u32>,
+    pub humidity: u8,
+    pub visibility: Option<u32>,
+    /// In m/s
+    pub wind_speed: Option<f64>,
+    pub wind_deg: Option<u16>,
+    pub clouds: Option<u8>,
+    pub weather: Vec<WeatherCondition>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Hour {
+    pub dt: i64,
+    /// In Kelvin
+    pub temp: f64,
+    pub weather: Vec<ForecastCondition>,
+    /// Probability of precipitation, from 0 to 1
+    #[serde(default)]
+    pub pop: f64,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Day {
+    pub dt: i64,
+    pub temp: DayTemperatures,
+    pub weather: Vec<ForecastCondition>,
+    #[serde(default)]
+    pub pop: f64,
+}
+
+/// The day's range in Kelvin, the other moments of the day are ignored
+#[derive(Debug, Deserialize)]
+pub struct DayTemperatures {
+    pub min: f64,
+    pub max: f64,
+}
+
+/// A One Call response split into what the rest of the CLI already knows
+/// how to show.
+#[derive(Debug)]
+pub struct OneCall {
+    pub weather: WeatherResponse,
+    pub hourly: Vec<Hour>,
+    pub daily: Vec<Day>,
+    pub alerts: Alerts,
+}
+
+impl OneCall {
+    pub fn new(place: GeoPlace, response: OneCallResponse) -> Self {
+        let current = response.current;
+        let weather = WeatherResponse {
+            name: place.name.clone(),
+            main: MainWeather {
+                temp: current.temp,
+                feels_like: current.feels_like,
+                humidity: current.humidity,
+                pressure: current.pressure,
+            },
+            weather: current.weather,
+            wind: current.wind_speed.map(|speed| Wind {
+                speed,
+                deg: current.wind_deg,
+            }),
+            clouds: current.clouds.map(|all| Clouds { all }),
+            sys: Some(Sys {
+                country: place.country.clone(),
+                sunrise: current.sunrise,
+                sunset: current.sunset,
+            }),
+            visibility: current.visibility,
+            timezone: response.timezone_offset,
+        };
+
+        OneCall {
+            weather,
+            hourly: response.hourly,
+            daily: response.daily,
+            alerts: Alerts {
+                place,
+                timezone_offset: response.timezone_offset,
+                alerts: response.alerts,
+            },
+        }
+    }
+
+    /// The current weather with the alerts, and the first `hours` hours
+    /// when there are any to show
+    pub fn view(&self, units: Units, hours: usize) -> Result<WeatherView, WeatherError> {
+        let mut view = self.weather.view(units)?;
+        view.alerts = Some(self.alerts.view()?.alerts);
+        if hours > 0 {
+            view.hourly = Some(self.hours(units, hours)?);
+        }
+
+        Ok(view)
+    }
+
+    pub fn hours(&self, units: Units, hours: usize) -> Result<Vec<HourView>, WeatherError> {
+        self.hourly
+            .iter()
+            .take(hours)
+            .map(|hour| {
+                Ok(HourView {
+                    time: self.local(hour.dt)?.format("%H:%M").to_string(),
+                    temperature: convert_temp(hour.temp, units).0,
+                    conditions: description(&hour.weather),
+                    precipitation: percent(hour.pop),
+                })
+            })
+            .collect()
+    }
+
+    /// The first `days` days, like the forecast endpoint's but from the
+    /// same request
+    pub fn forecast(&self, units: Units, days: usize) -> Result<ForecastView, WeatherError> {
+        let days = self
+            .daily
+            .iter()
+            .take(days)
+            .map(|day| {
+                Ok(DayView {
+                    date: self.local(day.dt)?.date(),
+                    min: convert_temp(day.temp.min, units).0,
+                    max: convert_temp(day.temp.max, units).0,
+                    conditions: description(&day.weather),
+                    precipitation: percent(day.pop),
+                })
+            })
+            .collect::<Result<_, WeatherError>>()?;
+
+        Ok(ForecastView {
+            city: self.alerts.place.name.clone(),
+            country: self.alerts.place.country.clone(),
+            temperature_unit: units.temperature_unit(),
+            days,
+        })
+    }
+
+    /// A timestamp on the place's own clock
+    fn local(&self, timestamp: i64) -> Result<NaiveDateTime, WeatherError> {
+        let local = timestamp + self.alerts.timezone_offset;
+        DateTime::from_timestamp(local, 0)
+            .map(|date_time| date_time.naive_utc())
+            .ok_or(WeatherError::InvalidTimestamp(timestamp))
+    }
+}
+
+fn description(weather: &[ForecastCondition]) -> String {
+    weather
+        .first()
+        .map(|condition| condition.description.clone())
+        .unwrap_or_default()
+}
+
+fn percent(probability: f64) -> u8 {
+    (probability * 100.0).round() as u8
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RESPONSE: &str = r#"{
+        "lat": 51.5085, "lon": -0.1257, "timezone": "Europe/London",
+        "timezone_offset": 3600,
+        "current": {
+            "dt": 1719820800, "sunrise": 1719805500, "sunset": 1719865000,
+            "temp": 293.15, "feels_like": 292.5, "pressure": 1015,
+            "humidity": 60, "visibility": 10000, "wind_speed": 5.0,
+            "wind_deg": 240, "clouds": 40,
+            "weather": [{"id": 802, "main": "Clouds", "description": "scattered clouds"}]
+        },
+        "hourly": [
+            {"dt": 1719820800, "temp": 293.15, "pop": 0,
+             "weather": [{"main": "Clouds", "description": "scattered clouds"}]},
+            {"dt": 1719824400, "temp": 294.15, "pop": 0.35,
+             "weather": [{"main": "Rain", "description": "light rain"}]}
+        ],
+        "daily": [
+            {"dt": 1719831600, "temp": {"day": 294.0, "min": 285.15, "max": 295.15},
+             "pop": 0.8, "weather": [{"main": "Rain", "description": "moderate rain"}]}
+        ]
+    }"#;
+
+    fn london() -> OneCall {
+        let place = GeoPlace {
+            name: "London".to_string(),
+            lat: 51.5085,
+            lon: -0.1257,
+            country: Some("GB".to_string()),
+        };
+        OneCall::new(place, serde_json::from_str(RESPONSE).unwrap())
+    }
+
+    #[test]
+    fn test_current() {
+        let view = london().view(Units::Metric, 0).unwrap();
+        assert_eq!(view.city, "London");
+        assert_eq!(view.country.as_deref(), Some("GB"));
+        assert_eq!(view.temperature, 20.0);
+        assert_eq!(view.wind_speed, Some(18.0));
+        assert_eq!(view.wind_direction.as_deref(), Some("SW"));
+        // 03:45 UTC is 04:45 in London in July
+        assert_eq!(view.sunrise.as_deref(), Some("04:45"));
+        assert_eq!(view.alerts, Some(Vec::new()));
+        assert_eq!(view.hourly, None);
+    }
+
+    #[test]
+    fn test_hours() {
+        let view = london().view(Units::Metric, 12).unwrap();
+        let hours = view.hourly.unwrap();
+        assert_eq!(hours.len(), 2);
+        assert_eq!(hours[1].time, "10:00");
+        assert_eq!(hours[1].temperature, 21.0);
+        assert_eq!(hours[1].conditions, "light rain");
+        assert_eq!(hours[1].precipitation, 35);
+    }
+
+    #[test]
+    fn test_forecast() {
+        let forecast = london().forecast(Units::Metric, 5).unwrap();
+        assert_eq!(forecast.days.len(), 1);
+        assert_eq!(forecast.days[0].date.to_string(), "2024-07-01");
+        assert_eq!(forecast.days[0].min, 12.0);
+        assert_eq!(forecast.days[0].max, 22.0);
+        assert_eq!(forecast.days[0].precipitation, 80);
+    }
+}
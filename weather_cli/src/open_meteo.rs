@@ -0,0 +1,363 @@
+use crate::cache::{self, Cache};
+use crate::error::WeatherError;
+use crate::forecast::{
+    ForecastCity, ForecastCondition, ForecastEntry, ForecastMain, ForecastResponse,
+};
+use crate::location::{GeoPlace, Location};
+use crate::model::{Clouds, MainWeather, Sys, WeatherCondition, WeatherResponse, Wind};
+use crate::provider::{WeatherProvider, fetch};
+use crate::retry::Retry;
+use crate::units::Units;
+use serde::Deserialize;
+
+const FORECAST_URL: &str = "https://api.open-meteo.com/v1/forecast";
+const GEOCODING_URL: &str = "https://geocoding-api.open-meteo.com/v1/search";
+
+const CURRENT_FIELDS: &str = "temperature_2m,relative_humidity_2m,apparent_temperature,\
+    weather_code,pressure_msl,cloud_cover,visibility,wind_speed_10m,wind_direction_10m";
+const DAILY_FIELDS: &str =
+    "weather_code,temperature_2m_max,temperature_2m_min,precipitation_probability_max";
+
+/// Open-Meteo, free for non-commercial use and without an API key. It
+/// answers in Celsius, converted to Kelvin like OpenWeather sends.
+#[derive(Debug, Clone)]
+pub struct OpenMeteo {
+    /// Language of the place names, the conditions stay in English
+    lang: Option<String>,
+    cache: Option<Cache>,
+    retry: Retry,
+}
+
+#[derive(Debug, Deserialize)]
+struct GeocodingResponse {
+    /// Left out when nothing matches
+    #[serde(default)]
+    results: Vec<GeocodingResult>,
+}
+
+#[derive(Debug, Deserialize)]
+struct GeocodingResult {
+    name: String,
+    latitude: f64,
+    longitude: f64,
+    country_code: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct CurrentResponse {
+    /// The place's own offset, asked for with `timezone=auto`
+    utc_offset_seconds: i64,
+    current: Current,
+    daily: SunTimes,
+}
+
+#[derive(Debug, Deserialize)]
+struct Current {
+    temperature_2m: f64,
+    relative_humidity_2m: u8,
+    apparent_temperature: f64,
+    weather_code: u8,
+    pressure_msl: Option<f64>,
+    cloud_cover: Option<u8>,
+    /// In meters
+    visibility: Option<f64>,
+    /// In m/s, as asked for
+    wind_speed_10m: Option<f64>,
+    wind_direction_10m: Option<u16>,
+}
+
+#[derive(Debug, Deserialize)]
+struct SunTimes {
+    sunrise: Vec<u64>,
+    sunset: Vec<u64>,
+}
+
+#[derive(Debug, Deserialize)]
+struct DailyResponse {
+    utc_offset_seconds: i64,
+    daily: Daily,
+}
+
+#[derive(Debug, Deserialize)]
+struct Daily {
+    /// Local midnight of each day, as Unix timestamps
+    time: Vec<i64>,
+    weather_code: Vec<Option<u8>>,
+    temperature_2m_max: Vec<Option<f64>>,
+    temperature_2m_min: Vec<Option<f64>>,
+    precipitation_probability_max: Vec<Option<f64>>,
+}
+
+impl WeatherProvider for OpenMeteo {
+    async fn current(
+        &self,
+        location: &Location,
+        units: Units,
+    ) -> Result<WeatherResponse, WeatherError> {
+        let place = self.place(location).await?;
+        let url = format!(
+            "{}?latitude={}&longitude={}&current={}&daily=sunrise,sunset&forecast_days=1\
+             &wind_speed_unit=ms&timeformat=unixtime&timezone=auto",
+            FORECAST_URL, place.lat, place.lon, CURRENT_FIELDS
+        );
+
+        let key = cache::key("open-meteo-weather", &location.to_string(), units.as_str());
+        let response: CurrentResponse =
+            fetch(&url, location, self.cache.as_ref(), &self.retry, &key).await?;
+        Ok(current_weather(place, response))
+    }
+
+    async fn forecast(
+        &self,
+        location: &Location,
+        units: Units,
+    ) -> Result<ForecastResponse, WeatherError> {
+        let place = self.place(location).await?;
+        let url = format!(
+            "{}?latitude={}&longitude={}&daily={}&forecast_days=5&timeformat=unixtime&timezone=auto",
+            FORECAST_URL, place.lat, place.lon, DAILY_FIELDS
+        );
+
+        let key = cache::key("open-meteo-forecast", &location.to_string(), units.as_str());
+        let response: DailyResponse =
+            fetch(&url, location, self.cache.as_ref(), &self.retry, &key).await?;
+        Ok(daily_forecast(place, response))
+    }
+}
+
+impl OpenMeteo {
+    pub fn new(lang: Option<String>, cache: Option<Cache>, retry: Retry) -> Self {
+        OpenMeteo { lang, cache, retry }
+    }
+
+    /// The name and coordinates of a location, geocoding city names
+    async fn place(&self, location: &Location) -> Result<GeoPlace, WeatherError> {
+        let name = match location {
+            Location::Coordinates { lat, lon } => {
+                return Ok(GeoPlace {
+                    name: location.to_string(),
+                    lat: *lat,
+                    lon: *lon,
+                    country: None,
+                });
+            }
+            Location::City(name) => name,
+        };
+
+        let lang = self.lang.as_deref().unwrap_or_default();
+        let url = geocoding_url(name, lang);
+        let key = cache::key("open-meteo-geocode", name, lang);
+        let response: GeocodingResponse =
+            fetch(&url, location, self.cache.as_ref(), &self.retry, &key).await?;
+        let found = response
+            .results
+            .into_iter()
+            .next()
+            .ok_or_else(|| WeatherError::CityNotFound(name.clone()))?;
+
+        Ok(GeoPlace {
+            name: found.name,
+            lat: found.latitude,
+            lon: found.longitude,
+            country: found.country_code,
+        })
+    }
+}
+
+fn current_weather(place: GeoPlace, response: CurrentResponse) -> WeatherResponse {
+    let current = response.current;
+    let (main, description) = condition(current.weather_code);
+
+    WeatherResponse {
+        name: place.name,
+        main: MainWeather {
+            temp: kelvin(current.temperature_2m),
+            feels_like: kelvin(current.apparent_temperature),
+            humidity: current.relative_humidity_2m,
+            pressure: current.pressure_msl.map(|p| p.round() as u32),
+        },
+        weather: vec![WeatherCondition {
+            main: main.to_string(),
+            description: description.to_string(),
+        }],
+        wind: current.wind_speed_10m.map(|speed| Wind {
+            speed,
+            deg: current.wind_direction_10m,
+        }),
+        clouds: current.cloud_cover.map(|all| Clouds { all }),
+        sys: Some(Sys {
+            country: place.country,
+            sunrise: response.daily.sunrise.first().copied(),
+            sunset: response.daily.sunset.first().copied(),
+        }),
+        visibility: current.visibility.map(|v| v.round() as u32),
+        timezone: response.utc_offset_seconds,
+    }
+}
+
+/// One forecast entry per day, so grouping by day keeps them as they are
+fn daily_forecast(place: GeoPlace, response: DailyResponse) -> ForecastResponse {
+    let daily = response.daily;
+    let list = daily
+        .time
+        .iter()
+        .enumerate()
+        .filter_map(|(i, dt)| {
+            let temp_min = (*daily.temperature_2m_min.get(i)?)?;
+            let temp_max = (*daily.temperature_2m_max.get(i)?)?;
+            let code = daily.weather_code.get(i).copied().flatten();
+            let pop = daily
+                .precipitation_probability_max
+                .get(i)
+                .copied()
+                .flatten()
+                .unwrap_or(0.0);
+
+            Some(ForecastEntry {
+                dt: *dt,
+                main: ForecastMain {
+                    temp_min: kelvin(temp_min),
+                    temp_max: kelvin(temp_max),
+                },
+                weather: code
+                    .map(|code| ForecastCondition {
+                        description: condition(code).1.to_string(),
+                    })
+                    .into_iter()
+                    .collect(),
+                pop: pop / 100.0,
+            })
+        })
+        .collect();
+
+    ForecastResponse {
+        list,
+        city: ForecastCity {
+            name: place.name,
+            country: place.country,
+            timezone: response.utc_offset_seconds,
+        },
+    }
+}
+
+fn kelvin(celsius: f64) -> f64 {
+    celsius + 273.15
+}
+
+/// The group and description of a WMO weather code, worded like OpenWeather
+fn condition(code: u8) -> (&'static str, &'static str) {
+    match code {
+        0 => ("Clear", "clear sky"),
+        1 => ("Clouds", "mainly clear"),
+        2 => ("Clouds", "partly cloudy"),
+        3 => ("Clouds", "overcast clouds"),
+        45 | 48 => ("Fog", "fog"),
+        51 | 53 | 55 => ("Drizzle", "drizzle"),
+        56 | 57 => ("Drizzle", "freezing drizzle"),
+        61 => ("Rain", "light rain"),
+        63 => ("Rain", "moderate rain"),
+        65 => ("Rain", "heavy rain"),
+        66 | 67 => ("Rain", "freezing rain"),
+        71 => ("Snow", "light snow"),
+        73 => ("Snow", "snow"),
+        75 => ("Snow", "heavy snow"),
+        77 => ("Snow", "snow grains"),
+        80..=82 => ("Rain", "rain showers"),
+        85 | 86 => ("Snow", "snow showers"),
+        95 => ("Thunderstorm", "thunderstorm"),
+        96 | 99 => ("Thunderstorm", "thunderstorm with hail"),
+        _ => ("Unknown", "unknown conditions"),
+    }
+}
+
+/// The search for `name`, escaped so `&`, `#` or `?` in it stay part of the
+/// name
+fn geocoding_url(name: &str, lang: &str) -> String {
+    let mut params = vec![("name", name), ("count", "1")];
+    if !lang.is_empty() {
+        params.push(("language", lang));
+    }
+
+    reqwest::Url::parse_with_params(GEOCODING_URL, &params)
+        .expect("GEOCODING_URL is a valid URL")
+        .into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn berlin() -> GeoPlace {
+        GeoPlace {
+            name: "Berlin".to_string(),
+            lat: 52.52,
+            lon: 13.41,
+            country: Some("DE".to_string()),
+        }
+    }
+
+    #[test]
+    fn test_geocoding_url() {
+        assert_eq!(
+            geocoding_url("Berlin", ""),
+            format!("{}?name=Berlin&count=1", GEOCODING_URL)
+        );
+        assert_eq!(
+            geocoding_url("Saint-Denis & Co #1?", "fr"),
+            format!(
+                "{}?name=Saint-Denis+%26+Co+%231%3F&count=1&language=fr",
+                GEOCODING_URL
+            )
+        );
+    }
+
+    #[test]
+    fn test_current_weather() {
+        let response: CurrentResponse = serde_json::from_str(
+            r#"{"latitude": 52.52, "longitude": 13.41, "utc_offset_seconds": 7200,
+                "timezone": "Europe/Berlin",
+                "current": {"time": 1760620500, "interval": 900, "temperature_2m": 14.2,
+                            "relative_humidity_2m": 71, "apparent_temperature": 12.9,
+                            "weather_code": 61, "pressure_msl": 1013.4, "cloud_cover": 88,
+                            "visibility": 24140.0, "wind_speed_10m": 3.6,
+                            "wind_direction_10m": 250},
+                "daily": {"time": [1760565600], "sunrise": [1760592060],
+                          "sunset": [1760630400]}}"#,
+        )
+        .unwrap();
+
+        let weather = current_weather(berlin(), response);
+        assert_eq!(weather.name(), "Berlin");
+        assert_eq!(weather.description(), "light rain");
+        assert_eq!(weather.country(), Some("DE"));
+        assert_eq!(weather.pressure(), Some(1013));
+        assert_eq!(weather.wind_direction().as_deref(), Some("W"));
+        let view = weather.view(Units::Metric).unwrap();
+        assert_eq!(view.temperature, 14.2);
+        assert_eq!(view.visibility_km, Some(24.14));
+        // in Berlin's own time, two hours ahead of UTC
+        assert_eq!(view.sunrise.as_deref(), Some("07:21"));
+    }
+
+    #[test]
+    fn test_daily_forecast() {
+        let response: DailyResponse = serde_json::from_str(
+            r#"{"utc_offset_seconds": 7200,
+                "daily": {"time": [1760565600, 1760652000, 1760738400],
+                          "weather_code": [3, 80, null],
+                          "temperature_2m_max": [16.1, 13.0, null],
+                          "temperature_2m_min": [8.4, 7.9, null],
+                          "precipitation_probability_max": [10, 85, null]}}"#,
+        )
+        .unwrap();
+
+        let forecast = daily_forecast(berlin(), response);
+        let days = forecast.view(Units::Metric, 5).unwrap().days;
+        // a day without temperatures is left out
+        assert_eq!(days.len(), 2);
+        assert_eq!(days[0].date.to_string(), "2025-10-16");
+        assert_eq!((days[0].min, days[0].max), (8.4, 16.1));
+        assert_eq!(days[1].conditions, "rain showers");
+        assert_eq!(days[1].precipitation, 85);
+    }
+}
@@ -0,0 +1,246 @@
+use crate::air::{AirQuality, AirResponse};
+use crate::alerts::{Alerts, AlertsResponse};
+use crate::cache::{self, Cache};
+use crate::error::WeatherError;
+use crate::forecast::ForecastResponse;
+use crate::history::{self, HistoryDay, TimeMachineResponse};
+use crate::location::{GeoPlace, Location};
+use crate::model::WeatherResponse;
+use crate::onecall::{OneCall, OneCallResponse};
+use crate::provider::{WeatherProvider, fetch};
+use crate::retry::Retry;
+use crate::units::Units;
+use chrono::{NaiveDate, NaiveTime};
+
+const BASE_URL: &str = "https://api.openweathermap.org";
+
+/// The OpenWeatherMap API, the only provider with air quality, alerts and
+/// history.
+#[derive(Debug, Clone)]
+pub struct OpenWeather {
+    api_key: String,
+    /// Language of the descriptions, English when `None`
+    lang: Option<String>,
+    cache: Option<Cache>,
+    retry: Retry,
+}
+
+impl WeatherProvider for OpenWeather {
+    async fn current(
+        &self,
+        location: &Location,
+        units: Units,
+    ) -> Result<WeatherResponse, WeatherError> {
+        let url = format!(
+            "{}/data/2.5/weather?{}{}&APPID={}",
+            BASE_URL,
+            location.query(),
+            self.lang_param(),
+            self.api_key
+        );
+
+        let key = cache::key("weather", &location.to_string(), &self.variant(units));
+        fetch(&url, location, self.cache.as_ref(), &self.retry, &key).await
+    }
+
+    async fn forecast(
+        &self,
+        location: &Location,
+        units: Units,
+    ) -> Result<ForecastResponse, WeatherError> {
+        let url = format!(
+            "{}/data/2.5/forecast?{}{}&APPID={}",
+            BASE_URL,
+            location.query(),
+            self.lang_param(),
+            self.api_key
+        );
+
+        let key = cache::key("forecast", &location.to_string(), &self.variant(units));
+        fetch(&url, location, self.cache.as_ref(), &self.retry, &key).await
+    }
+}
+
+impl OpenWeather {
+    pub fn new(api_key: String, lang: Option<String>, cache: Option<Cache>, retry: Retry) -> Self {
+        OpenWeather {
+            api_key,
+            lang,
+            cache,
+            retry,
+        }
+    }
+
+    /// Find a city's coordinates with the geocoding endpoint
+    pub async fn geocode(&self, location: &Location) -> Result<GeoPlace, WeatherError> {
+        let url = format!(
+            "{}/geo/1.0/direct?{}&limit=1&appid={}",
+            BASE_URL,
+            location.query(),
+            self.api_key
+        );
+
+        let key = cache::key("geocode", &location.to_string(), "");
+        let places: Vec<GeoPlace> =
+            fetch(&url, location, self.cache.as_ref(), &self.retry, &key).await?;
+        places
+            .into_iter()
+            .next()
+            .ok_or_else(|| WeatherError::CityNotFound(location.to_string()))
+    }
+
+    /// The temperature range and conditions of a whole day, from one
+    /// timemachine request every 3 hours
+    pub async fn history(
+        &self,
+        location: &Location,
+        date: NaiveDate,
+        units: Units,
+    ) -> Result<HistoryDay, WeatherError> {
+        let found = self.place(location).await?;
+        let place = Location::Coordinates {
+            lat: found.lat,
+            lon: found.lon,
+        };
+
+        // the local day depends on the timezone, which only comes with a response
+        let noon = date.and_time(NaiveTime::MIN).and_utc().timestamp() + 12 * 3600;
+        let first = self.moment(&place, noon, units).await?;
+
+        let mut handles = Vec::new();
+        for time in history::sample_times(date, first.timezone_offset) {
+            let provider = self.clone();
+            let place = place.clone();
+
+            handles.push(tokio::spawn(async move {
+                provider.moment(&place, time, units).await
+            }));
+        }
+
+        let mut points = Vec::new();
+        for handle in handles {
+            let moment = handle.await.map_err(|_| WeatherError::Unknown)??;
+            points.extend(moment.data);
+        }
+
+        Ok(HistoryDay::new(found.name, found.country, date, &points))
+    }
+
+    /// The current air quality index and pollutants
+    pub async fn air(&self, location: &Location) -> Result<AirQuality, WeatherError> {
+        let found = self.place(location).await?;
+        let coordinates = Location::Coordinates {
+            lat: found.lat,
+            lon: found.lon,
+        };
+        let url = format!(
+            "{}/data/2.5/air_pollution?{}&appid={}",
+            BASE_URL,
+            coordinates.query(),
+            self.api_key
+        );
+
+        let key = cache::key("air", &coordinates.to_string(), "");
+        let response: AirResponse =
+            fetch(&url, location, self.cache.as_ref(), &self.retry, &key).await?;
+        AirQuality::new(found, response)
+    }
+
+    /// The active weather alerts, from the One Call endpoint
+    pub async fn alerts(&self, location: &Location) -> Result<Alerts, WeatherError> {
+        let found = self.place(location).await?;
+        let coordinates = Location::Coordinates {
+            lat: found.lat,
+            lon: found.lon,
+        };
+        let url = format!(
+            "{}/data/3.0/onecall?{}&exclude=current,minutely,hourly,daily&appid={}",
+            BASE_URL,
+            coordinates.query(),
+            self.api_key
+        );
+
+        let key = cache::key("alerts", &coordinates.to_string(), "");
+        let response: AlertsResponse =
+            fetch(&url, location, self.cache.as_ref(), &self.retry, &key).await?;
+        Ok(Alerts::new(found, response))
+    }
+
+    /// The current weather, the next hours and days and the alerts, in one
+    /// One Call request once the place is known
+    pub async fn one_call(
+        &self,
+        location: &Location,
+        units: Units,
+    ) -> Result<OneCall, WeatherError> {
+        let found = self.place(location).await?;
+        let coordinates = Location::Coordinates {
+            lat: found.lat,
+            lon: found.lon,
+        };
+        let url = format!(
+            "{}/data/3.0/onecall?{}&exclude=minutely{}&appid={}",
+            BASE_URL,
+            coordinates.query(),
+            self.lang_param(),
+            self.api_key
+        );
+
+        let key = cache::key("onecall", &coordinates.to_string(), &self.variant(units));
+        let response: OneCallResponse =
+            fetch(&url, location, self.cache.as_ref(), &self.retry, &key).await?;
+        Ok(OneCall::new(found, response))
+    }
+
+    /// The name and coordinates of a location, geocoding city names
+    async fn place(&self, location: &Location) -> Result<GeoPlace, WeatherError> {
+        match location {
+            Location::Coordinates { lat, lon } => Ok(GeoPlace {
+                name: location.to_string(),
+                lat: *lat,
+                lon: *lon,
+                country: None,
+            }),
+            Location::City(_) => self.geocode(location).await,
+        }
+    }
+
+    async fn moment(
+        &self,
+        place: &Location,
+        time: i64,
+        units: Units,
+    ) -> Result<TimeMachineResponse, WeatherError> {
+        let url = format!(
+            "{}/data/3.0/onecall/timemachine?{}&dt={}{}&appid={}",
+            BASE_URL,
+            place.query(),
+            time,
+            self.lang_param(),
+            self.api_key
+        );
+
+        let key = cache::key(
+            "history",
+            &format!("{}@{}", place, time),
+            &self.variant(units),
+        );
+        fetch(&url, place, self.cache.as_ref(), &self.retry, &key).await
+    }
+
+    fn lang_param(&self) -> String {
+        match &self.lang {
+            Some(lang) => format!("&lang={}", lang),
+            None => String::new(),
+        }
+    }
+
+    /// The units part of a cache key, with the language when there is one
+    /// as the descriptions come translated
+    fn variant(&self, units: Units) -> String {
+        match &self.lang {
+            Some(lang) => format!("{}-{}", units, lang),
+            None => units.to_string(),
+        }
+    }
+}
@@ -0,0 +1,59 @@
+use crate::error::WeatherError;
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+/// Replace the file at `path` with `contents` in one step: they are written
+/// to a temporary file next to it, which is then renamed over it. Whatever
+/// reads the file sees the previous report or the new one, never half of
+/// one, and a failed write leaves the previous one alone
+pub fn write_atomic(path: &Path, contents: &str) -> Result<(), WeatherError> {
+    let failed = |reason: String| WeatherError::SaveFailed(path.display().to_string(), reason);
+
+    let temp = temp_path(path).ok_or_else(|| failed("not a file name".to_string()))?;
+    write_and_rename(&temp, path, contents).map_err(|e| {
+        fs::remove_file(&temp).ok();
+        failed(e.to_string())
+    })
+}
+
+/// A hidden file in the same directory, as a rename can't cross file
+/// systems
+fn temp_path(path: &Path) -> Option<PathBuf> {
+    let name = path.file_name()?.to_string_lossy();
+    Some(path.with_file_name(format!(".{}.{}.tmp", name, std::process::id())))
+}
+
+fn write_and_rename(temp: &Path, path: &Path, contents: &str) -> io::Result<()> {
+    let mut file = File::create(temp)?;
+    file.write_all(contents.as_bytes())?;
+    // on disk before the rename makes it visible
+    file.sync_all()?;
+    fs::rename(temp, path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_write_atomic() {
+        let dir = std::env::temp_dir().join(format!("weather_cli_output_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("weather.json");
+
+        write_atomic(&path, "first").unwrap();
+        write_atomic(&path, "second").unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "second");
+        // only the report is left, no temporary file
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 1);
+
+        assert!(matches!(
+            write_atomic(&dir.join("missing").join("weather.json"), "third"),
+            Err(WeatherError::SaveFailed(_, _))
+        ));
+        assert!(write_atomic(Path::new("/"), "fourth").is_err());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,129 @@
+use crate::cache::{self, Cache};
+use crate::error::WeatherError;
+use crate::forecast::ForecastResponse;
+use crate::location::Location;
+use crate::model::WeatherResponse;
+use crate::open_meteo::OpenMeteo;
+use crate::openweather::OpenWeather;
+use crate::retry::{self, Retry};
+use crate::units::Units;
+use clap::ValueEnum;
+use serde::de::DeserializeOwned;
+use std::time::SystemTime;
+
+/// A weather service the CLI can get the current weather and the forecast
+/// from. Whatever the service sends is turned into the OpenWeather shaped
+/// models, so everything after fetching is shared.
+pub trait WeatherProvider: Clone + Send + Sync + 'static {
+    fn current(
+        &self,
+        location: &Location,
+        units: Units,
+    ) -> impl Future<Output = Result<WeatherResponse, WeatherError>> + Send;
+
+    fn forecast(
+        &self,
+        location: &Location,
+        units: Units,
+    ) -> impl Future<Output = Result<ForecastResponse, WeatherError>> + Send;
+}
+
+/// The `--provider` choices.
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+pub enum ProviderKind {
+    /// OpenWeatherMap, needs an API key
+    #[value(name = "openweather")]
+    OpenWeather,
+    /// Open-Meteo, free without an API key
+    #[value(name = "open-meteo")]
+    OpenMeteo,
+}
+
+/// The provider picked on the command line.
+#[derive(Debug, Clone)]
+pub enum Provider {
+    OpenWeather(OpenWeather),
+    OpenMeteo(OpenMeteo),
+}
+
+impl Provider {
+    /// The OpenWeather provider, for what only OpenWeather offers
+    pub fn openweather(&self, command: &str) -> Result<&OpenWeather, WeatherError> {
+        match self {
+            Provider::OpenWeather(openweather) => Ok(openweather),
+            Provider::OpenMeteo(_) => Err(WeatherError::Unsupported(command.to_string())),
+        }
+    }
+}
+
+impl WeatherProvider for Provider {
+    async fn current(
+        &self,
+        location: &Location,
+        units: Units,
+    ) -> Result<WeatherResponse, WeatherError> {
+        match self {
+            Provider::OpenWeather(provider) => provider.current(location, units).await,
+            Provider::OpenMeteo(provider) => provider.current(location, units).await,
+        }
+    }
+
+    async fn forecast(
+        &self,
+        location: &Location,
+        units: Units,
+    ) -> Result<ForecastResponse, WeatherError> {
+        match self {
+            Provider::OpenWeather(provider) => provider.forecast(location, units).await,
+            Provider::OpenMeteo(provider) => provider.forecast(location, units).await,
+        }
+    }
+}
+
+/// GET a JSON endpoint and parse its body, going through the cache under
+/// `key` when there is one and retrying transient failures
+pub(crate) async fn fetch<T: DeserializeOwned>(
+    url: &str,
+    location: &Location,
+    cache: Option<&Cache>,
+    retry: &Retry,
+    key: &str,
+) -> Result<T, WeatherError> {
+    if let Some(cache) = cache
+        && cache.is_offline()
+    {
+        let (body, age) = cache
+            .get_any(key)
+            .ok_or_else(|| WeatherError::NotCached(location.to_string()))?;
+        eprintln!("{}: offline, cached {} ago", key, cache::format_age(age));
+        return Ok(serde_json::from_str(&body)?);
+    }
+
+    if let Some(body) = cache.and_then(|c| c.get(key))
+        && let Ok(parsed) = serde_json::from_str(&body)
+    {
+        return Ok(parsed);
+    }
+
+    let response = retry.get(url, key).await?;
+
+    match response.status() {
+        reqwest::StatusCode::OK => {
+            let body = response.text().await?;
+            let parsed = serde_json::from_str(&body)?;
+            if let Some(cache) = cache {
+                // a cache that can't be written only costs a request next time
+                cache.put(key, &body).ok();
+            }
+            Ok(parsed)
+        }
+        reqwest::StatusCode::NOT_FOUND => Err(WeatherError::CityNotFound(location.to_string())),
+        reqwest::StatusCode::UNAUTHORIZED => Err(WeatherError::InvalidApiKey),
+        reqwest::StatusCode::TOO_MANY_REQUESTS => {
+            let wait = retry::retry_after(response.headers(), SystemTime::now());
+            Err(WeatherError::RateLimited(wait.map(|wait| wait.as_secs())))
+        }
+        status if retry::is_transient(status) => Err(WeatherError::Unavailable(status.as_u16())),
+        _ => Err(WeatherError::Unknown),
+    }
+}
@@ -0,0 +1,290 @@
+use reqwest::header::{HeaderMap, RETRY_AFTER};
+use reqwest::{Response, StatusCode};
+use std::fmt;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
+use tokio::sync::Semaphore;
+use tokio::time::Instant;
+
+/// How many times a failed request is tried again by default.
+pub const DEFAULT_RETRIES: u32 = 3;
+
+/// How many requests are in flight at once by default.
+pub const DEFAULT_CONCURRENCY: u32 = 4;
+
+/// The wait before the first retry, doubled for every retry after it.
+const BASE_DELAY: Duration = Duration::from_millis(500);
+
+/// The longest `Retry-After` waited for, a longer one fails the request.
+const MAX_RETRY_AFTER: Duration = Duration::from_secs(60);
+
+/// Below this share of the quota left, a warning is printed even without
+/// --verbose.
+const LOW_QUOTA: f64 = 0.1;
+
+/// Trying a request again when it failed for a reason that may go away on
+/// its own: a network blip, a rate limit or a server error.
+#[derive(Debug, Clone)]
+pub struct Retry {
+    /// Retries after the first attempt, 0 to try only once
+    retries: u32,
+    /// Report every attempt on stderr
+    verbose: bool,
+    /// Shared by every clone, so a long list of cities queues its requests
+    /// instead of sending them all at once
+    slots: Arc<Semaphore>,
+    /// Set when the API rate limited a request, every clone waits for it
+    /// before sending anything
+    paused_until: Arc<Mutex<Option<Instant>>>,
+    /// Whether the low quota warning was printed already
+    warned: Arc<AtomicBool>,
+}
+
+impl Retry {
+    /// `concurrency` requests at most in flight at once, 1 to send them one
+    /// after another
+    pub fn new(retries: u32, concurrency: u32, verbose: bool) -> Self {
+        Retry {
+            retries,
+            verbose,
+            slots: Arc::new(Semaphore::new(concurrency.max(1) as usize)),
+            paused_until: Arc::new(Mutex::new(None)),
+            warned: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    /// GET `url`, again after a growing wait, or the one `Retry-After`
+    /// asks for, while the failure is transient. `label` names the request
+    /// in verbose mode, the URL is never printed as it holds the API key
+    pub async fn get(&self, url: &str, label: &str) -> Result<Response, reqwest::Error> {
+        let mut attempt = 1;
+        loop {
+            self.wait_for_pause().await;
+            let result = {
+                // a closed semaphore only means no limit
+                let _slot = self.slots.acquire().await.ok();
+                reqwest::get(url).await
+            };
+            if let Ok(response) = &result {
+                self.report_quota(response.headers(), label);
+            }
+
+            let outcome = match &result {
+                Ok(response) => response.status().to_string(),
+                Err(e) if e.is_timeout() => "a timeout".to_string(),
+                Err(e) if e.is_connect() => "no connection".to_string(),
+                Err(_) => "an error".to_string(),
+            };
+
+            let transient = match &result {
+                Ok(response) => is_transient(response.status()),
+                Err(e) => e.is_connect() || e.is_timeout(),
+            };
+            // the API's own wait wins over the backoff, unless it's too long
+            // to sit through
+            let asked_wait = match &result {
+                Ok(response) if transient => retry_after(response.headers(), SystemTime::now()),
+                _ => None,
+            };
+            let too_long = asked_wait.is_some_and(|wait| wait > MAX_RETRY_AFTER);
+            if !transient || too_long || attempt > self.retries {
+                if self.verbose {
+                    eprintln!("{}: attempt {} got {}", label, attempt, outcome);
+                }
+                return result;
+            }
+
+            let delay = asked_wait.unwrap_or_else(|| backoff(attempt, fastrand::f64()));
+            if let Ok(response) = &result
+                && response.status() == StatusCode::TOO_MANY_REQUESTS
+            {
+                self.pause(delay);
+            }
+            if self.verbose {
+                eprintln!(
+                    "{}: attempt {} got {}, retrying in {:.1}s",
+                    label,
+                    attempt,
+                    outcome,
+                    delay.as_secs_f64()
+                );
+            }
+            tokio::time::sleep(delay).await;
+            attempt += 1;
+        }
+    }
+
+    /// Hold every request back for `delay`, unless they already are for
+    /// longer
+    fn pause(&self, delay: Duration) {
+        let until = Instant::now() + delay;
+        if let Ok(mut paused_until) = self.paused_until.lock() {
+            *paused_until = (*paused_until).max(Some(until));
+        }
+    }
+
+    async fn wait_for_pause(&self) {
+        let until = self.paused_until.lock().ok().and_then(|until| *until);
+        if let Some(until) = until {
+            tokio::time::sleep_until(until).await;
+        }
+    }
+
+    /// The quota left on stderr, after every response with --verbose, or
+    /// once when it runs low
+    fn report_quota(&self, headers: &HeaderMap, label: &str) {
+        let Some(quota) = Quota::from_headers(headers) else {
+            return;
+        };
+
+        if self.verbose {
+            eprintln!("{}: {}", label, quota);
+        } else if quota.is_low() && !self.warned.swap(true, Ordering::Relaxed) {
+            eprintln!("Warning: {}", quota);
+        }
+    }
+}
+
+/// The requests left, from the `X-RateLimit-*` headers some APIs send.
+#[derive(Debug, PartialEq)]
+pub struct Quota {
+    pub remaining: u64,
+    pub limit: Option<u64>,
+}
+
+impl Quota {
+    pub fn from_headers(headers: &HeaderMap) -> Option<Self> {
+        let number = |names: &[&str]| {
+            names
+                .iter()
+                .find_map(|name| headers.get(*name)?.to_str().ok()?.trim().parse().ok())
+        };
+
+        Some(Quota {
+            remaining: number(&["x-ratelimit-remaining", "ratelimit-remaining"])?,
+            limit: number(&["x-ratelimit-limit", "ratelimit-limit"]),
+        })
+    }
+
+    /// Whether less than a tenth of the quota is left
+    pub fn is_low(&self) -> bool {
+        match self.limit {
+            Some(limit) if limit > 0 => (self.remaining as f64) < limit as f64 * LOW_QUOTA,
+            _ => self.remaining == 0,
+        }
+    }
+}
+
+impl fmt::Display for Quota {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.limit {
+            Some(limit) => write!(f, "{} of {} API requests left", self.remaining, limit),
+            None => write!(f, "{} API requests left", self.remaining),
+        }
+    }
+}
+
+/// How long a response asks to wait with `Retry-After`, given in seconds
+/// or as an HTTP date
+pub fn retry_after(headers: &HeaderMap, now: SystemTime) -> Option<Duration> {
+    let value = headers.get(RETRY_AFTER)?.to_str().ok()?.trim();
+    if let Ok(seconds) = value.parse() {
+        return Some(Duration::from_secs(seconds));
+    }
+
+    let date = chrono::DateTime::parse_from_rfc2822(value).ok()?;
+    Some(
+        SystemTime::from(date)
+            .duration_since(now)
+            .unwrap_or_default(),
+    )
+}
+
+/// Whether a response status is worth asking again for
+pub fn is_transient(status: StatusCode) -> bool {
+    status == StatusCode::TOO_MANY_REQUESTS || status.is_server_error()
+}
+
+/// The wait after the `attempt`th attempt: the base delay doubled every
+/// time, plus up to half of it again picked by `jitter` (from 0 to 1), so
+/// concurrent requests don't all retry at the same moment
+pub fn backoff(attempt: u32, jitter: f64) -> Duration {
+    let delay = BASE_DELAY * 2u32.pow(attempt.saturating_sub(1).min(10));
+    delay.mul_f64(1.0 + jitter.clamp(0.0, 1.0) / 2.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backoff() {
+        assert_eq!(backoff(1, 0.0), Duration::from_millis(500));
+        assert_eq!(backoff(2, 0.0), Duration::from_secs(1));
+        assert_eq!(backoff(3, 0.0), Duration::from_secs(2));
+        assert_eq!(backoff(3, 1.0), Duration::from_secs(3));
+        assert_eq!(backoff(2, 0.5), Duration::from_millis(1250));
+    }
+
+    fn headers(pairs: &[(&'static str, &str)]) -> HeaderMap {
+        pairs
+            .iter()
+            .map(|(name, value)| (*name, value.parse().unwrap()))
+            .map(|(name, value)| (reqwest::header::HeaderName::from_static(name), value))
+            .collect()
+    }
+
+    #[test]
+    fn test_retry_after() {
+        let now = SystemTime::UNIX_EPOCH + Duration::from_secs(1_445_412_480);
+        assert_eq!(
+            retry_after(&headers(&[("retry-after", "120")]), now),
+            Some(Duration::from_secs(120))
+        );
+        // 2015-10-21 07:28:00 UTC is 1445412480
+        assert_eq!(
+            retry_after(
+                &headers(&[("retry-after", "Wed, 21 Oct 2015 07:28:30 GMT")]),
+                now
+            ),
+            Some(Duration::from_secs(30))
+        );
+        assert_eq!(
+            retry_after(
+                &headers(&[("retry-after", "Wed, 21 Oct 2015 07:00:00 GMT")]),
+                now
+            ),
+            Some(Duration::ZERO)
+        );
+        assert_eq!(retry_after(&headers(&[("retry-after", "soon")]), now), None);
+        assert_eq!(retry_after(&HeaderMap::new(), now), None);
+    }
+
+    #[test]
+    fn test_quota() {
+        let quota = Quota::from_headers(&headers(&[
+            ("x-ratelimit-remaining", "5"),
+            ("x-ratelimit-limit", "60"),
+        ]))
+        .unwrap();
+        assert_eq!(quota.to_string(), "5 of 60 API requests left");
+        assert!(quota.is_low());
+
+        let quota = Quota::from_headers(&headers(&[("ratelimit-remaining", "12")])).unwrap();
+        assert_eq!(quota.limit, None);
+        assert!(!quota.is_low());
+
+        assert_eq!(Quota::from_headers(&HeaderMap::new()), None);
+    }
+
+    #[test]
+    fn test_is_transient() {
+        assert!(is_transient(StatusCode::TOO_MANY_REQUESTS));
+        assert!(is_transient(StatusCode::SERVICE_UNAVAILABLE));
+        assert!(is_transient(StatusCode::BAD_GATEWAY));
+        assert!(!is_transient(StatusCode::NOT_FOUND));
+        assert!(!is_transient(StatusCode::UNAUTHORIZED));
+        assert!(!is_transient(StatusCode::OK));
+    }
+}
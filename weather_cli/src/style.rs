@@ -0,0 +1,107 @@
+use colored::Colorize;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Whether the text output gets colors and condition icons. Off unless
+/// `main` turns it on, so the text stays plain when piped and in tests.
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+/// Turn colors and icons on or off for everything printed after
+pub fn set_enabled(enabled: bool) {
+    ENABLED.store(enabled, Ordering::Relaxed);
+    colored::control::set_override(enabled);
+}
+
+pub fn is_enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+/// A temperature with its unit, blue when cold, green when mild and red
+/// when hot
+pub fn temperature(value: f64, unit: &str) -> String {
+    by_temperature(&format!("{:.1}{}", value, unit), value, unit)
+}
+
+/// `text` in the color of the temperature `value`
+pub fn by_temperature(text: &str, value: f64, unit: &str) -> String {
+    if !is_enabled() {
+        return text.to_string();
+    }
+
+    let celsius = match unit {
+        "°F" => (value - 32.0) * 5.0 / 9.0,
+        "K" => value - 273.15,
+        _ => value,
+    };
+
+    if celsius < 10.0 {
+        text.blue().to_string()
+    } else if celsius < 25.0 {
+        text.green().to_string()
+    } else {
+        text.red().to_string()
+    }
+}
+
+/// A title line
+pub fn heading(text: &str) -> String {
+    if !is_enabled() {
+        return text.to_string();
+    }
+
+    text.bold().to_string()
+}
+
+/// The conditions, after an icon matching them when enabled
+pub fn conditions(description: &str) -> String {
+    if !is_enabled() {
+        return description.to_string();
+    }
+
+    format!("{} {}", icon(description), description)
+}
+
+/// An icon for an OpenWeatherMap or Open-Meteo description
+pub fn icon(description: &str) -> &'static str {
+    let description = description.to_lowercase();
+    let has = |words: &[&str]| words.iter().any(|word| description.contains(word));
+
+    if has(&["thunderstorm"]) {
+        "⛈️"
+    } else if has(&["snow", "sleet"]) {
+        "❄️"
+    } else if has(&["rain", "drizzle", "shower"]) {
+        "🌧️"
+    } else if has(&["mist", "fog", "haze", "smoke", "dust", "sand", "ash"]) {
+        "🌫️"
+    } else if has(&["squall", "tornado"]) {
+        "🌪️"
+    } else if has(&["few clouds", "mainly clear", "partly"]) {
+        "🌤️"
+    } else if has(&["scattered clouds"]) {
+        "⛅"
+    } else if has(&["cloud", "overcast"]) {
+        "☁️"
+    } else if has(&["clear"]) {
+        "☀️"
+    } else {
+        "🌡️"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_icon() {
+        assert_eq!(icon("clear sky"), "☀️");
+        assert_eq!(icon("few clouds"), "🌤️");
+        assert_eq!(icon("overcast clouds"), "☁️");
+        assert_eq!(icon("light rain"), "🌧️");
+        assert_eq!(icon("rain showers"), "🌧️");
+        assert_eq!(icon("light snow showers"), "❄️");
+        assert_eq!(icon("thunderstorm with heavy rain"), "⛈️");
+        assert_eq!(icon("Mist"), "🌫️");
+        assert_eq!(icon("unknown"), "🌡️");
+    }
+}
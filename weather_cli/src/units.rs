@@ -0,0 +1,50 @@
+use clap::ValueEnum;
+use serde::Deserialize;
+use std::fmt;
+
+/// The units temperatures and wind speeds are shown in.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Units {
+    /// Celsius and km/h
+    #[default]
+    Metric,
+    /// Fahrenheit and mph
+    Imperial,
+    /// Kelvin and m/s
+    Kelvin,
+}
+
+impl Units {
+    /// The name used on the command line, in the config file and in cache keys
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Units::Metric => "metric",
+            Units::Imperial => "imperial",
+            Units::Kelvin => "kelvin",
+        }
+    }
+
+    /// The symbol written after a temperature
+    pub fn temperature_unit(self) -> &'static str {
+        match self {
+            Units::Metric => "°C",
+            Units::Imperial => "°F",
+            Units::Kelvin => "K",
+        }
+    }
+
+    pub fn wind_unit(self) -> &'static str {
+        match self {
+            Units::Metric => "km/h",
+            Units::Imperial => "mph",
+            Units::Kelvin => "m/s",
+        }
+    }
+}
+
+impl fmt::Display for Units {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
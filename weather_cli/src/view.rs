@@ -0,0 +1,738 @@
+use crate::air::Level;
+use crate::alerts::Severity;
+use crate::error::WeatherError;
+use crate::i18n;
+use crate::style;
+use crate::units::Units;
+use chrono::NaiveDate;
+use serde::Serialize;
+
+/// The current weather of one place, converted to the requested units and
+/// ready to be printed as text, JSON or CSV.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct WeatherView {
+    pub city: String,
+    pub country: Option<String>,
+    pub temperature: f64,
+    pub feels_like: f64,
+    /// `°C`, `°F` or `K`
+    pub temperature_unit: &'static str,
+    pub conditions: String,
+    pub humidity: u8,
+    /// In hPa
+    pub pressure: Option<i32>,
+    pub visibility_km: Option<f64>,
+    pub wind_speed: Option<f64>,
+    /// `km/h`, `mph` or `m/s`
+    pub wind_unit: &'static str,
+    pub wind_direction: Option<String>,
+    /// In percent
+    pub cloudiness: Option<u8>,
+    pub sunrise: Option<String>,
+    pub sunset: Option<String>,
+    pub dew_point: f64,
+    /// Only when it's hot
+    pub heat_index: Option<f64>,
+    /// Only when it's cold and windy
+    pub wind_chill: Option<f64>,
+    /// The next hours, with --hourly
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hourly: Option<Vec<HourView>>,
+    /// The active alerts, when the weather came from One Call
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub alerts: Option<Vec<AlertView>>,
+}
+
+/// One hour of the One Call forecast.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct HourView {
+    /// `HH:MM` in the place's own time
+    pub time: String,
+    pub temperature: f64,
+    pub conditions: String,
+    /// Chance of precipitation in percent
+    pub precipitation: u8,
+}
+
+/// The daily forecast of one place.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ForecastView {
+    pub city: String,
+    pub country: Option<String>,
+    pub temperature_unit: &'static str,
+    pub days: Vec<DayView>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DayView {
+    pub date: NaiveDate,
+    pub min: f64,
+    pub max: f64,
+    pub conditions: String,
+    /// Chance of precipitation in percent
+    pub precipitation: u8,
+}
+
+/// The weather of one day in the past.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct HistoryView {
+    pub city: String,
+    pub country: Option<String>,
+    pub date: NaiveDate,
+    pub min: f64,
+    pub max: f64,
+    pub temperature_unit: &'static str,
+    pub conditions: String,
+}
+
+/// The air quality of one place.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct AirView {
+    pub city: String,
+    pub country: Option<String>,
+    /// From 1 (good) to 5 (very poor)
+    pub aqi: u8,
+    pub quality: Option<Level>,
+    pub components: Vec<ComponentView>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ComponentView {
+    pub name: &'static str,
+    /// In μg/m³
+    pub value: f64,
+    pub level: Level,
+}
+
+/// The active weather alerts of one place.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct AlertsView {
+    pub city: String,
+    pub country: Option<String>,
+    pub alerts: Vec<AlertView>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct AlertView {
+    pub event: String,
+    pub severity: Severity,
+    pub sender: String,
+    /// `YYYY-MM-DD HH:MM` in the place's own time
+    pub start: String,
+    pub end: String,
+    pub description: String,
+}
+
+const WEATHER_HEADERS: [&str; 18] = [
+    "city",
+    "country",
+    "temperature",
+    "feels_like",
+    "temperature_unit",
+    "conditions",
+    "humidity",
+    "pressure",
+    "visibility_km",
+    "wind_speed",
+    "wind_unit",
+    "wind_direction",
+    "cloudiness",
+    "sunrise",
+    "sunset",
+    "dew_point",
+    "heat_index",
+    "wind_chill",
+];
+
+const FORECAST_HEADERS: [&str; 8] = [
+    "city",
+    "country",
+    "date",
+    "min",
+    "max",
+    "temperature_unit",
+    "conditions",
+    "precipitation",
+];
+
+impl WeatherView {
+    /// The text `display` prints, with the extra sections when `detailed`
+    pub fn to_text(&self, detailed: bool) -> String {
+        let unit = self.temperature_unit;
+        let labels = i18n::labels();
+        let mut text = String::new();
+
+        let title = format!("{} {}", labels.weather_in, self.city);
+        text.push_str(&format!("\n {}\n", style::heading(&title)));
+        text.push_str(&format!(
+            "{}: {}\n",
+            labels.temperature,
+            style::temperature(self.temperature, unit)
+        ));
+        text.push_str(&format!(
+            "{}: {}\n",
+            labels.feels_like,
+            style::temperature(self.feels_like, unit)
+        ));
+        text.push_str(&format!(
+            "{}: {}\n",
+            labels.conditions,
+            style::conditions(&self.conditions)
+        ));
+        text.push_str(&format!("{}: {}%\n", labels.humidity, self.humidity));
+
+        if !detailed {
+            text.push_str(&self.one_call_text());
+            return text;
+        }
+
+        text.push_str(&format!("\n {}:\n", labels.additional_details));
+        if let Some(country) = &self.country {
+            text.push_str(&format!("{}: {}\n", labels.country, country));
+        }
+        if let Some(pressure) = self.pressure {
+            text.push_str(&format!("{}: {} hPa\n", labels.pressure, pressure));
+        }
+        if let Some(visibility) = self.visibility_km {
+            text.push_str(&format!("{}: {:.1} km\n", labels.visibility, visibility));
+        }
+
+        text.push_str(&format!("\n {}:\n", labels.wind));
+        if let Some(speed) = self.wind_speed {
+            text.push_str(&format!(
+                "  {}: {:.1} {}",
+                labels.speed, speed, self.wind_unit
+            ));
+            if let Some(direction) = &self.wind_direction {
+                text.push_str(&format!(" ({})", direction));
+            }
+            text.push('\n');
+        }
+
+        if let Some(clouds) = self.cloudiness {
+            text.push_str(&format!("\n  {}: {}%\n", labels.cloudiness, clouds));
+        }
+
+        text.push_str(&format!("\n {}:\n", labels.comfort));
+        text.push_str(&format!(
+            "  {}: {}\n",
+            labels.dew_point,
+            style::temperature(self.dew_point, unit)
+        ));
+        if let Some(heat_index) = self.heat_index {
+            text.push_str(&format!(
+                "  {}: {}\n",
+                labels.heat_index,
+                style::temperature(heat_index, unit)
+            ));
+        }
+        if let Some(wind_chill) = self.wind_chill {
+            text.push_str(&format!(
+                "  {}: {}\n",
+                labels.wind_chill,
+                style::temperature(wind_chill, unit)
+            ));
+        }
+
+        text.push_str(&format!("\n {}:\n", labels.sun_times));
+        if let Some(sunrise) = &self.sunrise {
+            text.push_str(&format!("  {}: {}\n", labels.sunrise, sunrise));
+        }
+        if let Some(sunset) = &self.sunset {
+            text.push_str(&format!("  {}: {}\n", labels.sunset, sunset));
+        }
+
+        text.push_str(&self.one_call_text());
+        text
+    }
+
+    /// The hours and alerts that only come with One Call, nothing for the
+    /// rest
+    fn one_call_text(&self) -> String {
+        let unit = self.temperature_unit;
+        let labels = i18n::labels();
+        let mut text = String::new();
+
+        if let Some(hours) = &self.hourly {
+            text.push_str(&format!("\n {}:\n", labels.next_hours));
+            for hour in hours {
+                let temperature = format!("{:.1}{}", hour.temperature, unit);
+                text.push_str(&format!(
+                    "  {}  {}  {:>3}%  {}\n",
+                    hour.time,
+                    style::by_temperature(&format!("{:>8}", temperature), hour.temperature, unit),
+                    hour.precipitation,
+                    style::conditions(&hour.conditions)
+                ));
+            }
+        }
+
+        if let Some(alerts) = self.alerts.as_ref().filter(|alerts| !alerts.is_empty()) {
+            text.push_str(&format!("\n {}:\n", labels.active_alerts));
+            for alert in alerts {
+                text.push_str(&format!(
+                    "  {} ({}), {} {}\n",
+                    alert.event, alert.severity, labels.until, alert.end
+                ));
+            }
+        }
+
+        text
+    }
+
+    fn compare_row(&self) -> [String; 6] {
+        let place = match &self.country {
+            Some(country) => format!("{}, {}", self.city, country),
+            None => self.city.clone(),
+        };
+        let wind = match (self.wind_speed, &self.wind_direction) {
+            (Some(speed), Some(direction)) => {
+                format!("{:.1} {} {}", speed, self.wind_unit, direction)
+            }
+            (Some(speed), None) => format!("{:.1} {}", speed, self.wind_unit),
+            (None, _) => "-".to_string(),
+        };
+
+        [
+            place,
+            format!("{:.1}{}", self.temperature, self.temperature_unit),
+            format!("{:.1}{}", self.feels_like, self.temperature_unit),
+            format!("{}%", self.humidity),
+            wind,
+            self.conditions.clone(),
+        ]
+    }
+
+    fn csv_record(&self) -> Vec<String> {
+        vec![
+            self.city.clone(),
+            optional(&self.country),
+            self.temperature.to_string(),
+            self.feels_like.to_string(),
+            self.temperature_unit.to_string(),
+            self.conditions.clone(),
+            self.humidity.to_string(),
+            optional(&self.pressure),
+            optional(&self.visibility_km),
+            optional(&self.wind_speed),
+            self.wind_unit.to_string(),
+            optional(&self.wind_direction),
+            optional(&self.cloudiness),
+            optional(&self.sunrise),
+            optional(&self.sunset),
+            self.dew_point.to_string(),
+            optional(&self.heat_index),
+            optional(&self.wind_chill),
+        ]
+    }
+}
+
+impl ForecastView {
+    pub fn to_text(&self) -> String {
+        let mut text = String::new();
+
+        let labels = i18n::labels();
+        match &self.country {
+            Some(country) => text.push_str(&format!(
+                "\n {} {}, {}\n",
+                labels.forecast_for, self.city, country
+            )),
+            None => text.push_str(&format!("\n {} {}\n", labels.forecast_for, self.city)),
+        }
+
+        for day in &self.days {
+            text.push_str(&format!(
+                "{}  {:>8} / {:<8} {:<24} {:>3}% {}\n",
+                day.date.format("%a %d %b"),
+                format!("{:.1}{}", day.min, self.temperature_unit),
+                format!("{:.1}{}", day.max, self.temperature_unit),
+                day.conditions,
+                day.precipitation,
+                labels.precipitation
+            ));
+        }
+
+        text
+    }
+}
+
+impl HistoryView {
+    pub fn to_text(&self) -> String {
+        let place = match &self.country {
+            Some(country) => format!("{}, {}", self.city, country),
+            None => self.city.clone(),
+        };
+
+        let labels = i18n::labels();
+        let mut text = String::new();
+        text.push_str(&format!(
+            "\n {} {} {} {}\n",
+            labels.weather_in,
+            place,
+            labels.on,
+            self.date.format("%a %d %b %Y")
+        ));
+        text.push_str(&format!(
+            "{}: {:.1}{} {} {:.1}{}\n",
+            labels.temperature,
+            self.min,
+            self.temperature_unit,
+            labels.to,
+            self.max,
+            self.temperature_unit
+        ));
+        text.push_str(&format!("{}: {}\n", labels.conditions, self.conditions));
+
+        text
+    }
+}
+
+impl AirView {
+    pub fn to_text(&self) -> String {
+        let labels = i18n::labels();
+        let mut text = String::new();
+        match &self.country {
+            Some(country) => text.push_str(&format!(
+                "\n {} {}, {}\n",
+                labels.air_quality_in, self.city, country
+            )),
+            None => text.push_str(&format!("\n {} {}\n", labels.air_quality_in, self.city)),
+        }
+
+        match self.quality {
+            Some(quality) => text.push_str(&format!("AQI: {} ({})\n", self.aqi, quality)),
+            None => text.push_str(&format!("AQI: {}\n", self.aqi)),
+        }
+        for component in &self.components {
+            text.push_str(&format!(
+                "  {}: {:.1} μg/m³ ({})\n",
+                component.name, component.value, component.level
+            ));
+        }
+
+        text
+    }
+}
+
+impl AlertsView {
+    pub fn to_text(&self) -> String {
+        let labels = i18n::labels();
+        let mut text = String::new();
+        match &self.country {
+            Some(country) => text.push_str(&format!(
+                "\n {} {}, {}\n",
+                labels.alerts_for, self.city, country
+            )),
+            None => text.push_str(&format!("\n {} {}\n", labels.alerts_for, self.city)),
+        }
+
+        if self.alerts.is_empty() {
+            text.push_str(&format!("{}\n", labels.no_alerts));
+        }
+        for alert in &self.alerts {
+            text.push_str(&format!(
+                "\n[{}] {} ({})\n",
+                alert.severity, alert.event, alert.sender
+            ));
+            text.push_str(&format!(
+                "  {} {} {} {}\n",
+                labels.from, alert.start, labels.to, alert.end
+            ));
+            for line in alert.description.lines() {
+                text.push_str(&format!("  {}\n", line));
+            }
+        }
+
+        text
+    }
+}
+
+/// The current weather of several places side by side, one row each
+/// with the columns lined up
+pub fn compare_table(views: &[WeatherView]) -> String {
+    let labels = i18n::labels();
+    let headers = [
+        labels.city,
+        labels.temp,
+        labels.feels_like,
+        labels.humidity,
+        labels.wind,
+        labels.conditions,
+    ];
+    let rows: Vec<[String; 6]> = views.iter().map(WeatherView::compare_row).collect();
+    let mut widths = headers.map(|header| header.chars().count());
+    for row in &rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+
+    let mut text = String::from("\n");
+    let headers = headers.map(String::from);
+    text.push_str(&table_line(&headers, &widths, |_, cell| {
+        style::heading(cell)
+    }));
+    for (view, row) in views.iter().zip(&rows) {
+        text.push_str(&table_line(row, &widths, |column, cell| match column {
+            1 => style::by_temperature(cell, view.temperature, view.temperature_unit),
+            2 => style::by_temperature(cell, view.feels_like, view.temperature_unit),
+            _ => cell.to_string(),
+        }));
+    }
+
+    text
+}
+
+/// One row of a table, padded by the width of the plain text so colors
+/// don't shift the columns
+fn table_line(cells: &[String], widths: &[usize], paint: impl Fn(usize, &str) -> String) -> String {
+    let mut line = String::new();
+    for (column, (cell, width)) in cells.iter().zip(widths).enumerate() {
+        if column > 0 {
+            line.push_str("  ");
+        }
+        line.push_str(&paint(column, cell));
+        if column + 1 < cells.len() {
+            line.push_str(&" ".repeat(width - cell.chars().count()));
+        }
+    }
+    line.push('\n');
+
+    line
+}
+
+/// All the places as CSV, one row each after a header row
+pub fn weather_csv(views: &[WeatherView]) -> Result<String, WeatherError> {
+    to_csv(
+        &WEATHER_HEADERS,
+        views.iter().map(WeatherView::csv_record).collect(),
+    )
+}
+
+/// The forecast as CSV, one row per day after a header row
+pub fn forecast_csv(view: &ForecastView) -> Result<String, WeatherError> {
+    let rows = view
+        .days
+        .iter()
+        .map(|day| {
+            vec![
+                view.city.clone(),
+                optional(&view.country),
+                day.date.to_string(),
+                day.min.to_string(),
+                day.max.to_string(),
+                view.temperature_unit.to_string(),
+                day.conditions.clone(),
+                day.precipitation.to_string(),
+            ]
+        })
+        .collect();
+
+    to_csv(&FORECAST_HEADERS, rows)
+}
+
+/// The day as CSV, a header row and one row
+pub fn history_csv(view: &HistoryView) -> Result<String, WeatherError> {
+    let row = vec![
+        view.city.clone(),
+        optional(&view.country),
+        view.date.to_string(),
+        view.min.to_string(),
+        view.max.to_string(),
+        view.temperature_unit.to_string(),
+        view.conditions.clone(),
+    ];
+
+    // the forecast columns, without precipitation
+    to_csv(&FORECAST_HEADERS[..7], vec![row])
+}
+
+/// The air quality as CSV, the index then one column per pollutant
+pub fn air_csv(view: &AirView) -> Result<String, WeatherError> {
+    // pm2_5 like the API rather than PM2.5
+    let columns: Vec<String> = view
+        .components
+        .iter()
+        .map(|c| c.name.to_lowercase().replace('.', "_"))
+        .collect();
+    let mut headers = vec!["city", "country", "aqi", "quality"];
+    headers.extend(columns.iter().map(String::as_str));
+
+    let mut row = vec![
+        view.city.clone(),
+        optional(&view.country),
+        view.aqi.to_string(),
+        optional(&view.quality),
+    ];
+    row.extend(view.components.iter().map(|c| c.value.to_string()));
+
+    to_csv(&headers, vec![row])
+}
+
+/// The alerts as CSV, one row each after a header row
+pub fn alerts_csv(view: &AlertsView) -> Result<String, WeatherError> {
+    let headers = [
+        "city",
+        "country",
+        "event",
+        "severity",
+        "sender",
+        "start",
+        "end",
+        "description",
+    ];
+    let rows = view
+        .alerts
+        .iter()
+        .map(|alert| {
+            vec![
+                view.city.clone(),
+                optional(&view.country),
+                alert.event.clone(),
+                alert.severity.to_string(),
+                alert.sender.clone(),
+                alert.start.clone(),
+                alert.end.clone(),
+                alert.description.clone(),
+            ]
+        })
+        .collect();
+
+    to_csv(&headers, rows)
+}
+
+fn to_csv(headers: &[&str], rows: Vec<Vec<String>>) -> Result<String, WeatherError> {
+    let failed = |e: csv::Error| WeatherError::Output(e.to_string());
+
+    let mut writer = csv::Writer::from_writer(Vec::new());
+    writer.write_record(headers).map_err(failed)?;
+    for row in rows {
+        writer.write_record(row).map_err(failed)?;
+    }
+
+    let bytes = writer
+        .into_inner()
+        .map_err(|e| WeatherError::Output(e.to_string()))?;
+    String::from_utf8(bytes).map_err(|e| WeatherError::Output(e.to_string()))
+}
+
+/// An empty CSV field for `None`
+fn optional<T: ToString>(value: &Option<T>) -> String {
+    value.as_ref().map(T::to_string).unwrap_or_default()
+}
+
+/// Convert a Kelvin temperature to the requested units, rounded to one
+/// decimal, with the unit's symbol
+pub fn convert_temp(kelvin: f64, units: Units) -> (f64, &'static str) {
+    let value = match units {
+        Units::Metric => kelvin - 273.15,
+        Units::Imperial => (kelvin - 273.15) * 9.0 / 5.0 + 32.0,
+        Units::Kelvin => kelvin,
+    };
+
+    (round(value), units.temperature_unit())
+}
+
+/// Round to one decimal, so JSON and CSV don't show float noise
+pub fn round(value: f64) -> f64 {
+    (value * 10.0).round() / 10.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn london() -> WeatherView {
+        WeatherView {
+            city: "London".to_string(),
+            country: Some("GB".to_string()),
+            temperature: 11.8,
+            feels_like: 11.3,
+            temperature_unit: "°C",
+            conditions: "overcast clouds".to_string(),
+            humidity: 87,
+            pressure: Some(1012),
+            visibility_km: Some(10.0),
+            wind_speed: Some(14.8),
+            wind_unit: "km/h",
+            wind_direction: Some("SW".to_string()),
+            cloudiness: Some(75),
+            sunrise: Some("07:30".to_string()),
+            sunset: None,
+            dew_point: 9.7,
+            heat_index: None,
+            wind_chill: None,
+            hourly: None,
+            alerts: None,
+        }
+    }
+
+    #[test]
+    fn test_text() {
+        let view = london();
+        assert_eq!(
+            view.to_text(false),
+            "\n Weather in London\nTemperature: 11.8°C\nFeels like: 11.3°C\n\
+             Conditions: overcast clouds\nHumidity: 87%\n"
+        );
+
+        let detailed = view.to_text(true);
+        assert!(detailed.starts_with(&view.to_text(false)));
+        assert!(detailed.contains("\n  Speed: 14.8 km/h (SW)\n"));
+        assert!(detailed.contains("  Sunrise: 07:30\n"));
+        assert!(!detailed.contains("Sunset"));
+        assert!(detailed.contains("\n  Dew point: 9.7°C\n"));
+        assert!(!detailed.contains("Heat index"));
+    }
+
+    #[test]
+    fn test_compare_table() {
+        let mut lagos = london();
+        lagos.city = "Lagos".to_string();
+        lagos.country = Some("NG".to_string());
+        lagos.temperature = 31.0;
+        lagos.wind_speed = None;
+        lagos.conditions = "clear sky".to_string();
+
+        assert_eq!(
+            compare_table(&[london(), lagos]),
+            "\n\
+             City        Temp    Feels like  Humidity  Wind          Conditions\n\
+             London, GB  11.8°C  11.3°C      87%       14.8 km/h SW  overcast clouds\n\
+             Lagos, NG   31.0°C  11.3°C      87%       -             clear sky\n"
+        );
+    }
+
+    #[test]
+    fn test_csv() {
+        let mut paris = london();
+        paris.city = "Paris, 6e".to_string();
+        paris.country = None;
+
+        let csv = weather_csv(&[london(), paris]).unwrap();
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines.len(), 3);
+        assert!(lines[0].starts_with("city,country,temperature,"));
+        assert_eq!(
+            lines[1],
+            "London,GB,11.8,11.3,°C,overcast clouds,87,1012,10,14.8,km/h,SW,75,07:30,,9.7,,"
+        );
+        assert!(lines[2].starts_with("\"Paris, 6e\",,11.8,"));
+    }
+
+    #[test]
+    fn test_json() {
+        let json = serde_json::to_value(london()).unwrap();
+        assert_eq!(json["city"], "London");
+        assert_eq!(json["temperature"], 11.8);
+        assert_eq!(json["sunset"], serde_json::Value::Null);
+    }
+
+    #[test]
+    fn test_convert_temp() {
+        assert_eq!(convert_temp(273.15, Units::Metric), (0.0, "°C"));
+        assert_eq!(convert_temp(273.15, Units::Imperial), (32.0, "°F"));
+        assert_eq!(convert_temp(285.27, Units::Metric), (12.1, "°C"));
+        assert_eq!(convert_temp(280.0, Units::Kelvin), (280.0, "K"));
+    }
+}